@@ -0,0 +1,120 @@
+//! Benchmarks of the traversal and search subsystems on a synthetic tree.
+//!
+//! Run with `cargo bench`. The tree has 2000 files by default, `BENCH_FILES` sets another size.
+//! The same tree can be generated with the hidden `generate-tree` subcommand to compare the tools
+//! on other hardware.
+
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use rust_mcp_filesystem::fs_service::FileSystemService;
+use rust_mcp_filesystem::synthetic_tree::{
+    SYNTHETIC_TREE_NEEDLE, SyntheticTreeOptions, generate_tree,
+};
+use std::hint::black_box;
+use std::path::Path;
+
+fn benchmarks(c: &mut Criterion) {
+    let files = std::env::var("BENCH_FILES")
+        .ok()
+        .and_then(|files| files.parse().ok())
+        .unwrap_or(2000);
+    let temp_dir = tempfile::tempdir().unwrap();
+    let root = temp_dir.path().join("tree");
+    let options = SyntheticTreeOptions {
+        files,
+        ..Default::default()
+    };
+    generate_tree(&root, &options).unwrap();
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .unwrap();
+    let service = FileSystemService::try_new(&[temp_dir.path().display().to_string()]).unwrap();
+
+    let mut group = c.benchmark_group(format!("tree_{files}_files"));
+    group.sample_size(10);
+
+    group.bench_function("search_files_iter", |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                let count = service
+                    .search_files_iter(&root, "*.rs".to_string(), vec![], None, None)
+                    .await
+                    .unwrap()
+                    .count();
+                black_box(count)
+            })
+        })
+    });
+
+    group.bench_function("search_files_content", |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                let results = service
+                    .search_files_content(
+                        &root,
+                        "*",
+                        &[SYNTHETIC_TREE_NEEDLE],
+                        false,
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                    )
+                    .await
+                    .unwrap();
+                black_box(results)
+            })
+        })
+    });
+
+    group.bench_function("find_duplicate_files", |b| {
+        b.iter(|| {
+            runtime.block_on(async {
+                let duplicates = service
+                    .find_duplicate_files(&root, None, None, None, None)
+                    .await
+                    .unwrap();
+                black_box(duplicates)
+            })
+        })
+    });
+
+    let zip_file = temp_dir.path().join("tree.zip");
+    group.bench_function("zip_directory", |b| {
+        b.iter_batched(
+            || remove_file_if_exists(&zip_file),
+            |_| {
+                runtime.block_on(async {
+                    service
+                        .zip_directory(
+                            root.display().to_string(),
+                            "*".to_string(),
+                            vec![],
+                            zip_file.display().to_string(),
+                            false,
+                            false,
+                            false,
+                            true,
+                            None,
+                        )
+                        .await
+                        .unwrap()
+                })
+            },
+            BatchSize::PerIteration,
+        )
+    });
+
+    group.finish();
+}
+
+fn remove_file_if_exists(path: &Path) {
+    if path.exists() {
+        std::fs::remove_file(path).unwrap();
+    }
+}
+
+criterion_group!(benches, benchmarks);
+criterion_main!(benches);
@@ -1,12 +1,16 @@
+use crate::fs_service::utils::{LineEndingPolicy, ValidationProfile, WriteIfExists};
+use crate::i18n::Locale;
 use crate::tools::FileSystemTools;
-use clap::{Parser, arg, command};
+use clap::{Parser, Subcommand, arg, command};
 use std::collections::HashSet;
+use std::path::PathBuf;
 
 #[derive(Parser, Debug)]
 #[command(name =  env!("CARGO_PKG_NAME"))]
 #[command(version = env!("CARGO_PKG_VERSION"))]
 #[command(about = "A lightning-fast, asynchronous, and lightweight MCP server designed for efficient handling of various filesystem operations",
 long_about = None)]
+#[command(subcommand_precedence_over_arg = true)]
 pub struct CommandArguments {
     #[arg(
         short = 'w',
@@ -36,6 +40,209 @@ pub struct CommandArguments {
     )]
     pub enable_roots: bool,
 
+    #[arg(
+        long = "write-if-exists",
+        value_enum,
+        default_value_t = WriteIfExists::Overwrite,
+        help = "Default behavior of write_file when the target file already exists: fail, overwrite or append. Defaults to overwrite.\nClients can still choose a different behavior per call using the 'if_exists' argument.",
+        env = "WRITE_IF_EXISTS"
+    )]
+    pub write_if_exists: WriteIfExists,
+
+    #[arg(
+        long = "write-line-endings",
+        value_enum,
+        default_value_t = LineEndingPolicy::Preserve,
+        help = "Line endings of the content written by write_file and edit_file: preserve, lf, crlf or os. Defaults to preserve.\nA policy other than preserve takes precedence over the 'end_of_line' setting of the '.editorconfig' files.",
+        env = "WRITE_LINE_ENDINGS"
+    )]
+    pub write_line_endings: LineEndingPolicy,
+
+    #[arg(
+        long = "enable-backups",
+        action = clap::ArgAction::SetTrue,
+        value_parser = clap::value_parser!(bool),
+        help = "Keeps a timestamped backup copy of files before they are modified by write_file or edit_file. Defaults to disabled.\nBackups can be listed and restored using the 'list_backups' and 'restore_backup' tools.",
+        env = "ENABLE_BACKUPS"
+    )]
+    pub enable_backups: bool,
+
+    #[arg(
+        long = "backup-dir",
+        default_value = ".mcp-backups",
+        help = "Directory where backups are stored, relative to each allowed directory. Defaults to '.mcp-backups'.",
+        env = "BACKUP_DIR"
+    )]
+    pub backup_dir: String,
+
+    #[arg(
+        long = "backup-retention",
+        default_value_t = 10,
+        help = "Maximum number of backups kept for each file, older backups are removed first. Defaults to 10.",
+        env = "BACKUP_RETENTION"
+    )]
+    pub backup_retention: usize,
+
+    #[arg(
+        long = "locale",
+        default_value = "en",
+        value_parser = clap::value_parser!(Locale),
+        help = "Language of the result and error messages returned to clients: en, de, es or fr. Defaults to en.\nPOSIX locale names such as 'de_DE.UTF-8' are accepted as well. Tool errors carry an untranslated code in '_meta.error_code', e.g. 'access_denied'.",
+        env = "LOCALE"
+    )]
+    pub locale: Locale,
+
+    #[arg(
+        long = "base-dir",
+        help = "Directory relative paths in tool arguments are resolved against. Defaults to the first allowed directory.\nA relative base directory is resolved against the first allowed directory. Paths outside the allowed directories are still rejected.",
+        env = "BASE_DIR"
+    )]
+    pub base_dir: Option<String>,
+
+    #[arg(
+        long = "io-retries",
+        default_value_t = 3,
+        help = "Number of times a file operation failing with a transient error (file busy or locked by another process) is retried. Defaults to 3, 0 disables retries.",
+        env = "IO_RETRIES"
+    )]
+    pub io_retries: u32,
+
+    #[arg(
+        long = "io-retry-backoff",
+        default_value_t = 100,
+        help = "Delay in milliseconds before the first retry of a failed file operation, doubled after each retry. Defaults to 100.",
+        env = "IO_RETRY_BACKOFF"
+    )]
+    pub io_retry_backoff: u64,
+
+    #[arg(
+        long = "batch-concurrency",
+        default_value_t = 2,
+        help = "Number of long-running scans (e.g. find_duplicate_files, find_relevant_files) running at once, others wait for their turn. Defaults to 2.\nScans run on a separate thread pool, so other tool calls stay responsive meanwhile.",
+        env = "BATCH_CONCURRENCY"
+    )]
+    pub batch_concurrency: usize,
+
+    #[arg(
+        long = "batch-threads",
+        default_value_t = 0,
+        help = "Number of threads of the pool long-running scans and archive compression run on. Defaults to 0, half of the CPUs.",
+        env = "BATCH_THREADS"
+    )]
+    pub batch_threads: usize,
+
+    #[arg(
+        long = "watchdog-interval",
+        default_value_t = 10,
+        help = "Interval in seconds at which the existence of the allowed directories is checked. Defaults to 10, 0 disables the check.\nMissing directories (e.g. unmounted volumes) are excluded until they return.",
+        env = "WATCHDOG_INTERVAL"
+    )]
+    pub watchdog_interval: u64,
+
+    #[arg(
+        long = "response-cache-ttl",
+        default_value_t = 0,
+        help = "Number of seconds during which an identical call of a read-only tool returns the previous result instead of running again. Defaults to 0, disabled.\nOnly the modification time of the paths named in the arguments is checked: a file rewritten deeper in the tree of a directory is missed until the time expires. Calls following a call modifying files through the server run again. Cached results have `cached: true` in their `_meta`.",
+        env = "RESPONSE_CACHE_TTL"
+    )]
+    pub response_cache_ttl: u64,
+
+    #[arg(
+        long = "ascii-output",
+        action = clap::ArgAction::SetTrue,
+        value_parser = clap::value_parser!(bool),
+        help = "Escapes the non-ASCII characters of the tool results as \\uXXXX sequences, for clients failing to parse responses containing e.g. CJK or Cyrillic text. Defaults to disabled.\nText contents, resources, structured content and error messages are escaped, the client receives the escape sequences as text.",
+        env = "ASCII_OUTPUT"
+    )]
+    pub ascii_output: bool,
+
+    #[arg(
+        long = "lazy-validation",
+        action = clap::ArgAction::SetTrue,
+        value_parser = clap::value_parser!(bool),
+        help = "Starts the server even if some allowed directories don't exist yet (e.g. network shares not mounted yet). Defaults to disabled.\nMissing directories are rejected by tools until they exist.",
+        env = "LAZY_VALIDATION"
+    )]
+    pub lazy_validation: bool,
+
+    #[arg(
+        long = "pin",
+        value_delimiter = ',',
+        help = "File or directory that can't be modified through the server, regardless of the write mode (e.g. 'Cargo.lock'). Can be repeated or given as a comma-separated list.\nRelative paths are resolved against the base directory, pinning a directory pins its content.",
+        env = "PINNED_PATHS"
+    )]
+    pub pinned_paths: Vec<String>,
+
+    #[arg(
+        long = "validation-profile",
+        value_enum,
+        default_value_t = ValidationProfile::Strict,
+        help = "How paths are checked against the allowed directories: strict or relaxed. Defaults to strict.\nThe relaxed profile doesn't resolve symbolic links, for trusted environments such as containers whose mounts are the boundary.",
+        env = "VALIDATION_PROFILE"
+    )]
+    pub validation_profile: ValidationProfile,
+
+    #[arg(
+        long = "record",
+        help = "Directory where each session is recorded to a new JSON Lines file, with the requests and their results. Defaults to disabled.\nRecordings are sanitized (file contents are redacted unless 'record-content' is set, media data is omitted and long text truncated) and can be reproduced without a client using the 'replay' subcommand.",
+        env = "RECORD_DIR"
+    )]
+    pub record_dir: Option<String>,
+
+    #[arg(
+        long = "record-content",
+        action = clap::ArgAction::SetTrue,
+        value_parser = clap::value_parser!(bool),
+        requires = "record_dir",
+        help = "Keeps the file contents in the recordings of '--record': the written content and edits in the tool arguments, the text, resources and structured content of the results. Defaults to disabled.\nOtherwise they are replaced with their size, and the tool calls writing or editing files are skipped by 'replay'.",
+        env = "RECORD_CONTENT"
+    )]
+    pub record_content: bool,
+
+    #[arg(
+        long = "no-editorconfig",
+        action = clap::ArgAction::SetTrue,
+        value_parser = clap::value_parser!(bool),
+        help = "Writes and edits files as requested, ignoring the '.editorconfig' files. Defaults to disabled.\nOtherwise the indentation, line endings, final newline and trailing whitespace of the written content follow the '.editorconfig' files within the allowed directories.",
+        env = "NO_EDITORCONFIG"
+    )]
+    pub no_editorconfig: bool,
+
+    #[arg(
+        long = "simulate",
+        action = clap::ArgAction::SetTrue,
+        value_parser = clap::value_parser!(bool),
+        help = "Simulates the mutating tools: they are validated and return their usual results, but their changes are kept in memory and nothing is written to disk. Defaults to disabled.\nImplies write mode. Reads, directory listings, searches and metadata see the simulated changes. Useful for demos and for testing clients safely.",
+        env = "SIMULATE"
+    )]
+    pub simulate: bool,
+
+    #[arg(
+        long = "overlay-dir",
+        help = "Directory where the mutating tools write their changes instead of the allowed directories, until they are applied or discarded with the 'commit_overlay' tool. Defaults to disabled.\nImplies write mode. Reads, directory listings, searches and metadata see the changes of the overlay. Changes not committed are kept in the directory and reloaded when the server restarts.",
+        conflicts_with = "simulate",
+        env = "OVERLAY_DIR"
+    )]
+    pub overlay_dir: Option<String>,
+
+    #[arg(
+        long = "count-errors",
+        action = clap::ArgAction::SetTrue,
+        value_parser = clap::value_parser!(bool),
+        help = "Counts the tool errors in a file of the user state directory, readable only by the user, so the 'debug-info' subcommand can report them. Defaults to disabled.\nThe last error message of each tool is kept, it may contain paths.",
+        env = "COUNT_ERRORS"
+    )]
+    pub count_errors: bool,
+
+    #[arg(
+        long = "cache-directory-sizes",
+        action = clap::ArgAction::SetTrue,
+        value_parser = clap::value_parser!(bool),
+        help = "Caches the sizes computed by calculate_directory_size per directory, until the directory is modified. Defaults to disabled.\nFiles rewritten in place don't modify their directory: their new size is only counted when the tool is called with 'force_refresh'. Enable it for trees whose files are added or replaced rather than rewritten.",
+        env = "CACHE_DIRECTORY_SIZES"
+    )]
+    pub cache_directory_sizes: bool,
+
     #[arg(
         help = "List of directories that are permitted for the operation. It is required when 'enable-roots' is not provided OR client does not support Roots.",
         long_help = concat!("Provide a space-separated list of directories that are permitted for the operation.\nThis list allows multiple directories to be provided.\n\nExample:  ", env!("CARGO_PKG_NAME"), " /path/to/dir1 /path/to/dir2 /path/to/dir3"),
@@ -43,11 +250,60 @@ pub struct CommandArguments {
     )]
     pub allowed_directories: Vec<String>,
 
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     // internal-only field, not exposed as CLI arg
     #[arg(skip)]
     pub disabled_tool_names: Option<Vec<String>>,
 }
 
+#[derive(Subcommand, Debug, Clone, PartialEq, Eq)]
+pub enum Command {
+    /// Prints the version, platform, effective configuration, capabilities and recent tool errors
+    /// as JSON, to be attached to issue reports. Options and directories go before the subcommand.
+    DebugInfo {
+        /// Replaces configured paths with placeholders and omits error messages.
+        #[arg(long)]
+        redact_paths: bool,
+    },
+    /// Calls the tools of a session recorded with `--record` again, without a client, and prints
+    /// how the results compare with the recorded ones as JSON. Options and directories go before
+    /// the subcommand.
+    Replay {
+        /// Recording file of the session.
+        recording: PathBuf,
+        /// Applies the changes of the replayed tools to the allowed directories. Otherwise they are
+        /// simulated and nothing is written, even with '--allow-write'.
+        #[arg(long)]
+        apply: bool,
+    },
+    /// Generates a synthetic directory tree to reproduce the benchmark numbers, see `cargo bench`.
+    #[command(hide = true)]
+    GenerateTree {
+        /// Directory in which the tree is generated, it must be empty or not exist.
+        target_dir: PathBuf,
+        /// Number of files.
+        #[arg(long, default_value_t = 10_000)]
+        files: usize,
+        /// Maximum nesting level of the directories.
+        #[arg(long, default_value_t = 4)]
+        depth: usize,
+        /// Number of subdirectories of each directory.
+        #[arg(long, default_value_t = 8)]
+        fan_out: usize,
+        /// Approximate size of each file in bytes.
+        #[arg(long, default_value_t = 4096)]
+        file_size: usize,
+        /// Percentage of files that are copies of another file.
+        #[arg(long, default_value_t = 10, value_parser = clap::value_parser!(u8).range(0..=100))]
+        duplicate_percent: u8,
+        /// Seed of the generated names and contents, the same seed generates the same tree.
+        #[arg(long, default_value_t = 42)]
+        seed: u64,
+    },
+}
+
 impl CommandArguments {
     pub fn validate(&mut self) -> Result<(), String> {
         if !self.enable_roots && self.allowed_directories.is_empty() {
@@ -57,6 +313,23 @@ impl CommandArguments {
             ));
         }
 
+        // backups must stay inside the allowed directories
+        let backup_dir = std::path::Path::new(&self.backup_dir);
+        if self.backup_dir.trim().is_empty()
+            || !backup_dir
+                .components()
+                .all(|c| matches!(c, std::path::Component::Normal(_)))
+        {
+            return Err(format!(
+                "Invalid backup directory '{}'. `--backup-dir` must be a relative path without '..' segments.",
+                self.backup_dir
+            ));
+        }
+
+        if self.backup_retention == 0 {
+            return Err("`--backup-retention` must be greater than zero.".to_string());
+        }
+
         // verify disable_tools are valid
         if let Some(tools) = self.disable_tools.as_ref() {
             let disabled_tools: Vec<_> = tools
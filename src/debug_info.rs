@@ -0,0 +1,176 @@
+//! Diagnostics printed by the `debug-info` subcommand, to be attached to issue reports.
+//!
+//! With `--count-errors`, tool errors of the server are counted in a small JSON file of the user
+//! state directory, so a separate `debug-info` invocation can report them. The file is only
+//! readable by the user, as error messages may contain paths.
+
+use crate::cli::CommandArguments;
+use crate::fs_service::platform::is_read_only_filesystem;
+use crate::tools::FileSystemTools;
+use chrono::Utc;
+use serde_json::{Map, Value, json};
+use std::{
+    fs::{DirBuilder, OpenOptions},
+    io::{Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+const ERROR_COUNTERS_DIR_NAME: &str = "rust-mcp-filesystem";
+const ERROR_COUNTERS_FILE_NAME: &str = "tool-errors.json";
+
+/// Location of the tool error counters shared between the server and `debug-info`, in the state
+/// directory of the user (e.g. `~/.local/state` on Linux). `None` when the platform has none.
+pub fn error_counters_path() -> Option<PathBuf> {
+    dirs::state_dir().or_else(dirs::data_local_dir).map(|dir| {
+        dir.join(ERROR_COUNTERS_DIR_NAME)
+            .join(ERROR_COUNTERS_FILE_NAME)
+    })
+}
+
+/// Counts a failed call of `tool_name` in the file at `counters_path`, remembering the last error.
+/// Counting is best-effort, failures to update the file are ignored. The file is locked during
+/// the update, as several servers may count their errors at once.
+pub fn record_tool_error(counters_path: &Path, tool_name: &str, error: &str) {
+    let _ = update_error_counters(counters_path, tool_name, error);
+}
+
+fn update_error_counters(
+    counters_path: &Path,
+    tool_name: &str,
+    error: &str,
+) -> std::io::Result<()> {
+    if let Some(parent) = counters_path.parent() {
+        let mut builder = DirBuilder::new();
+        builder.recursive(true);
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder.create(parent)?;
+    }
+    let mut options = OpenOptions::new();
+    options.read(true).write(true).create(true).truncate(false);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    let mut file = options.open(counters_path)?;
+    file.lock()?;
+
+    let mut content = String::new();
+    file.read_to_string(&mut content)?;
+    let mut counters: Map<String, Value> = serde_json::from_str(&content).unwrap_or_default();
+    let entry = counters
+        .entry(tool_name.to_string())
+        .or_insert_with(|| json!({ "count": 0 }));
+    let count = entry["count"].as_u64().unwrap_or_default() + 1;
+    *entry = json!({
+        "count": count,
+        "last_error": error,
+        "last_error_at": Utc::now().to_rfc3339(),
+    });
+
+    file.set_len(0)?;
+    file.seek(SeekFrom::Start(0))?;
+    file.write_all(serde_json::to_string_pretty(&counters)?.as_bytes())
+}
+
+/// Tool error counters recorded in the file at `counters_path`, by tool name.
+pub fn read_error_counters(counters_path: &Path) -> Map<String, Value> {
+    std::fs::read_to_string(counters_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+        .unwrap_or_default()
+}
+
+/// Collects the version, platform, effective configuration and capabilities of the server along
+/// with the recorded tool errors. `validation_error` is the result of validating the arguments.
+/// With `redact_paths`, configured paths are replaced by placeholders and error messages are omitted.
+pub fn debug_info(
+    args: &CommandArguments,
+    validation_error: Option<&str>,
+    redact_paths: bool,
+    counters_path: Option<&Path>,
+) -> Value {
+    let redact = |path: &str, placeholder: String| {
+        if redact_paths {
+            placeholder
+        } else {
+            path.to_string()
+        }
+    };
+
+    let allowed_directories: Vec<Value> = args
+        .allowed_directories
+        .iter()
+        .enumerate()
+        .map(|(index, dir)| {
+            let path = Path::new(dir);
+            json!({
+                "path": redact(dir, format!("<allowed_directory_{}>", index + 1)),
+                "exists": path.exists(),
+                "is_dir": path.is_dir(),
+                "read_only_filesystem": is_read_only_filesystem(path),
+            })
+        })
+        .collect();
+
+    let tools = FileSystemTools::tools();
+    let disabled_tools = args.disabled_tool_names.clone().unwrap_or_default();
+
+    let mut error_counters = counters_path.map(read_error_counters).unwrap_or_default();
+    if redact_paths {
+        for counter in error_counters.values_mut() {
+            if let Some(counter) = counter.as_object_mut() {
+                counter.remove("last_error");
+            }
+        }
+    }
+
+    json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "os": std::env::consts::OS,
+        "os_family": std::env::consts::FAMILY,
+        "arch": std::env::consts::ARCH,
+        "configuration": {
+            "allow_write": args.allow_write,
+            "enable_roots": args.enable_roots,
+            "allowed_directories": allowed_directories,
+            "disabled_tools": disabled_tools,
+            "write_if_exists": args.write_if_exists,
+            "write_line_endings": format!("{:?}", args.write_line_endings).to_lowercase(),
+            "enable_backups": args.enable_backups,
+            "backup_dir": redact(&args.backup_dir, "<backup_dir>".to_string()),
+            "backup_retention": args.backup_retention,
+            "locale": format!("{:?}", args.locale).to_lowercase(),
+            "base_dir": args.base_dir.as_deref().map(|dir| redact(dir, "<base_dir>".to_string())),
+            "io_retries": args.io_retries,
+            "io_retry_backoff_ms": args.io_retry_backoff,
+            "batch_concurrency": args.batch_concurrency,
+            "batch_threads": args.batch_threads,
+            "watchdog_interval_s": args.watchdog_interval,
+            "response_cache_ttl_s": args.response_cache_ttl,
+            "ascii_output": args.ascii_output,
+            "lazy_validation": args.lazy_validation,
+            "validation_profile": format!("{:?}", args.validation_profile).to_lowercase(),
+            "record_dir": args.record_dir.as_deref().map(|dir| redact(dir, "<record_dir>".to_string())),
+            "record_content": args.record_content,
+            "editorconfig": !args.no_editorconfig,
+            "simulate": args.simulate,
+            "overlay_dir": args.overlay_dir.as_deref().map(|dir| redact(dir, "<overlay_dir>".to_string())),
+            "count_errors": args.count_errors,
+            "cache_directory_sizes": args.cache_directory_sizes,
+            "pinned_paths": args
+                .pinned_paths
+                .iter()
+                .enumerate()
+                .map(|(index, path)| redact(path, format!("<pinned_path_{}>", index + 1)))
+                .collect::<Vec<_>>(),
+        },
+        "validation_error": validation_error,
+        "capabilities": {
+            "tools": tools.len(),
+            "enabled_tools": tools.len() - disabled_tools.len(),
+            "finder_tags": cfg!(target_os = "macos"),
+            "extended_attributes": cfg!(any(target_os = "macos", target_os = "linux")),
+            "lock_diagnostics": cfg!(windows),
+        },
+        "tool_errors": error_counters,
+    })
+}
@@ -1,6 +1,19 @@
+//! Errors returned by the filesystem service and the server.
+//!
+//! `ServiceError` is part of the public API of the library. Its variants are `non_exhaustive` so
+//! new failure cases can be added without breaking embedding applications, which should match on
+//! the variants they handle and fall back to `ServiceError::kind` for the others. Messages are
+//! meant for humans and may be localized; use the variant or the kind to identify a failure.
+//! Clients receive the stable `code` of a failed tool call in the `error_code` field of the
+//! `_meta` of the result.
+
 use async_zip::error::ZipError;
-use rust_mcp_sdk::schema::{RpcError, schema_utils::SdkError};
+use rust_mcp_sdk::schema::{
+    CallToolResult, RpcError, TextContent,
+    schema_utils::{CallToolError, SdkError},
+};
 use rust_mcp_sdk::{TransportError, error::McpSdkError};
+use serde_json::{Map, json};
 
 use thiserror::Error;
 use tokio::io;
@@ -8,29 +21,46 @@ use tokio::io;
 pub type ServiceResult<T> = core::result::Result<T, ServiceError>;
 
 #[derive(Debug, Error)]
+#[non_exhaustive]
 pub enum ServiceError {
+    /// A tool that modifies the filesystem was called while the server runs in read-only mode.
     #[error(
         "Service is running in read-only mode. To enable write access, please run with the --allow-write flag."
     )]
     NoWriteAccess,
+    /// A path is outside of the allowed directories, the localized message names the path.
+    #[error("{0}")]
+    AccessDenied(String),
+    /// A path is pinned with `--pin` and can't be modified.
+    #[error("{0}")]
+    PinnedPath(String),
+    /// A path is on a filesystem mounted read-only.
+    #[error("{0}")]
+    ReadOnlyFilesystem(String),
+    /// The configuration of the server, e.g. the allowed directories, is invalid.
     #[error("{0}")]
     InvalidConfig(String),
+    /// Any other failure, described by its message only.
     #[error("{0}")]
     FromString(String),
     #[error("{0}")]
     TransportError(#[from] TransportError),
     #[error("{0}")]
     SdkError(#[from] SdkError),
+    /// The client sent an invalid request, or a request to the client failed.
     #[error("{0}")]
     RpcError(#[from] RpcError),
+    /// A filesystem operation failed, inspect the `io::ErrorKind` for the cause.
     #[error("{0}")]
     IoError(#[from] io::Error),
     #[error("{0}")]
     SerdeJsonError(#[from] serde_json::Error),
+    /// The content search query is not a valid regular expression.
     #[error("{0}")]
     ContentSearchError(#[from] grep::regex::Error),
     #[error("{0}")]
     McpSdkError(#[from] McpSdkError),
+    /// Reading or writing a zip archive failed.
     #[error("{0}")]
     ZipError(#[from] ZipError),
     // #[error("{0}")]
@@ -41,4 +71,152 @@ pub enum ServiceError {
     FileTooSmall(usize),
     #[error("The file is either not an image/audio type or is unsupported (mime:{0}).")]
     InvalidMediaFile(String),
+    /// The operation was interrupted because the server is shutting down, e.g. the client closed
+    /// the transport. Its partial output was removed.
+    #[error("The operation was cancelled because the server is shutting down.")]
+    Cancelled,
+}
+
+/// Category of a `ServiceError`, for callers that handle failures by category.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
+    /// The path is outside of the allowed directories.
+    AccessDenied,
+    /// The path can't be modified: read-only mode, pinned path or read-only filesystem.
+    NotWritable,
+    /// The file or directory doesn't exist.
+    NotFound,
+    /// The file or directory already exists.
+    AlreadyExists,
+    /// The operating system denied the operation.
+    PermissionDenied,
+    /// The arguments or the configuration are invalid.
+    InvalidInput,
+    /// The file is too large, too small or of an unsupported type.
+    UnsupportedFile,
+    /// Any other filesystem or archive failure.
+    Io,
+    /// A failure of the MCP transport or protocol.
+    Protocol,
+    /// The operation was cancelled by the shutdown of the server.
+    Cancelled,
+    /// A failure described by its message only.
+    Other,
+}
+
+impl ServiceError {
+    /// The category of the error.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ServiceError::AccessDenied(_) => ErrorKind::AccessDenied,
+            ServiceError::NoWriteAccess
+            | ServiceError::PinnedPath(_)
+            | ServiceError::ReadOnlyFilesystem(_) => ErrorKind::NotWritable,
+            ServiceError::IoError(err) => match err.kind() {
+                io::ErrorKind::NotFound => ErrorKind::NotFound,
+                io::ErrorKind::AlreadyExists => ErrorKind::AlreadyExists,
+                io::ErrorKind::PermissionDenied => ErrorKind::PermissionDenied,
+                io::ErrorKind::ReadOnlyFilesystem => ErrorKind::NotWritable,
+                io::ErrorKind::InvalidInput | io::ErrorKind::InvalidFilename => {
+                    ErrorKind::InvalidInput
+                }
+                _ => ErrorKind::Io,
+            },
+            ServiceError::InvalidConfig(_)
+            | ServiceError::SerdeJsonError(_)
+            | ServiceError::ContentSearchError(_) => ErrorKind::InvalidInput,
+            ServiceError::FileTooLarge(_)
+            | ServiceError::FileTooSmall(_)
+            | ServiceError::InvalidMediaFile(_) => ErrorKind::UnsupportedFile,
+            ServiceError::ZipError(_) => ErrorKind::Io,
+            ServiceError::TransportError(_)
+            | ServiceError::SdkError(_)
+            | ServiceError::RpcError(_)
+            | ServiceError::McpSdkError(_) => ErrorKind::Protocol,
+            ServiceError::FromString(_) => ErrorKind::Other,
+            ServiceError::Cancelled => ErrorKind::Cancelled,
+        }
+    }
+
+    /// Stable identifier of the error, identical in all locales. Errors with a localized message
+    /// have the code of that message (see `Message::code`), others the code of their kind.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ServiceError::NoWriteAccess => "no_write_access",
+            ServiceError::AccessDenied(_) => "access_denied",
+            ServiceError::PinnedPath(_) => "pinned_path",
+            ServiceError::ReadOnlyFilesystem(_) => "read_only_filesystem",
+            _ => self.kind().code(),
+        }
+    }
+}
+
+impl ErrorKind {
+    /// Stable identifier of the kind.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ErrorKind::AccessDenied => "access_denied",
+            ErrorKind::NotWritable => "not_writable",
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::AlreadyExists => "already_exists",
+            ErrorKind::PermissionDenied => "permission_denied",
+            ErrorKind::InvalidInput => "invalid_input",
+            ErrorKind::UnsupportedFile => "unsupported_file",
+            ErrorKind::Io => "io",
+            ErrorKind::Protocol => "protocol",
+            ErrorKind::Cancelled => "cancelled",
+            ErrorKind::Other => "other",
+        }
+    }
+}
+
+/// The error of a tool call as returned to the client: its message and stable code.
+#[derive(Debug, Error)]
+#[error("{message}")]
+pub struct ToolError {
+    pub code: &'static str,
+    pub message: String,
+}
+
+impl ToolError {
+    /// The error of a tool call, with the code of the `ServiceError` or `ToolError` it wraps,
+    /// `other` for errors described by their message only.
+    pub fn from_call_error(err: &CallToolError) -> Self {
+        let code = if let Some(err) = err.0.downcast_ref::<ToolError>() {
+            err.code
+        } else if let Some(err) = err.0.downcast_ref::<ServiceError>() {
+            err.code()
+        } else {
+            ErrorKind::Other.code()
+        };
+        Self {
+            code,
+            message: err.to_string(),
+        }
+    }
+
+    /// The result of the failed tool call, with the code in the `error_code` field of its `_meta`.
+    pub fn into_result(self) -> CallToolResult {
+        let mut meta = Map::new();
+        meta.insert("error_code".to_string(), json!(self.code));
+        CallToolResult {
+            content: vec![TextContent::new(self.message, None, None).into()],
+            is_error: Some(true),
+            meta: Some(meta),
+            structured_content: None,
+        }
+    }
+}
+
+impl From<walkdir::Error> for ServiceError {
+    fn from(err: walkdir::Error) -> Self {
+        ServiceError::IoError(err.into())
+    }
+}
+
+impl From<std::path::StripPrefixError> for ServiceError {
+    fn from(err: std::path::StripPrefixError) -> Self {
+        ServiceError::IoError(io::Error::new(io::ErrorKind::InvalidInput, err))
+    }
 }
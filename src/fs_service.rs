@@ -1,9 +1,45 @@
 mod archive;
+mod confirmation;
 mod core;
+mod editorconfig;
+mod executors;
+pub mod ignore;
 mod io;
+mod locks;
+mod path_limits;
+pub mod platform;
+mod resolve;
+mod retry;
 mod search;
+mod simulation;
+mod symlinks;
 pub mod utils;
+mod watchdog;
 
-pub use core::FileSystemService;
-pub use io::FileInfo;
-pub use search::FileSearchResult;
+pub use archive::{
+    entry::ArchiveEntryContent,
+    unzip::{OverwrittenFile, SkippedZipEntry, UnzipReport},
+    zip::ZipProgress,
+};
+pub use confirmation::{CONFIRMATION_TOKEN_LIFETIME, TwoPhaseOutcome};
+pub use core::{AllowedDirectoriesDelta, FileSystemService};
+pub use editorconfig::EditorConfigRules;
+pub use executors::BatchLimits;
+pub use io::{
+    BackupConfig, BackupEntry, FileInfo, FilePreview, FileProfile, FileSimilarity,
+    FileWriteOutcome, FinderMetadata, FinderTag, FinderTagColor, FinderTagsUpdate, Indentation,
+    MAX_COMPARED_FILES, SimilarityReport, WhitespaceCleanup, detect_file_profile,
+};
+pub use locks::{
+    FileLockState, LockHolder, RECENT_MODIFICATION_WINDOW, describe_lock_violation,
+    is_lock_violation, lock_holders,
+};
+pub use path_limits::{PathLengthIssue, PathLengthReport, PathPlatform, check_path_length};
+pub use resolve::{PROJECT_MARKERS, ProjectKind, ProjectRoot, ResolvedPath};
+pub use retry::{RetryPolicy, is_transient_error};
+pub use search::{
+    DEPENDENCY_MANIFESTS, DependencyManifest, Ecosystem, EcosystemManifests, ExtensionUsage,
+    FileAge, FileSearchResult, FileTypeMismatch, LanguageLines, RelevantFile, TimelineBucket,
+};
+pub use simulation::{FileChangeStatus, OverlayChange, TreeEntry, WorkspaceFileChange};
+pub use watchdog::DirectoryAvailabilityChange;
@@ -1,2 +1,3 @@
+pub mod entry;
 pub mod unzip;
 pub mod zip;
@@ -0,0 +1,82 @@
+use crate::{
+    error::{ServiceError, ServiceResult},
+    fs_service::FileSystemService,
+};
+use async_zip::tokio::read::seek::ZipFileReader;
+use futures::AsyncReadExt;
+use std::path::Path;
+use tokio::{fs::File, io::BufReader};
+
+/// Content of a single entry read from an archive.
+#[derive(Debug)]
+pub struct ArchiveEntryContent {
+    /// Name of the entry within the archive.
+    pub name: String,
+    /// Uncompressed size of the entry.
+    pub size: u64,
+    /// MIME type detected from the content, if it could be determined.
+    pub mime_type: Option<String>,
+    /// Uncompressed content of the entry.
+    pub content: Vec<u8>,
+}
+
+impl FileSystemService {
+    /// Reads the content of the entry named `entry_name` from `zip_file` without extracting it to disk.
+    /// Entries larger than `max_bytes` are rejected.
+    pub async fn read_archive_entry(
+        &self,
+        zip_file: &Path,
+        entry_name: &str,
+        max_bytes: usize,
+    ) -> ServiceResult<ArchiveEntryContent> {
+        let allowed_directories = self.allowed_directories().await;
+        let zip_file = self.validate_path(zip_file, allowed_directories)?;
+
+        let file = BufReader::new(File::open(&zip_file).await?);
+        let mut zip = ZipFileReader::with_tokio(file).await?;
+
+        let index = zip
+            .file()
+            .entries()
+            .iter()
+            .position(|entry| {
+                entry
+                    .filename()
+                    .as_str()
+                    .is_ok_and(|name| name == entry_name)
+            })
+            .ok_or(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!("Entry '{entry_name}' does not exist in the archive."),
+            ))?;
+
+        let entry = &zip.file().entries()[index];
+        if entry.dir()? {
+            return Err(ServiceError::FromString(format!(
+                "Entry '{entry_name}' is a directory."
+            )));
+        }
+        let size = entry.uncompressed_size();
+        if size > max_bytes as u64 {
+            return Err(ServiceError::FileTooLarge(max_bytes));
+        }
+
+        // the declared size can't be trusted, the content is read up to the limit regardless
+        let mut content = Vec::with_capacity(size as usize);
+        zip.reader_with_entry(index)
+            .await?
+            .take(max_bytes as u64 + 1)
+            .read_to_end(&mut content)
+            .await?;
+        if content.len() > max_bytes {
+            return Err(ServiceError::FileTooLarge(max_bytes));
+        }
+
+        Ok(ArchiveEntryContent {
+            name: entry_name.to_string(),
+            size: content.len() as u64,
+            mime_type: infer::get(&content).map(|kind| kind.mime_type().to_string()),
+            content,
+        })
+    }
+}
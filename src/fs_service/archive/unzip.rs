@@ -1,19 +1,157 @@
-use crate::{error::ServiceResult, fs_service::FileSystemService};
-use async_zip::tokio::read::seek::ZipFileReader;
-use std::path::Path;
+use crate::{
+    error::{ServiceError, ServiceResult},
+    fs_service::{
+        FileSystemService,
+        utils::{clean_path, format_bytes},
+    },
+    i18n::{Locale, Message},
+};
+use async_zip::{error::ZipError, tokio::read::seek::ZipFileReader};
+use std::{
+    collections::BTreeMap,
+    io::Cursor,
+    path::{Component, Path, PathBuf},
+    sync::Arc,
+};
 use tokio::{
     fs::File,
-    io::{AsyncWriteExt, BufReader},
+    io::{AsyncBufRead, AsyncSeek, AsyncWriteExt, BufReader},
 };
 use tokio_util::compat::FuturesAsyncReadCompatExt;
 
+/// An entry of an archive that couldn't be extracted.
+#[derive(Debug, Clone, PartialEq, Eq, ::serde::Serialize)]
+pub struct SkippedZipEntry {
+    /// Name of the entry in the archive.
+    pub entry: String,
+    pub error: String,
+}
+
+/// Number of top-level entries listed in the summary of an extraction.
+const MAX_SUMMARY_ENTRIES: usize = 20;
+
+/// A file or directory extracted at the root of the target directory.
+#[derive(Debug, Clone, PartialEq, Eq, ::serde::Serialize)]
+pub struct ExtractedTopLevelEntry {
+    pub name: String,
+    pub is_directory: bool,
+    /// Number of files extracted in the entry, 1 for a file.
+    pub files: usize,
+    /// Total size of the files extracted in the entry.
+    pub bytes: u64,
+}
+
+/// An existing file that the extraction of an archive would replace.
+#[derive(Debug, Clone, PartialEq, Eq, ::serde::Serialize)]
+pub struct OverwrittenFile {
+    /// Path of the file, relative to the target directory.
+    pub path: PathBuf,
+    /// Current size of the file.
+    pub size: u64,
+    /// Size of the file in the archive.
+    pub new_size: u64,
+}
+
+/// Outcome of the extraction of an archive.
+#[derive(Debug, Clone, Default, PartialEq, Eq, ::serde::Serialize)]
+pub struct UnzipReport {
+    pub target_dir: PathBuf,
+    /// Number of extracted files.
+    pub files: usize,
+    /// Number of extracted directory entries.
+    pub directories: usize,
+    /// Total size of the extracted files.
+    pub bytes: u64,
+    /// Entries extracted at the root of the target directory, sorted by name.
+    pub top_level: Vec<ExtractedTopLevelEntry>,
+    /// Entries skipped because they are unreadable or corrupt, with `continue_on_error` only.
+    pub skipped: Vec<SkippedZipEntry>,
+}
+
+impl UnzipReport {
+    /// Summary of the extraction, listing the top-level entries and the skipped entries.
+    pub fn message(&self, locale: Locale) -> String {
+        let mut message = Message::FilesExtracted {
+            files: self.files,
+            directories: self.directories,
+            size: &format_bytes(self.bytes),
+            target_dir: &self.target_dir.display().to_string(),
+        }
+        .localize(locale);
+        if !self.top_level.is_empty() {
+            message.push_str("\nTop-level entries:");
+            for entry in self.top_level.iter().take(MAX_SUMMARY_ENTRIES) {
+                if entry.is_directory {
+                    message.push_str(&format!(
+                        "\n  {}/ ({} {}, {})",
+                        entry.name,
+                        entry.files,
+                        if entry.files == 1 { "file" } else { "files" },
+                        format_bytes(entry.bytes)
+                    ));
+                } else {
+                    message.push_str(&format!(
+                        "\n  {} ({})",
+                        entry.name,
+                        format_bytes(entry.bytes)
+                    ));
+                }
+            }
+            if self.top_level.len() > MAX_SUMMARY_ENTRIES {
+                message.push_str(&format!(
+                    "\n  ... and {} more",
+                    self.top_level.len() - MAX_SUMMARY_ENTRIES
+                ));
+            }
+        }
+        if !self.skipped.is_empty() {
+            message.push_str(&format!(
+                "\nSkipped {} unreadable {}:",
+                self.skipped.len(),
+                if self.skipped.len() == 1 {
+                    "entry"
+                } else {
+                    "entries"
+                }
+            ));
+            for skipped in &self.skipped {
+                message.push_str(&format!("\n  {}: {}", skipped.entry, skipped.error));
+            }
+        }
+        message
+    }
+}
+
 impl FileSystemService {
     pub async fn unzip_file(&self, zip_file: &str, target_dir: &str) -> ServiceResult<String> {
+        Ok(self
+            .unzip_file_with_report(zip_file, target_dir, false, false)
+            .await?
+            .message(self.locale()))
+    }
+
+    /// Extracts `zip_file` into `target_dir`, which must not exist yet unless `merge` is set, in
+    /// which case the archive is extracted into the existing directory, replacing the files of the
+    /// same paths (see `unzip_overwrites`).
+    /// By default the extraction stops at the first entry that can't be extracted. With
+    /// `continue_on_error`, unreadable or corrupt entries are skipped and listed in the report, the
+    /// extraction fails only if no entry could be extracted. The target directory is removed when
+    /// the extraction fails, instead of leaving a partially extracted tree behind, unless it
+    /// existed before: the entries extracted until then are kept, and a file is only replaced once
+    /// its entry is fully extracted.
+    pub async fn unzip_file_with_report(
+        &self,
+        zip_file: &str,
+        target_dir: &str,
+        continue_on_error: bool,
+        merge: bool,
+    ) -> ServiceResult<UnzipReport> {
         let allowed_directories = self.allowed_directories().await;
 
         let zip_file = self.validate_path(Path::new(&zip_file), allowed_directories.clone())?;
         let target_dir_path = self.validate_path(Path::new(target_dir), allowed_directories)?;
-        if !zip_file.exists() {
+        self.ensure_modifiable(&target_dir_path).await?;
+        if !self.path_exists(&zip_file) {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::NotFound,
                 "Zip file does not exists.",
@@ -21,43 +159,363 @@ impl FileSystemService {
             .into());
         }
 
-        if target_dir_path.exists() {
+        let target_exists = self.path_exists(&target_dir_path);
+        if target_exists && !merge {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::AlreadyExists,
                 format!("'{target_dir}' directory already exists!"),
             )
             .into());
         }
+        let target_is_dir = match self.simulated_content(&target_dir_path) {
+            Some(content) => {
+                content.is_err_and(|err| err.kind() == std::io::ErrorKind::IsADirectory)
+            }
+            None => target_dir_path.is_dir(),
+        };
+        if target_exists && !target_is_dir {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotADirectory,
+                format!("'{target_dir}' is not a directory."),
+            )
+            .into());
+        }
+
+        // an archive created by the simulation is read from memory
+        match self.simulated_content(&zip_file) {
+            Some(content) => {
+                let zip = ZipFileReader::with_tokio(Cursor::new(content?)).await?;
+                self.extract_zip(zip, target_dir_path, continue_on_error, !target_exists)
+                    .await
+            }
+            None => {
+                let file = BufReader::new(File::open(zip_file).await?);
+                let zip = ZipFileReader::with_tokio(file).await?;
+                self.extract_zip(zip, target_dir_path, continue_on_error, !target_exists)
+                    .await
+            }
+        }
+    }
+
+    /// Lists the existing files that extracting `zip_file` into `target_dir` would replace,
+    /// sorted by path, without extracting anything.
+    pub async fn unzip_overwrites(
+        &self,
+        zip_file: &str,
+        target_dir: &str,
+    ) -> ServiceResult<Vec<OverwrittenFile>> {
+        let allowed_directories = self.allowed_directories().await;
+        let zip_file = self.validate_path(Path::new(&zip_file), allowed_directories.clone())?;
+        let target_dir_path =
+            self.validate_path(Path::new(target_dir), allowed_directories.clone())?;
+
+        let entries = match self.simulated_content(&zip_file) {
+            Some(content) => {
+                let zip = ZipFileReader::with_tokio(Cursor::new(content?)).await?;
+                file_entries(&zip)
+            }
+            None => {
+                let file = BufReader::new(File::open(zip_file).await?);
+                let zip = ZipFileReader::with_tokio(file).await?;
+                file_entries(&zip)
+            }
+        };
+
+        // the entries that can't be extracted replace nothing
+        let mut overwritten: Vec<OverwrittenFile> = vec![];
+        for (name, new_size) in entries {
+            let Ok((entry_path, path)) = self
+                .entry_target(&target_dir_path, &name, allowed_directories.clone())
+                .await
+            else {
+                continue;
+            };
+            let size = match self.simulated_content(&entry_path) {
+                Some(Ok(content)) => content.len() as u64,
+                Some(Err(_)) => continue,
+                None => match std::fs::symlink_metadata(&entry_path) {
+                    Ok(metadata) if metadata.is_file() => metadata.len(),
+                    _ => continue,
+                },
+            };
+            overwritten.push(OverwrittenFile {
+                path,
+                size,
+                new_size,
+            });
+        }
+        overwritten.sort_by(|a, b| a.path.cmp(&b.path));
+        overwritten.dedup_by(|a, b| a.path == b.path);
+        Ok(overwritten)
+    }
+
+    async fn extract_zip<R>(
+        &self,
+        mut zip: ZipFileReader<R>,
+        target_dir_path: PathBuf,
+        continue_on_error: bool,
+        created_target_dir: bool,
+    ) -> ServiceResult<UnzipReport>
+    where
+        R: AsyncBufRead + AsyncSeek + Unpin,
+    {
+        let mut report = UnzipReport {
+            target_dir: target_dir_path.clone(),
+            ..Default::default()
+        };
+        let entry_count = zip.file().entries().len();
+        let allowed_directories = self.allowed_directories().await;
+        let mut top_level: BTreeMap<String, ExtractedTopLevelEntry> = BTreeMap::new();
+
+        for index in 0..entry_count {
+            if let Err(err) = self.check_cancelled() {
+                if created_target_dir {
+                    self.remove_target_dir(&target_dir_path).await;
+                }
+                return Err(err);
+            }
+            match self
+                .extract_zip_entry(
+                    &mut zip,
+                    index,
+                    &target_dir_path,
+                    allowed_directories.clone(),
+                )
+                .await
+            {
+                Ok(extracted) => {
+                    match extracted.size {
+                        Some(size) => {
+                            report.files += 1;
+                            report.bytes += size;
+                        }
+                        None => report.directories += 1,
+                    }
+                    if let Some((name, is_directory)) = top_level_name(&extracted) {
+                        let entry =
+                            top_level
+                                .entry(name.clone())
+                                .or_insert(ExtractedTopLevelEntry {
+                                    name,
+                                    is_directory,
+                                    files: 0,
+                                    bytes: 0,
+                                });
+                        entry.is_directory |= is_directory;
+                        if let Some(size) = extracted.size {
+                            entry.files += 1;
+                            entry.bytes += size;
+                        }
+                    }
+                }
+                Err(err) if continue_on_error => {
+                    let entry = zip.file().entries()[index]
+                        .filename()
+                        .as_str()
+                        .map_or_else(|_| format!("#{index}"), str::to_string);
+                    report.skipped.push(SkippedZipEntry {
+                        entry,
+                        error: err.to_string(),
+                    });
+                }
+                Err(err) => {
+                    if created_target_dir {
+                        self.remove_target_dir(&target_dir_path).await;
+                    }
+                    return Err(err);
+                }
+            }
+        }
+
+        if !report.skipped.is_empty() && report.files + report.directories == 0 {
+            if created_target_dir {
+                self.remove_target_dir(&target_dir_path).await;
+            }
+            return Err(ServiceError::FromString(format!(
+                "None of the {} entries of the archive could be extracted.{}",
+                report.skipped.len(),
+                report
+                    .skipped
+                    .iter()
+                    .map(|skipped| format!("\n  {}: {}", skipped.entry, skipped.error))
+                    .collect::<String>()
+            )));
+        }
+
+        report.top_level = top_level.into_values().collect();
+        Ok(report)
+    }
+
+    /// Resolves the path the entry `name` is extracted to within `target_dir`, and the path
+    /// relative to it. Entries leading outside of the target directory, directly or through a
+    /// symbolic link of the existing tree, and entries replacing a file that can't be modified
+    /// are refused.
+    async fn entry_target(
+        &self,
+        target_dir: &Path,
+        name: &str,
+        allowed_directories: Arc<Vec<PathBuf>>,
+    ) -> ServiceResult<(PathBuf, PathBuf)> {
+        let entry_path = clean_path(&target_dir.join(name));
+        // entries named e.g. '../file' would be written outside of the target directory
+        let Ok(relative_path) = entry_path.strip_prefix(target_dir).map(Path::to_path_buf) else {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidData,
+                "The entry path points outside of the target directory.",
+            )
+            .into());
+        };
+
+        // writes would follow the links of the existing tree, e.g. when merging
+        let mut existing_path = target_dir.to_path_buf();
+        for component in relative_path.components() {
+            existing_path.push(component);
+            match std::fs::symlink_metadata(&existing_path) {
+                Ok(metadata) if metadata.file_type().is_symlink() => {
+                    return Err(std::io::Error::new(
+                        std::io::ErrorKind::InvalidData,
+                        format!(
+                            "The entry path goes through the symbolic link '{}'.",
+                            existing_path.display()
+                        ),
+                    )
+                    .into());
+                }
+                Ok(_) => {}
+                Err(_) => break,
+            }
+        }
 
-        let file = BufReader::new(File::open(zip_file).await?);
-        let mut zip = ZipFileReader::with_tokio(file).await?;
+        let entry_path = self.validate_path(&entry_path, allowed_directories)?;
+        self.ensure_modifiable(&entry_path).await?;
+        Ok((entry_path, relative_path))
+    }
 
-        let file_count = zip.file().entries().len();
+    /// Extracts the entry at `index` into `target_dir`. A file that fails to extract is removed.
+    async fn extract_zip_entry<R>(
+        &self,
+        zip: &mut ZipFileReader<R>,
+        index: usize,
+        target_dir: &Path,
+        allowed_directories: Arc<Vec<PathBuf>>,
+    ) -> ServiceResult<ExtractedEntry>
+    where
+        R: AsyncBufRead + AsyncSeek + Unpin,
+    {
+        let entry = zip.file().entries()[index].clone();
+        let (entry_path, relative_path) = self
+            .entry_target(target_dir, entry.filename().as_str()?, allowed_directories)
+            .await?;
 
-        for index in 0..file_count {
-            let entry = zip.file().entries().get(index).unwrap();
-            let entry_path = target_dir_path.join(entry.filename().as_str()?);
-            // Ensure the parent directory exists
-            if let Some(parent) = entry_path.parent() {
-                tokio::fs::create_dir_all(parent).await?;
+        // Directory entries have no content, they are recreated so that empty directories are preserved
+        if entry.dir()? {
+            if !self.simulate_create_dir(&entry_path) {
+                tokio::fs::create_dir_all(&entry_path).await?;
             }
+            return Ok(ExtractedEntry {
+                relative_path,
+                size: None,
+            });
+        }
 
-            // Extract the file
-            let reader = zip.reader_without_entry(index).await?;
-            let mut compat_reader = reader.compat();
-            let mut output_file = File::create(&entry_path).await?;
+        // Ensure the parent directory exists
+        if let Some(parent) = entry_path.parent()
+            && !self.simulate_create_dir(parent)
+        {
+            tokio::fs::create_dir_all(parent).await?;
+        }
 
-            tokio::io::copy(&mut compat_reader, &mut output_file).await?;
-            output_file.flush().await?;
+        // An existing file, when merging into an existing directory, is extracted next to it and
+        // only replaced once its content is complete
+        let replaced = !self.is_simulation() && entry_path.is_file();
+        if replaced {
+            self.backup_file(&entry_path).await?;
         }
+        let output_path = if replaced {
+            partial_path(&entry_path)
+        } else {
+            entry_path.clone()
+        };
 
-        let result_message = format!(
-            "Successfully extracted {} {} into '{}'.",
-            file_count,
-            if file_count == 1 { "file" } else { "files" },
-            target_dir_path.display()
-        );
+        // Extract the file, checking its content against the CRC-32 of the entry
+        let extracted = async {
+            let mut reader = zip.reader_without_entry(index).await?;
+            let mut content = vec![];
+            let size = if self.is_simulation() {
+                tokio::io::copy(&mut (&mut reader).compat(), &mut content).await?
+            } else {
+                let mut output_file = File::create(&output_path).await?;
+                let size = tokio::io::copy(&mut (&mut reader).compat(), &mut output_file).await?;
+                output_file.flush().await?;
+                size
+            };
+            if reader.compute_hash() != entry.crc32() {
+                return Err(ZipError::CRC32CheckError.into());
+            }
+            if replaced {
+                tokio::fs::rename(&output_path, &entry_path).await?;
+            }
+            self.simulate_write(&entry_path, &content)?;
+            ServiceResult::Ok(size)
+        }
+        .await;
+        if extracted.is_err() && !self.is_simulation() {
+            let _ = tokio::fs::remove_file(&output_path).await;
+        }
+        extracted.map(|size| ExtractedEntry {
+            relative_path,
+            size: Some(size),
+        })
+    }
 
-        Ok(result_message)
+    /// Removes the partially extracted target directory, created by the extraction.
+    async fn remove_target_dir(&self, target_dir: &Path) {
+        if !self.simulate_remove(target_dir) {
+            let _ = tokio::fs::remove_dir_all(target_dir).await;
+        }
     }
 }
+
+/// An entry written by the extraction.
+struct ExtractedEntry {
+    /// Path of the entry, relative to the target directory.
+    relative_path: PathBuf,
+    /// Size of the file, `None` for a directory.
+    size: Option<u64>,
+}
+
+/// Names and sizes of the file entries of `zip`, skipping the entries with an invalid name.
+fn file_entries<R>(zip: &ZipFileReader<R>) -> Vec<(String, u64)>
+where
+    R: AsyncBufRead + AsyncSeek + Unpin,
+{
+    zip.file()
+        .entries()
+        .iter()
+        .filter(|entry| entry.dir().is_ok_and(|is_dir| !is_dir))
+        .filter_map(|entry| {
+            let name = entry.filename().as_str().ok()?;
+            Some((name.to_string(), entry.uncompressed_size()))
+        })
+        .collect()
+}
+
+/// Path an entry replacing the existing file at `path` is extracted to, until it is complete.
+fn partial_path(path: &Path) -> PathBuf {
+    let mut file_name = path.file_name().unwrap_or_default().to_os_string();
+    file_name.push(".unzip-partial");
+    path.with_file_name(file_name)
+}
+
+/// Name of the top-level entry containing `extracted`, and whether it is a directory.
+fn top_level_name(extracted: &ExtractedEntry) -> Option<(String, bool)> {
+    let mut components = extracted
+        .relative_path
+        .components()
+        .filter(|component| matches!(component, Component::Normal(_)));
+    let name = components.next()?.as_os_str().to_string_lossy().to_string();
+    Some((
+        name,
+        extracted.size.is_none() || components.next().is_some(),
+    ))
+}
@@ -1,23 +1,249 @@
 use crate::{
-    error::ServiceResult,
+    error::{ServiceError, ServiceResult},
     fs_service::{
         FileSystemService,
-        utils::{format_bytes, write_zip_entry},
+        ignore::IgnoreRules,
+        utils::{
+            compress_zip_entry, format_bytes, matches_exclude_patterns, write_zip_entry,
+            zip_directory_entry,
+        },
     },
+    i18n::Message,
 };
+use async_zip::ZipEntry;
 use async_zip::tokio::write::ZipFileWriter;
 use glob_match::glob_match;
-use std::path::Path;
-use tokio::fs::File;
-use tokio_util::compat::TokioAsyncReadCompatExt;
+use sha2::{Digest, Sha256};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use tokio::{
+    fs::File,
+    io::AsyncWrite,
+    sync::{mpsc, oneshot},
+};
+use tokio_util::compat::TokioAsyncWriteCompatExt;
 use walkdir::WalkDir;
 
+/// Progress of a running `zip_directory` operation.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ZipProgress {
+    /// Number of files added to the archive so far.
+    pub files: usize,
+    /// Uncompressed size of the files added so far.
+    pub bytes: u64,
+}
+
+type CompressedEntry = (ZipEntry, Vec<u8>);
+
+/// Number of input directories whose last archive is remembered for reuse.
+const ZIP_ARCHIVE_CACHE_CAPACITY: usize = 64;
+
+/// The last archive created from an input directory, reused while the directory is unchanged.
+#[derive(Debug, Clone)]
+pub(crate) struct ZipArchiveRecord {
+    /// Hash of the options and of the path, size and modification time of each archived entry.
+    fingerprint: Vec<u8>,
+    archive: PathBuf,
+    /// Size and modification time of the archive when it was created, to detect later changes.
+    archive_len: u64,
+    archive_modified: Option<SystemTime>,
+    files: usize,
+    empty_dirs: usize,
+    created_at: SystemTime,
+}
+
+/// Archives created by `zip_directory`, by canonical input directory.
+pub(crate) type ZipArchiveCache = HashMap<PathBuf, ZipArchiveRecord>;
+
+/// Where an archive is written: its target file, or memory in simulation mode.
+enum ArchiveSink {
+    File(File),
+    Memory(Vec<u8>),
+}
+
+impl AsyncWrite for ArchiveSink {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        match self.get_mut() {
+            ArchiveSink::File(file) => Pin::new(file).poll_write(cx, buf),
+            ArchiveSink::Memory(content) => Pin::new(content).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            ArchiveSink::File(file) => Pin::new(file).poll_flush(cx),
+            ArchiveSink::Memory(content) => Pin::new(content).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            ArchiveSink::File(file) => Pin::new(file).poll_shutdown(cx),
+            ArchiveSink::Memory(content) => Pin::new(content).poll_shutdown(cx),
+        }
+    }
+}
+
+/// A file or empty directory selected to be added to an archive.
+struct ZipEntrySource {
+    name: String,
+    path: PathBuf,
+    is_empty_dir: bool,
+}
+
+/// Which entries of an input directory are added to an archive.
+struct ZipSelection<'a> {
+    input_dir: &'a Path,
+    input_dir_str: &'a str,
+    glob_pattern: &'a str,
+    exclude_patterns: &'a [String],
+    ignore_rules: &'a IgnoreRules,
+    allowed_directories: &'a Arc<Vec<PathBuf>>,
+    include_empty_dirs: bool,
+    deterministic: bool,
+}
+
+impl ZipSelection<'_> {
+    /// The entries to archive in walk order, `is_target_archive` recognizes the archive being written.
+    fn entries<'s>(
+        &'s self,
+        service: &'s FileSystemService,
+        is_target_archive: impl Fn(&Path) -> bool + 's,
+    ) -> impl Iterator<Item = ServiceResult<ZipEntrySource>> + 's {
+        let mut walker = WalkDir::new(self.input_dir).follow_links(true);
+        if self.deterministic {
+            walker = walker.sort_by_file_name();
+        }
+        walker
+            .into_iter()
+            .filter_entry(|dir_entry| {
+                if dir_entry.depth() == 0 {
+                    return true;
+                }
+                let relative_path = dir_entry
+                    .path()
+                    .strip_prefix(self.input_dir)
+                    .unwrap_or(dir_entry.path());
+                !matches_exclude_patterns(relative_path, self.exclude_patterns)
+                    && !self
+                        .ignore_rules
+                        .is_ignored(dir_entry.path(), dir_entry.file_type().is_dir())
+            })
+            .filter_map(|entry| entry.ok())
+            .filter_map(move |entry| {
+                let entry_path_buf = service
+                    .validate_path(entry.path(), self.allowed_directories.clone())
+                    .ok()?;
+
+                let is_empty_dir = entry_path_buf.is_dir()
+                    && self.include_empty_dirs
+                    && std::fs::read_dir(&entry_path_buf)
+                        .is_ok_and(|mut dir_entries| dir_entries.next().is_none());
+
+                if entry_path_buf == self.input_dir
+                    || is_target_archive(&entry_path_buf)
+                    || (entry_path_buf.is_dir() && !is_empty_dir)
+                    || !glob_match(
+                        self.glob_pattern,
+                        entry_path_buf.display().to_string().as_ref(),
+                    )
+                {
+                    return None;
+                }
+                Some(self.entry_source(entry_path_buf, is_empty_dir))
+            })
+    }
+
+    fn entry_source(
+        &self,
+        entry_path_buf: PathBuf,
+        is_empty_dir: bool,
+    ) -> ServiceResult<ZipEntrySource> {
+        let entry_str = entry_path_buf
+            .as_os_str()
+            .to_str()
+            .ok_or(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Invalid UTF-8 in file name",
+            ))?;
+
+        if !entry_str.starts_with(self.input_dir_str) {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "Entry file path does not start with base input directory path.",
+            )
+            .into());
+        }
+
+        Ok(ZipEntrySource {
+            name: entry_str[self.input_dir_str.len() + 1..].to_string(),
+            path: entry_path_buf,
+            is_empty_dir,
+        })
+    }
+
+    /// Hashes the options and the name, size and modification time of `entries`, in name order.
+    fn fingerprint(&self, mut entries: Vec<(String, u64, Option<SystemTime>)>) -> Vec<u8> {
+        entries.sort();
+        let mut hasher = Sha256::new();
+        hasher.update(self.glob_pattern.as_bytes());
+        for exclude_pattern in self.exclude_patterns {
+            hasher.update([0]);
+            hasher.update(exclude_pattern.as_bytes());
+        }
+        hasher.update([self.include_empty_dirs as u8, self.deterministic as u8]);
+        for (name, len, modified) in entries {
+            let modified = modified
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |modified| modified.as_nanos());
+            hasher.update([0]);
+            hasher.update(name.as_bytes());
+            hasher.update(len.to_le_bytes());
+            hasher.update(modified.to_le_bytes());
+        }
+        hasher.finalize().to_vec()
+    }
+}
+
+/// Size and modification time of the file at `path`, as fingerprinted.
+fn entry_metadata(path: &Path) -> (u64, Option<SystemTime>) {
+    std::fs::metadata(path).map_or((0, None), |metadata| {
+        (metadata.len(), metadata.modified().ok())
+    })
+}
+
 impl FileSystemService {
+    /// Compresses the files of `input_dir` matching `pattern` into `target_zip_file`.
+    /// Paths matching any of the `exclude_patterns` are skipped, an excluded directory is skipped entirely.
+    /// When `include_empty_dirs` is set, empty directories matching `pattern` are stored as directory entries.
+    /// When `deterministic` is set, entries are written in sorted order with a fixed timestamp and permissions,
+    /// so archiving the same tree twice produces identical archives.
+    /// When the tree and the options are unchanged since the last archive of `input_dir` (same entries,
+    /// sizes and modification times) and that archive is unchanged too, it is copied instead of
+    /// compressing the files again, unless `force` is set.
+    /// When provided, `on_progress` is invoked after each file is added to the archive.
+    #[allow(clippy::too_many_arguments)]
     pub async fn zip_directory(
         &self,
         input_dir: String,
         pattern: String,
+        exclude_patterns: Vec<String>,
         target_zip_file: String,
+        create_parents: bool,
+        include_empty_dirs: bool,
+        deterministic: bool,
+        force: bool,
+        on_progress: Option<&(dyn Fn(ZipProgress) + Send + Sync)>,
     ) -> ServiceResult<String> {
         let allowed_directories = self.allowed_directories().await;
         let valid_dir_path =
@@ -34,13 +260,14 @@ impl FileSystemService {
         let target_path =
             self.validate_path(Path::new(&target_zip_file), allowed_directories.clone())?;
 
-        if target_path.exists() {
+        if self.path_exists(&target_path) {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::AlreadyExists,
                 format!("'{target_zip_file}' already exists!"),
             )
             .into());
         }
+        self.ensure_modifiable(&target_path).await?;
 
         let updated_pattern = if pattern.contains('*') {
             pattern.to_lowercase()
@@ -48,73 +275,297 @@ impl FileSystemService {
             format!("*{}*", &pattern.to_lowercase())
         };
 
-        let glob_pattern = &updated_pattern;
+        let ignore_rules = IgnoreRules::load(&allowed_directories);
+        let selection = ZipSelection {
+            input_dir: &valid_dir_path,
+            input_dir_str,
+            glob_pattern: &updated_pattern,
+            exclude_patterns: &exclude_patterns,
+            ignore_rules: &ignore_rules,
+            allowed_directories: &allowed_directories,
+            include_empty_dirs,
+            deterministic,
+        };
+        let cache_key = tokio::fs::canonicalize(&valid_dir_path).await?;
 
-        let entries: Vec<_> = WalkDir::new(&valid_dir_path)
-            .follow_links(true)
-            .into_iter()
-            .filter_map(|entry| entry.ok())
-            .filter_map(|entry| {
-                let full_path = entry.path();
-
-                self.validate_path(full_path, allowed_directories.clone())
-                    .ok()
-                    .and_then(|path| {
-                        if path != valid_dir_path
-                            && glob_match(glob_pattern, path.display().to_string().as_ref())
-                        {
-                            Some(path)
-                        } else {
-                            None
-                        }
-                    })
-            })
-            .collect();
+        if create_parents {
+            self.create_parent_directories(&target_path).await?;
+        }
+
+        if !force
+            && !self.is_simulation()
+            && let Some(message) = self
+                .reuse_zip_archive(&selection, &cache_key, &input_dir, &target_path)
+                .await?
+        {
+            return Ok(message);
+        }
 
-        let zip_file = File::create(&target_path).await?;
-        let mut zip_writer = ZipFileWriter::new(zip_file.compat());
+        let mut zip_writer =
+            ZipFileWriter::new(self.create_archive_sink(&target_path).await?.compat_write());
+        let mut progress = ZipProgress::default();
+        let mut empty_dirs = 0;
+        let mut fingerprint_entries = vec![];
 
-        for entry_path_buf in &entries {
-            if entry_path_buf.is_dir() {
-                continue;
+        // Resolve symlinks and relative segments, so the archive being written is recognized
+        // when the walker reaches it through a different path.
+        let target_canonical = match self.is_simulation() {
+            // the archive is not on disk, its directory is
+            true => match (target_path.parent(), target_path.file_name()) {
+                (Some(parent), Some(file_name)) => tokio::fs::canonicalize(parent)
+                    .await
+                    .map_or(target_path.clone(), |parent| parent.join(file_name)),
+                _ => target_path.clone(),
+            },
+            false => tokio::fs::canonicalize(&target_path).await?,
+        };
+        let target_in_input_dir = target_canonical.starts_with(&cache_key);
+        let is_target_archive = |path: &Path| {
+            target_in_input_dir
+                && path.file_name() == target_canonical.file_name()
+                && std::fs::canonicalize(path).is_ok_and(|path| path == target_canonical)
+        };
+
+        // Deflate is CPU-bound: entries are compressed in parallel on the batch thread pool while
+        // this task appends finished entries in walk order. The bounded channel limits the number of
+        // entries held in memory, as entries are produced while the directory is being walked.
+        let max_in_flight = self.batch_limits().threads * 2;
+        let (entry_sender, mut entry_receiver) =
+            mpsc::channel::<oneshot::Receiver<ServiceResult<CompressedEntry>>>(max_in_flight);
+
+        let produce_entries = async {
+            // owned by the producer, so the channel closes once the walk is complete
+            let entry_sender = entry_sender;
+            for entry in selection.entries(self, is_target_archive) {
+                self.check_cancelled()?;
+                let ZipEntrySource {
+                    name: entry_name,
+                    path: entry_path_buf,
+                    is_empty_dir,
+                } = entry?;
+                let (entry_len, entry_modified) = entry_metadata(&entry_path_buf);
+                fingerprint_entries.push((entry_name.clone(), entry_len, entry_modified));
+
+                let (result_sender, result_receiver) = oneshot::channel();
+                if is_empty_dir {
+                    let result = zip_directory_entry(&entry_name, &entry_path_buf, deterministic)
+                        .map(|entry| (entry, vec![]));
+                    let _ = result_sender.send(result);
+                } else {
+                    self.batch_executor.spawn(move || {
+                        let result =
+                            compress_zip_entry(&entry_name, &entry_path_buf, deterministic);
+                        let _ = result_sender.send(result);
+                    });
+                }
+
+                // the writer stopped, its error is reported by `write_entries`
+                if entry_sender.send(result_receiver).await.is_err() {
+                    break;
+                }
             }
-            let entry_path = entry_path_buf.as_path();
-            let entry_str = entry_path.as_os_str().to_str().ok_or(std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                "Invalid UTF-8 in file name",
-            ))?;
+            ServiceResult::Ok(())
+        };
 
-            if !entry_str.starts_with(input_dir_str) {
-                return Err(std::io::Error::new(
-                    std::io::ErrorKind::InvalidInput,
-                    "Entry file path does not start with base input directory path.",
-                )
-                .into());
+        let write_entries = async {
+            while let Some(result_receiver) = entry_receiver.recv().await {
+                let (entry, compressed) = result_receiver.await.map_err(|_| {
+                    ServiceError::FromString("Compression of a zip entry was aborted.".to_string())
+                })??;
+                let entry_size = entry.uncompressed_size();
+                let is_dir = entry.dir()?;
+                zip_writer
+                    .write_entry_whole_precompressed(entry, &compressed)
+                    .await?;
+
+                if is_dir {
+                    empty_dirs += 1;
+                    continue;
+                }
+                progress.files += 1;
+                progress.bytes += entry_size;
+                if let Some(on_progress) = on_progress {
+                    on_progress(progress);
+                }
             }
+            ServiceResult::Ok(())
+        };
 
-            let entry_str = &entry_str[input_dir_str.len() + 1..];
-            write_zip_entry(entry_str, entry_path, &mut zip_writer).await?;
+        let (produced, written) = tokio::join!(produce_entries, write_entries);
+        let archived = async {
+            written?;
+            produced?;
+            // a cancelled walk ends early, the archive would be incomplete
+            self.check_cancelled()?;
+            let z_file = zip_writer.close().await?;
+            ServiceResult::Ok(
+                self.finish_archive(&target_path, z_file.into_inner())
+                    .await?,
+            )
+        }
+        .await;
+        let zip_file_size = match archived {
+            Ok(size) => size.map_or("unknown".to_string(), format_bytes),
+            Err(err) => {
+                self.discard_archive(&target_path).await;
+                return Err(err);
+            }
+        };
+        if !self.is_simulation() {
+            self.record_zip_archive(
+                cache_key,
+                ZipArchiveRecord {
+                    fingerprint: selection.fingerprint(fingerprint_entries),
+                    archive: target_canonical.clone(),
+                    archive_len: 0,
+                    archive_modified: None,
+                    files: progress.files,
+                    empty_dirs,
+                    created_at: SystemTime::now(),
+                },
+            );
+        }
+        let mut result_message = Message::DirectoryCompressed {
+            input_dir: &input_dir,
+            target: &target_path.display().to_string(),
+            files: progress.files,
+            size: &zip_file_size,
+        }
+        .localize(self.locale());
+        if empty_dirs > 0 {
+            result_message.push_str(&format!(
+                "\nIncluded {} empty {}.",
+                empty_dirs,
+                if empty_dirs == 1 {
+                    "directory"
+                } else {
+                    "directories"
+                }
+            ));
+        }
+        if target_in_input_dir {
+            result_message.push_str(
+                "\nNote: the target archive is located inside the input directory and was excluded from the archive.",
+            );
         }
+        Ok(result_message)
+    }
 
-        let z_file = zip_writer.close().await?;
-        let zip_file_size = if let Ok(meta_data) = z_file.into_inner().metadata().await {
-            format_bytes(meta_data.len())
-        } else {
-            "unknown".to_string()
+    /// Copies the last archive of the input directory to `target_path` if neither the tree nor the
+    /// archive changed since it was created, returning the result message.
+    async fn reuse_zip_archive(
+        &self,
+        selection: &ZipSelection<'_>,
+        cache_key: &Path,
+        input_dir: &str,
+        target_path: &Path,
+    ) -> ServiceResult<Option<String>> {
+        let record = self
+            .zip_archive_cache
+            .lock()
+            .unwrap()
+            .get(cache_key)
+            .cloned();
+        let Some(record) = record else {
+            return Ok(None);
         };
-        let result_message = format!(
-            "Successfully compressed '{}' directory into '{}' ({}).",
+        if record.archive_modified.is_none()
+            || entry_metadata(&record.archive) != (record.archive_len, record.archive_modified)
+        {
+            return Ok(None);
+        }
+
+        // the target archive doesn't exist yet, it can't be part of the tree
+        let entries = selection
+            .entries(self, |_| false)
+            .map(|entry| {
+                entry.map(|entry| {
+                    let (len, modified) = entry_metadata(&entry.path);
+                    (entry.name, len, modified)
+                })
+            })
+            .collect::<ServiceResult<Vec<_>>>()?;
+        if selection.fingerprint(entries) != record.fingerprint {
+            return Ok(None);
+        }
+
+        tokio::fs::copy(&record.archive, target_path).await?;
+        let mut result_message = format!(
+            "Reused the archive '{}' of the unchanged '{}' directory, copied into '{}' ({} {}, {}).\nSet 'force' to compress the files again.",
+            record.archive.display(),
             input_dir,
             target_path.display(),
-            zip_file_size
+            record.files,
+            if record.files == 1 { "file" } else { "files" },
+            format_bytes(record.archive_len)
         );
-        Ok(result_message)
+        if record.empty_dirs > 0 {
+            result_message.push_str(&format!(
+                "\nIncluded {} empty {}.",
+                record.empty_dirs,
+                if record.empty_dirs == 1 {
+                    "directory"
+                } else {
+                    "directories"
+                }
+            ));
+        }
+        Ok(Some(result_message))
+    }
+
+    /// Opens the target of an archive, in memory when simulating.
+    async fn create_archive_sink(&self, target_path: &Path) -> std::io::Result<ArchiveSink> {
+        if self.is_simulation() {
+            return Ok(ArchiveSink::Memory(vec![]));
+        }
+        Ok(ArchiveSink::File(File::create(target_path).await?))
+    }
+
+    /// Completes an archive written to `sink`, recording it in the overlay when simulating.
+    /// Returns its size if known.
+    async fn finish_archive(
+        &self,
+        target_path: &Path,
+        sink: ArchiveSink,
+    ) -> std::io::Result<Option<u64>> {
+        Ok(match sink {
+            ArchiveSink::File(file) => file.metadata().await.ok().map(|metadata| metadata.len()),
+            ArchiveSink::Memory(content) => {
+                self.simulate_write(target_path, &content)?;
+                Some(content.len() as u64)
+            }
+        })
+    }
+
+    /// Removes the partial archive left by a failed or cancelled operation.
+    async fn discard_archive(&self, target_path: &Path) {
+        if !self.is_simulation() {
+            let _ = tokio::fs::remove_file(target_path).await;
+        }
+    }
+
+    /// Remembers the archive just created from the input directory `cache_key`, along with the
+    /// size and modification time it has now.
+    fn record_zip_archive(&self, cache_key: PathBuf, mut record: ZipArchiveRecord) {
+        (record.archive_len, record.archive_modified) = entry_metadata(&record.archive);
+        let mut cache = self.zip_archive_cache.lock().unwrap();
+        if cache.len() >= ZIP_ARCHIVE_CACHE_CAPACITY
+            && !cache.contains_key(&cache_key)
+            && let Some(oldest) = cache
+                .iter()
+                .min_by_key(|(_, record)| record.created_at)
+                .map(|(input_dir, _)| input_dir.clone())
+        {
+            cache.remove(&oldest);
+        }
+        cache.insert(cache_key, record);
     }
 
     pub async fn zip_files(
         &self,
         input_files: Vec<String>,
         target_zip_file: String,
+        create_parents: bool,
     ) -> ServiceResult<String> {
         let file_count = input_files.len();
 
@@ -129,49 +580,63 @@ impl FileSystemService {
         let target_path =
             self.validate_path(Path::new(&target_zip_file), allowed_directories.clone())?;
 
-        if target_path.exists() {
+        if self.path_exists(&target_path) {
             return Err(std::io::Error::new(
                 std::io::ErrorKind::AlreadyExists,
                 format!("'{target_zip_file}' already exists!"),
             )
             .into());
         }
+        self.ensure_modifiable(&target_path).await?;
 
-        let source_paths = input_files
-            .iter()
-            .map(|p| self.validate_path(Path::new(p), allowed_directories.clone()))
+        let input_paths: Vec<PathBuf> = input_files.iter().map(PathBuf::from).collect();
+        let source_paths = self
+            .validate_paths(&input_paths)
+            .await
+            .into_iter()
             .collect::<Result<Vec<_>, _>>()?;
 
-        let zip_file = File::create(&target_path).await?;
-        let mut zip_writer = ZipFileWriter::new(zip_file.compat());
-        for path in source_paths {
-            let filename = path.file_name().ok_or(std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                "Invalid path!",
-            ))?;
+        if create_parents {
+            self.create_parent_directories(&target_path).await?;
+        }
 
-            let filename = filename.to_str().ok_or(std::io::Error::new(
-                std::io::ErrorKind::InvalidInput,
-                "Invalid UTF-8 in file name",
-            ))?;
+        let mut zip_writer =
+            ZipFileWriter::new(self.create_archive_sink(&target_path).await?.compat_write());
+        let archived = async {
+            for path in source_paths {
+                self.check_cancelled()?;
+                let filename = path.file_name().ok_or(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "Invalid path!",
+                ))?;
 
-            write_zip_entry(filename, &path, &mut zip_writer).await?;
-        }
-        let z_file = zip_writer.close().await?;
+                let filename = filename.to_str().ok_or(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "Invalid UTF-8 in file name",
+                ))?;
 
-        let zip_file_size = if let Ok(meta_data) = z_file.into_inner().metadata().await {
-            format_bytes(meta_data.len())
-        } else {
-            "unknown".to_string()
+                write_zip_entry(filename, &path, &mut zip_writer).await?;
+            }
+            let z_file = zip_writer.close().await?;
+            ServiceResult::Ok(
+                self.finish_archive(&target_path, z_file.into_inner())
+                    .await?,
+            )
+        }
+        .await;
+        let zip_file_size = match archived {
+            Ok(size) => size.map_or("unknown".to_string(), format_bytes),
+            Err(err) => {
+                self.discard_archive(&target_path).await;
+                return Err(err);
+            }
         };
 
-        let result_message = format!(
-            "Successfully compressed {} {} into '{}' ({}).",
-            file_count,
-            if file_count == 1 { "file" } else { "files" },
-            target_path.display(),
-            zip_file_size
-        );
-        Ok(result_message)
+        Ok(Message::FilesCompressed {
+            files: file_count,
+            target: &target_path.display().to_string(),
+            size: &zip_file_size,
+        }
+        .localize(self.locale()))
     }
 }
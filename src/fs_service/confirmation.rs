@@ -0,0 +1,197 @@
+//! Two-phase execution of bulk operations: preview first, apply with a confirmation token.
+//!
+//! A bulk tool computes what it would do and, without a token, returns that preview along with a
+//! confirmation token. The operation is only executed when the tool is called again with the same
+//! arguments and the token, and only if the preview computed at that time is still identical.
+//!
+//! Tokens are signed with a secret generated when the server starts (HMAC-SHA256) and bind the
+//! operation name, its arguments and its preview, so a token can't be forged, reused for other
+//! arguments, or applied to a filesystem that changed since the preview. Tokens expire after
+//! `CONFIRMATION_TOKEN_LIFETIME` and can only be used once.
+
+use crate::{
+    error::{ServiceError, ServiceResult},
+    fs_service::FileSystemService,
+};
+use chrono::{DateTime, TimeDelta, Utc};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use std::collections::HashMap;
+
+/// How long a confirmation token can be used after the preview.
+pub const CONFIRMATION_TOKEN_LIFETIME: TimeDelta = TimeDelta::minutes(10);
+
+/// Outcome of a two-phase operation.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "status", rename_all = "snake_case")]
+pub enum TwoPhaseOutcome<P, T> {
+    /// Nothing was changed, call the tool again with `confirmation_token` to apply the preview.
+    Preview {
+        preview: P,
+        confirmation_token: String,
+        /// RFC 3339 expiration time of the token.
+        expires_at: String,
+    },
+    /// The operation was applied.
+    Applied { result: T },
+}
+
+/// Secret used to sign confirmation tokens and the tokens already used, until they expire.
+pub(crate) struct ConfirmationTokens {
+    secret: [u8; 32],
+    used: std::sync::Mutex<HashMap<String, DateTime<Utc>>>,
+}
+
+impl Default for ConfirmationTokens {
+    fn default() -> Self {
+        let mut secret = [0u8; 32];
+        getrandom::fill(&mut secret).expect("the operating system failed to provide random bytes");
+        Self {
+            secret,
+            used: Default::default(),
+        }
+    }
+}
+
+impl ConfirmationTokens {
+    /// The HMAC of the operation, its arguments and preview, and the expiration time of the token.
+    fn mac(
+        &self,
+        operation: &str,
+        arguments: &[u8],
+        preview: &[u8],
+        expires_at: DateTime<Utc>,
+    ) -> Hmac<Sha256> {
+        let mut mac =
+            Hmac::<Sha256>::new_from_slice(&self.secret).expect("HMAC accepts keys of any length");
+        for part in [
+            operation.as_bytes(),
+            arguments,
+            preview,
+            expires_at.timestamp().to_string().as_bytes(),
+        ] {
+            // length prefixes keep the parts from being shifted into one another
+            mac.update(&(part.len() as u64).to_be_bytes());
+            mac.update(part);
+        }
+        mac
+    }
+
+    fn sign(
+        &self,
+        operation: &str,
+        arguments: &[u8],
+        preview: &[u8],
+        expires_at: DateTime<Utc>,
+    ) -> String {
+        let signature = self
+            .mac(operation, arguments, preview, expires_at)
+            .finalize()
+            .into_bytes();
+        format!("{}.{}", expires_at.timestamp(), hex(&signature))
+    }
+
+    fn verify(
+        &self,
+        token: &str,
+        operation: &str,
+        arguments: &[u8],
+        preview: &[u8],
+    ) -> ServiceResult<()> {
+        let invalid_token = || {
+            ServiceError::FromString(format!(
+                "The confirmation token is invalid, expired or already used, or the preview of '{operation}' changed since it was issued. Call the tool again without a confirmation token to get a new preview."
+            ))
+        };
+
+        let (expires_at, signature) = token.split_once('.').ok_or_else(invalid_token)?;
+        let expires_at = expires_at
+            .parse::<i64>()
+            .ok()
+            .and_then(|expires_at| DateTime::from_timestamp(expires_at, 0))
+            .ok_or_else(invalid_token)?;
+        let signature = unhex(signature).ok_or_else(invalid_token)?;
+        let now = Utc::now();
+        // the signature is compared in constant time
+        if expires_at < now
+            || self
+                .mac(operation, arguments, preview, expires_at)
+                .verify_slice(&signature)
+                .is_err()
+        {
+            return Err(invalid_token());
+        }
+
+        let mut used = self.used.lock().unwrap();
+        used.retain(|_, expires_at| *expires_at >= now);
+        // tokens differing only by the case of their digits are the same token
+        let token = format!("{}.{}", expires_at.timestamp(), hex(&signature));
+        if used.insert(token, expires_at).is_some() {
+            return Err(invalid_token());
+        }
+        Ok(())
+    }
+}
+
+impl FileSystemService {
+    /// Runs a bulk operation in two phases. `preview` describes what the operation would do and is
+    /// computed by the caller on every call. Without `confirmation_token`, the preview is returned
+    /// along with a token. With a valid token for the same `operation`, `arguments` and `preview`,
+    /// `apply` is executed. `arguments` must not include the confirmation token.
+    pub async fn preview_or_apply<A, P, T, F, Fut>(
+        &self,
+        operation: &str,
+        arguments: &A,
+        preview: P,
+        confirmation_token: Option<&str>,
+        apply: F,
+    ) -> ServiceResult<TwoPhaseOutcome<P, T>>
+    where
+        A: Serialize,
+        P: Serialize,
+        F: FnOnce(P) -> Fut,
+        Fut: Future<Output = ServiceResult<T>>,
+    {
+        let arguments = serde_json::to_vec(arguments)?;
+        let preview_bytes = serde_json::to_vec(&preview)?;
+
+        match confirmation_token {
+            None => {
+                let expires_at = Utc::now() + CONFIRMATION_TOKEN_LIFETIME;
+                let confirmation_token = self.confirmation_tokens.sign(
+                    operation,
+                    &arguments,
+                    &preview_bytes,
+                    expires_at,
+                );
+                Ok(TwoPhaseOutcome::Preview {
+                    preview,
+                    confirmation_token,
+                    expires_at: expires_at.to_rfc3339(),
+                })
+            }
+            Some(token) => {
+                self.confirmation_tokens
+                    .verify(token, operation, &arguments, &preview_bytes)?;
+                Ok(TwoPhaseOutcome::Applied {
+                    result: apply(preview).await?,
+                })
+            }
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn unhex(text: &str) -> Option<Vec<u8>> {
+    if !text.len().is_multiple_of(2) || !text.bytes().all(|byte| byte.is_ascii_hexdigit()) {
+        return None;
+    }
+    (0..text.len())
+        .step_by(2)
+        .map(|index| u8::from_str_radix(text.get(index..index + 2)?, 16).ok())
+        .collect()
+}
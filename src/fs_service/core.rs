@@ -1,19 +1,112 @@
 use crate::{
     error::{ServiceError, ServiceResult},
-    fs_service::utils::{contains_symlink, expand_home, normalize_path, parse_file_path},
+    fs_service::{
+        BackupConfig,
+        archive::zip::ZipArchiveCache,
+        confirmation::ConfirmationTokens,
+        executors::{BatchExecutor, BatchLimits},
+        locks::OwnWrites,
+        platform::is_read_only_filesystem,
+        retry::RetryPolicy,
+        search::DirectorySizeCache,
+        simulation::Overlay,
+        symlinks::SymlinkCache,
+        utils::{
+            LineEndingPolicy, ValidationProfile, WriteIfExists, clean_path, contains_symlink,
+            expand_home, normalize_path, parse_file_path,
+        },
+    },
+    i18n::{Locale, Message},
 };
 use std::{
     collections::HashSet,
-    env,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{
+        Arc,
+        atomic::{AtomicBool, Ordering},
+    },
 };
 use tokio::sync::RwLock;
 
 type PathResultList = Vec<Result<PathBuf, ServiceError>>;
 
+/// The allowed directories and base directory, resolved once to validate one or more paths.
+struct ValidationRoots {
+    allowed_directories: Arc<Vec<PathBuf>>,
+    normalized_directories: Vec<PathBuf>,
+    base_dir: PathBuf,
+}
+
+/// Changes of the allowed directories, e.g. after the client updated its roots.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AllowedDirectoriesDelta {
+    pub added: Vec<PathBuf>,
+    pub removed: Vec<PathBuf>,
+}
+
+impl AllowedDirectoriesDelta {
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty()
+    }
+
+    /// Describes the changes and the resulting `allowed_directories`.
+    pub fn summary(&self, allowed_directories: &[PathBuf]) -> String {
+        let list = |dirs: &[PathBuf]| {
+            dirs.iter()
+                .map(|dir| format!("  {}", dir.display()))
+                .collect::<Vec<_>>()
+                .join("\n")
+        };
+        let mut summary = if self.is_empty() {
+            "Allowed directories unchanged.".to_string()
+        } else {
+            format!(
+                "Allowed directories changed: {} added, {} removed.",
+                self.added.len(),
+                self.removed.len()
+            )
+        };
+        if !self.added.is_empty() {
+            summary.push_str(&format!("\nAdded:\n{}", list(&self.added)));
+        }
+        if !self.removed.is_empty() {
+            summary.push_str(&format!(
+                "\nRemoved (paths within them are now rejected):\n{}",
+                list(&self.removed)
+            ));
+        }
+        summary.push_str(&format!(
+            "\nAllowed directories:\n{}",
+            list(allowed_directories)
+        ));
+        summary
+    }
+}
+
 pub struct FileSystemService {
     allowed_path: RwLock<Arc<Vec<PathBuf>>>,
+    write_if_exists: WriteIfExists,
+    backup_config: Option<BackupConfig>,
+    locale: Locale,
+    base_dir: std::sync::RwLock<Option<PathBuf>>,
+    retry_policy: RetryPolicy,
+    pinned_paths: Vec<PathBuf>,
+    validation_profile: ValidationProfile,
+    symlink_cache: SymlinkCache,
+    pub(super) confirmation_tokens: ConfirmationTokens,
+    /// Allowed directories found missing by `check_allowed_directories`, excluded until they return.
+    pub(super) unavailable_directories: std::sync::RwLock<HashSet<PathBuf>>,
+    /// Sizes of the directories, `None` unless caching them is enabled.
+    pub(super) directory_size_cache: Option<std::sync::Mutex<DirectorySizeCache>>,
+    pub(super) batch_executor: BatchExecutor,
+    pub(super) zip_archive_cache: std::sync::Mutex<ZipArchiveCache>,
+    pub(super) editorconfig: bool,
+    write_line_endings: LineEndingPolicy,
+    pub(super) own_writes: std::sync::Mutex<OwnWrites>,
+    /// Changes of the simulation mode, `None` unless simulating.
+    pub(super) simulation: Option<std::sync::Mutex<Overlay>>,
+    /// Set when the server shuts down, long operations stop at their next checkpoint.
+    cancelled: AtomicBool,
 }
 
 impl FileSystemService {
@@ -32,19 +125,217 @@ impl FileSystemService {
             })
             .collect();
 
-        Ok(Self {
-            allowed_path: RwLock::new(Arc::new(normalized_dirs?)),
+        Ok(Self::new(normalized_dirs?))
+    }
+
+    /// Creates the service without requiring the allowed directories to exist, e.g. network shares
+    /// not mounted yet. Missing directories are excluded from path validation until they exist.
+    pub fn new_lazy(allowed_directories: &[String]) -> Self {
+        let normalized_dirs: Vec<PathBuf> = allowed_directories
+            .iter()
+            .map(fix_dockerhub_mcp_registry_gateway)
+            .map(|dir| expand_home(dir.into()))
+            .collect();
+        let unavailable_directories = normalized_dirs
+            .iter()
+            .filter(|dir| !dir.is_dir())
+            .cloned()
+            .collect();
+
+        let service = Self::new(normalized_dirs);
+        *service.unavailable_directories.write().unwrap() = unavailable_directories;
+        service
+    }
+
+    fn new(allowed_directories: Vec<PathBuf>) -> Self {
+        Self {
+            allowed_path: RwLock::new(Arc::new(allowed_directories)),
+            write_if_exists: WriteIfExists::default(),
+            backup_config: None,
+            locale: Locale::default(),
+            base_dir: std::sync::RwLock::new(None),
+            retry_policy: RetryPolicy::default(),
+            pinned_paths: vec![],
+            validation_profile: ValidationProfile::default(),
+            symlink_cache: Default::default(),
+            confirmation_tokens: Default::default(),
+            unavailable_directories: Default::default(),
+            directory_size_cache: None,
+            batch_executor: BatchExecutor::new(BatchLimits::default()),
+            zip_archive_cache: Default::default(),
+            editorconfig: true,
+            write_line_endings: LineEndingPolicy::default(),
+            own_writes: Default::default(),
+            simulation: None,
+            cancelled: AtomicBool::new(false),
+        }
+    }
+
+    /// Cancels the operations in progress and any operation started afterwards, when the server
+    /// shuts down: traversals stop and archive operations remove their partial output.
+    pub fn cancel_operations(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// Fails with `ServiceError::Cancelled` once `cancel_operations` was called.
+    pub(crate) fn check_cancelled(&self) -> ServiceResult<()> {
+        if self.is_cancelled() {
+            return Err(ServiceError::Cancelled);
+        }
+        Ok(())
+    }
+
+    /// Sets the behavior of `write_file` for existing files when the caller does not specify one.
+    pub fn with_write_if_exists(mut self, write_if_exists: WriteIfExists) -> Self {
+        self.write_if_exists = write_if_exists;
+        self
+    }
+
+    pub fn write_if_exists(&self) -> WriteIfExists {
+        self.write_if_exists
+    }
+
+    /// Sets the line endings enforced by `write_file` and `edit_file` (default: preserve).
+    pub fn with_write_line_endings(mut self, write_line_endings: LineEndingPolicy) -> Self {
+        self.write_line_endings = write_line_endings;
+        self
+    }
+
+    pub fn write_line_endings(&self) -> LineEndingPolicy {
+        self.write_line_endings
+    }
+
+    /// Enables server-managed backups for files modified by `write_file` and `edit_file`.
+    pub fn with_backups(mut self, backup_config: BackupConfig) -> Self {
+        self.backup_config = Some(backup_config);
+        self
+    }
+
+    pub fn backup_config(&self) -> Option<&BackupConfig> {
+        self.backup_config.as_ref()
+    }
+
+    /// Sets the language of user-facing messages.
+    pub fn with_locale(mut self, locale: Locale) -> Self {
+        self.locale = locale;
+        self
+    }
+
+    pub fn locale(&self) -> Locale {
+        self.locale
+    }
+
+    /// Sets how file operations failing with a transient error (busy or locked files) are retried.
+    pub fn with_retry_policy(mut self, retry_policy: RetryPolicy) -> Self {
+        self.retry_policy = retry_policy;
+        self
+    }
+
+    pub fn retry_policy(&self) -> RetryPolicy {
+        self.retry_policy
+    }
+
+    /// Sets whether symbolic links are resolved when validating paths (default: strict).
+    pub fn with_validation_profile(mut self, validation_profile: ValidationProfile) -> Self {
+        self.validation_profile = validation_profile;
+        self
+    }
+
+    pub fn validation_profile(&self) -> ValidationProfile {
+        self.validation_profile
+    }
+
+    /// Sets the directory relative paths are resolved against, instead of the first allowed directory.
+    /// A relative `base_dir` is itself resolved against the first allowed directory.
+    pub fn with_base_dir(self, base_dir: PathBuf) -> Self {
+        *self.base_dir.write().unwrap() = Some(expand_home(base_dir));
+        self
+    }
+
+    /// Changes the directory relative paths are resolved against for the rest of the session.
+    /// `base_dir` must be an existing directory within the allowed directories, a relative path
+    /// is resolved against the current base directory. Returns the new base directory.
+    pub async fn set_base_dir(&self, base_dir: &Path) -> ServiceResult<PathBuf> {
+        let allowed_directories = self.allowed_directories().await;
+        let valid_path = normalize_path(&self.validate_path(base_dir, allowed_directories)?);
+        if !valid_path.is_dir() {
+            return Err(ServiceError::FromString(format!(
+                "'{}' is not a directory.",
+                valid_path.display()
+            )));
+        }
+        *self.base_dir.write().unwrap() = Some(valid_path.clone());
+        Ok(valid_path)
+    }
+
+    /// Directory relative paths are resolved against, `None` when no directory is allowed.
+    pub fn base_dir(&self, allowed_directories: &[PathBuf]) -> Option<PathBuf> {
+        let first_allowed_directory = allowed_directories.first()?;
+        Some(match &*self.base_dir.read().unwrap() {
+            Some(base_dir) => first_allowed_directory.join(base_dir),
+            None => first_allowed_directory.to_path_buf(),
         })
     }
 
+    /// The allowed directories, excluding those found missing by the last `check_allowed_directories`
+    /// that still don't exist.
     pub async fn allowed_directories(&self) -> Arc<Vec<PathBuf>> {
+        let configured_directories = self.configured_directories().await;
+        let unavailable = self.unavailable_directories.read().unwrap();
+        if unavailable.is_empty() {
+            return configured_directories;
+        }
+        // directories coming back are allowed right away, without waiting for the next check
+        Arc::new(
+            configured_directories
+                .iter()
+                .filter(|dir| !unavailable.contains(*dir) || dir.is_dir())
+                .cloned()
+                .collect(),
+        )
+    }
+
+    /// Allowed directories currently excluded because they don't exist.
+    pub fn unavailable_directories(&self) -> Vec<PathBuf> {
+        let mut unavailable: Vec<PathBuf> = self
+            .unavailable_directories
+            .read()
+            .unwrap()
+            .iter()
+            .filter(|dir| !dir.is_dir())
+            .cloned()
+            .collect();
+        unavailable.sort();
+        unavailable
+    }
+
+    /// The allowed directories as configured, including unavailable ones.
+    pub async fn configured_directories(&self) -> Arc<Vec<PathBuf>> {
         let guard = self.allowed_path.read().await;
         guard.clone()
     }
 
-    pub async fn update_allowed_paths(&self, valid_roots: Vec<PathBuf>) {
+    /// Replaces the allowed directories, returning the directories added and removed.
+    pub async fn update_allowed_paths(&self, valid_roots: Vec<PathBuf>) -> AllowedDirectoriesDelta {
         let mut guard = self.allowed_path.write().await;
-        *guard = Arc::new(valid_roots)
+        let delta = AllowedDirectoriesDelta {
+            added: valid_roots
+                .iter()
+                .filter(|dir| !guard.contains(dir))
+                .cloned()
+                .collect(),
+            removed: guard
+                .iter()
+                .filter(|dir| !valid_roots.contains(dir))
+                .cloned()
+                .collect(),
+        };
+        *guard = Arc::new(valid_roots);
+        delta
     }
 
     pub fn validate_path(
@@ -52,51 +343,178 @@ impl FileSystemService {
         requested_path: &Path,
         allowed_directories: Arc<Vec<PathBuf>>,
     ) -> ServiceResult<PathBuf> {
+        let roots = self.validation_roots(allowed_directories)?;
+        self.validate_path_with_roots(requested_path, &roots)
+    }
+
+    /// Validates `paths` against the allowed directories, returning one result per path in the
+    /// same order. The allowed directories and the base directory are resolved once for all paths,
+    /// and symbolic link checks share the cached directories.
+    pub async fn validate_paths(&self, paths: &[PathBuf]) -> Vec<ServiceResult<PathBuf>> {
+        let allowed_directories = self.allowed_directories().await;
+        match self.validation_roots(allowed_directories) {
+            Ok(roots) => paths
+                .iter()
+                .map(|path| self.validate_path_with_roots(path, &roots))
+                .collect(),
+            Err(err) => {
+                let message = err.to_string();
+                paths
+                    .iter()
+                    .map(|_| Err(ServiceError::FromString(message.clone())))
+                    .collect()
+            }
+        }
+    }
+
+    fn validation_roots(
+        &self,
+        allowed_directories: Arc<Vec<PathBuf>>,
+    ) -> ServiceResult<ValidationRoots> {
         if allowed_directories.is_empty() {
             return Err(ServiceError::FromString(
                 "Allowed directories list is empty. Client did not provide any valid root directories.".to_string()
             ));
         }
+        Ok(ValidationRoots {
+            normalized_directories: allowed_directories
+                .iter()
+                .map(|dir| self.normalize_for_validation(dir))
+                .collect(),
+            base_dir: self.base_dir(&allowed_directories).unwrap_or_default(),
+            allowed_directories,
+        })
+    }
 
+    fn validate_path_with_roots(
+        &self,
+        requested_path: &Path,
+        roots: &ValidationRoots,
+    ) -> ServiceResult<PathBuf> {
         // Expand ~ to home directory
         let expanded_path = expand_home(requested_path.to_path_buf());
 
-        // Resolve the absolute path
+        // Resolve the absolute path, relative paths don't depend on the working directory of the server
         let absolute_path = if expanded_path.as_path().is_absolute() {
             expanded_path.clone()
         } else {
-            env::current_dir().unwrap().join(&expanded_path)
+            roots.base_dir.join(&expanded_path)
+        };
+
+        // Apply the `..` components, the checked path is the path returned to the caller
+        let absolute_path = match self.validation_profile {
+            ValidationProfile::Strict => self
+                .symlink_cache
+                .resolve_parent_components(&absolute_path)?,
+            ValidationProfile::Relaxed => clean_path(&absolute_path),
         };
 
         // Normalize the path
-        let normalized_requested = normalize_path(&absolute_path);
+        let normalized_requested = self.normalize_for_validation(&absolute_path);
 
         // Check if path is within allowed directories
-        if !allowed_directories.iter().any(|dir| {
-            // Must account for both scenarios - the requested path may not exist yet, making canonicalization impossible.
-            normalized_requested.starts_with(dir)
-                || normalized_requested.starts_with(normalize_path(dir))
-        }) {
-            let symlink_target = if contains_symlink(&absolute_path)? {
-                "a symlink target path"
-            } else {
-                "path"
-            };
-            return Err(ServiceError::FromString(format!(
-                "Access denied - {} is outside allowed directories: {} not in {}",
-                symlink_target,
-                absolute_path.display(),
-                allowed_directories
-                    .iter()
-                    .map(|p| p.display().to_string())
-                    .collect::<Vec<_>>()
-                    .join(",\n"),
-            )));
+        if !roots
+            .allowed_directories
+            .iter()
+            .zip(&roots.normalized_directories)
+            .any(|(dir, normalized_dir)| {
+                // Must account for both scenarios - the requested path may not exist yet, making canonicalization impossible.
+                normalized_requested.starts_with(dir)
+                    || normalized_requested.starts_with(normalized_dir)
+            })
+        {
+            let allowed_directories = roots
+                .allowed_directories
+                .iter()
+                .map(|p| p.display().to_string())
+                .collect::<Vec<_>>()
+                .join(",\n");
+            return Err(ServiceError::AccessDenied(
+                Message::AccessDenied {
+                    is_symlink_target: contains_symlink(&absolute_path)?,
+                    path: &absolute_path.display().to_string(),
+                    allowed_directories: &allowed_directories,
+                }
+                .localize(self.locale),
+            ));
         }
 
         Ok(absolute_path)
     }
 
+    /// Normalizes `path` for the comparison with the allowed directories. The strict profile
+    /// resolves symbolic links, including those of paths that don't exist yet, the relaxed profile
+    /// only resolves `.` and `..` components.
+    fn normalize_for_validation(&self, path: &Path) -> PathBuf {
+        match self.validation_profile {
+            ValidationProfile::Strict => self
+                .symlink_cache
+                .resolve(&clean_path(path))
+                .unwrap_or_else(|_| clean_path(path)),
+            ValidationProfile::Relaxed => clean_path(path),
+        }
+    }
+
+    /// Paths that can't be modified through the server, regardless of the write mode.
+    /// Relative paths are resolved against the base directory, pinning a directory pins its content.
+    pub fn with_pinned_paths(mut self, pinned_paths: Vec<PathBuf>) -> Self {
+        self.pinned_paths = pinned_paths.into_iter().map(expand_home).collect();
+        self
+    }
+
+    /// The pinned paths, resolved against the base directory.
+    pub async fn pinned_paths(&self) -> Vec<PathBuf> {
+        let configured_directories = self.configured_directories().await;
+        let base_dir = self.base_dir(&configured_directories).unwrap_or_default();
+        self.pinned_paths
+            .iter()
+            .map(|pinned_path| normalize_path(&base_dir.join(pinned_path)))
+            .collect()
+    }
+
+    /// Fails when `path` is pinned or within a pinned directory, or when `include_content` is set
+    /// and a pinned path is within `path` (e.g. moving a directory containing a pinned file).
+    pub async fn ensure_not_pinned(&self, path: &Path, include_content: bool) -> ServiceResult<()> {
+        if self.pinned_paths.is_empty() {
+            return Ok(());
+        }
+        let normalized_path = normalize_path(path);
+        let pinned_path = self.pinned_paths().await.into_iter().find(|pinned_path| {
+            normalized_path.starts_with(pinned_path)
+                || (include_content && pinned_path.starts_with(&normalized_path))
+        });
+        match pinned_path {
+            Some(pinned_path) => Err(ServiceError::PinnedPath(
+                Message::PinnedPath {
+                    path: &path.display().to_string(),
+                    pinned_path: &pinned_path.display().to_string(),
+                }
+                .localize(self.locale),
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Checks that `path` can be modified: it must not be pinned nor on a read-only filesystem.
+    pub async fn ensure_modifiable(&self, path: &Path) -> ServiceResult<()> {
+        self.ensure_not_pinned(path, false).await?;
+        self.ensure_writable_filesystem(path)
+    }
+
+    /// Fails with a specific error when `path` is on a filesystem mounted read-only, so writes
+    /// are not reported as generic permission failures.
+    pub fn ensure_writable_filesystem(&self, path: &Path) -> ServiceResult<()> {
+        if is_read_only_filesystem(path) {
+            return Err(ServiceError::ReadOnlyFilesystem(
+                Message::ReadOnlyFilesystem {
+                    path: &path.display().to_string(),
+                }
+                .localize(self.locale),
+            ));
+        }
+        Ok(())
+    }
+
     pub fn valid_roots(&self, roots: Vec<&str>) -> ServiceResult<(Vec<PathBuf>, Option<String>)> {
         let paths: Vec<Result<PathBuf, ServiceError>> =
             roots.iter().map(|p| parse_file_path(p)).collect::<Vec<_>>();
@@ -0,0 +1,165 @@
+//! Support of the `.editorconfig` files, applied to the content written by `write_file` and
+//! `edit_file` so it follows the conventions of the project.
+//!
+//! Only the `.editorconfig` files within the allowed directories are read, those of the parent
+//! directories are ignored as if the allowed directory contained a `root = true` file.
+
+use crate::fs_service::{
+    FileSystemService,
+    utils::{detect_line_ending, normalize_path},
+};
+use ec4rs::{
+    ConfigFiles, Properties, PropertiesSource,
+    property::{EndOfLine, FinalNewline, IndentSize, IndentStyle, TabWidth, TrimTrailingWs},
+};
+use std::path::{Path, PathBuf};
+
+/// The conventions set by the `.editorconfig` files for a file, `None` for the unset ones.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct EditorConfigRules {
+    /// Whether lines are indented with tabs rather than spaces.
+    pub indent_with_tabs: Option<bool>,
+    /// Width of one indentation level, in columns.
+    pub indent_size: Option<usize>,
+    /// Width of a tab, in columns.
+    pub tab_width: Option<usize>,
+    /// `\n`, `\r\n` or `\r`.
+    pub end_of_line: Option<&'static str>,
+    pub insert_final_newline: Option<bool>,
+    pub trim_trailing_whitespace: Option<bool>,
+}
+
+impl EditorConfigRules {
+    fn from_properties(mut properties: Properties) -> Self {
+        properties.use_fallbacks();
+        Self {
+            indent_with_tabs: properties
+                .get::<IndentStyle>()
+                .ok()
+                .map(|style| style == IndentStyle::Tabs),
+            indent_size: match properties.get::<IndentSize>() {
+                Ok(IndentSize::Value(size)) if size > 0 => Some(size),
+                _ => None,
+            },
+            tab_width: match properties.get::<TabWidth>() {
+                Ok(TabWidth::Value(width)) if width > 0 => Some(width),
+                _ => None,
+            },
+            end_of_line: properties.get::<EndOfLine>().ok().map(|eol| match eol {
+                EndOfLine::Lf => "\n",
+                EndOfLine::CrLf => "\r\n",
+                EndOfLine::Cr => "\r",
+            }),
+            insert_final_newline: match properties.get::<FinalNewline>() {
+                Ok(FinalNewline::Value(value)) => Some(value),
+                Err(_) => None,
+            },
+            trim_trailing_whitespace: match properties.get::<TrimTrailingWs>() {
+                Ok(TrimTrailingWs::Value(value)) => Some(value),
+                Err(_) => None,
+            },
+        }
+    }
+
+    /// Applies the indentation and trailing whitespace rules to `text`, whose line endings are
+    /// normalized to `\n`. Used for the text inserted by an edit, the rest of the file is kept as is.
+    pub fn format_lines(&self, text: &str) -> String {
+        if self.indent_with_tabs.is_none() && self.trim_trailing_whitespace != Some(true) {
+            return text.to_string();
+        }
+        text.split('\n')
+            .map(|line| {
+                let line = match self.trim_trailing_whitespace {
+                    Some(true) => line.trim_end_matches([' ', '\t']),
+                    _ => line,
+                };
+                self.reindent(line)
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+
+    /// Applies all the rules to `text`, the whole content of a file.
+    pub fn format_content(&self, text: &str) -> String {
+        if *self == Self::default() {
+            return text.to_string();
+        }
+        let mut content = self.format_lines(&text.replace("\r\n", "\n").replace('\r', "\n"));
+        match self.insert_final_newline {
+            Some(true) if !content.is_empty() && !content.ends_with('\n') => content.push('\n'),
+            Some(false) => content.truncate(content.trim_end_matches('\n').len()),
+            _ => {}
+        }
+        // the line endings of the text when unset
+        let end_of_line = self.end_of_line.unwrap_or(detect_line_ending(text));
+        content.replace('\n', end_of_line)
+    }
+
+    /// Converts the leading whitespace of `line` to the indentation style, when its width is known.
+    fn reindent(&self, line: &str) -> String {
+        let content = line.trim_start_matches([' ', '\t']);
+        let indent = &line[..line.len() - content.len()];
+        let (Some(indent_with_tabs), Some(tab_width)) =
+            (self.indent_with_tabs, self.tab_width.or(self.indent_size))
+        else {
+            return line.to_string();
+        };
+        let columns: usize = indent
+            .chars()
+            .map(|c| if c == '\t' { tab_width } else { 1 })
+            .sum();
+        if indent_with_tabs {
+            format!(
+                "{}{}{content}",
+                "\t".repeat(columns / tab_width),
+                " ".repeat(columns % tab_width)
+            )
+        } else {
+            format!("{}{content}", " ".repeat(columns))
+        }
+    }
+}
+
+impl FileSystemService {
+    /// Sets whether the `.editorconfig` files are applied when writing and editing files
+    /// (default: true).
+    pub fn with_editorconfig(mut self, enabled: bool) -> Self {
+        self.editorconfig = enabled;
+        self
+    }
+
+    pub fn editorconfig_enabled(&self) -> bool {
+        self.editorconfig
+    }
+
+    /// The conventions of the `.editorconfig` files applying to `file_path`, a validated path. Only
+    /// the files within `allowed_directories` are read, malformed files are ignored.
+    pub fn editorconfig_rules(
+        &self,
+        file_path: &Path,
+        allowed_directories: &[PathBuf],
+    ) -> EditorConfigRules {
+        if !self.editorconfig {
+            return EditorConfigRules::default();
+        }
+        let Ok(config_files) = ConfigFiles::open(file_path, None::<&Path>) else {
+            return EditorConfigRules::default();
+        };
+        let allowed_directories: Vec<PathBuf> = allowed_directories
+            .iter()
+            .map(|dir| normalize_path(dir))
+            .collect();
+        let mut properties = Properties::new();
+        for mut config_file in config_files {
+            let within_allowed_directories = config_file.path.parent().is_some_and(|dir| {
+                allowed_directories
+                    .iter()
+                    .any(|allowed_dir| dir.starts_with(allowed_dir))
+            });
+            if within_allowed_directories {
+                let _ = config_file.apply_to(&mut properties, file_path);
+            }
+        }
+        EditorConfigRules::from_properties(properties)
+    }
+}
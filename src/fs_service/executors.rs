@@ -0,0 +1,108 @@
+//! Concurrency groups of the operations of the service.
+//!
+//! Interactive operations, such as reading or writing a file, run on the async runtime. Batch
+//! operations scanning whole trees, such as finding duplicate files, run on a separate, bounded
+//! thread pool and only a limited number of them run at once, the others waiting for their turn.
+//! A long analysis therefore can't occupy all the threads of the runtime or of the global rayon
+//! pool, and quick tool calls stay responsive while it runs.
+
+use crate::fs_service::FileSystemService;
+use std::sync::OnceLock;
+use tokio::runtime::{Handle, RuntimeFlavor};
+use tokio::sync::Semaphore;
+
+/// Limits of the batch operations, such as duplicate detection or relevance ranking.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BatchLimits {
+    /// Number of batch operations running at once, the others wait for their turn.
+    pub concurrency: usize,
+    /// Number of threads of the pool the batch operations run on, shared by all of them.
+    pub threads: usize,
+}
+
+impl Default for BatchLimits {
+    fn default() -> Self {
+        let cpus = std::thread::available_parallelism().map_or(1, |cpus| cpus.get());
+        Self {
+            concurrency: 2,
+            // leave half of the CPUs to interactive operations
+            threads: (cpus / 2).max(1),
+        }
+    }
+}
+
+/// Runs the batch operations within their limits.
+pub(crate) struct BatchExecutor {
+    limits: BatchLimits,
+    permits: Semaphore,
+    // built on first use, most sessions never run a batch operation
+    pool: OnceLock<Option<rayon::ThreadPool>>,
+}
+
+impl BatchExecutor {
+    pub(crate) fn new(limits: BatchLimits) -> Self {
+        let limits = BatchLimits {
+            concurrency: limits.concurrency.max(1),
+            threads: limits.threads.max(1),
+        };
+        Self {
+            limits,
+            permits: Semaphore::new(limits.concurrency),
+            pool: OnceLock::new(),
+        }
+    }
+
+    pub(crate) fn limits(&self) -> BatchLimits {
+        self.limits
+    }
+
+    /// The batch thread pool, `None` if it couldn't be created and the global pool is used instead.
+    fn pool(&self) -> Option<&rayon::ThreadPool> {
+        self.pool
+            .get_or_init(|| {
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(self.limits.threads)
+                    .thread_name(|index| format!("batch-{index}"))
+                    .build()
+                    .ok()
+            })
+            .as_ref()
+    }
+
+    /// Waits for a batch slot, then runs the blocking `operation` in the batch thread pool, where
+    /// its parallel iterators run as well.
+    pub(crate) async fn run<R: Send>(&self, operation: impl FnOnce() -> R + Send) -> R {
+        // the semaphore is never closed
+        let _permit = self.permits.acquire().await.ok();
+        let run = || match self.pool() {
+            Some(pool) => pool.install(operation),
+            None => operation(),
+        };
+        // frees the worker thread of the runtime for other tasks while the operation runs,
+        // a single threaded runtime (e.g. in tests) has no other worker to hand over to
+        match Handle::try_current().map(|handle| handle.runtime_flavor()) {
+            Ok(RuntimeFlavor::MultiThread) => tokio::task::block_in_place(run),
+            _ => run(),
+        }
+    }
+
+    /// Runs `task` in the background on the batch thread pool.
+    pub(crate) fn spawn(&self, task: impl FnOnce() + Send + 'static) {
+        match self.pool() {
+            Some(pool) => pool.spawn(task),
+            None => rayon::spawn(task),
+        }
+    }
+}
+
+impl FileSystemService {
+    /// Sets the limits of the batch operations (default: 2 at once, on half of the CPUs).
+    pub fn with_batch_limits(mut self, batch_limits: BatchLimits) -> Self {
+        self.batch_executor = BatchExecutor::new(batch_limits);
+        self
+    }
+
+    pub fn batch_limits(&self) -> BatchLimits {
+        self.batch_executor.limits()
+    }
+}
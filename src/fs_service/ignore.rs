@@ -0,0 +1,125 @@
+//! Support for `.mcpignore` files.
+//!
+//! An `.mcpignore` file at the root of an allowed directory lists gitignore-style patterns of paths
+//! hidden from the traversal-based tools (search, tree, size and zip), regardless of the arguments
+//! of the call. The supported syntax is a subset of gitignore:
+//! - blank lines and lines starting with `#` are ignored,
+//! - a leading `!` negates the pattern, re-including paths excluded by a previous pattern,
+//! - a trailing `/` only matches directories,
+//! - a pattern containing a `/` elsewhere is relative to the allowed directory, otherwise it matches at any depth,
+//! - `*`, `?`, `**` and character classes are supported, as in glob patterns.
+//!
+//! Excluding a directory excludes all its content, as it's not traversed.
+
+use glob_match::glob_match;
+use std::path::{Path, PathBuf};
+
+use crate::fs_service::utils::normalize_path;
+
+pub const IGNORE_FILE_NAME: &str = ".mcpignore";
+
+#[derive(Debug, Clone, PartialEq)]
+struct IgnorePattern {
+    glob: String,
+    negated: bool,
+    directory_only: bool,
+}
+
+impl IgnorePattern {
+    fn parse(line: &str) -> Option<Self> {
+        let line = line.trim_end();
+        if line.is_empty() || line.starts_with('#') {
+            return None;
+        }
+
+        let (negated, pattern) = match line.strip_prefix('!') {
+            Some(pattern) => (true, pattern),
+            None => (false, line.strip_prefix('\\').unwrap_or(line)),
+        };
+        let (directory_only, pattern) = match pattern.strip_suffix('/') {
+            Some(pattern) => (true, pattern),
+            None => (false, pattern),
+        };
+        if pattern.is_empty() {
+            return None;
+        }
+
+        let glob = if pattern.contains('/') {
+            pattern.trim_start_matches('/').to_string()
+        } else {
+            format!("**/{pattern}")
+        };
+        Some(Self {
+            glob,
+            negated,
+            directory_only,
+        })
+    }
+
+    fn matches(&self, relative_path: &str, is_dir: bool) -> bool {
+        (!self.directory_only || is_dir) && glob_match(&self.glob, relative_path)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct RootIgnoreFile {
+    root: PathBuf,
+    canonical_root: PathBuf,
+    patterns: Vec<IgnorePattern>,
+}
+
+/// Patterns of the `.mcpignore` files of the allowed directories.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IgnoreRules {
+    roots: Vec<RootIgnoreFile>,
+}
+
+impl IgnoreRules {
+    /// Loads the `.mcpignore` files found at the root of the `allowed_directories`.
+    pub fn load(allowed_directories: &[PathBuf]) -> Self {
+        let roots = allowed_directories
+            .iter()
+            .filter_map(|root| {
+                let content = std::fs::read_to_string(root.join(IGNORE_FILE_NAME)).ok()?;
+                let patterns: Vec<IgnorePattern> =
+                    content.lines().filter_map(IgnorePattern::parse).collect();
+                (!patterns.is_empty()).then(|| RootIgnoreFile {
+                    root: root.clone(),
+                    canonical_root: normalize_path(root),
+                    patterns,
+                })
+            })
+            .collect();
+        Self { roots }
+    }
+
+    /// Whether `path` is excluded by the `.mcpignore` file of the allowed directory containing it.
+    /// The last matching pattern decides, so negated patterns can re-include paths.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        self.roots.iter().any(|root_file| {
+            let Some(relative_path) = path
+                .strip_prefix(&root_file.root)
+                .or_else(|_| path.strip_prefix(&root_file.canonical_root))
+                .ok()
+            else {
+                return false;
+            };
+            // patterns use forward slashes on all platforms
+            let relative_path = relative_path
+                .components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            if relative_path.is_empty() {
+                return false;
+            }
+
+            root_file
+                .patterns
+                .iter()
+                .rev()
+                .find(|pattern| pattern.matches(&relative_path, is_dir))
+                .is_some_and(|pattern| !pattern.negated)
+        })
+    }
+}
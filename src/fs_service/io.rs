@@ -1,5 +1,16 @@
+mod backup;
+mod cleanup;
 mod edit;
+mod finder;
+mod profile;
 mod read;
+mod similarity;
 mod write;
 
-pub use read::FileInfo;
+pub use backup::{BackupConfig, BackupEntry};
+pub use cleanup::WhitespaceCleanup;
+pub use finder::{FinderMetadata, FinderTag, FinderTagColor, FinderTagsUpdate};
+pub use profile::{FileProfile, Indentation, detect_file_profile};
+pub use read::{FileInfo, FilePreview};
+pub use similarity::{FileSimilarity, MAX_COMPARED_FILES, SimilarityReport};
+pub use write::FileWriteOutcome;
@@ -0,0 +1,249 @@
+use crate::{
+    error::{ServiceError, ServiceResult},
+    fs_service::{
+        FileSystemService,
+        utils::{create_parent_dirs, normalize_path},
+    },
+};
+use chrono::{NaiveDateTime, Utc};
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+const BACKUP_EXTENSION: &str = "bak";
+const BACKUP_TIMESTAMP_FORMAT: &str = "%Y%m%dT%H%M%S%3fZ";
+
+/// Configuration of server-managed backups.
+#[derive(Debug, Clone)]
+pub struct BackupConfig {
+    /// Directory, relative to each allowed directory, where backups are stored.
+    pub dir: PathBuf,
+    /// Maximum number of backups kept for a single file, older backups are removed first.
+    pub retention: usize,
+}
+
+/// A backup copy created before a file was modified.
+#[derive(Debug, Clone, ::serde::Serialize)]
+pub struct BackupEntry {
+    /// Path of the backup copy.
+    pub backup_path: PathBuf,
+    /// Path of the file the backup was taken from.
+    pub original_path: PathBuf,
+    /// Time the backup was taken (UTC), as encoded in the backup file name.
+    pub created: String,
+    /// Size of the backup in bytes.
+    pub size: u64,
+}
+
+/// Splits a backup file name into the original file name and the timestamp of the backup.
+fn parse_backup_name(file_name: &str) -> Option<(&str, NaiveDateTime)> {
+    let stem = file_name.strip_suffix(&format!(".{BACKUP_EXTENSION}"))?;
+    let (original_name, timestamp) = stem.rsplit_once('.')?;
+    let created = NaiveDateTime::parse_from_str(timestamp, BACKUP_TIMESTAMP_FORMAT).ok()?;
+    Some((original_name, created))
+}
+
+impl FileSystemService {
+    fn require_backup_config(&self) -> ServiceResult<&BackupConfig> {
+        self.backup_config().ok_or(ServiceError::FromString(
+            "Backups are not enabled. Run the server with the --enable-backups flag to use this feature."
+                .to_string(),
+        ))
+    }
+
+    /// Returns the allowed directory that contains `path`, preferring the most specific one.
+    async fn allowed_root_of(&self, path: &Path) -> Option<PathBuf> {
+        let normalized = normalize_path(path);
+        self.allowed_directories()
+            .await
+            .iter()
+            .filter(|dir| {
+                path.starts_with(dir)
+                    || normalized.starts_with(dir)
+                    || normalized.starts_with(normalize_path(dir))
+            })
+            .max_by_key(|dir| dir.components().count())
+            .map(|dir| normalize_path(dir))
+    }
+
+    /// Copies `valid_path` into the backup directory before it gets modified.
+    ///
+    /// Backups mirror the file's location relative to its allowed directory and are named
+    /// `<file name>.<timestamp>.bak`. Only the most recent `retention` backups of a file are kept.
+    /// Returns `None` when backups are disabled or the file doesn't exist yet.
+    pub async fn backup_file(&self, valid_path: &Path) -> ServiceResult<Option<PathBuf>> {
+        // nothing is modified on disk
+        if self.is_simulation() {
+            return Ok(None);
+        }
+        self.backup_disk_file(valid_path).await
+    }
+
+    /// `backup_file` even when the changes are kept in an overlay, for the files replaced when
+    /// it is committed.
+    pub(crate) async fn backup_disk_file(
+        &self,
+        valid_path: &Path,
+    ) -> ServiceResult<Option<PathBuf>> {
+        let Some(backup_config) = self.backup_config() else {
+            return Ok(None);
+        };
+        if !valid_path.is_file() {
+            return Ok(None);
+        }
+
+        let normalized = normalize_path(valid_path);
+        let Some(root) = self.allowed_root_of(&normalized).await else {
+            return Ok(None);
+        };
+        let backup_root = root.join(&backup_config.dir);
+        // never back up the backups themselves
+        if normalized.starts_with(&backup_root) {
+            return Ok(None);
+        }
+
+        let relative_path = normalized.strip_prefix(&root).unwrap_or(&normalized);
+        let file_name = relative_path
+            .file_name()
+            .ok_or(ServiceError::FromString("Invalid path".to_string()))?
+            .to_string_lossy()
+            .into_owned();
+        let backup_parent = match relative_path.parent() {
+            Some(parent) => backup_root.join(parent),
+            None => backup_root.clone(),
+        };
+
+        let timestamp = Utc::now().format(BACKUP_TIMESTAMP_FORMAT);
+        let backup_path = backup_parent.join(format!("{file_name}.{timestamp}.{BACKUP_EXTENSION}"));
+        create_parent_dirs(&backup_path).await?;
+        self.retry_io(|| tokio::fs::copy(&normalized, &backup_path))
+            .await?;
+
+        // enforce the retention policy, timestamps sort chronologically
+        let mut existing: Vec<PathBuf> = std::fs::read_dir(&backup_parent)?
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.path())
+            .filter(|path| {
+                path.file_name()
+                    .and_then(|name| name.to_str())
+                    .and_then(parse_backup_name)
+                    .is_some_and(|(name, _)| name == file_name)
+            })
+            .collect();
+        existing.sort();
+        let excess = existing
+            .len()
+            .saturating_sub(backup_config.retention.max(1));
+        for old_backup in existing.into_iter().take(excess) {
+            tokio::fs::remove_file(old_backup).await?;
+        }
+
+        Ok(Some(backup_path))
+    }
+
+    /// Lists backups, newest first. When `file_path` is provided only backups of that file are returned.
+    pub async fn list_backups(&self, file_path: Option<&Path>) -> ServiceResult<Vec<BackupEntry>> {
+        let backup_config = self.require_backup_config()?;
+
+        let target = match file_path {
+            Some(path) => {
+                let allowed_directories = self.allowed_directories().await;
+                Some(normalize_path(
+                    &self.validate_path(path, allowed_directories)?,
+                ))
+            }
+            None => None,
+        };
+
+        let mut backups = vec![];
+        for root in self.allowed_directories().await.iter() {
+            let root = normalize_path(root);
+            let backup_root = root.join(&backup_config.dir);
+            if !backup_root.is_dir() {
+                continue;
+            }
+
+            for entry in WalkDir::new(&backup_root)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+            {
+                let Some((original_name, created)) =
+                    entry.file_name().to_str().and_then(parse_backup_name)
+                else {
+                    continue;
+                };
+
+                let relative_parent = entry
+                    .path()
+                    .parent()
+                    .and_then(|parent| parent.strip_prefix(&backup_root).ok())
+                    .unwrap_or(Path::new(""));
+                let original_path = root.join(relative_parent).join(original_name);
+
+                if target.as_ref().is_some_and(|t| *t != original_path) {
+                    continue;
+                }
+
+                backups.push(BackupEntry {
+                    backup_path: entry.path().to_path_buf(),
+                    original_path,
+                    created: created.format("%Y-%m-%d %H:%M:%S%.3f UTC").to_string(),
+                    size: entry.metadata().map(|m| m.len()).unwrap_or_default(),
+                });
+            }
+        }
+
+        backups.sort_by(|a, b| b.backup_path.file_name().cmp(&a.backup_path.file_name()));
+        Ok(backups)
+    }
+
+    /// Restores a backup over its original file. The current content of the original file
+    /// is backed up first, so a restore can itself be undone.
+    pub async fn restore_backup(&self, backup_path: &Path) -> ServiceResult<PathBuf> {
+        let backup_config = self.require_backup_config()?;
+        let allowed_directories = self.allowed_directories().await;
+        let valid_backup_path =
+            normalize_path(&self.validate_path(backup_path, allowed_directories.clone())?);
+
+        let invalid_backup = || {
+            ServiceError::FromString(format!(
+                "'{}' is not a backup managed by this server.",
+                backup_path.display()
+            ))
+        };
+
+        let root = self
+            .allowed_root_of(&valid_backup_path)
+            .await
+            .ok_or_else(invalid_backup)?;
+        let backup_root = root.join(&backup_config.dir);
+        let relative_path = valid_backup_path
+            .strip_prefix(&backup_root)
+            .map_err(|_| invalid_backup())?;
+        let (original_name, _) = relative_path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(parse_backup_name)
+            .filter(|(original_name, _)| !matches!(*original_name, "" | "." | ".."))
+            .ok_or_else(invalid_backup)?;
+
+        // the original path derives from the name of the backup, it is checked like any written path
+        let original_path = match relative_path.parent() {
+            Some(parent) => root.join(parent).join(original_name),
+            None => root.join(original_name),
+        };
+        let original_path = self.validate_path(&original_path, allowed_directories)?;
+        self.ensure_modifiable(&original_path).await?;
+        self.backup_file(&original_path).await?;
+        self.create_parent_directories(&original_path).await?;
+        if self.is_simulation() {
+            let content = self.read_file_bytes(&valid_backup_path).await?;
+            self.simulate_write(&original_path, &content)?;
+        } else {
+            self.retry_io(|| tokio::fs::copy(&valid_backup_path, &original_path))
+                .await?;
+        }
+
+        Ok(original_path)
+    }
+}
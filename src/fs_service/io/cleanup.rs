@@ -0,0 +1,145 @@
+use crate::{
+    error::ServiceResult,
+    fs_service::{
+        EditorConfigRules, FileSystemService, Indentation, describe_lock_violation,
+        detect_file_profile,
+        utils::{detect_line_ending, format_diff_block, normalize_line_endings},
+    },
+    metrics::record_bytes,
+};
+use std::path::{Path, PathBuf};
+
+/// Indentation width used to convert spaces to tabs when neither the file nor its `.editorconfig`
+/// tells it.
+const DEFAULT_INDENT_WIDTH: usize = 4;
+
+/// A file whose whitespace is cleaned up.
+#[derive(::serde::Serialize, Debug, Clone)]
+pub struct WhitespaceCleanup {
+    pub path: PathBuf,
+    /// Unified diff of the cleanup.
+    pub diff: String,
+    /// Why the file couldn't be modified, e.g. a pinned file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Strips the trailing whitespace of the lines of `content`, converts the indentation of the lines
+/// not following the style of the file (or the one set by `rules`) and leaves a single final newline.
+fn cleanup_whitespace_text(content: &str, rules: &EditorConfigRules) -> String {
+    let profile = detect_file_profile(content.as_bytes());
+    // only a mixed indentation is converted to the style of the file, unless set by the editorconfig
+    let mixed = profile.tab_indented_lines > 0 && profile.space_indented_lines > 0;
+    let indent_with_tabs = rules
+        .indent_with_tabs
+        .or(mixed.then_some(profile.indentation == Indentation::Tabs));
+    let indent_width = rules
+        .tab_width
+        .or(rules.indent_size)
+        .or(match profile.indentation {
+            Indentation::Spaces { width } => Some(width),
+            _ => None,
+        })
+        .unwrap_or(DEFAULT_INDENT_WIDTH);
+    let cleanup_rules = EditorConfigRules {
+        indent_with_tabs,
+        indent_size: Some(indent_width),
+        tab_width: Some(indent_width),
+        trim_trailing_whitespace: Some(true),
+        ..Default::default()
+    };
+
+    let mut cleaned = cleanup_rules.format_lines(&normalize_line_endings(content));
+    cleaned.truncate(cleaned.trim_end_matches('\n').len());
+    if !cleaned.is_empty() {
+        cleaned.push('\n');
+    }
+    cleaned.replace('\n', detect_line_ending(content))
+}
+
+impl FileSystemService {
+    /// Cleans up the whitespace of the text files under `root_path` matching `pattern`: strips the
+    /// trailing whitespace, converts a mix of tabs and spaces to the indentation style of the file
+    /// (or of its `.editorconfig`) and leaves a single final newline.
+    ///
+    /// Returns the files needing a cleanup with their diff, they are modified unless `dry_run` is
+    /// true. Binary and non UTF-8 files are skipped.
+    pub async fn cleanup_whitespace(
+        &self,
+        root_path: &Path,
+        pattern: String,
+        exclude_patterns: Vec<String>,
+        dry_run: bool,
+    ) -> ServiceResult<Vec<WhitespaceCleanup>> {
+        let allowed_directories = self.allowed_directories().await;
+        let valid_path = self.validate_path(root_path, allowed_directories.clone())?;
+
+        let entries = self
+            .search_files_iter(&valid_path, pattern, exclude_patterns, None, None)
+            .await?
+            .filter(|entry| entry.is_file());
+
+        // A batch operation: reading every file of the tree must not hold up other tool calls
+        let cleanups = self
+            .batch_executor
+            .run(|| {
+                let mut cleanups = vec![];
+                for entry in entries {
+                    let Ok(bytes) = self.read_tree_file(entry.path()) else {
+                        continue;
+                    };
+                    record_bytes(bytes.len() as u64);
+                    let Ok(content) = String::from_utf8(bytes) else {
+                        continue;
+                    };
+                    if content.contains('\0') {
+                        continue;
+                    }
+                    let rules = self.editorconfig_rules(entry.path(), &allowed_directories);
+                    let cleaned = cleanup_whitespace_text(&content, &rules);
+                    if cleaned != content {
+                        cleanups.push((entry.into_path(), content, cleaned));
+                    }
+                }
+                cleanups
+            })
+            .await;
+
+        let mut result = Vec::with_capacity(cleanups.len());
+        for (path, content, cleaned) in cleanups {
+            let diff = format_diff_block(&self.create_unified_diff(
+                &content,
+                &cleaned,
+                Some(path.display().to_string()),
+            ));
+            let error = if dry_run {
+                None
+            } else {
+                self.write_cleaned_file(&path, cleaned)
+                    .await
+                    .err()
+                    .map(|err| err.to_string())
+            };
+            result.push(WhitespaceCleanup { path, diff, error });
+        }
+        Ok(result)
+    }
+
+    async fn write_cleaned_file(&self, path: &Path, content: String) -> ServiceResult<()> {
+        self.ensure_modifiable(path).await?;
+        let content = match self.write_line_endings().line_ending() {
+            Some(line_ending) => normalize_line_endings(&content).replace('\n', line_ending),
+            None => content,
+        };
+        if self.simulate_write(path, content.as_bytes())? {
+            return Ok(());
+        }
+        self.backup_file(path).await?;
+        self.retry_io(|| tokio::fs::write(path, &content))
+            .await
+            .map_err(|err| describe_lock_violation(err, &[path]))?;
+        record_bytes(content.len() as u64);
+        self.record_own_write(path);
+        Ok(())
+    }
+}
@@ -1,9 +1,11 @@
 use crate::{
-    error::ServiceResult,
+    error::{ServiceError, ServiceResult},
     fs_service::{
-        FileSystemService,
-        utils::{detect_line_ending, normalize_line_endings},
+        FileSystemService, detect_file_profile,
+        locks::describe_lock_violation,
+        utils::{detect_line_ending, format_diff_block, line_anchor, normalize_line_endings},
     },
+    metrics::record_bytes,
     tools::EditOperation,
 };
 use rust_mcp_sdk::schema::RpcError;
@@ -11,6 +13,50 @@ use similar::TextDiff;
 use std::path::Path;
 
 impl FileSystemService {
+    /// Checks that the lines identified by `anchors` (as returned by `read_file_lines`) are unchanged,
+    /// so edits based on a previous read are not applied to a file that has changed since.
+    pub async fn verify_line_anchors(
+        &self,
+        file_path: &Path,
+        anchors: &[String],
+    ) -> ServiceResult<()> {
+        let allowed_directories = self.allowed_directories().await;
+        let valid_path = self.validate_path(file_path, allowed_directories)?;
+        let content = self.read_file_string(&valid_path).await?;
+        let lines: Vec<&str> = content.lines().collect();
+
+        let mut mismatches = vec![];
+        for anchor in anchors {
+            let line_number = anchor
+                .split_once(':')
+                .and_then(|(line_number, _)| line_number.parse::<usize>().ok())
+                .filter(|line_number| *line_number > 0)
+                .ok_or(ServiceError::FromString(format!(
+                    "Invalid line anchor '{anchor}', expected '<line number>:<hash>'."
+                )))?;
+            match lines.get(line_number - 1) {
+                Some(line) if line_anchor(line_number, line) == *anchor => {}
+                Some(line) => mismatches.push(format!(
+                    "line {line_number} changed (expected {anchor}, found {})",
+                    line_anchor(line_number, line)
+                )),
+                None => mismatches.push(format!(
+                    "line {line_number} no longer exists (the file has {} lines)",
+                    lines.len()
+                )),
+            }
+        }
+
+        if mismatches.is_empty() {
+            Ok(())
+        } else {
+            Err(ServiceError::FromString(format!(
+                "The file has changed since it was read: {}. Read the lines again before editing.",
+                mismatches.join(", ")
+            )))
+        }
+    }
+
     pub fn create_unified_diff(
         &self,
         original_content: &str,
@@ -47,11 +93,14 @@ impl FileSystemService {
         replace_all: Option<bool>,
     ) -> ServiceResult<String> {
         let allowed_directories = self.allowed_directories().await;
-        let valid_path = self.validate_path(file_path, allowed_directories)?;
+        let valid_path = self.validate_path(file_path, allowed_directories.clone())?;
+        let rules = self.editorconfig_rules(&valid_path, &allowed_directories);
 
         // Read file content and normalize line endings
-        let content_str = tokio::fs::read_to_string(&valid_path).await?;
+        let content_str = self.read_file_string(&valid_path).await?;
+        record_bytes(content_str.len() as u64);
         let original_line_ending = detect_line_ending(&content_str);
+        let profile = detect_file_profile(content_str.as_bytes());
         let content_str = normalize_line_endings(&content_str);
 
         // Apply edits sequentially
@@ -59,7 +108,7 @@ impl FileSystemService {
 
         for edit in edits {
             let normalized_old = normalize_line_endings(&edit.old_text);
-            let normalized_new = normalize_line_endings(&edit.new_text);
+            let normalized_new = rules.format_lines(&normalize_line_endings(&edit.new_text));
             let do_replace_all = replace_all.unwrap_or(false);
 
             // If exact match exists, use it
@@ -94,6 +143,9 @@ impl FileSystemService {
                 .split('\n')
                 .map(|s| s.to_string())
                 .collect();
+            // restored once the lines are joined back, so the final newline is preserved
+            let trailing_whitespace =
+                modified_content[modified_content.trim_end().len()..].to_string();
 
             // skip when the match is impossible:
             if old_lines.len() > content_lines.len() {
@@ -211,7 +263,7 @@ impl FileSystemService {
                         i += 1;
                     }
                 }
-                modified_content = content_lines.join("\n");
+                modified_content = content_lines.join("\n") + &trailing_whitespace;
             } else {
                 // Single match case - use last_match_idx
                 let i = last_match_idx;
@@ -262,7 +314,7 @@ impl FileSystemService {
                     .collect();
 
                 content_lines.splice(i..i + old_lines.len(), new_lines);
-                modified_content = content_lines.join("\n");
+                modified_content = content_lines.join("\n") + &trailing_whitespace;
             }
             if !do_replace_all && match_count == 1 {
                 continue;
@@ -272,6 +324,18 @@ impl FileSystemService {
             }
         }
 
+        // keep the final newline of the file unless set by the editorconfig, edits replacing its
+        // last line may drop it
+        match rules.insert_final_newline.unwrap_or(profile.final_newline) {
+            true if !modified_content.is_empty() && !modified_content.ends_with('\n') => {
+                modified_content.push('\n')
+            }
+            false if rules.insert_final_newline.is_some() => {
+                modified_content.truncate(modified_content.trim_end_matches('\n').len())
+            }
+            _ => {}
+        }
+
         let diff = self.create_unified_diff(
             &content_str,
             &modified_content,
@@ -279,23 +343,28 @@ impl FileSystemService {
         );
 
         // Format diff with appropriate number of backticks
-        let mut num_backticks = 3;
-        while diff.contains(&"`".repeat(num_backticks)) {
-            num_backticks += 1;
-        }
-        let formatted_diff = format!(
-            "{}diff\n{}{}\n\n",
-            "`".repeat(num_backticks),
-            diff,
-            "`".repeat(num_backticks)
-        );
+        let formatted_diff = format_diff_block(&diff);
 
         let is_dry_run = dry_run.unwrap_or(false);
 
         if !is_dry_run {
             let target = save_to.unwrap_or(valid_path.as_path());
-            let modified_content = modified_content.replace("\n", original_line_ending);
-            tokio::fs::write(target, modified_content).await?;
+            self.ensure_modifiable(target).await?;
+            let line_ending = self
+                .write_line_endings()
+                .line_ending()
+                .or(rules.end_of_line)
+                .unwrap_or(original_line_ending);
+            let modified_content = modified_content.replace("\n", line_ending);
+            if self.simulate_write(target, modified_content.as_bytes())? {
+                return Ok(formatted_diff);
+            }
+            self.backup_file(target).await?;
+            self.retry_io(|| tokio::fs::write(target, &modified_content))
+                .await
+                .map_err(|err| describe_lock_violation(err, &[target]))?;
+            record_bytes(modified_content.len() as u64);
+            self.record_own_write(target);
         }
 
         Ok(formatted_diff)
@@ -0,0 +1,391 @@
+use crate::{
+    error::{ServiceError, ServiceResult},
+    fs_service::FileSystemService,
+};
+use rust_mcp_sdk::macros::JsonSchema;
+use std::path::Path;
+
+#[cfg(target_os = "macos")]
+const TAGS_XATTR: &str = "com.apple.metadata:_kMDItemUserTags";
+#[cfg(target_os = "macos")]
+const QUARANTINE_XATTR: &str = "com.apple.quarantine";
+
+const BPLIST_HEADER: &[u8] = b"bplist00";
+const BPLIST_TRAILER_LEN: usize = 32;
+
+/// Color of a Finder tag, as shown next to the file name in Finder.
+#[derive(
+    ::serde::Deserialize, ::serde::Serialize, Clone, Copy, Debug, Default, PartialEq, Eq, JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum FinderTagColor {
+    #[default]
+    None,
+    Gray,
+    Green,
+    Purple,
+    Blue,
+    Yellow,
+    Red,
+    Orange,
+}
+
+impl FinderTagColor {
+    const COLORS: [FinderTagColor; 8] = [
+        FinderTagColor::None,
+        FinderTagColor::Gray,
+        FinderTagColor::Green,
+        FinderTagColor::Purple,
+        FinderTagColor::Blue,
+        FinderTagColor::Yellow,
+        FinderTagColor::Red,
+        FinderTagColor::Orange,
+    ];
+
+    fn from_index(index: usize) -> Self {
+        Self::COLORS.get(index).copied().unwrap_or_default()
+    }
+
+    fn index(self) -> usize {
+        Self::COLORS.iter().position(|c| *c == self).unwrap_or(0)
+    }
+}
+
+/// A macOS Finder tag (label).
+#[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+pub struct FinderTag {
+    /// Name of the tag, e.g. `Important`.
+    pub name: String,
+    /// Optional color of the tag (default: none).
+    #[serde(default)]
+    pub color: FinderTagColor,
+}
+
+impl FinderTag {
+    /// Decodes the binary property list stored in the `com.apple.metadata:_kMDItemUserTags` attribute.
+    /// Returns `None` if the data is not a binary plist holding an array of strings.
+    pub fn decode_list(data: &[u8]) -> Option<Vec<FinderTag>> {
+        let names = decode_bplist_string_array(data)?;
+        Some(
+            names
+                .into_iter()
+                .map(|entry| match entry.rsplit_once('\n') {
+                    Some((name, color)) => FinderTag {
+                        name: name.to_string(),
+                        color: FinderTagColor::from_index(color.parse().unwrap_or(0)),
+                    },
+                    None => FinderTag {
+                        name: entry,
+                        color: FinderTagColor::None,
+                    },
+                })
+                .collect(),
+        )
+    }
+
+    /// Encodes tags as a binary property list, the format Finder stores them in.
+    pub fn encode_list(tags: &[FinderTag]) -> Vec<u8> {
+        let entries: Vec<String> = tags
+            .iter()
+            .map(|tag| match tag.color {
+                FinderTagColor::None => tag.name.clone(),
+                color => format!("{}\n{}", tag.name, color.index()),
+            })
+            .collect();
+        encode_bplist_string_array(&entries)
+    }
+}
+
+impl std::fmt::Display for FinderTag {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.color {
+            FinderTagColor::None => write!(f, "{}", self.name),
+            color => write!(f, "{} ({color:?})", self.name),
+        }
+    }
+}
+
+/// How `update_finder_tags` combines the provided tags with the existing ones.
+#[derive(::serde::Deserialize, ::serde::Serialize, Clone, Copy, Debug, Default, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum FinderTagsUpdate {
+    /// Add the tags, keeping existing ones.
+    #[default]
+    Add,
+    /// Remove tags with matching names.
+    Remove,
+    /// Replace all existing tags.
+    Replace,
+}
+
+/// Finder metadata of a file, read from its extended attributes.
+#[derive(Debug, Clone, Default, ::serde::Serialize)]
+pub struct FinderMetadata {
+    pub tags: Vec<FinderTag>,
+    /// Raw value of the `com.apple.quarantine` attribute, set on files downloaded from the internet.
+    pub quarantine: Option<String>,
+}
+
+impl FileSystemService {
+    /// Reads the Finder tags and quarantine attribute of a file. Only supported on macOS.
+    pub async fn get_finder_metadata(&self, file_path: &Path) -> ServiceResult<FinderMetadata> {
+        let allowed_directories = self.allowed_directories().await;
+        let valid_path = self.validate_path(file_path, allowed_directories)?;
+        read_finder_metadata(&valid_path)
+    }
+
+    /// Updates the Finder tags of a file and returns the resulting tags. Only supported on macOS.
+    pub async fn update_finder_tags(
+        &self,
+        file_path: &Path,
+        tags: Vec<FinderTag>,
+        update: FinderTagsUpdate,
+    ) -> ServiceResult<Vec<FinderTag>> {
+        let allowed_directories = self.allowed_directories().await;
+        let valid_path = self.validate_path(file_path, allowed_directories)?;
+        self.ensure_modifiable(&valid_path).await?;
+
+        let mut current_tags = read_finder_metadata(&valid_path)?.tags;
+        match update {
+            FinderTagsUpdate::Add => {
+                for tag in tags {
+                    match current_tags.iter_mut().find(|t| t.name == tag.name) {
+                        Some(existing) => existing.color = tag.color,
+                        None => current_tags.push(tag),
+                    }
+                }
+            }
+            FinderTagsUpdate::Remove => {
+                current_tags.retain(|t| !tags.iter().any(|tag| tag.name == t.name));
+            }
+            FinderTagsUpdate::Replace => current_tags = tags,
+        }
+
+        // the tags are not kept by the simulation
+        if !self.is_simulation() {
+            write_finder_tags(&valid_path, &current_tags)?;
+        }
+        Ok(current_tags)
+    }
+}
+
+/// Reads Finder metadata of `path`, returns `None` on platforms other than macOS.
+pub(crate) fn finder_metadata(path: &Path) -> Option<FinderMetadata> {
+    if cfg!(target_os = "macos") {
+        read_finder_metadata(path).ok()
+    } else {
+        None
+    }
+}
+
+#[cfg(target_os = "macos")]
+fn read_xattr(path: &Path, name: &str) -> std::io::Result<Option<Vec<u8>>> {
+    use rustix::io::Errno;
+
+    let len = match rustix::fs::getxattr(path, name, &mut [0u8; 0][..]) {
+        Ok(len) => len,
+        Err(Errno::NOATTR) => return Ok(None),
+        Err(err) => return Err(err.into()),
+    };
+    let mut value = vec![0u8; len];
+    let len = rustix::fs::getxattr(path, name, &mut value[..])?;
+    value.truncate(len);
+    Ok(Some(value))
+}
+
+#[cfg(target_os = "macos")]
+fn read_finder_metadata(path: &Path) -> ServiceResult<FinderMetadata> {
+    let tags = match read_xattr(path, TAGS_XATTR)? {
+        Some(data) => FinderTag::decode_list(&data).ok_or(ServiceError::FromString(format!(
+            "Unable to parse Finder tags of '{}'.",
+            path.display()
+        )))?,
+        None => vec![],
+    };
+    let quarantine = read_xattr(path, QUARANTINE_XATTR)?.map(|value| {
+        String::from_utf8_lossy(&value)
+            .trim_end_matches('\0')
+            .to_string()
+    });
+
+    Ok(FinderMetadata { tags, quarantine })
+}
+
+#[cfg(target_os = "macos")]
+fn write_finder_tags(path: &Path, tags: &[FinderTag]) -> ServiceResult<()> {
+    use rustix::{fs::XattrFlags, io::Errno};
+
+    if tags.is_empty() {
+        return match rustix::fs::removexattr(path, TAGS_XATTR) {
+            Ok(()) | Err(Errno::NOATTR) => Ok(()),
+            Err(err) => Err(std::io::Error::from(err).into()),
+        };
+    }
+    rustix::fs::setxattr(
+        path,
+        TAGS_XATTR,
+        &FinderTag::encode_list(tags),
+        XattrFlags::empty(),
+    )
+    .map_err(std::io::Error::from)?;
+    Ok(())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn finder_unsupported() -> ServiceError {
+    ServiceError::FromString("Finder tags are only supported on macOS.".to_string())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn read_finder_metadata(_path: &Path) -> ServiceResult<FinderMetadata> {
+    Err(finder_unsupported())
+}
+
+#[cfg(not(target_os = "macos"))]
+fn write_finder_tags(_path: &Path, _tags: &[FinderTag]) -> ServiceResult<()> {
+    Err(finder_unsupported())
+}
+
+/// Reads a big-endian unsigned integer of `size` bytes.
+fn read_be_uint(data: &[u8], offset: usize, size: usize) -> Option<usize> {
+    let bytes = data.get(offset..offset.checked_add(size)?)?;
+    if size > std::mem::size_of::<usize>() {
+        return None;
+    }
+    Some(bytes.iter().fold(0usize, |acc, b| (acc << 8) | *b as usize))
+}
+
+/// Decodes a binary property list whose top object is an array of strings.
+fn decode_bplist_string_array(data: &[u8]) -> Option<Vec<String>> {
+    if !data.starts_with(BPLIST_HEADER) || data.len() < BPLIST_HEADER.len() + BPLIST_TRAILER_LEN {
+        return None;
+    }
+
+    let trailer = data.len() - BPLIST_TRAILER_LEN;
+    let offset_size = *data.get(trailer + 6)? as usize;
+    let ref_size = *data.get(trailer + 7)? as usize;
+    let num_objects = read_be_uint(data, trailer + 8, 8)?;
+    let top_object = read_be_uint(data, trailer + 16, 8)?;
+    let offset_table = read_be_uint(data, trailer + 24, 8)?;
+
+    let object_offset = |index: usize| -> Option<usize> {
+        if index >= num_objects {
+            return None;
+        }
+        read_be_uint(data, offset_table + index * offset_size, offset_size)
+    };
+
+    // returns the length of an object and where its content starts
+    let object_length = |offset: usize| -> Option<(usize, usize)> {
+        let marker = *data.get(offset)?;
+        if marker & 0x0F != 0x0F {
+            return Some(((marker & 0x0F) as usize, offset + 1));
+        }
+        let int_marker = *data.get(offset + 1)?;
+        if int_marker & 0xF0 != 0x10 {
+            return None;
+        }
+        let int_size = 1usize << (int_marker & 0x0F);
+        Some((
+            read_be_uint(data, offset + 2, int_size)?,
+            offset + 2 + int_size,
+        ))
+    };
+
+    let array_offset = object_offset(top_object)?;
+    if data.get(array_offset)? & 0xF0 != 0xA0 {
+        return None;
+    }
+    let (count, refs_start) = object_length(array_offset)?;
+
+    (0..count)
+        .map(|i| {
+            let object_ref = read_be_uint(data, refs_start + i * ref_size, ref_size)?;
+            let offset = object_offset(object_ref)?;
+            let (len, start) = object_length(offset)?;
+            match data.get(offset)? & 0xF0 {
+                // ASCII string
+                0x50 => Some(String::from_utf8_lossy(data.get(start..start + len)?).into_owned()),
+                // UTF-16BE string, length is in code units
+                0x60 => {
+                    let units: Vec<u16> = data
+                        .get(start..start + len * 2)?
+                        .chunks_exact(2)
+                        .map(|c| u16::from_be_bytes([c[0], c[1]]))
+                        .collect();
+                    String::from_utf16(&units).ok()
+                }
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+/// Writes the marker of an object, followed by its length as an integer object when it doesn't fit the marker.
+fn write_bplist_marker(out: &mut Vec<u8>, kind: u8, len: usize) {
+    if len < 0x0F {
+        out.push(kind | len as u8);
+        return;
+    }
+    out.push(kind | 0x0F);
+    match len {
+        0..=0xFF => out.extend_from_slice(&[0x10, len as u8]),
+        0x100..=0xFFFF => {
+            out.push(0x11);
+            out.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        _ => {
+            out.push(0x12);
+            out.extend_from_slice(&(len as u32).to_be_bytes());
+        }
+    }
+}
+
+/// Encodes an array of strings as a binary property list.
+fn encode_bplist_string_array(strings: &[String]) -> Vec<u8> {
+    let num_objects = strings.len() + 1;
+    let ref_size: usize = if num_objects <= 0xFF { 1 } else { 2 };
+
+    let mut out = BPLIST_HEADER.to_vec();
+    let mut offsets = Vec::with_capacity(num_objects);
+
+    // object 0 is the array, objects 1..=n are the strings
+    offsets.push(out.len());
+    write_bplist_marker(&mut out, 0xA0, strings.len());
+    for index in 1..num_objects {
+        out.extend_from_slice(&index.to_be_bytes()[std::mem::size_of::<usize>() - ref_size..]);
+    }
+
+    for string in strings {
+        offsets.push(out.len());
+        if string.is_ascii() {
+            write_bplist_marker(&mut out, 0x50, string.len());
+            out.extend_from_slice(string.as_bytes());
+        } else {
+            let units: Vec<u16> = string.encode_utf16().collect();
+            write_bplist_marker(&mut out, 0x60, units.len());
+            for unit in units {
+                out.extend_from_slice(&unit.to_be_bytes());
+            }
+        }
+    }
+
+    let offset_table = out.len();
+    let offset_size: usize = match offset_table {
+        0..=0xFF => 1,
+        0x100..=0xFFFF => 2,
+        0x1_0000..=0xFFFF_FFFF => 4,
+        _ => 8,
+    };
+    for offset in offsets {
+        out.extend_from_slice(&(offset as u64).to_be_bytes()[8 - offset_size..]);
+    }
+
+    out.extend_from_slice(&[0; 6]);
+    out.push(offset_size as u8);
+    out.push(ref_size as u8);
+    out.extend_from_slice(&(num_objects as u64).to_be_bytes());
+    out.extend_from_slice(&0u64.to_be_bytes());
+    out.extend_from_slice(&(offset_table as u64).to_be_bytes());
+    out
+}
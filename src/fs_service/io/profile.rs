@@ -0,0 +1,176 @@
+use crate::{
+    error::ServiceResult,
+    fs_service::{FileSystemService, utils::detect_line_ending},
+    metrics::record_bytes,
+};
+use std::{collections::HashMap, fmt::Display, path::Path};
+
+/// Number of lines examined to detect the indentation of a file.
+const MAX_PROFILED_LINES: usize = 10_000;
+
+/// Indentation style of a text file.
+#[derive(::serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(tag = "style", rename_all = "snake_case")]
+pub enum Indentation {
+    /// No indented line.
+    None,
+    Tabs,
+    /// Spaces, `width` being the size of one indentation level.
+    Spaces {
+        width: usize,
+    },
+}
+
+impl Display for Indentation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Indentation::None => write!(f, "none"),
+            Indentation::Tabs => write!(f, "tabs"),
+            Indentation::Spaces { width } => write!(f, "{width} spaces"),
+        }
+    }
+}
+
+/// Conventions of a text file, which edits should preserve.
+#[derive(::serde::Serialize, Debug, Clone, PartialEq, Eq)]
+pub struct FileProfile {
+    /// `utf-8`, `utf-8-bom`, `utf-16le`, `utf-16be` or `unknown` when the content isn't valid UTF-8.
+    pub encoding: &'static str,
+    /// `lf`, `crlf` or `cr`, the first one found.
+    pub line_ending: &'static str,
+    /// Whether the file uses more than one kind of line ending.
+    pub mixed_line_endings: bool,
+    /// Whether the content ends with a line ending.
+    pub final_newline: bool,
+    pub indentation: Indentation,
+    /// Number of lines indented with tabs and with spaces, both being non-zero for mixed indentation.
+    pub tab_indented_lines: usize,
+    pub space_indented_lines: usize,
+    pub line_count: usize,
+}
+
+impl Display for FileProfile {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "encoding: {}", self.encoding)?;
+        writeln!(
+            f,
+            "line ending: {}{}",
+            self.line_ending,
+            if self.mixed_line_endings {
+                " (mixed)"
+            } else {
+                ""
+            }
+        )?;
+        writeln!(
+            f,
+            "final newline: {}",
+            if self.final_newline { "yes" } else { "no" }
+        )?;
+        write!(f, "indentation: {}", self.indentation)?;
+        if self.tab_indented_lines > 0 && self.space_indented_lines > 0 {
+            write!(
+                f,
+                " (mixed: {} lines indented with tabs, {} with spaces)",
+                self.tab_indented_lines, self.space_indented_lines
+            )?;
+        }
+        write!(f, "\nlines: {}", self.line_count)
+    }
+}
+
+/// Detects the encoding, line endings and indentation of `bytes`, the content of a text file.
+pub fn detect_file_profile(bytes: &[u8]) -> FileProfile {
+    let (encoding, text) = if let Some(text) = bytes.strip_prefix(b"\xEF\xBB\xBF") {
+        ("utf-8-bom", String::from_utf8_lossy(text))
+    } else if let Some(text) = bytes.strip_prefix(b"\xFF\xFE") {
+        ("utf-16le", decode_utf16(text, u16::from_le_bytes).into())
+    } else if let Some(text) = bytes.strip_prefix(b"\xFE\xFF") {
+        ("utf-16be", decode_utf16(text, u16::from_be_bytes).into())
+    } else {
+        match std::str::from_utf8(bytes) {
+            Ok(text) => ("utf-8", text.into()),
+            Err(_) => ("unknown", String::from_utf8_lossy(bytes)),
+        }
+    };
+
+    let line_ending = match detect_line_ending(&text) {
+        "\r\n" => "crlf",
+        "\r" => "cr",
+        _ => "lf",
+    };
+    let crlf_count = text.matches("\r\n").count();
+    let cr_count = text.matches('\r').count() - crlf_count;
+    let lf_count = text.matches('\n').count() - crlf_count;
+    let mixed_line_endings = [crlf_count, cr_count, lf_count]
+        .iter()
+        .filter(|count| **count > 0)
+        .count()
+        > 1;
+
+    let mut tab_indented_lines = 0;
+    let mut space_indented_lines = 0;
+    // occurrences of the increase of indentation between consecutive lines indented with spaces
+    let mut space_steps: HashMap<usize, usize> = HashMap::new();
+    let mut previous_spaces = 0;
+    for line in text.lines().take(MAX_PROFILED_LINES) {
+        if line.trim().is_empty() {
+            continue;
+        }
+        if line.starts_with('\t') {
+            tab_indented_lines += 1;
+            continue;
+        }
+        let spaces = line.len() - line.trim_start_matches(' ').len();
+        if spaces > 0 {
+            space_indented_lines += 1;
+        }
+        if spaces > previous_spaces {
+            *space_steps.entry(spaces - previous_spaces).or_default() += 1;
+        }
+        previous_spaces = spaces;
+    }
+    let indentation = if tab_indented_lines == 0 && space_indented_lines == 0 {
+        Indentation::None
+    } else if tab_indented_lines >= space_indented_lines {
+        Indentation::Tabs
+    } else {
+        // the most frequent step, the smallest one on ties
+        let width = space_steps
+            .into_iter()
+            .max_by_key(|(step, count)| (*count, std::cmp::Reverse(*step)))
+            .map_or(1, |(step, _)| step);
+        Indentation::Spaces { width }
+    };
+
+    FileProfile {
+        encoding,
+        line_ending,
+        mixed_line_endings,
+        final_newline: text.ends_with(['\n', '\r']),
+        indentation,
+        tab_indented_lines,
+        space_indented_lines,
+        line_count: text.lines().count(),
+    }
+}
+
+fn decode_utf16(bytes: &[u8], from_bytes: fn([u8; 2]) -> u16) -> String {
+    let units = bytes
+        .chunks_exact(2)
+        .map(|pair| from_bytes([pair[0], pair[1]]));
+    char::decode_utf16(units)
+        .map(|c| c.unwrap_or(char::REPLACEMENT_CHARACTER))
+        .collect()
+}
+
+impl FileSystemService {
+    /// Detects the encoding, line endings, final newline and indentation style of a text file.
+    pub async fn get_file_profile(&self, file_path: &Path) -> ServiceResult<FileProfile> {
+        let allowed_directories = self.allowed_directories().await;
+        let valid_path = self.validate_path(file_path, allowed_directories)?;
+        let bytes = self.read_file_bytes(&valid_path).await?;
+        record_bytes(bytes.len() as u64);
+        Ok(detect_file_profile(&bytes))
+    }
+}
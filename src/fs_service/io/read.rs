@@ -1,17 +1,23 @@
+use super::finder::finder_metadata;
 use crate::{
     error::ServiceResult,
     fs_service::{
-        FileSystemService,
+        FileSystemService, FinderMetadata,
+        platform::raw_metadata,
         utils::{
-            format_permissions, format_system_time, mime_from_path, read_file_as_base64,
-            validate_file_size,
+            FileDigest, effective_access_summary, format_permissions, format_system_time,
+            mime_from_path, read_file_as_base64, sha256_file, validate_file_size,
         },
     },
+    metrics::record_bytes,
 };
-use futures::{StreamExt, stream};
+use futures::{StreamExt, future::join_all, stream};
 use std::fs::{self};
 use std::time::SystemTime;
-use std::{io::SeekFrom, path::Path};
+use std::{
+    io::SeekFrom,
+    path::{Path, PathBuf},
+};
 use tokio::{
     fs::File,
     io::{AsyncBufReadExt, AsyncReadExt, AsyncSeekExt, BufReader},
@@ -27,7 +33,35 @@ impl FileSystemService {
     ) -> ServiceResult<String> {
         let allowed_directories = self.allowed_directories().await;
         let valid_path = self.validate_path(file_path, allowed_directories)?;
-        let content = tokio::fs::read_to_string(valid_path).await?;
+        self.read_valid_text_file(&valid_path, with_line_numbers)
+            .await
+    }
+
+    /// Reads several text files concurrently, returning one result per path in the same order.
+    /// The paths are validated in a single pass.
+    pub async fn read_text_files(
+        &self,
+        file_paths: &[PathBuf],
+        with_line_numbers: bool,
+    ) -> Vec<ServiceResult<String>> {
+        let reads =
+            self.validate_paths(file_paths)
+                .await
+                .into_iter()
+                .map(|valid_path| async move {
+                    self.read_valid_text_file(&valid_path?, with_line_numbers)
+                        .await
+                });
+        join_all(reads).await
+    }
+
+    async fn read_valid_text_file(
+        &self,
+        valid_path: &Path,
+        with_line_numbers: bool,
+    ) -> ServiceResult<String> {
+        let content = self.read_file_string(valid_path).await?;
+        record_bytes(content.len() as u64);
 
         if with_line_numbers {
             Ok(content
@@ -50,6 +84,10 @@ impl FileSystemService {
         // Validate file path against allowed directories
         let allowed_directories = self.allowed_directories().await;
         let valid_path = self.validate_path(file_path, allowed_directories)?;
+        if let Some(content) = self.simulated_content(&valid_path) {
+            let content = String::from_utf8_lossy(&content?).into_owned();
+            return Ok(content.split_inclusive('\n').take(n).collect());
+        }
 
         // Open file asynchronously and create a BufReader
         let file = File::open(&valid_path).await?;
@@ -81,6 +119,11 @@ impl FileSystemService {
         // Validate file path against allowed directories
         let allowed_directories = self.allowed_directories().await;
         let valid_path = self.validate_path(file_path, allowed_directories)?;
+        if let Some(content) = self.simulated_content(&valid_path) {
+            let content = String::from_utf8_lossy(&content?).into_owned();
+            let lines: Vec<&str> = content.split_inclusive('\n').collect();
+            return Ok(lines[lines.len().saturating_sub(n)..].concat());
+        }
 
         // Open file asynchronously
         let file = File::open(&valid_path).await?;
@@ -171,6 +214,14 @@ impl FileSystemService {
         // Validate file path against allowed directories
         let allowed_directories = self.allowed_directories().await;
         let valid_path = self.validate_path(path, allowed_directories)?;
+        if let Some(content) = self.simulated_content(&valid_path) {
+            let content = String::from_utf8_lossy(&content?).into_owned();
+            return Ok(content
+                .split_inclusive('\n')
+                .skip(offset)
+                .take(limit.unwrap_or(usize::MAX))
+                .collect());
+        }
 
         // Open file and get metadata before moving into BufReader
         let file = File::open(&valid_path).await?;
@@ -224,16 +275,17 @@ impl FileSystemService {
         paths: Vec<String>,
         max_bytes: Option<usize>,
     ) -> ServiceResult<Vec<(infer::Type, String)>> {
-        let results = stream::iter(paths)
-            .map(|path| async {
-                self.read_media_file(Path::new(&path), max_bytes)
-                    .await
-                    .map_err(|e| (path, e))
-            })
-            .buffer_unordered(MAX_CONCURRENT_FILE_READ) // Process up to MAX_CONCURRENT_FILE_READ files concurrently
-            .filter_map(|result| async move { result.ok() })
-            .collect::<Vec<_>>()
-            .await;
+        let paths: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+        let valid_paths = self.validate_paths(&paths).await;
+        let results =
+            stream::iter(valid_paths)
+                .map(|valid_path| async move {
+                    self.read_valid_media_file(&valid_path?, max_bytes).await
+                })
+                .buffer_unordered(MAX_CONCURRENT_FILE_READ) // Process up to MAX_CONCURRENT_FILE_READ files concurrently
+                .filter_map(|result| async move { result.ok() })
+                .collect::<Vec<_>>()
+                .await;
         Ok(results)
     }
 
@@ -244,25 +296,85 @@ impl FileSystemService {
     ) -> ServiceResult<(infer::Type, String)> {
         let allowed_directories = self.allowed_directories().await;
         let valid_path = self.validate_path(file_path, allowed_directories)?;
-        validate_file_size(&valid_path, None, max_bytes).await?;
-        let kind = mime_from_path(&valid_path)?;
-        let content = read_file_as_base64(&valid_path).await?;
+        self.read_valid_media_file(&valid_path, max_bytes).await
+    }
+
+    async fn read_valid_media_file(
+        &self,
+        valid_path: &Path,
+        max_bytes: Option<usize>,
+    ) -> ServiceResult<(infer::Type, String)> {
+        validate_file_size(valid_path, None, max_bytes).await?;
+        let kind = mime_from_path(valid_path)?;
+        let content = read_file_as_base64(valid_path).await?;
         Ok((kind, content))
     }
 
-    // Get file stats
+    /// Reads up to `max_bytes` from the beginning of a file to be used as a lightweight preview.
+    /// The MIME type is detected from the file extension or the leading bytes when possible.
+    pub async fn read_file_preview(
+        &self,
+        file_path: &Path,
+        max_bytes: usize,
+    ) -> ServiceResult<FilePreview> {
+        let allowed_directories = self.allowed_directories().await;
+        let valid_path = self.validate_path(file_path, allowed_directories)?;
+
+        let (content, file_size) = match self.simulated_content(&valid_path) {
+            Some(content) => {
+                let mut content = content?;
+                let file_size = content.len() as u64;
+                content.truncate(max_bytes);
+                (content, file_size)
+            }
+            None => {
+                let file = File::open(&valid_path).await?;
+                let file_size = file.metadata().await?.len();
+
+                let mut content = Vec::with_capacity(max_bytes.min(file_size as usize));
+                file.take(max_bytes as u64)
+                    .read_to_end(&mut content)
+                    .await?;
+                (content, file_size)
+            }
+        };
+
+        let mime_type = mime_from_path(&valid_path)
+            .ok()
+            .map(|kind| kind.mime_type().to_string());
+
+        Ok(FilePreview {
+            truncated: (content.len() as u64) < file_size,
+            mime_type,
+            content,
+        })
+    }
+
+    /// Returns the SHA-256 digest and byte count of the current on-disk content of a file, or of
+    /// its simulated content.
+    pub async fn file_digest(&self, file_path: &Path) -> ServiceResult<FileDigest> {
+        let allowed_directories = self.allowed_directories().await;
+        let valid_path = self.validate_path(file_path, allowed_directories)?;
+        if let Some(content) = self.simulated_content(&valid_path) {
+            return Ok(FileDigest::of(&content?));
+        }
+        Ok(sha256_file(&valid_path).await?)
+    }
+
+    // Get file stats, through the overlay of the changes if any
     pub async fn get_file_stats(&self, file_path: &Path) -> ServiceResult<FileInfo> {
         let allowed_directories = self.allowed_directories().await;
         let valid_path = self.validate_path(file_path, allowed_directories)?;
 
-        let metadata = std::fs::metadata(valid_path)?;
+        let entry = self.tree_entry(&valid_path)?;
+        let metadata = entry.metadata().ok();
 
-        let size = metadata.len();
-        let created = metadata.created().ok();
-        let modified = metadata.modified().ok();
-        let accessed = metadata.accessed().ok();
-        let is_directory = metadata.is_dir();
-        let is_file = metadata.is_file();
+        let size = entry.size()?;
+        let created = metadata.as_ref().and_then(|m| m.created().ok());
+        let modified = metadata.as_ref().and_then(|m| m.modified().ok());
+        let accessed = metadata.as_ref().and_then(|m| m.accessed().ok());
+        let is_directory = entry.is_dir();
+        let is_file = entry.is_file();
 
         Ok(FileInfo {
             size,
@@ -271,9 +383,33 @@ impl FileSystemService {
             accessed,
             is_directory,
             is_file,
+            access: effective_access_summary(&valid_path),
+            finder_metadata: finder_metadata(&valid_path),
             metadata,
         })
     }
+
+    /// Returns all the metadata the platform provides for `file_path` as JSON,
+    /// including the names of its extended attributes where supported.
+    pub async fn get_raw_metadata(
+        &self,
+        file_path: &Path,
+    ) -> ServiceResult<serde_json::Map<String, serde_json::Value>> {
+        let allowed_directories = self.allowed_directories().await;
+        let valid_path = self.validate_path(file_path, allowed_directories)?;
+        Ok(raw_metadata(&valid_path)?)
+    }
+}
+
+/// Leading bytes of a file, as returned by `read_file_preview`.
+#[derive(Debug)]
+pub struct FilePreview {
+    /// Detected MIME type, if it could be determined.
+    pub mime_type: Option<String>,
+    /// Raw bytes read from the start of the file.
+    pub content: Vec<u8>,
+    /// Whether the file is larger than the preview.
+    pub truncated: bool,
 }
 
 #[derive(Debug)]
@@ -284,7 +420,12 @@ pub struct FileInfo {
     pub accessed: Option<SystemTime>,
     pub is_directory: bool,
     pub is_file: bool,
-    pub metadata: fs::Metadata,
+    /// Effective access rights of the current user according to the file's ACL (Windows only).
+    pub access: Option<String>,
+    /// Finder tags and quarantine attribute (macOS only).
+    pub finder_metadata: Option<FinderMetadata>,
+    /// `None` for a file changed in memory or a directory created by the overlay.
+    pub metadata: Option<fs::Metadata>,
 }
 
 impl std::fmt::Display for FileInfo {
@@ -305,7 +446,34 @@ permissions: {}
             self.accessed.map_or("".to_string(), format_system_time),
             self.is_directory,
             self.is_file,
-            format_permissions(&self.metadata)
-        )
+            self.metadata
+                .as_ref()
+                .map(format_permissions)
+                .unwrap_or_default()
+        )?;
+
+        // Unix-style permissions are meaningless on Windows, report attributes and ACL instead
+        #[cfg(windows)]
+        if let Some(metadata) = self.metadata.as_ref() {
+            writeln!(
+                f,
+                "attributes: {}",
+                crate::fs_service::utils::format_file_attributes(metadata)
+            )?;
+        }
+        if let Some(access) = self.access.as_ref() {
+            writeln!(f, "access: {access}")?;
+        }
+        if let Some(finder_metadata) = self.finder_metadata.as_ref() {
+            if !finder_metadata.tags.is_empty() {
+                let tags: Vec<String> =
+                    finder_metadata.tags.iter().map(|t| t.to_string()).collect();
+                writeln!(f, "tags: {}", tags.join(", "))?;
+            }
+            if let Some(quarantine) = finder_metadata.quarantine.as_ref() {
+                writeln!(f, "quarantine: {quarantine}")?;
+            }
+        }
+        Ok(())
     }
 }
@@ -0,0 +1,156 @@
+use crate::{
+    error::{ServiceError, ServiceResult},
+    fs_service::FileSystemService,
+};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use similar::{ChangeTag, TextDiff};
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+/// Maximum number of files compared at once, every pair of them being diffed.
+pub const MAX_COMPARED_FILES: usize = 50;
+
+/// Time allowed for the diff of a pair of files, after which a less precise diff is used.
+const PAIR_DIFF_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// Line-based similarity of two files.
+#[derive(::serde::Serialize, Debug, Clone)]
+pub struct FileSimilarity {
+    pub path_a: PathBuf,
+    pub path_b: PathBuf,
+    /// Percentage of the lines of both files they have in common: twice the number of common
+    /// lines over the total number of lines.
+    pub similarity: f64,
+    pub common_lines: usize,
+    pub lines_a: usize,
+    pub lines_b: usize,
+}
+
+/// Similarity of a set of files, as returned by `compare_similarity`.
+#[derive(::serde::Serialize, Debug, Clone)]
+pub struct SimilarityReport {
+    /// Every pair of files, most similar first.
+    pub pairs: Vec<FileSimilarity>,
+    /// Groups of files linked by pairs at least `threshold` percent similar, largest first.
+    pub clusters: Vec<Vec<PathBuf>>,
+    pub threshold: f64,
+}
+
+fn similarity(path_a: &Path, a: &str, path_b: &Path, b: &str) -> FileSimilarity {
+    let diff = TextDiff::configure()
+        .timeout(PAIR_DIFF_TIMEOUT)
+        .diff_lines(a, b);
+    let common_lines = diff
+        .iter_all_changes()
+        .filter(|change| change.tag() == ChangeTag::Equal)
+        .count();
+    let lines_a = a.lines().count();
+    let lines_b = b.lines().count();
+    let similarity = if lines_a + lines_b == 0 {
+        100.0
+    } else {
+        (2 * common_lines) as f64 * 100.0 / (lines_a + lines_b) as f64
+    };
+    FileSimilarity {
+        path_a: path_a.to_path_buf(),
+        path_b: path_b.to_path_buf(),
+        similarity: (similarity * 10.0).round() / 10.0,
+        common_lines,
+        lines_a,
+        lines_b,
+    }
+}
+
+/// Groups the indexes of `count` files linked by `pairs` (single linkage).
+fn clusters(count: usize, pairs: &[(usize, usize)]) -> Vec<Vec<usize>> {
+    let mut parents: Vec<usize> = (0..count).collect();
+    fn find(parents: &mut [usize], index: usize) -> usize {
+        let mut root = index;
+        while parents[root] != root {
+            root = parents[root];
+        }
+        parents[index] = root;
+        root
+    }
+    for &(a, b) in pairs {
+        let (root_a, root_b) = (find(&mut parents, a), find(&mut parents, b));
+        parents[root_a.max(root_b)] = root_a.min(root_b);
+    }
+
+    let mut groups: Vec<Vec<usize>> = vec![vec![]; count];
+    for index in 0..count {
+        let root = find(&mut parents, index);
+        groups[root].push(index);
+    }
+    let mut groups: Vec<Vec<usize>> = groups.into_iter().filter(|group| group.len() > 1).collect();
+    groups.sort_by_key(|group| std::cmp::Reverse(group.len()));
+    groups
+}
+
+impl FileSystemService {
+    /// Computes the line-based similarity of every pair of `paths`, and groups the files whose
+    /// similarity with another file of the group is at least `threshold` percent.
+    pub async fn compare_similarity(
+        &self,
+        paths: &[PathBuf],
+        threshold: f64,
+    ) -> ServiceResult<SimilarityReport> {
+        if paths.len() < 2 {
+            return Err(ServiceError::FromString(
+                "At least two files are required to compare their similarity.".to_string(),
+            ));
+        }
+        if paths.len() > MAX_COMPARED_FILES {
+            return Err(ServiceError::FromString(format!(
+                "At most {MAX_COMPARED_FILES} files can be compared at once, got {}.",
+                paths.len()
+            )));
+        }
+
+        let contents = self
+            .read_text_files(paths, false)
+            .await
+            .into_iter()
+            .zip(paths)
+            .map(|(content, path)| {
+                content
+                    .map_err(|err| ServiceError::FromString(format!("{}: {err}", path.display())))
+            })
+            .collect::<ServiceResult<Vec<String>>>()?;
+
+        let indexes: Vec<(usize, usize)> = (0..paths.len())
+            .flat_map(|a| (a + 1..paths.len()).map(move |b| (a, b)))
+            .collect();
+        let mut pairs: Vec<(usize, usize, FileSimilarity)> = indexes
+            .into_par_iter()
+            .map(|(a, b)| {
+                let pair = similarity(&paths[a], &contents[a], &paths[b], &contents[b]);
+                (a, b, pair)
+            })
+            .collect();
+        pairs.sort_by(|(_, _, a), (_, _, b)| b.similarity.total_cmp(&a.similarity));
+
+        let linked: Vec<(usize, usize)> = pairs
+            .iter()
+            .filter(|(_, _, pair)| pair.similarity >= threshold)
+            .map(|(a, b, _)| (*a, *b))
+            .collect();
+        let clusters = clusters(paths.len(), &linked)
+            .into_iter()
+            .map(|group| {
+                group
+                    .into_iter()
+                    .map(|index| paths[index].clone())
+                    .collect()
+            })
+            .collect();
+
+        Ok(SimilarityReport {
+            pairs: pairs.into_iter().map(|(_, _, pair)| pair).collect(),
+            clusters,
+            threshold,
+        })
+    }
+}
@@ -1,26 +1,224 @@
-use crate::{error::ServiceResult, fs_service::FileSystemService};
+use crate::{
+    error::ServiceResult,
+    fs_service::{
+        FileSystemService,
+        locks::describe_lock_violation,
+        utils::{FileDigest, WriteIfExists, format_diff_block, normalize_line_endings},
+    },
+    metrics::record_bytes,
+};
+use sha2::{Digest, Sha256};
 use std::path::Path;
+use tokio::{
+    fs::OpenOptions,
+    io::{AsyncReadExt, AsyncWriteExt},
+};
+
+/// Result of `write_file`.
+#[derive(Debug)]
+pub struct FileWriteOutcome {
+    /// Unified diff of the previous and the new content, when requested and the file existed.
+    pub diff: Option<String>,
+    /// Digest of the content of the file once written, computed from the written bytes.
+    pub digest: FileDigest,
+}
 
 impl FileSystemService {
-    pub async fn write_file(&self, file_path: &Path, content: &String) -> ServiceResult<()> {
+    /// Writes `content` to `file_path`, honoring the `if_exists` policy (or the service default).
+    ///
+    /// When `with_diff` is true and the file already existed, a unified diff between the previous
+    /// and the new content is returned as a fenced code block. The digest of the file is computed
+    /// from the written bytes, not by reading the file again.
+    pub async fn write_file(
+        &self,
+        file_path: &Path,
+        content: &str,
+        create_parents: bool,
+        if_exists: Option<WriteIfExists>,
+        with_diff: bool,
+    ) -> ServiceResult<FileWriteOutcome> {
         let allowed_directories = self.allowed_directories().await;
-        let valid_path = self.validate_path(file_path, allowed_directories)?;
-        tokio::fs::write(valid_path, content).await?;
-        Ok(())
+        let valid_path = self.validate_path(file_path, allowed_directories.clone())?;
+        self.ensure_modifiable(&valid_path).await?;
+        if create_parents {
+            self.create_parent_directories(&valid_path).await?;
+        }
+        let mut content = self
+            .editorconfig_rules(&valid_path, &allowed_directories)
+            .format_content(content);
+        if let Some(line_ending) = self.write_line_endings().line_ending() {
+            content = normalize_line_endings(&content).replace('\n', line_ending);
+        }
+        let content = content.as_str();
+
+        let if_exists = if_exists.unwrap_or(self.write_if_exists());
+
+        if self.is_simulation() {
+            return self.simulate_write_file(&valid_path, content, if_exists, with_diff);
+        }
+
+        let previous_content = if with_diff && if_exists != WriteIfExists::Fail {
+            match tokio::fs::read(&valid_path).await {
+                Ok(bytes) => Some(String::from_utf8_lossy(&bytes).into_owned()),
+                Err(err) if err.kind() == std::io::ErrorKind::NotFound => None,
+                Err(err) => return Err(err.into()),
+            }
+        } else {
+            None
+        };
+
+        if if_exists != WriteIfExists::Fail {
+            self.backup_file(&valid_path).await?;
+        }
+
+        let mut options = OpenOptions::new();
+        match if_exists {
+            WriteIfExists::Fail => options.write(true).create_new(true),
+            WriteIfExists::Overwrite => options.write(true).create(true).truncate(true),
+            // the existing content is read through the same handle, for the digest
+            WriteIfExists::Append => options.read(true).append(true).create(true),
+        };
+
+        // only opening is retried, a partially written content must not be written again
+        let mut file = self.retry_io(|| options.open(&valid_path)).await.map_err(|err| {
+            if err.kind() == std::io::ErrorKind::AlreadyExists {
+                std::io::Error::new(
+                    err.kind(),
+                    format!(
+                        "'{}' already exists. Use if_exists=overwrite or if_exists=append to modify it.",
+                        valid_path.display()
+                    ),
+                )
+            } else {
+                describe_lock_violation(err, &[&valid_path])
+            }
+        })?;
+        let mut hasher = Sha256::new();
+        let mut bytes = content.len() as u64;
+        if if_exists == WriteIfExists::Append {
+            let mut existing_content = vec![];
+            file.read_to_end(&mut existing_content).await?;
+            hasher.update(&existing_content);
+            bytes += existing_content.len() as u64;
+        }
+        file.write_all(content.as_bytes()).await?;
+        file.flush().await?;
+        hasher.update(content.as_bytes());
+        record_bytes(content.len() as u64);
+        self.record_own_write(&valid_path);
+        let digest = FileDigest {
+            sha256: format!("{:x}", hasher.finalize()),
+            bytes,
+        };
+
+        let diff = previous_content.map(|previous_content| {
+            let new_content = match if_exists {
+                WriteIfExists::Append => format!("{previous_content}{content}"),
+                _ => content.to_string(),
+            };
+            format_diff_block(&self.create_unified_diff(
+                &previous_content,
+                &new_content,
+                Some(valid_path.display().to_string()),
+            ))
+        });
+
+        Ok(FileWriteOutcome { diff, digest })
+    }
+
+    /// `write_file` in simulation mode, the file is written to the overlay.
+    fn simulate_write_file(
+        &self,
+        valid_path: &Path,
+        content: &str,
+        if_exists: WriteIfExists,
+        with_diff: bool,
+    ) -> ServiceResult<FileWriteOutcome> {
+        let previous_content = match self.simulated_content(valid_path) {
+            Some(Err(err)) if err.kind() == std::io::ErrorKind::NotFound => None,
+            Some(previous_content) => Some(previous_content?),
+            None if valid_path.exists() => Some(std::fs::read(valid_path)?),
+            None => None,
+        };
+        if !valid_path
+            .parent()
+            .is_none_or(|parent| self.path_exists(parent))
+        {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::NotFound,
+                format!(
+                    "The parent directory of '{}' does not exist.",
+                    valid_path.display()
+                ),
+            )
+            .into());
+        }
+
+        let new_content = match (if_exists, &previous_content) {
+            (WriteIfExists::Fail, Some(_)) => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::AlreadyExists,
+                    format!(
+                        "'{}' already exists. Use if_exists=overwrite or if_exists=append to modify it.",
+                        valid_path.display()
+                    ),
+                )
+                .into());
+            }
+            (WriteIfExists::Append, Some(previous_content)) => {
+                [previous_content.as_slice(), content.as_bytes()].concat()
+            }
+            _ => content.as_bytes().to_vec(),
+        };
+
+        let diff = previous_content
+            .filter(|_| with_diff)
+            .map(|previous_content| {
+                format_diff_block(&self.create_unified_diff(
+                    &String::from_utf8_lossy(&previous_content),
+                    &String::from_utf8_lossy(&new_content),
+                    Some(valid_path.display().to_string()),
+                ))
+            });
+        self.simulate_write(valid_path, &new_content)?;
+        Ok(FileWriteOutcome {
+            diff,
+            digest: FileDigest::of(&new_content),
+        })
     }
 
     pub async fn create_directory(&self, file_path: &Path) -> ServiceResult<()> {
         let allowed_directories = self.allowed_directories().await;
         let valid_path = self.validate_path(file_path, allowed_directories)?;
-        tokio::fs::create_dir_all(valid_path).await?;
+        self.ensure_modifiable(&valid_path).await?;
+        if !self.simulate_create_dir(&valid_path) {
+            tokio::fs::create_dir_all(valid_path).await?;
+        }
         Ok(())
     }
 
-    pub async fn move_file(&self, src_path: &Path, dest_path: &Path) -> ServiceResult<()> {
+    pub async fn move_file(
+        &self,
+        src_path: &Path,
+        dest_path: &Path,
+        create_parents: bool,
+    ) -> ServiceResult<()> {
         let allowed_directories = self.allowed_directories().await;
         let valid_src_path = self.validate_path(src_path, allowed_directories.clone())?;
         let valid_dest_path = self.validate_path(dest_path, allowed_directories)?;
-        tokio::fs::rename(valid_src_path, valid_dest_path).await?;
+        // moving a directory moves the pinned paths it contains
+        self.ensure_not_pinned(&valid_src_path, true).await?;
+        self.ensure_writable_filesystem(&valid_src_path)?;
+        self.ensure_modifiable(&valid_dest_path).await?;
+        if create_parents {
+            self.create_parent_directories(&valid_dest_path).await?;
+        }
+        if let Some(moved) = self.simulate_rename(&valid_src_path, &valid_dest_path) {
+            return Ok(moved?);
+        }
+        self.retry_io(|| tokio::fs::rename(&valid_src_path, &valid_dest_path))
+            .await
+            .map_err(|err| describe_lock_violation(err, &[&valid_src_path, &valid_dest_path]))?;
         Ok(())
     }
 }
@@ -0,0 +1,338 @@
+//! Diagnostics for files locked by other processes.
+//!
+//! On Windows, opening, writing or renaming a file held open by another process without sharing
+//! fails with a sharing violation that doesn't tell which process is involved. The Restart Manager
+//! API lists the processes using a file, which turns the error into actionable guidance.
+//!
+//! Before a file is modified, its lock state tells whether another process appears to be using it:
+//! holding a `flock` lock on Unix, or opened without sharing on Windows, or having just modified it.
+//! It is advisory, the write still happens and the tools only warn about possible lost updates.
+
+use crate::{error::ServiceResult, fs_service::FileSystemService};
+use std::{
+    collections::HashMap,
+    io,
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+/// Files modified within this duration, other than by the server, are reported as recently modified.
+pub const RECENT_MODIFICATION_WINDOW: Duration = Duration::from_secs(5);
+
+/// Modification times of the files last written by the server, to tell its own writes apart.
+pub(crate) type OwnWrites = HashMap<PathBuf, SystemTime>;
+
+/// Windows `ERROR_SHARING_VIOLATION`.
+#[cfg(windows)]
+const ERROR_SHARING_VIOLATION: i32 = 32;
+/// Windows `ERROR_LOCK_VIOLATION`.
+#[cfg(windows)]
+const ERROR_LOCK_VIOLATION: i32 = 33;
+
+/// A process holding a file open.
+#[derive(Debug, Clone, PartialEq, Eq, ::serde::Serialize)]
+pub struct LockHolder {
+    pub process_id: u32,
+    pub name: String,
+}
+
+/// Whether `err` is a sharing or lock violation caused by another process using the file.
+pub fn is_lock_violation(err: &io::Error) -> bool {
+    #[cfg(windows)]
+    {
+        matches!(
+            err.raw_os_error(),
+            Some(ERROR_SHARING_VIOLATION | ERROR_LOCK_VIOLATION)
+        )
+    }
+
+    #[cfg(not(windows))]
+    {
+        let _ = err;
+        false
+    }
+}
+
+/// Lists the processes holding any of `paths` open, using the Restart Manager API.
+/// Returns an empty list where the information is unavailable.
+#[cfg(windows)]
+pub fn lock_holders(paths: &[&Path]) -> Vec<LockHolder> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::{
+        Foundation::{ERROR_MORE_DATA, ERROR_SUCCESS},
+        System::RestartManager::{
+            CCH_RM_SESSION_KEY, RM_PROCESS_INFO, RmEndSession, RmGetList, RmRegisterResources,
+            RmStartSession,
+        },
+    };
+
+    let wide_paths: Vec<Vec<u16>> = paths
+        .iter()
+        .map(|path| path.as_os_str().encode_wide().chain(Some(0)).collect())
+        .collect();
+    let path_pointers: Vec<*const u16> = wide_paths.iter().map(|path| path.as_ptr()).collect();
+
+    let mut session: u32 = 0;
+    let mut session_key = [0u16; CCH_RM_SESSION_KEY as usize + 1];
+
+    // SAFETY: all pointers passed to the Win32 calls are valid for the duration of the calls,
+    // the paths are null-terminated and the session is ended before returning.
+    unsafe {
+        if RmStartSession(&mut session, 0, session_key.as_mut_ptr()) != ERROR_SUCCESS {
+            return vec![];
+        }
+
+        let mut processes: Vec<RM_PROCESS_INFO> = vec![];
+        let registered = RmRegisterResources(
+            session,
+            path_pointers.len() as u32,
+            path_pointers.as_ptr(),
+            0,
+            std::ptr::null(),
+            0,
+            std::ptr::null(),
+        ) == ERROR_SUCCESS;
+
+        if registered {
+            // the list can grow between the calls, retry a few times with the reported size
+            for _ in 0..3 {
+                let mut needed: u32 = 0;
+                let mut count = processes.len() as u32;
+                let mut reboot_reasons: u32 = 0;
+                let status = RmGetList(
+                    session,
+                    &mut needed,
+                    &mut count,
+                    processes.as_mut_ptr(),
+                    &mut reboot_reasons,
+                );
+                if status == ERROR_SUCCESS {
+                    processes.truncate(count as usize);
+                    break;
+                }
+                processes.clear();
+                if status != ERROR_MORE_DATA {
+                    break;
+                }
+                processes.resize(needed as usize, std::mem::zeroed());
+            }
+        }
+
+        RmEndSession(session);
+
+        processes
+            .iter()
+            .map(|process| {
+                let name_length = process
+                    .strAppName
+                    .iter()
+                    .position(|c| *c == 0)
+                    .unwrap_or(process.strAppName.len());
+                LockHolder {
+                    process_id: process.Process.dwProcessId,
+                    name: String::from_utf16_lossy(&process.strAppName[..name_length]),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(not(windows))]
+pub fn lock_holders(_paths: &[&Path]) -> Vec<LockHolder> {
+    vec![]
+}
+
+/// Adds the processes holding `paths` open to the message of lock violations, so users know which
+/// application to close. Other errors are returned unchanged.
+pub fn describe_lock_violation(err: io::Error, paths: &[&Path]) -> io::Error {
+    if !is_lock_violation(&err) {
+        return err;
+    }
+
+    let holders = lock_holders(paths);
+    let locked = paths
+        .iter()
+        .map(|path| format!("'{}'", path.display()))
+        .collect::<Vec<_>>()
+        .join(" or ");
+    let message = if holders.is_empty() {
+        format!("{locked} is locked by another process: {err}")
+    } else {
+        let holders = holders
+            .iter()
+            .map(|holder| format!("{} (PID {})", holder.name, holder.process_id))
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "{locked} is locked by another process: {holders}. Close the file in that application and try again."
+        )
+    };
+    io::Error::new(err.kind(), message)
+}
+
+/// Whether another process appears to be using a file.
+#[derive(Debug, Clone, PartialEq, ::serde::Serialize)]
+pub struct FileLockState {
+    pub path: PathBuf,
+    pub exists: bool,
+    /// Whether another process holds a lock on the file, `None` where it can't be checked.
+    pub locked: Option<bool>,
+    /// The processes holding the file open, where they can be listed (Windows).
+    pub holders: Vec<LockHolder>,
+    /// Seconds since the last modification of the file.
+    pub modified_seconds_ago: Option<f64>,
+    /// Whether the file was modified within the last few seconds, other than by the server.
+    pub recently_modified: bool,
+}
+
+impl std::fmt::Display for FileLockState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if !self.exists {
+            return write!(f, "'{}' doesn't exist.", self.path.display());
+        }
+        let locked = match self.locked {
+            Some(true) => "yes",
+            Some(false) => "no",
+            None => "unknown",
+        };
+        write!(f, "locked: {locked}")?;
+        for holder in &self.holders {
+            write!(f, "\n  {} (PID {})", holder.name, holder.process_id)?;
+        }
+        if let Some(modified_seconds_ago) = self.modified_seconds_ago {
+            write!(f, "\nmodified: {modified_seconds_ago:.1} seconds ago")?;
+        }
+        match self.warning() {
+            Some(warning) => write!(f, "\nwarning: {warning}"),
+            None => write!(f, "\nwarning: none"),
+        }
+    }
+}
+
+impl FileLockState {
+    /// A warning for the tools about to modify the file, `None` if it doesn't appear to be in use.
+    pub fn warning(&self) -> Option<String> {
+        let path = self.path.display();
+        if self.locked == Some(true) {
+            let holders = self
+                .holders
+                .iter()
+                .map(|holder| format!("{} (PID {})", holder.name, holder.process_id))
+                .collect::<Vec<_>>();
+            return Some(if holders.is_empty() {
+                format!(
+                    "'{path}' is locked by another process, its changes may overwrite or be overwritten by this one."
+                )
+            } else {
+                format!(
+                    "'{path}' is open in {}, its changes may overwrite or be overwritten by this one.",
+                    holders.join(", ")
+                )
+            });
+        }
+        if self.recently_modified {
+            return Some(format!(
+                "'{path}' was modified by another process {:.1} seconds ago, read it again if the change is unexpected.",
+                self.modified_seconds_ago.unwrap_or_default()
+            ));
+        }
+        None
+    }
+}
+
+/// Checks whether another process holds a lock on `path`, by trying to take an exclusive `flock`
+/// lock without blocking.
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn probe_lock(path: &Path) -> Option<bool> {
+    use rustix::{fs::FlockOperation, io::Errno};
+    let file = std::fs::File::open(path).ok()?;
+    match rustix::fs::flock(&file, FlockOperation::NonBlockingLockExclusive) {
+        // released when the file is closed
+        Ok(()) => Some(false),
+        Err(Errno::WOULDBLOCK) => Some(true),
+        Err(_) => None,
+    }
+}
+
+/// Checks whether another process holds `path` open, by opening it without sharing.
+#[cfg(windows)]
+fn probe_lock(path: &Path) -> Option<bool> {
+    use std::os::windows::fs::OpenOptionsExt;
+    match std::fs::OpenOptions::new()
+        .read(true)
+        .share_mode(0)
+        .open(path)
+    {
+        Ok(_) => Some(false),
+        Err(err) if is_lock_violation(&err) => Some(true),
+        Err(_) => None,
+    }
+}
+
+#[cfg(not(any(target_os = "macos", target_os = "linux", windows)))]
+fn probe_lock(_path: &Path) -> Option<bool> {
+    None
+}
+
+impl FileSystemService {
+    /// Reports whether another process appears to be using `file_path`: holding a lock on it or
+    /// having modified it within the last few seconds.
+    pub async fn file_lock_state(&self, file_path: &Path) -> ServiceResult<FileLockState> {
+        let allowed_directories = self.allowed_directories().await;
+        let valid_path = self.validate_path(file_path, allowed_directories)?;
+
+        let metadata = match tokio::fs::metadata(&valid_path).await {
+            Ok(metadata) => Some(metadata),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => None,
+            Err(err) => return Err(err.into()),
+        };
+        let Some(metadata) = metadata.filter(|metadata| metadata.is_file()) else {
+            return Ok(FileLockState {
+                path: valid_path,
+                exists: false,
+                locked: None,
+                holders: vec![],
+                modified_seconds_ago: None,
+                recently_modified: false,
+            });
+        };
+
+        let locked = probe_lock(&valid_path);
+        let holders = if locked == Some(true) {
+            lock_holders(&[&valid_path])
+        } else {
+            vec![]
+        };
+        let modified = metadata.modified().ok();
+        let modified_ago = modified.map(|modified| modified.elapsed().unwrap_or_default());
+        let own_write = modified.is_some()
+            && self.own_writes.lock().unwrap().get(&valid_path) == modified.as_ref();
+        let recently_modified = !own_write
+            && modified_ago.is_some_and(|modified_ago| modified_ago < RECENT_MODIFICATION_WINDOW);
+
+        Ok(FileLockState {
+            path: valid_path,
+            exists: true,
+            locked,
+            holders,
+            modified_seconds_ago: modified_ago.map(|modified_ago| modified_ago.as_secs_f64()),
+            recently_modified,
+        })
+    }
+
+    /// Remembers the modification time of `path`, just written by the server, so its own writes
+    /// are not reported as recent modifications by another process.
+    pub(crate) fn record_own_write(&self, path: &Path) {
+        let Ok(modified) = std::fs::metadata(path).and_then(|metadata| metadata.modified()) else {
+            return;
+        };
+        let mut own_writes = self.own_writes.lock().unwrap();
+        // older writes can't be recent modifications anymore
+        own_writes.retain(|_, modified| {
+            modified
+                .elapsed()
+                .is_ok_and(|elapsed| elapsed < RECENT_MODIFICATION_WINDOW)
+        });
+        own_writes.insert(path.to_path_buf(), modified);
+    }
+}
@@ -0,0 +1,171 @@
+//! Validation of path lengths against the limits of the supported platforms.
+//!
+//! Limits are checked on the resolved absolute path, as that's what the operating system sees.
+//! Linux and macOS count bytes of the UTF-8 encoding, Windows counts UTF-16 code units.
+
+use crate::error::ServiceResult;
+use crate::fs_service::FileSystemService;
+use rust_mcp_sdk::macros::JsonSchema;
+use std::path::{Component, Path};
+
+/// Platform whose path length limits are checked.
+#[derive(
+    ::serde::Deserialize, ::serde::Serialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum PathPlatform {
+    Linux,
+    Macos,
+    Windows,
+}
+
+impl PathPlatform {
+    /// The platform the server is running on.
+    pub fn current() -> Self {
+        if cfg!(windows) {
+            PathPlatform::Windows
+        } else if cfg!(target_os = "macos") {
+            PathPlatform::Macos
+        } else {
+            PathPlatform::Linux
+        }
+    }
+
+    /// Maximum length of a single path component (`NAME_MAX`).
+    fn max_component_length(self) -> usize {
+        255
+    }
+
+    /// Maximum length of a full path (`PATH_MAX`, `MAX_PATH` on Windows), excluding the terminating NUL.
+    fn max_path_length(self) -> usize {
+        match self {
+            PathPlatform::Linux => 4095,
+            PathPlatform::Macos => 1023,
+            // MAX_PATH is 260 including the terminating NUL character
+            PathPlatform::Windows => 259,
+        }
+    }
+
+    fn length(self, value: &str) -> usize {
+        match self {
+            PathPlatform::Windows => value.encode_utf16().count(),
+            PathPlatform::Linux | PathPlatform::Macos => value.len(),
+        }
+    }
+
+    fn unit(self) -> &'static str {
+        match self {
+            PathPlatform::Windows => "characters",
+            PathPlatform::Linux | PathPlatform::Macos => "bytes",
+        }
+    }
+}
+
+/// Maximum length of an extended-length (`\\?\`) path on Windows.
+const WINDOWS_MAX_LONG_PATH_LENGTH: usize = 32767;
+
+/// A path or path component exceeding a platform limit.
+#[derive(::serde::Serialize, Clone, Debug)]
+pub struct PathLengthIssue {
+    /// The offending component, `None` when the full path is too long.
+    pub component: Option<String>,
+    pub length: usize,
+    pub limit: usize,
+    pub message: String,
+}
+
+/// Result of checking a single path.
+#[derive(::serde::Serialize, Clone, Debug)]
+pub struct PathLengthReport {
+    /// The requested path.
+    pub path: String,
+    /// Length of the resolved absolute path.
+    pub length: usize,
+    pub valid: bool,
+    pub issues: Vec<PathLengthIssue>,
+}
+
+/// Checks `path` against the path and component length limits of `platform`.
+pub fn check_path_length(path: &Path, platform: PathPlatform) -> Vec<PathLengthIssue> {
+    let unit = platform.unit();
+    let mut issues = vec![];
+
+    for component in path.components() {
+        let Component::Normal(name) = component else {
+            continue;
+        };
+        let name = name.to_string_lossy();
+        let length = platform.length(&name);
+        let limit = platform.max_component_length();
+        if length > limit {
+            issues.push(PathLengthIssue {
+                message: format!(
+                    "Component '{name}' is {length} {unit} long, exceeding the limit of {limit} {unit}."
+                ),
+                component: Some(name.into_owned()),
+                length,
+                limit,
+            });
+        }
+    }
+
+    let length = platform.length(&path.to_string_lossy());
+    let limit = platform.max_path_length();
+    if platform == PathPlatform::Windows && length > WINDOWS_MAX_LONG_PATH_LENGTH {
+        issues.push(PathLengthIssue {
+            component: None,
+            length,
+            limit: WINDOWS_MAX_LONG_PATH_LENGTH,
+            message: format!(
+                "Path is {length} {unit} long, exceeding the limit of {WINDOWS_MAX_LONG_PATH_LENGTH} {unit} for extended-length paths."
+            ),
+        });
+    } else if platform == PathPlatform::Windows && length > limit {
+        issues.push(PathLengthIssue {
+            component: None,
+            length,
+            limit,
+            message: format!(
+                "Path is {length} {unit} long, exceeding MAX_PATH ({limit} {unit}). It only works where long paths are enabled."
+            ),
+        });
+    } else if length > limit {
+        issues.push(PathLengthIssue {
+            component: None,
+            length,
+            limit,
+            message: format!(
+                "Path is {length} {unit} long, exceeding the limit of {limit} {unit}."
+            ),
+        });
+    }
+
+    issues
+}
+
+impl FileSystemService {
+    /// Checks whether the proposed `paths` would exceed the path length limits of `platform`.
+    /// The paths don't need to exist, but must be within the allowed directories.
+    pub async fn check_path_lengths(
+        &self,
+        paths: &[String],
+        platform: PathPlatform,
+    ) -> ServiceResult<Vec<PathLengthReport>> {
+        let allowed_directories = self.allowed_directories().await;
+
+        paths
+            .iter()
+            .map(|path| {
+                let valid_path =
+                    self.validate_path(Path::new(path), allowed_directories.clone())?;
+                let issues = check_path_length(&valid_path, platform);
+                Ok(PathLengthReport {
+                    path: path.to_owned(),
+                    length: platform.length(&valid_path.to_string_lossy()),
+                    valid: issues.is_empty(),
+                    issues,
+                })
+            })
+            .collect()
+    }
+}
@@ -0,0 +1,249 @@
+//! Platform-independent access to file metadata that is only natively available on some platforms.
+//!
+//! `std::os::unix::fs::MetadataExt` and `std::os::windows::fs::MetadataExt` expose different
+//! information, `PortableMetadata` provides a common subset with sensible equivalents where a
+//! platform lacks a concept, so callers don't need to be cfg-gated.
+
+use serde_json::{Map, Value, json};
+use std::fs::Metadata;
+#[cfg(unix)]
+use std::os::unix::fs::MetadataExt;
+#[cfg(windows)]
+use std::os::windows::fs::MetadataExt;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+/// Size of the blocks reported by `allocated_blocks`, as used by `st_blocks` on Unix.
+pub const BLOCK_SIZE: u64 = 512;
+
+pub trait PortableMetadata {
+    /// Permission bits in Unix notation (e.g. `0o644`).
+    /// Where Unix permissions don't exist they are derived from the read-only flag.
+    fn permission_bits(&self) -> u32;
+
+    /// User id of the owner, `None` on platforms without numeric owners (Windows).
+    fn owner_uid(&self) -> Option<u32>;
+
+    /// Group id of the owner, `None` on platforms without numeric groups (Windows).
+    fn owner_gid(&self) -> Option<u32>;
+
+    /// Number of `BLOCK_SIZE` blocks allocated on disk, estimated from the file size where unavailable.
+    fn allocated_blocks(&self) -> u64;
+
+    /// Windows file attributes (`FILE_ATTRIBUTE_*` flags), `None` on other platforms.
+    fn windows_attributes(&self) -> Option<u32>;
+}
+
+impl PortableMetadata for Metadata {
+    fn permission_bits(&self) -> u32 {
+        #[cfg(unix)]
+        {
+            self.mode() & 0o7777
+        }
+
+        #[cfg(not(unix))]
+        {
+            let bits = if self.permissions().readonly() {
+                0o444
+            } else {
+                0o666
+            };
+            // directories need the execute bit to be traversable
+            if self.is_dir() { bits | 0o111 } else { bits }
+        }
+    }
+
+    fn owner_uid(&self) -> Option<u32> {
+        #[cfg(unix)]
+        {
+            Some(self.uid())
+        }
+
+        #[cfg(not(unix))]
+        {
+            None
+        }
+    }
+
+    fn owner_gid(&self) -> Option<u32> {
+        #[cfg(unix)]
+        {
+            Some(self.gid())
+        }
+
+        #[cfg(not(unix))]
+        {
+            None
+        }
+    }
+
+    fn allocated_blocks(&self) -> u64 {
+        #[cfg(unix)]
+        {
+            self.blocks()
+        }
+
+        #[cfg(not(unix))]
+        {
+            self.len().div_ceil(BLOCK_SIZE)
+        }
+    }
+
+    fn windows_attributes(&self) -> Option<u32> {
+        #[cfg(windows)]
+        {
+            Some(self.file_attributes())
+        }
+
+        #[cfg(not(windows))]
+        {
+            None
+        }
+    }
+}
+
+/// Dumps all the metadata the platform provides for `path` as JSON, for debugging purposes.
+/// Field names follow the platform APIs (e.g. `stat` fields on Unix), timestamps are in seconds
+/// since the Unix epoch. Symbolic links are followed, whether `path` is one is reported separately.
+pub fn raw_metadata(path: &Path) -> std::io::Result<Map<String, Value>> {
+    let metadata = std::fs::metadata(path)?;
+    let timestamp = |time: std::io::Result<std::time::SystemTime>| {
+        time.ok()
+            .and_then(|time| time.duration_since(UNIX_EPOCH).ok())
+            .map(|duration| duration.as_secs_f64())
+    };
+
+    let mut raw = Map::new();
+    raw.insert(
+        "is_symlink".to_string(),
+        json!(path.symlink_metadata()?.file_type().is_symlink()),
+    );
+    raw.insert("is_file".to_string(), json!(metadata.is_file()));
+    raw.insert("is_dir".to_string(), json!(metadata.is_dir()));
+    raw.insert("len".to_string(), json!(metadata.len()));
+    raw.insert(
+        "readonly".to_string(),
+        json!(metadata.permissions().readonly()),
+    );
+    raw.insert("created".to_string(), json!(timestamp(metadata.created())));
+    raw.insert(
+        "modified".to_string(),
+        json!(timestamp(metadata.modified())),
+    );
+    raw.insert(
+        "accessed".to_string(),
+        json!(timestamp(metadata.accessed())),
+    );
+
+    #[cfg(unix)]
+    {
+        let stat = json!({
+            "st_dev": metadata.dev(),
+            "st_ino": metadata.ino(),
+            "st_mode": format!("0{:o}", metadata.mode()),
+            "st_nlink": metadata.nlink(),
+            "st_uid": metadata.uid(),
+            "st_gid": metadata.gid(),
+            "st_rdev": metadata.rdev(),
+            "st_size": metadata.size(),
+            "st_atime": metadata.atime(),
+            "st_atime_nsec": metadata.atime_nsec(),
+            "st_mtime": metadata.mtime(),
+            "st_mtime_nsec": metadata.mtime_nsec(),
+            "st_ctime": metadata.ctime(),
+            "st_ctime_nsec": metadata.ctime_nsec(),
+            "st_blksize": metadata.blksize(),
+            "st_blocks": metadata.blocks(),
+        });
+        raw.insert("stat".to_string(), stat);
+    }
+
+    #[cfg(windows)]
+    {
+        let attributes = json!({
+            "file_attributes": format!("0x{:08X}", metadata.file_attributes()),
+            "creation_time": metadata.creation_time(),
+            "last_access_time": metadata.last_access_time(),
+            "last_write_time": metadata.last_write_time(),
+            "file_size": metadata.file_size(),
+        });
+        raw.insert("windows".to_string(), attributes);
+    }
+
+    #[cfg(any(target_os = "macos", target_os = "linux"))]
+    raw.insert("xattr_names".to_string(), json!(xattr_names(path)?));
+
+    Ok(raw)
+}
+
+/// Names of the extended attributes of `path`.
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn xattr_names(path: &Path) -> std::io::Result<Vec<String>> {
+    use rustix::io::Errno;
+
+    let len = match rustix::fs::listxattr(path, &mut [0u8; 0][..]) {
+        Ok(len) => len,
+        // the filesystem does not support extended attributes
+        Err(Errno::NOTSUP) => return Ok(vec![]),
+        Err(err) => return Err(err.into()),
+    };
+    let mut names = vec![0u8; len];
+    let len = rustix::fs::listxattr(path, &mut names[..])?;
+    names.truncate(len);
+
+    // names are NUL-terminated
+    Ok(names
+        .split(|byte| *byte == 0)
+        .filter(|name| !name.is_empty())
+        .map(|name| String::from_utf8_lossy(name).into_owned())
+        .collect())
+}
+
+/// Whether `path`, or its closest existing ancestor when it doesn't exist yet, is on a filesystem
+/// mounted read-only (e.g. a Docker volume mounted with `:ro`). Returns `false` when unknown.
+pub fn is_read_only_filesystem(path: &Path) -> bool {
+    let Some(existing) = path.ancestors().find(|ancestor| ancestor.exists()) else {
+        return false;
+    };
+
+    #[cfg(any(target_os = "macos", target_os = "linux"))]
+    {
+        use rustix::fs::StatVfsMountFlags;
+        rustix::fs::statvfs(existing)
+            .is_ok_and(|stat| stat.f_flag.contains(StatVfsMountFlags::RDONLY))
+    }
+
+    #[cfg(windows)]
+    {
+        use std::os::windows::ffi::OsStrExt;
+        use windows_sys::Win32::Storage::FileSystem::{GetVolumeInformationW, GetVolumePathNameW};
+        /// `FILE_READ_ONLY_VOLUME` file system flag.
+        const FILE_READ_ONLY_VOLUME: u32 = 0x0008_0000;
+
+        let wide_path: Vec<u16> = existing.as_os_str().encode_wide().chain(Some(0)).collect();
+        let mut volume = [0u16; 261];
+        let mut flags: u32 = 0;
+        // SAFETY: the buffers are valid for the duration of the calls and their sizes are passed along,
+        // the path is null-terminated.
+        unsafe {
+            GetVolumePathNameW(wide_path.as_ptr(), volume.as_mut_ptr(), volume.len() as u32) != 0
+                && GetVolumeInformationW(
+                    volume.as_ptr(),
+                    std::ptr::null_mut(),
+                    0,
+                    std::ptr::null_mut(),
+                    std::ptr::null_mut(),
+                    &mut flags,
+                    std::ptr::null_mut(),
+                    0,
+                ) != 0
+                && flags & FILE_READ_ONLY_VOLUME != 0
+        }
+    }
+
+    #[cfg(not(any(target_os = "macos", target_os = "linux", windows)))]
+    {
+        let _ = existing;
+        false
+    }
+}
@@ -0,0 +1,178 @@
+use crate::{
+    error::ServiceResult,
+    fs_service::{
+        FileSystemService,
+        utils::{clean_path, normalize_path, relative_path},
+    },
+};
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+/// Kind of project, recognized by its marker file.
+#[derive(::serde::Serialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum ProjectKind {
+    Rust,
+    Node,
+    Python,
+    Git,
+}
+
+impl Display for ProjectKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ProjectKind::Rust => write!(f, "rust"),
+            ProjectKind::Node => write!(f, "node"),
+            ProjectKind::Python => write!(f, "python"),
+            ProjectKind::Git => write!(f, "git"),
+        }
+    }
+}
+
+/// Files or directories marking the root of a project, in order of precedence.
+pub const PROJECT_MARKERS: [(&str, ProjectKind); 4] = [
+    ("Cargo.toml", ProjectKind::Rust),
+    ("package.json", ProjectKind::Node),
+    ("pyproject.toml", ProjectKind::Python),
+    (".git", ProjectKind::Git),
+];
+
+/// The project a path belongs to.
+#[derive(::serde::Serialize, Debug)]
+pub struct ProjectRoot {
+    /// The resolved path the search started from.
+    pub path: PathBuf,
+    /// The nearest directory containing a project marker, up to the allowed directory.
+    pub root: Option<PathBuf>,
+    /// The kinds of project marked in `root`.
+    pub kinds: Vec<ProjectKind>,
+    /// The markers found in `root`.
+    pub markers: Vec<String>,
+    /// The nearest directory containing `.git`, which may be above `root`.
+    pub repository_root: Option<PathBuf>,
+}
+
+/// A path resolved against the allowed directories.
+#[derive(::serde::Serialize, Debug)]
+pub struct ResolvedPath {
+    /// The requested path.
+    pub path: String,
+    /// Absolute path, with symlinks resolved when the path exists.
+    pub resolved: PathBuf,
+    pub exists: bool,
+    pub is_directory: bool,
+    /// The allowed directory the path falls under.
+    pub allowed_root: Option<PathBuf>,
+    /// The path relative to `allowed_root`.
+    pub relative_to_root: Option<PathBuf>,
+    /// The path relative to the requested base directory, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub relative_path: Option<PathBuf>,
+}
+
+impl FileSystemService {
+    /// Resolves `path` to its absolute form and reports the allowed directory containing it.
+    /// When `relative_to` is provided, the path relative to that directory is computed as well.
+    /// Both paths must be within the allowed directories but don't need to exist.
+    pub async fn resolve_path(
+        &self,
+        path: &Path,
+        relative_to: Option<&Path>,
+    ) -> ServiceResult<ResolvedPath> {
+        let allowed_directories = self.allowed_directories().await;
+
+        let resolved = resolve(&self.validate_path(path, allowed_directories.clone())?);
+        let allowed_root = allowed_root(&allowed_directories, &resolved);
+        let relative_to_root = allowed_root
+            .as_ref()
+            .and_then(|root| relative_path(root, &resolved));
+
+        let relative_path = match relative_to {
+            Some(base) => {
+                let base = resolve(&self.validate_path(base, allowed_directories)?);
+                relative_path(&base, &resolved)
+            }
+            None => None,
+        };
+
+        Ok(ResolvedPath {
+            path: path.display().to_string(),
+            exists: resolved.exists(),
+            is_directory: resolved.is_dir(),
+            resolved,
+            allowed_root,
+            relative_to_root,
+            relative_path,
+        })
+    }
+
+    /// Finds the project `path` belongs to: walks up from it, without leaving its allowed
+    /// directory, to the nearest directory containing a project marker (`Cargo.toml`,
+    /// `package.json`, `pyproject.toml` or `.git`).
+    pub async fn find_project_root(&self, path: &Path) -> ServiceResult<ProjectRoot> {
+        let allowed_directories = self.allowed_directories().await;
+        let resolved = resolve(&self.validate_path(path, allowed_directories.clone())?);
+        let allowed_root = allowed_root(&allowed_directories, &resolved);
+        let start = match resolved.parent() {
+            Some(parent) if resolved.is_file() => parent,
+            _ => resolved.as_path(),
+        };
+
+        let mut project_root = ProjectRoot {
+            path: resolved.clone(),
+            root: None,
+            kinds: vec![],
+            markers: vec![],
+            repository_root: None,
+        };
+        let directories = start.ancestors().take_while(|dir| {
+            allowed_root
+                .as_ref()
+                .is_some_and(|root| dir.starts_with(root))
+        });
+        for dir in directories {
+            let markers: Vec<_> = PROJECT_MARKERS
+                .iter()
+                .filter(|(marker, _)| dir.join(marker).exists())
+                .collect();
+            if project_root.root.is_none() && !markers.is_empty() {
+                project_root.root = Some(dir.to_path_buf());
+                project_root.kinds = markers.iter().map(|(_, kind)| *kind).collect();
+                project_root.markers = markers
+                    .iter()
+                    .map(|(marker, _)| marker.to_string())
+                    .collect();
+            }
+            if markers.iter().any(|(_, kind)| *kind == ProjectKind::Git) {
+                project_root.repository_root = Some(dir.to_path_buf());
+                break;
+            }
+        }
+        Ok(project_root)
+    }
+}
+
+/// The allowed directory containing `path`, the innermost one when allowed directories are nested.
+fn allowed_root(allowed_directories: &[PathBuf], path: &Path) -> Option<PathBuf> {
+    allowed_directories
+        .iter()
+        .map(|dir| normalize_path(dir))
+        .filter(|dir| path.starts_with(dir))
+        .max_by_key(|dir| dir.components().count())
+}
+
+fn resolve(path: &Path) -> PathBuf {
+    if let Ok(canonical) = path.canonicalize() {
+        return canonical;
+    }
+    // canonicalize the longest existing ancestor, so the path is comparable with canonical roots
+    let path = clean_path(path);
+    path.ancestors()
+        .find_map(|ancestor| {
+            let canonical = ancestor.canonicalize().ok()?;
+            Some(canonical.join(path.strip_prefix(ancestor).ok()?))
+        })
+        .unwrap_or(path)
+}
@@ -0,0 +1,60 @@
+//! Retries of filesystem operations failing with transient errors.
+//!
+//! Files can be briefly locked by editors, indexers or antivirus scanners, in particular on Windows
+//! where opening a file held open by another process fails with a sharing violation. Such operations
+//! are retried with an exponential backoff before the error is reported.
+
+use crate::fs_service::{FileSystemService, locks::is_lock_violation};
+use std::{io, time::Duration};
+
+/// How operations failing with a transient error are retried.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RetryPolicy {
+    /// Number of retries after the first attempt, 0 disables retries.
+    pub retries: u32,
+    /// Delay before the first retry, doubled after each retry.
+    pub initial_backoff: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            retries: 3,
+            initial_backoff: Duration::from_millis(100),
+        }
+    }
+}
+
+/// Whether `err` is likely to go away when the operation is retried shortly after:
+/// busy resources (`EBUSY`), operations that would block (`EAGAIN`) and interrupted system calls,
+/// as well as sharing and lock violations on Windows.
+pub fn is_transient_error(err: &io::Error) -> bool {
+    matches!(
+        err.kind(),
+        io::ErrorKind::ResourceBusy | io::ErrorKind::WouldBlock | io::ErrorKind::Interrupted
+    ) || is_lock_violation(err)
+}
+
+impl FileSystemService {
+    /// Runs `operation`, retrying it according to the retry policy of the service as long as it
+    /// fails with a transient error. Operations must be safe to repeat.
+    pub async fn retry_io<T, F, Fut>(&self, mut operation: F) -> io::Result<T>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = io::Result<T>>,
+    {
+        let policy = self.retry_policy();
+        let mut backoff = policy.initial_backoff;
+        let mut retries = 0;
+        loop {
+            match operation().await {
+                Err(err) if retries < policy.retries && is_transient_error(&err) => {
+                    tokio::time::sleep(backoff).await;
+                    backoff *= 2;
+                    retries += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+}
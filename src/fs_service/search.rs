@@ -1,5 +1,14 @@
 mod content;
 mod files;
+mod lines;
+mod manifests;
+mod relevance;
+mod size;
 mod tree;
 
 pub use content::FileSearchResult;
+pub use files::{ExtensionUsage, FileAge, FileTypeMismatch, TimelineBucket};
+pub use lines::LanguageLines;
+pub use manifests::{DEPENDENCY_MANIFESTS, DependencyManifest, Ecosystem, EcosystemManifests};
+pub use relevance::RelevantFile;
+pub(crate) use size::DirectorySizeCache;
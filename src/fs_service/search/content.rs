@@ -1,10 +1,13 @@
 use crate::{
     error::ServiceResult,
-    fs_service::{FileSystemService, utils::escape_regex},
+    fs_service::{
+        FileSystemService,
+        utils::{escape_regex, filesize_in_range},
+    },
 };
 use grep::{
     matcher::{Match, Matcher},
-    regex::RegexMatcherBuilder,
+    regex::{RegexMatcher, RegexMatcherBuilder},
     searcher::{BinaryDetection, Searcher, sinks::UTF8},
 };
 use std::path::{Path, PathBuf};
@@ -13,18 +16,25 @@ const SNIPPET_MAX_LENGTH: usize = 200;
 const SNIPPET_BACKWARD_CHARS: usize = 30;
 
 /// Represents a single match found in a file's content.
-#[derive(Debug, Clone)]
+/// A line containing several occurrences of the query yields one result per occurrence.
+#[derive(Debug, Clone, ::serde::Serialize)]
 pub struct ContentMatchResult {
     /// The line number where the match occurred (1-based).
     pub line_number: u64,
+    /// The byte offset within the line where the match starts.
     pub start_pos: usize,
+    /// The byte offset within the line where the match ends (exclusive).
+    pub end_pos: usize,
     /// The line of text containing the match.
     /// If the line exceeds 255 characters (excluding the search term), only a truncated portion will be shown.
     pub line_text: String,
+    /// The query that matched, only reported when searching for several queries at once.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub query: Option<String>,
 }
 
 /// Represents all matches found in a specific file.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, ::serde::Serialize)]
 pub struct FileSearchResult {
     /// The path to the file where matches were found.
     pub file_path: PathBuf,
@@ -32,6 +42,91 @@ pub struct FileSearchResult {
     pub matches: Vec<ContentMatchResult>,
 }
 
+/// Matcher for one or more queries, searched for in a single pass over each file.
+pub(super) struct ContentMatcher {
+    matcher: RegexMatcher,
+    /// One matcher per query, used to tell which query produced a match when there are several.
+    queries: Vec<(String, RegexMatcher)>,
+}
+
+impl ContentMatcher {
+    pub(super) fn new(queries: &[&str], is_regex: bool) -> ServiceResult<Self> {
+        let patterns: Vec<String> = queries
+            .iter()
+            .map(|query| {
+                if is_regex {
+                    query.to_string()
+                } else {
+                    escape_regex(query)
+                }
+            })
+            .collect();
+
+        let mut builder = RegexMatcherBuilder::new();
+        builder.case_insensitive(true);
+        let matcher = if is_regex {
+            builder.build_many(&patterns)?
+        } else {
+            builder.build_literals(&patterns)?
+        };
+
+        let queries = if queries.len() > 1 {
+            queries
+                .iter()
+                .zip(&patterns)
+                .map(|(query, pattern)| Ok((query.to_string(), builder.build(pattern)?)))
+                .collect::<ServiceResult<_>>()?
+        } else {
+            vec![]
+        };
+
+        Ok(Self { matcher, queries })
+    }
+
+    /// The first query matching exactly `found` in `line`, as the alternation of all queries
+    /// reports the match of the first query that matches at a given position.
+    pub(super) fn matched_query(&self, line: &[u8], found: Match) -> Option<String> {
+        self.queries.iter().find_map(|(query, matcher)| {
+            matcher
+                .find_at(line, found.start())
+                .ok()
+                .flatten()
+                .filter(|m| m.start() == found.start() && m.end() == found.end())
+                .map(|_| query.clone())
+        })
+    }
+
+    /// Calls `on_match` for every occurrence of the queries in `haystack`.
+    pub(super) fn find_iter(&self, haystack: &[u8], mut on_match: impl FnMut(Match)) {
+        // the regex matcher never fails
+        let _ = self.matcher.find_iter(haystack, |found| {
+            on_match(found);
+            true
+        });
+    }
+
+    /// Whether any of the queries occurs in the file at `file_path`, stopping at the first match.
+    /// `content` is the content of the file when the overlay changed it.
+    fn is_found_in(
+        &self,
+        file_path: &Path,
+        content: Option<std::io::Result<Vec<u8>>>,
+    ) -> ServiceResult<bool> {
+        let mut searcher = Searcher::new();
+        searcher.set_binary_detection(BinaryDetection::quit(b'\x00'));
+        let mut found = false;
+        let sink = UTF8(|_, _| {
+            found = true;
+            Ok(false)
+        });
+        match content {
+            Some(content) => searcher.search_slice(&self.matcher, &content?, sink)?,
+            None => searcher.search_path(&self.matcher, file_path, sink)?,
+        }
+        Ok(found)
+    }
+}
+
 impl FileSystemService {
     // Searches the content of a file for occurrences of the given query string.
     ///
@@ -39,24 +134,34 @@ impl FileSystemService {
     /// The search can be performed as a regular expression or as a literal string,
     /// depending on the `is_regex` flag.
     ///
-    /// If matched line is larger than 255 characters, a snippet will be extracted around the matched text.
+    /// If matched line is larger than `snippet_max_chars` characters, a snippet will be extracted around the matched text,
+    /// starting `snippet_context_chars` characters before the match.
     ///
     pub fn content_search(
         &self,
         query: &str,
         file_path: impl AsRef<Path>,
         is_regex: Option<bool>,
+        snippet_max_chars: Option<usize>,
+        snippet_context_chars: Option<usize>,
     ) -> ServiceResult<Option<FileSearchResult>> {
-        let query = if is_regex.unwrap_or_default() {
-            query.to_string()
-        } else {
-            escape_regex(query)
-        };
-
-        let matcher = RegexMatcherBuilder::new()
-            .case_insensitive(true)
-            .build(query.as_str())?;
+        let matcher = ContentMatcher::new(&[query], is_regex.unwrap_or_default())?;
+        self.search_with_matcher(
+            &matcher,
+            file_path,
+            snippet_max_chars,
+            snippet_context_chars,
+        )
+    }
 
+    fn search_with_matcher(
+        &self,
+        content_matcher: &ContentMatcher,
+        file_path: impl AsRef<Path>,
+        snippet_max_chars: Option<usize>,
+        snippet_context_chars: Option<usize>,
+    ) -> ServiceResult<Option<FileSearchResult>> {
+        let matcher = &content_matcher.matcher;
         let mut searcher = Searcher::new();
         let mut result = FileSearchResult {
             file_path: file_path.as_ref().to_path_buf(),
@@ -65,20 +170,30 @@ impl FileSystemService {
 
         searcher.set_binary_detection(BinaryDetection::quit(b'\x00'));
 
-        searcher.search_path(
-            &matcher,
-            file_path,
-            UTF8(|line_number, line| {
-                let actual_match = matcher.find(line.as_bytes())?.unwrap();
-
+        let sink = UTF8(|line_number, line| {
+            // report every occurrence on the line, each with its own range
+            matcher.find_iter(line.as_bytes(), |actual_match| {
                 result.matches.push(ContentMatchResult {
                     line_number,
                     start_pos: actual_match.start(),
-                    line_text: self.extract_snippet(line, actual_match, None, None),
+                    end_pos: actual_match.end(),
+                    line_text: self.extract_snippet(
+                        line,
+                        actual_match,
+                        snippet_max_chars,
+                        snippet_context_chars,
+                    ),
+                    query: content_matcher.matched_query(line.as_bytes(), actual_match),
                 });
-                Ok(true)
-            }),
-        )?;
+                true
+            })?;
+            Ok(true)
+        });
+        // the files changed by the overlay are searched in their new content
+        match self.simulated_content(file_path.as_ref()) {
+            Some(content) => searcher.search_slice(matcher, &content?, sink)?,
+            None => searcher.search_path(matcher, file_path, sink)?,
+        }
 
         if result.matches.is_empty() {
             return Ok(None);
@@ -110,8 +225,12 @@ impl FileSystemService {
 
         // Calculate the desired start byte index by adjusting match start for trimming and backward chars
         // match_result.start() is the byte index in the original string
-        // Subtract start_pos to account for trimmed whitespace and backward_chars to include context before the match
-        let desired_start = (match_result.start() - start_pos).saturating_sub(backward_chars);
+        // Subtract start_pos to account for trimmed whitespace (a match may start within it) and backward_chars
+        // to include context before the match
+        let desired_start = match_result
+            .start()
+            .saturating_sub(start_pos)
+            .saturating_sub(backward_chars);
 
         // Find the nearest valid UTF-8 character boundary at or after desired_start
         // Prevents "byte index is not a char boundary" panic by ensuring the slice starts at a valid character (issue #37)
@@ -119,7 +238,7 @@ impl FileSystemService {
             .char_indices()
             .map(|(i, _)| i)
             .find(|&i| i >= desired_start)
-            .unwrap_or(desired_start.min(line.len()));
+            .unwrap_or(line.len());
         // Initialize a counter for tracking characters to respect max_length
         let mut char_count = 0;
 
@@ -169,12 +288,15 @@ impl FileSystemService {
         &self,
         root_path: impl AsRef<Path>,
         pattern: &str,
-        query: &str,
+        queries: &[&str],
         is_regex: bool,
         exclude_patterns: Option<Vec<String>>,
         min_bytes: Option<u64>,
         max_bytes: Option<u64>,
+        snippet_max_chars: Option<usize>,
+        snippet_context_chars: Option<usize>,
     ) -> ServiceResult<Vec<FileSearchResult>> {
+        let matcher = ContentMatcher::new(queries, is_regex)?;
         let files_iter = self
             .search_files_iter(
                 root_path.as_ref(),
@@ -187,11 +309,131 @@ impl FileSystemService {
 
         let results: Vec<FileSearchResult> = files_iter
             .filter_map(|entry| {
-                self.content_search(query, entry.path(), Some(is_regex))
+                self.search_with_matcher(
+                    &matcher,
+                    entry.path(),
+                    snippet_max_chars,
+                    snippet_context_chars,
+                )
+                .ok()
+                .and_then(|v| v)
+            })
+            .collect();
+        Ok(results)
+    }
+
+    /// Searches the content of the files listed in `paths`, e.g. the result of a previous
+    /// `search_files` call, without traversing any directory. Every path must be within the
+    /// allowed directories; directories and files outside of the `min_bytes`..`max_bytes` range
+    /// are skipped.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn search_paths_content(
+        &self,
+        paths: &[String],
+        queries: &[&str],
+        is_regex: bool,
+        min_bytes: Option<u64>,
+        max_bytes: Option<u64>,
+        snippet_max_chars: Option<usize>,
+        snippet_context_chars: Option<usize>,
+    ) -> ServiceResult<Vec<FileSearchResult>> {
+        let matcher = ContentMatcher::new(queries, is_regex)?;
+        let results: Vec<FileSearchResult> = self
+            .searchable_paths(paths, min_bytes, max_bytes)
+            .await?
+            .iter()
+            .filter_map(|path| {
+                self.search_with_matcher(&matcher, path, snippet_max_chars, snippet_context_chars)
                     .ok()
                     .and_then(|v| v)
             })
             .collect();
         Ok(results)
     }
+
+    /// Lists the files matching the glob `pattern` under `root_path` that contain none of the
+    /// `queries`, e.g. to find the source files lacking a license header.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn search_files_without_content(
+        &self,
+        root_path: impl AsRef<Path>,
+        pattern: &str,
+        queries: &[&str],
+        is_regex: bool,
+        exclude_patterns: Option<Vec<String>>,
+        min_bytes: Option<u64>,
+        max_bytes: Option<u64>,
+    ) -> ServiceResult<Vec<PathBuf>> {
+        let matcher = ContentMatcher::new(queries, is_regex)?;
+        let files: Vec<PathBuf> = self
+            .search_files_iter(
+                root_path.as_ref(),
+                pattern.to_string(),
+                exclude_patterns.unwrap_or_default(),
+                min_bytes,
+                max_bytes,
+            )
+            .await?
+            .filter(|entry| entry.is_file())
+            .map(|entry| entry.into_path())
+            .filter(|path| {
+                matches!(
+                    matcher.is_found_in(path, self.simulated_content(path)),
+                    Ok(false)
+                )
+            })
+            .collect();
+        Ok(files)
+    }
+
+    /// Lists the files in `paths` that contain none of the `queries`, see `search_paths_content`.
+    pub async fn search_paths_without_content(
+        &self,
+        paths: &[String],
+        queries: &[&str],
+        is_regex: bool,
+        min_bytes: Option<u64>,
+        max_bytes: Option<u64>,
+    ) -> ServiceResult<Vec<PathBuf>> {
+        let matcher = ContentMatcher::new(queries, is_regex)?;
+        let files: Vec<PathBuf> = self
+            .searchable_paths(paths, min_bytes, max_bytes)
+            .await?
+            .into_iter()
+            .filter(|path| {
+                matches!(
+                    matcher.is_found_in(path, self.simulated_content(path)),
+                    Ok(false)
+                )
+            })
+            .collect();
+        Ok(files)
+    }
+
+    /// Validates `paths`, keeping the files within the `min_bytes`..`max_bytes` range.
+    async fn searchable_paths(
+        &self,
+        paths: &[String],
+        min_bytes: Option<u64>,
+        max_bytes: Option<u64>,
+    ) -> ServiceResult<Vec<PathBuf>> {
+        let paths: Vec<PathBuf> = paths.iter().map(PathBuf::from).collect();
+        let valid_paths = self
+            .validate_paths(&paths)
+            .await
+            .into_iter()
+            .collect::<ServiceResult<Vec<_>>>()?;
+
+        Ok(valid_paths
+            .into_iter()
+            .filter(|path| {
+                self.tree_entry(path).is_ok_and(|entry| {
+                    entry.is_file()
+                        && entry
+                            .size()
+                            .is_ok_and(|size| filesize_in_range(size, min_bytes, max_bytes))
+                })
+            })
+            .collect())
+    }
 }
@@ -1,13 +1,79 @@
 use crate::{
     error::ServiceResult,
-    fs_service::{FileSystemService, utils::filesize_in_range},
+    fs_service::{
+        FileSystemService, TreeEntry,
+        ignore::IgnoreRules,
+        utils::{
+            FileTimestamp, MimeCategory, TimelineInterval, filesize_in_range,
+            matches_exclude_patterns, mime_category_from_bytes, mime_category_from_content,
+            mime_category_from_extension,
+        },
+    },
 };
+use chrono::{DateTime, Datelike, Local, NaiveDate};
 use glob_match::glob_match;
-use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use rayon::iter::{IntoParallelIterator, ParallelBridge, ParallelIterator};
 use sha2::{Digest, Sha256};
-use std::{collections::HashMap, path::Path};
-use tokio::{fs::File, io::AsyncReadExt};
-use walkdir::WalkDir;
+use std::{
+    collections::{BTreeMap, BinaryHeap, HashMap},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use std::{fs::File, io::Read};
+
+/// A file whose content does not match the kind of file suggested by its extension.
+#[derive(Debug, Clone, ::serde::Serialize)]
+pub struct FileTypeMismatch {
+    /// The path of the mismatched file.
+    pub path: PathBuf,
+    /// The file kind implied by the extension.
+    pub expected: MimeCategory,
+    /// The file kind detected from the file header.
+    pub detected: MimeCategory,
+    /// The MIME type detected from the file header, if it is a known signature.
+    pub detected_mime_type: Option<String>,
+}
+
+/// A file returned by `find_oldest_files`.
+#[derive(Debug, Clone)]
+pub struct FileAge {
+    pub path: PathBuf,
+    pub size: u64,
+    pub modified: Option<SystemTime>,
+    pub accessed: Option<SystemTime>,
+    pub created: Option<SystemTime>,
+}
+
+/// Disk usage of the files sharing an extension, as returned by `usage_by_extension`.
+#[derive(Debug, Clone, ::serde::Serialize)]
+pub struct ExtensionUsage {
+    /// Lowercase extension without the leading dot, empty for files without extension.
+    pub extension: String,
+    /// The file kind implied by the extension, if it is a well-known one.
+    pub category: Option<MimeCategory>,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Files whose timestamp falls within a period, as returned by `file_timeline`.
+#[derive(Debug, Clone, ::serde::Serialize)]
+pub struct TimelineBucket {
+    /// Label of the period: `2024-03-14` for days, `2024-W11` for weeks and `2024-03` for months.
+    pub period: String,
+    /// First day of the period, in `YYYY-MM-DD` format.
+    pub start: String,
+    pub files: usize,
+    pub bytes: u64,
+}
+
+/// Metadata kept alongside the paths while selecting the oldest files.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord)]
+struct FileAgeTimes {
+    size: u64,
+    modified: Option<SystemTime>,
+    accessed: Option<SystemTime>,
+    created: Option<SystemTime>,
+}
 
 impl FileSystemService {
     /// Searches for files in the directory tree starting at `root_path` that match the given `pattern`,
@@ -19,9 +85,11 @@ impl FileSystemService {
     ///   the pattern is wrapped in '*' for partial matching.
     /// * `exclude_patterns` - A list of glob patterns to exclude paths (case-sensitive).
     ///
+    /// Paths excluded by the `.mcpignore` files of the allowed directories are always skipped.
+    ///
     /// # Returns
-    /// A `ServiceResult` containing a vector of`walkdir::DirEntry` objects for matching files,
-    /// or a `ServiceError` if an error occurs.
+    /// A `ServiceResult` containing a vector of `TreeEntry` objects for matching files, as seen
+    /// through the overlay of the changes if any, or a `ServiceError` if an error occurs.
     pub async fn search_files(
         &self,
         root_path: &Path,
@@ -29,11 +97,11 @@ impl FileSystemService {
         exclude_patterns: Vec<String>,
         min_bytes: Option<u64>,
         max_bytes: Option<u64>,
-    ) -> ServiceResult<Vec<walkdir::DirEntry>> {
+    ) -> ServiceResult<Vec<TreeEntry>> {
         let result = self
             .search_files_iter(root_path, pattern, exclude_patterns, min_bytes, max_bytes)
             .await?;
-        Ok(result.collect::<Vec<walkdir::DirEntry>>())
+        Ok(result.collect::<Vec<TreeEntry>>())
     }
 
     /// Returns an iterator over files in the directory tree starting at `root_path` that match
@@ -45,7 +113,7 @@ impl FileSystemService {
     /// * `exclude_patterns` - A list of glob patterns to exclude paths (case-sensitive).
     ///
     /// # Returns
-    /// A `ServiceResult` containing an iterator yielding `walkdir::DirEntry` objects for matching files,
+    /// A `ServiceResult` containing an iterator yielding `TreeEntry` objects for matching files,
     /// or a `ServiceError` if an error occurs.
     pub async fn search_files_iter<'a>(
         &'a self,
@@ -55,7 +123,7 @@ impl FileSystemService {
         exclude_patterns: Vec<String>,
         min_bytes: Option<u64>,
         max_bytes: Option<u64>,
-    ) -> ServiceResult<impl Iterator<Item = walkdir::DirEntry> + 'a> {
+    ) -> ServiceResult<impl Iterator<Item = TreeEntry> + 'a> {
         let allowed_directories = self.allowed_directories().await;
         let valid_path = self.validate_path(root_path, allowed_directories.clone())?;
 
@@ -65,11 +133,14 @@ impl FileSystemService {
             format!("**/*{}*", &pattern.to_lowercase())
         };
         let glob_pattern = updated_pattern;
+        let ignore_rules = IgnoreRules::load(&allowed_directories);
 
-        let result = WalkDir::new(valid_path)
-            .follow_links(true)
-            .into_iter()
-            .filter_entry(move |dir_entry| {
+        let result = self
+            .walk_tree(&valid_path, move |dir_entry| {
+                // the walk ends when the server shuts down
+                if self.is_cancelled() {
+                    return false;
+                }
                 let full_path = dir_entry.path();
 
                 // Validate each path before processing
@@ -82,24 +153,20 @@ impl FileSystemService {
                     return false;
                 }
 
+                if ignore_rules.is_ignored(full_path, dir_entry.is_dir()) {
+                    return false;
+                }
+
                 // Get the relative path from the root_path
                 let relative_path = full_path.strip_prefix(root_path).unwrap_or(full_path);
 
-                let mut should_exclude = exclude_patterns.iter().any(|pattern| {
-                    let glob_pattern = if pattern.contains('*') {
-                        pattern.strip_prefix("/").unwrap_or(pattern).to_owned()
-                    } else {
-                        format!("*{pattern}*")
-                    };
-
-                    glob_match(&glob_pattern, relative_path.to_str().unwrap_or(""))
-                });
+                let mut should_exclude = matches_exclude_patterns(relative_path, &exclude_patterns);
 
                 // enforce min/max bytes
                 if !should_exclude && (min_bytes.is_none() || max_bytes.is_none()) {
-                    match dir_entry.metadata().ok() {
-                        Some(metadata) => {
-                            if !filesize_in_range(metadata.len(), min_bytes, max_bytes) {
+                    match dir_entry.size().ok() {
+                        Some(size) => {
+                            if !filesize_in_range(size, min_bytes, max_bytes) {
                                 should_exclude = true;
                             }
                         }
@@ -111,7 +178,6 @@ impl FileSystemService {
 
                 !should_exclude
             })
-            .filter_map(|v| v.ok())
             .filter(move |entry| {
                 if root_path == entry.path() {
                     return false;
@@ -141,11 +207,6 @@ impl FileSystemService {
         let allowed_directories = self.allowed_directories().await;
         let valid_path = self.validate_path(root_path, allowed_directories)?;
 
-        // Get Tokio runtime handle
-        let rt = tokio::runtime::Handle::current();
-
-        // Step 1: Collect files and group by size
-        let mut size_map: HashMap<u64, Vec<String>> = HashMap::new();
         let entries = self
             .search_files_iter(
                 &valid_path,
@@ -155,94 +216,296 @@ impl FileSystemService {
                 max_bytes,
             )
             .await?
-            .filter(|e| e.file_type().is_file()); // Only files
+            .filter(|e| e.is_file()); // Only files
 
-        for entry in entries {
-            if let Ok(metadata) = entry.metadata()
-                && let Some(path_str) = entry.path().to_str()
-            {
-                size_map
-                    .entry(metadata.len())
-                    .or_default()
-                    .push(path_str.to_string());
-            }
-        }
+        // A batch operation: reading every file of the tree must not hold up other tool calls
+        let duplicates = self
+            .batch_executor
+            .run(move || {
+                // Step 1: Collect files and group by size
+                let mut size_map: HashMap<u64, Vec<String>> = HashMap::new();
+                for entry in entries {
+                    if let Ok(size) = entry.size()
+                        && let Some(path_str) = entry.path().to_str()
+                    {
+                        size_map.entry(size).or_default().push(path_str.to_string());
+                    }
+                }
 
-        // Filter out sizes with only one file (no duplicates possible)
-        let size_groups: Vec<Vec<String>> = size_map
-            .into_iter()
-            .collect::<Vec<_>>() // Collect into Vec to enable parallel iteration
-            .into_par_iter()
-            .filter(|(_, paths)| paths.len() > 1)
-            .map(|(_, paths)| paths)
-            .collect();
+                // Filter out sizes with only one file (no duplicates possible)
+                let size_groups = size_map
+                    .into_values()
+                    .filter(|paths| paths.len() > 1)
+                    .collect();
 
-        // Step 2: Group by quick hash (first 4KB)
-        let mut quick_hash_map: HashMap<Vec<u8>, Vec<String>> = HashMap::new();
-        for paths in size_groups.into_iter() {
-            let quick_hashes: Vec<(String, Vec<u8>)> = paths
-                .into_par_iter()
-                .filter_map(|path| {
-                    let rt = rt.clone(); // Clone the runtime handle for this task
-                    rt.block_on(async {
-                        let file = File::open(&path).await.ok()?;
-                        let mut reader = tokio::io::BufReader::new(file);
-                        let mut buffer = vec![0u8; 4096]; // Read first 4KB
-                        let bytes_read = reader.read(&mut buffer).await.ok()?;
-                        let mut hasher = Sha256::new();
-                        hasher.update(&buffer[..bytes_read]);
-                        Some((path, hasher.finalize().to_vec()))
-                    })
+                // Step 2: Group by quick hash (first 4KB), of the new content of the files changed
+                // by the overlay
+                let quick_hash_groups = group_by_hash(size_groups, |path| {
+                    if let Some(content) = self.simulated_content(Path::new(path)) {
+                        let content = content.ok()?;
+                        return Some(Sha256::digest(&content[..content.len().min(4096)]).to_vec());
+                    }
+                    let mut buffer = vec![0u8; 4096]; // Read first 4KB
+                    let bytes_read = File::open(path).ok()?.read(&mut buffer).ok()?;
+                    Some(Sha256::digest(&buffer[..bytes_read]).to_vec())
+                });
+
+                // Step 3: Group by full hash for groups with multiple files
+                group_by_hash(quick_hash_groups, |path| {
+                    if let Some(content) = self.simulated_content(Path::new(path)) {
+                        return Some(Sha256::digest(content.ok()?).to_vec());
+                    }
+                    let mut hasher = Sha256::new();
+                    std::io::copy(&mut File::open(path).ok()?, &mut hasher).ok()?;
+                    Some(hasher.finalize().to_vec())
                 })
-                .collect();
+            })
+            .await;
+
+        Ok(duplicates)
+    }
+
+    /// Scans files under `root_path` and reports those whose magic bytes don't match their extension,
+    /// such as a `.jpg` that is actually an HTML page or a `.zip` that is actually an executable.
+    ///
+    /// Only files with a well-known extension are checked. Empty files and `.svg` images (which are
+    /// XML text by nature) are skipped.
+    pub async fn find_file_type_mismatches(
+        &self,
+        root_path: &Path,
+        pattern: Option<String>,
+        exclude_patterns: Option<Vec<String>>,
+    ) -> ServiceResult<Vec<FileTypeMismatch>> {
+        let entries = self
+            .search_files_iter(
+                root_path,
+                pattern.unwrap_or("**/*".to_string()),
+                exclude_patterns.unwrap_or_default(),
+                Some(1),
+                None,
+            )
+            .await?
+            .filter(|e| e.is_file());
+
+        let mut mismatches: Vec<FileTypeMismatch> = entries
+            .par_bridge()
+            .filter_map(|entry| {
+                let path = entry.path();
+                if path
+                    .extension()
+                    .is_some_and(|ext| ext.eq_ignore_ascii_case("svg"))
+                {
+                    return None;
+                }
+                let expected = mime_category_from_extension(path)?;
+                // the files changed by the overlay are checked in their new content
+                let content = self.simulated_content(path).transpose().ok()?;
+                let detected = match &content {
+                    Some(content) => mime_category_from_bytes(content),
+                    None => mime_category_from_content(path).ok()?,
+                };
+                if expected == detected {
+                    return None;
+                }
+                let detected_kind = match &content {
+                    Some(content) => infer::get(content),
+                    None => infer::get_from_path(path).ok().flatten(),
+                };
+                let detected_mime_type = detected_kind.map(|kind| kind.mime_type().to_string());
+                Some(FileTypeMismatch {
+                    path: path.to_path_buf(),
+                    expected,
+                    detected,
+                    detected_mime_type,
+                })
+            })
+            .collect();
 
-            for (path, hash) in quick_hashes {
-                quick_hash_map.entry(hash).or_default().push(path);
+        mismatches.sort_by(|a, b| a.path.cmp(&b.path));
+        Ok(mismatches)
+    }
+
+    /// Finds the `limit` files under `root_path` with the oldest `timestamp`, oldest first.
+    /// Files whose timestamp is not available on the platform are skipped.
+    pub async fn find_oldest_files(
+        &self,
+        root_path: &Path,
+        pattern: Option<String>,
+        exclude_patterns: Option<Vec<String>>,
+        timestamp: FileTimestamp,
+        limit: usize,
+    ) -> ServiceResult<Vec<FileAge>> {
+        let entries = self
+            .search_files_iter(
+                root_path,
+                pattern.unwrap_or("**/*".to_string()),
+                exclude_patterns.unwrap_or_default(),
+                None,
+                None,
+            )
+            .await?
+            .filter(|e| e.is_file());
+
+        // max-heap on the timestamp, holding the `limit` oldest files seen so far
+        let mut oldest: BinaryHeap<(SystemTime, PathBuf, FileAgeTimes)> = BinaryHeap::new();
+        for entry in entries {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let Ok(time) = timestamp.of(&metadata) else {
+                continue;
+            };
+            let times = FileAgeTimes {
+                size: metadata.len(),
+                modified: metadata.modified().ok(),
+                accessed: metadata.accessed().ok(),
+                created: metadata.created().ok(),
+            };
+            oldest.push((time, entry.into_path(), times));
+            if oldest.len() > limit {
+                oldest.pop();
             }
         }
 
-        // Step 3: Group by full hash for groups with multiple files
-        let mut full_hash_map: HashMap<Vec<u8>, Vec<String>> = HashMap::new();
-        let filtered_quick_hashes: Vec<(Vec<u8>, Vec<String>)> = quick_hash_map
+        Ok(oldest
+            .into_sorted_vec()
             .into_iter()
-            .collect::<Vec<_>>()
-            .into_par_iter()
-            .filter(|(_, paths)| paths.len() > 1)
-            .collect();
+            .map(|(_, path, times)| FileAge {
+                path,
+                size: times.size,
+                modified: times.modified,
+                accessed: times.accessed,
+                created: times.created,
+            })
+            .collect())
+    }
 
-        for (_quick_hash, paths) in filtered_quick_hashes {
-            let full_hashes: Vec<(String, Vec<u8>)> = paths
-                .into_par_iter()
-                .filter_map(|path| {
-                    let rt = rt.clone(); // Clone the runtime handle for this task
-                    rt.block_on(async {
-                        let file = File::open(&path).await.ok()?;
-                        let mut reader = tokio::io::BufReader::new(file);
-                        let mut hasher = Sha256::new();
-                        let mut buffer = vec![0u8; 8192]; // 8KB chunks
-                        loop {
-                            let bytes_read = reader.read(&mut buffer).await.ok()?;
-                            if bytes_read == 0 {
-                                break;
-                            }
-                            hasher.update(&buffer[..bytes_read]);
-                        }
-                        Some((path, hasher.finalize().to_vec()))
-                    })
-                })
-                .collect();
+    /// Sums the sizes and counts of the files under `root_path` by extension (case-insensitive),
+    /// sorted by size, largest first.
+    pub async fn usage_by_extension(
+        &self,
+        root_path: &Path,
+        pattern: Option<String>,
+        exclude_patterns: Option<Vec<String>>,
+    ) -> ServiceResult<Vec<ExtensionUsage>> {
+        let entries = self
+            .search_files_iter(
+                root_path,
+                pattern.unwrap_or("**/*".to_string()),
+                exclude_patterns.unwrap_or_default(),
+                None,
+                None,
+            )
+            .await?
+            .filter(|e| e.is_file());
 
-            for (path, hash) in full_hashes {
-                full_hash_map.entry(hash).or_default().push(path);
-            }
+        let mut usage: HashMap<String, ExtensionUsage> = HashMap::new();
+        for entry in entries {
+            let Ok(size) = entry.size() else {
+                continue;
+            };
+            let extension = entry
+                .path()
+                .extension()
+                .map(|extension| extension.to_string_lossy().to_lowercase())
+                .unwrap_or_default();
+            let extension_usage =
+                usage
+                    .entry(extension.clone())
+                    .or_insert_with(|| ExtensionUsage {
+                        category: mime_category_from_extension(entry.path()),
+                        extension,
+                        files: 0,
+                        bytes: 0,
+                    });
+            extension_usage.files += 1;
+            extension_usage.bytes += size;
         }
 
-        // Collect groups of duplicates (only groups with more than one file)
-        let duplicates: Vec<Vec<String>> = full_hash_map
-            .into_values()
-            .filter(|group| group.len() > 1)
-            .collect();
+        let mut usage: Vec<ExtensionUsage> = usage.into_values().collect();
+        usage.sort_by(|a, b| {
+            b.bytes
+                .cmp(&a.bytes)
+                .then_with(|| a.extension.cmp(&b.extension))
+        });
+        Ok(usage)
+    }
 
-        Ok(duplicates)
+    /// Groups the files under `root_path` by the period of `interval` their `timestamp` falls in
+    /// (local time), returning the number and total size of the files of each period in chronological order.
+    /// Periods without files are omitted.
+    pub async fn file_timeline(
+        &self,
+        root_path: &Path,
+        pattern: Option<String>,
+        exclude_patterns: Option<Vec<String>>,
+        timestamp: FileTimestamp,
+        interval: TimelineInterval,
+    ) -> ServiceResult<Vec<TimelineBucket>> {
+        let entries = self
+            .search_files_iter(
+                root_path,
+                pattern.unwrap_or("**/*".to_string()),
+                exclude_patterns.unwrap_or_default(),
+                None,
+                None,
+            )
+            .await?
+            .filter(|e| e.is_file());
+
+        let mut buckets: BTreeMap<NaiveDate, (usize, u64)> = BTreeMap::new();
+        for entry in entries {
+            let Ok(metadata) = entry.metadata() else {
+                continue;
+            };
+            let Ok(time) = timestamp.of(&metadata) else {
+                continue;
+            };
+            let date = DateTime::<Local>::from(time).date_naive();
+            let start = match interval {
+                TimelineInterval::Day => date,
+                TimelineInterval::Week => date.week(chrono::Weekday::Mon).first_day(),
+                TimelineInterval::Month => date.with_day(1).unwrap_or(date),
+            };
+            let bucket = buckets.entry(start).or_default();
+            bucket.0 += 1;
+            bucket.1 += metadata.len();
+        }
+
+        Ok(buckets
+            .into_iter()
+            .map(|(start, (files, bytes))| TimelineBucket {
+                period: match interval {
+                    TimelineInterval::Day => start.format("%Y-%m-%d").to_string(),
+                    TimelineInterval::Week => start.format("%G-W%V").to_string(),
+                    TimelineInterval::Month => start.format("%Y-%m").to_string(),
+                },
+                start: start.format("%Y-%m-%d").to_string(),
+                files,
+                bytes,
+            })
+            .collect())
     }
 }
+
+/// Splits each group of paths into groups of files with the same `hash`, hashing files in parallel.
+/// Only groups of more than one file are kept, files that can't be read are left out.
+fn group_by_hash(
+    groups: Vec<Vec<String>>,
+    hash: impl Fn(&str) -> Option<Vec<u8>> + Sync,
+) -> Vec<Vec<String>> {
+    groups
+        .into_par_iter()
+        .flat_map_iter(|paths| {
+            let hashes: Vec<(String, Vec<u8>)> = paths
+                .into_par_iter()
+                .filter_map(|path| hash(&path).map(|digest| (path, digest)))
+                .collect();
+            let mut hash_map: HashMap<Vec<u8>, Vec<String>> = HashMap::new();
+            for (path, digest) in hashes {
+                hash_map.entry(digest).or_default().push(path);
+            }
+            hash_map.into_values().filter(|group| group.len() > 1)
+        })
+        .collect()
+}
@@ -0,0 +1,275 @@
+//! Line counts by language.
+//!
+//! Languages are told apart by file extension only. When lines are classified, a line is blank
+//! when it only holds whitespace, a comment when it starts with a comment marker of its language
+//! or lies within a block comment, and code otherwise: a line with code followed by a comment is
+//! code, and comment markers within string literals aren't recognized.
+
+use crate::{error::ServiceResult, fs_service::FileSystemService};
+use rayon::iter::{ParallelBridge, ParallelIterator};
+use std::{collections::HashMap, path::Path};
+
+/// Comment syntax of a language: line comment markers and block comment delimiters.
+struct CommentSyntax {
+    line: &'static [&'static str],
+    block: Option<(&'static str, &'static str)>,
+}
+
+const C_STYLE: CommentSyntax = CommentSyntax {
+    line: &["//"],
+    block: Some(("/*", "*/")),
+};
+const HASH: CommentSyntax = CommentSyntax {
+    line: &["#"],
+    block: None,
+};
+const MARKUP: CommentSyntax = CommentSyntax {
+    line: &[],
+    block: Some(("<!--", "-->")),
+};
+const NONE: CommentSyntax = CommentSyntax {
+    line: &[],
+    block: None,
+};
+
+/// Well-known languages with their lowercase extensions and comment syntax.
+const LANGUAGES: &[(&str, &[&str], CommentSyntax)] = &[
+    ("Rust", &["rs"], C_STYLE),
+    ("C", &["c", "h"], C_STYLE),
+    ("C++", &["cpp", "cc", "cxx", "hpp", "hh", "hxx"], C_STYLE),
+    ("C#", &["cs"], C_STYLE),
+    ("Go", &["go"], C_STYLE),
+    ("Java", &["java"], C_STYLE),
+    ("Kotlin", &["kt", "kts"], C_STYLE),
+    ("Swift", &["swift"], C_STYLE),
+    ("JavaScript", &["js", "mjs", "cjs", "jsx"], C_STYLE),
+    ("TypeScript", &["ts", "mts", "cts", "tsx"], C_STYLE),
+    (
+        "CSS",
+        &["css"],
+        CommentSyntax {
+            line: &[],
+            block: Some(("/*", "*/")),
+        },
+    ),
+    ("SCSS", &["scss", "less"], C_STYLE),
+    (
+        "PHP",
+        &["php"],
+        CommentSyntax {
+            line: &["//", "#"],
+            block: Some(("/*", "*/")),
+        },
+    ),
+    ("Python", &["py", "pyi"], HASH),
+    ("Ruby", &["rb"], HASH),
+    ("Shell", &["sh", "bash", "zsh"], HASH),
+    ("Perl", &["pl", "pm"], HASH),
+    ("R", &["r"], HASH),
+    ("YAML", &["yaml", "yml"], HASH),
+    ("TOML", &["toml"], HASH),
+    ("Makefile", &["mk"], HASH),
+    ("Dockerfile", &["dockerfile"], HASH),
+    (
+        "SQL",
+        &["sql"],
+        CommentSyntax {
+            line: &["--"],
+            block: Some(("/*", "*/")),
+        },
+    ),
+    (
+        "Lua",
+        &["lua"],
+        CommentSyntax {
+            line: &["--"],
+            block: Some(("--[[", "]]")),
+        },
+    ),
+    (
+        "Haskell",
+        &["hs"],
+        CommentSyntax {
+            line: &["--"],
+            block: Some(("{-", "-}")),
+        },
+    ),
+    ("HTML", &["html", "htm"], MARKUP),
+    ("XML", &["xml", "xsd", "xsl", "svg"], MARKUP),
+    ("Vue", &["vue"], MARKUP),
+    ("Markdown", &["md", "markdown"], MARKUP),
+    ("JSON", &["json"], NONE),
+    ("Text", &["txt"], NONE),
+];
+
+/// Files named after their language rather than by extension.
+const LANGUAGE_FILE_NAMES: &[(&str, &str)] = &[
+    ("makefile", "Makefile"),
+    ("gnumakefile", "Makefile"),
+    ("dockerfile", "Dockerfile"),
+];
+
+/// Line counts of the files of a language, as returned by `count_lines_of_code`.
+#[derive(Debug, Clone, ::serde::Serialize)]
+pub struct LanguageLines {
+    /// Name of the language, or the lowercase extension with a leading dot for files of other
+    /// extensions, `(none)` for files without extension.
+    pub language: String,
+    pub files: usize,
+    pub lines: usize,
+    /// Lines of code, when lines are classified.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<usize>,
+    /// Comment lines, when lines are classified.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub comments: Option<usize>,
+    /// Blank lines, when lines are classified.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub blanks: Option<usize>,
+}
+
+#[derive(Default)]
+struct LineCounts {
+    lines: usize,
+    code: usize,
+    comments: usize,
+    blanks: usize,
+}
+
+/// The language of a file and its comment syntax, `None` for unknown languages.
+fn language_of(path: &Path) -> (String, Option<&'static CommentSyntax>) {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let language_name = LANGUAGE_FILE_NAMES
+        .iter()
+        .find(|(name, _)| *name == file_name)
+        .map(|(_, language)| *language);
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase());
+
+    let language = LANGUAGES.iter().find(|(name, extensions, _)| {
+        language_name.map_or_else(
+            || {
+                extension
+                    .as_deref()
+                    .is_some_and(|extension| extensions.contains(&extension))
+            },
+            |language_name| language_name == *name,
+        )
+    });
+    match (language, extension) {
+        (Some((name, _, syntax)), _) => (name.to_string(), Some(syntax)),
+        (None, Some(extension)) => (format!(".{extension}"), None),
+        (None, None) => ("(none)".to_string(), None),
+    }
+}
+
+/// Counts the lines of `content`, classifying them with `syntax` when given.
+fn count_lines(content: &str, syntax: Option<&CommentSyntax>) -> LineCounts {
+    let mut counts = LineCounts::default();
+    // the closing delimiter of the block comment the current line is in
+    let mut block_end: Option<&str> = None;
+    for line in content.lines() {
+        counts.lines += 1;
+        let line = line.trim();
+        if line.is_empty() {
+            counts.blanks += 1;
+            continue;
+        }
+        if let Some(end) = block_end {
+            counts.comments += 1;
+            if line.contains(end) {
+                block_end = None;
+            }
+            continue;
+        }
+        let Some(syntax) = syntax else {
+            counts.code += 1;
+            continue;
+        };
+        if let Some((start, end)) = syntax.block
+            && let Some(rest) = line.strip_prefix(start)
+        {
+            counts.comments += 1;
+            if !rest.contains(end) {
+                block_end = Some(end);
+            }
+        } else if syntax.line.iter().any(|marker| line.starts_with(marker)) {
+            counts.comments += 1;
+        } else {
+            counts.code += 1;
+        }
+    }
+    counts
+}
+
+impl FileSystemService {
+    /// Counts the files and lines under `root_path` by language, sorted by number of lines,
+    /// largest first. Files which aren't valid UTF-8 or contain NUL bytes are skipped as binary.
+    ///
+    /// With `classify` set, lines are also counted as code, comment or blank lines.
+    pub async fn count_lines_of_code(
+        &self,
+        root_path: &Path,
+        pattern: Option<String>,
+        exclude_patterns: Option<Vec<String>>,
+        classify: bool,
+    ) -> ServiceResult<Vec<LanguageLines>> {
+        let entries = self
+            .search_files_iter(
+                root_path,
+                pattern.unwrap_or("**/*".to_string()),
+                exclude_patterns.unwrap_or_default(),
+                None,
+                None,
+            )
+            .await?
+            .filter(|e| e.is_file());
+
+        let file_counts: Vec<(String, LineCounts)> = entries
+            .par_bridge()
+            .filter_map(|entry| {
+                let bytes = self.read_tree_file(entry.path()).ok()?;
+                if bytes.contains(&0) {
+                    return None;
+                }
+                let content = String::from_utf8(bytes).ok()?;
+                let (language, syntax) = language_of(entry.path());
+                Some((language, count_lines(&content, syntax)))
+            })
+            .collect();
+
+        let mut languages: HashMap<String, LanguageLines> = HashMap::new();
+        for (language, counts) in file_counts {
+            let language_lines =
+                languages
+                    .entry(language.clone())
+                    .or_insert_with(|| LanguageLines {
+                        language,
+                        files: 0,
+                        lines: 0,
+                        code: classify.then_some(0),
+                        comments: classify.then_some(0),
+                        blanks: classify.then_some(0),
+                    });
+            language_lines.files += 1;
+            language_lines.lines += counts.lines;
+            if classify {
+                *language_lines.code.get_or_insert(0) += counts.code;
+                *language_lines.comments.get_or_insert(0) += counts.comments;
+                *language_lines.blanks.get_or_insert(0) += counts.blanks;
+            }
+        }
+
+        let mut languages: Vec<LanguageLines> = languages.into_values().collect();
+        languages.sort_by(|a, b| {
+            b.lines
+                .cmp(&a.lines)
+                .then_with(|| a.language.cmp(&b.language))
+        });
+        Ok(languages)
+    }
+}
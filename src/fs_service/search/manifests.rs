@@ -0,0 +1,139 @@
+use crate::{error::ServiceResult, fs_service::FileSystemService};
+use std::{
+    fmt::Display,
+    path::{Path, PathBuf},
+};
+
+/// Package ecosystem of a dependency manifest.
+#[derive(::serde::Serialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum Ecosystem {
+    Rust,
+    Node,
+    Python,
+    Go,
+}
+
+impl Display for Ecosystem {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Ecosystem::Rust => write!(f, "rust"),
+            Ecosystem::Node => write!(f, "node"),
+            Ecosystem::Python => write!(f, "python"),
+            Ecosystem::Go => write!(f, "go"),
+        }
+    }
+}
+
+/// File names of the dependency manifests of each ecosystem.
+pub const DEPENDENCY_MANIFESTS: [(&str, Ecosystem); 5] = [
+    ("Cargo.toml", Ecosystem::Rust),
+    ("package.json", Ecosystem::Node),
+    ("requirements.txt", Ecosystem::Python),
+    ("pyproject.toml", Ecosystem::Python),
+    ("go.mod", Ecosystem::Go),
+];
+
+/// Directories holding installed dependencies rather than the manifests of the project.
+const DEPENDENCY_DIRECTORIES: [&str; 1] = ["node_modules"];
+
+/// A dependency manifest, as returned by `find_dependency_manifests`.
+#[derive(::serde::Serialize, Debug, Clone)]
+pub struct DependencyManifest {
+    pub path: PathBuf,
+    pub size: u64,
+    /// The content of the manifest, cut at a line boundary when it exceeds the size limit.
+    pub content: String,
+    pub truncated: bool,
+}
+
+/// The dependency manifests of an ecosystem.
+#[derive(::serde::Serialize, Debug, Clone)]
+pub struct EcosystemManifests {
+    pub ecosystem: Ecosystem,
+    pub manifests: Vec<DependencyManifest>,
+}
+
+/// Keeps at most `max_bytes` of `content`, up to the end of the last complete line if any.
+fn truncate_content(mut content: String, max_bytes: usize) -> (String, bool) {
+    if content.len() <= max_bytes {
+        return (content, false);
+    }
+    let mut end = max_bytes;
+    while !content.is_char_boundary(end) {
+        end -= 1;
+    }
+    if let Some(line_end) = content[..end].rfind('\n') {
+        end = line_end + 1;
+    }
+    content.truncate(end);
+    (content, true)
+}
+
+impl FileSystemService {
+    /// Finds the dependency manifests under `root_path`, grouped by ecosystem, with their content
+    /// limited to `max_bytes` each. Manifests of installed dependencies (within `node_modules`)
+    /// are skipped, manifests within an ecosystem are sorted by path.
+    pub async fn find_dependency_manifests(
+        &self,
+        root_path: &Path,
+        exclude_patterns: Option<Vec<String>>,
+        max_bytes: usize,
+    ) -> ServiceResult<Vec<EcosystemManifests>> {
+        let entries = self
+            .search_files_iter(
+                root_path,
+                "**/*".to_string(),
+                exclude_patterns.unwrap_or_default(),
+                None,
+                None,
+            )
+            .await?
+            .filter(|e| e.is_file());
+
+        let mut groups: Vec<EcosystemManifests> = vec![];
+        for entry in entries {
+            let path = entry.path();
+            let Some((_, ecosystem)) = DEPENDENCY_MANIFESTS
+                .iter()
+                .find(|(name, _)| path.file_name().is_some_and(|file_name| file_name == *name))
+            else {
+                continue;
+            };
+            if path.components().any(|component| {
+                DEPENDENCY_DIRECTORIES.contains(&component.as_os_str().to_string_lossy().as_ref())
+            }) {
+                continue;
+            }
+            let Ok(bytes) = self.read_tree_file(path) else {
+                continue;
+            };
+            let size = bytes.len() as u64;
+            let (content, truncated) =
+                truncate_content(String::from_utf8_lossy(&bytes).into_owned(), max_bytes);
+            let manifest = DependencyManifest {
+                path: path.to_path_buf(),
+                size,
+                content,
+                truncated,
+            };
+
+            match groups
+                .iter_mut()
+                .find(|group| group.ecosystem == *ecosystem)
+            {
+                Some(group) => group.manifests.push(manifest),
+                None => groups.push(EcosystemManifests {
+                    ecosystem: *ecosystem,
+                    manifests: vec![manifest],
+                }),
+            }
+        }
+
+        groups.sort_by_key(|group| group.ecosystem);
+        for group in groups.iter_mut() {
+            group.manifests.sort_by(|a, b| a.path.cmp(&b.path));
+        }
+        Ok(groups)
+    }
+}
@@ -0,0 +1,170 @@
+use super::content::ContentMatcher;
+use crate::{error::ServiceResult, fs_service::FileSystemService};
+use rayon::iter::{IntoParallelIterator, ParallelIterator};
+use std::path::{Path, PathBuf};
+
+/// Number of matches per line from which the content of a file is considered fully dense.
+const DENSE_MATCHES_PER_LINE: f64 = 0.1;
+
+/// A file ranked by `find_relevant_files`.
+#[derive(Debug, Clone, ::serde::Serialize)]
+pub struct RelevantFile {
+    pub path: PathBuf,
+    /// Combined score, the sum of `path_score` and `content_score` (0 to 2).
+    pub score: f64,
+    /// How many of the query terms appear in the path, terms in the file name count more (0 to 1).
+    pub path_score: f64,
+    /// Share of the query terms found in the content and density of the matches (0 to 1).
+    pub content_score: f64,
+    /// Number of occurrences of the query terms in the content.
+    pub matches: usize,
+    pub lines: usize,
+    /// Query terms found in the content.
+    pub matched_terms: Vec<String>,
+}
+
+impl FileSystemService {
+    /// Finds the files under `root_path` matching the glob `pattern` that are the most relevant to
+    /// `query`, a list of terms separated by whitespace, searched case-insensitively in both the
+    /// paths and the contents of the files. Returns at most `limit` files, by decreasing score.
+    pub async fn find_relevant_files(
+        &self,
+        root_path: &Path,
+        pattern: &str,
+        query: &str,
+        exclude_patterns: Option<Vec<String>>,
+        limit: usize,
+    ) -> ServiceResult<Vec<RelevantFile>> {
+        let terms: Vec<String> = query
+            .split_whitespace()
+            .map(|term| term.to_lowercase())
+            .collect();
+        if terms.is_empty() {
+            return Ok(vec![]);
+        }
+        let term_refs: Vec<&str> = terms.iter().map(String::as_str).collect();
+        let matcher = ContentMatcher::new(&term_refs, false)?;
+
+        let entries = self
+            .search_files_iter(
+                root_path,
+                pattern.to_string(),
+                exclude_patterns.unwrap_or_default(),
+                None,
+                None,
+            )
+            .await?;
+
+        // A batch operation: reading every file of the tree must not hold up other tool calls
+        let mut ranked: Vec<RelevantFile> = self
+            .batch_executor
+            .run(|| {
+                let files: Vec<PathBuf> = entries
+                    .filter(|entry| entry.is_file())
+                    .map(|entry| entry.into_path())
+                    .collect();
+
+                files
+                    .into_par_iter()
+                    .filter_map(|path| {
+                        let path_score = path_score(root_path, &path, &terms);
+                        let (matches, lines, matched_terms) =
+                            content_matches(&matcher, self.read_tree_file(&path).ok(), &terms)
+                                .unwrap_or_default();
+                        let coverage = matched_terms.len() as f64 / terms.len() as f64;
+                        let density = if lines == 0 {
+                            0.0
+                        } else {
+                            (matches as f64 / lines as f64 / DENSE_MATCHES_PER_LINE).min(1.0)
+                        };
+                        let content_score = (coverage + density) / 2.0;
+                        let score = path_score + content_score;
+                        (score > 0.0).then(|| RelevantFile {
+                            path,
+                            score: round_score(score),
+                            path_score: round_score(path_score),
+                            content_score: round_score(content_score),
+                            matches,
+                            lines,
+                            matched_terms,
+                        })
+                    })
+                    .collect()
+            })
+            .await;
+
+        ranked.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.path.cmp(&b.path))
+        });
+        ranked.truncate(limit);
+        Ok(ranked)
+    }
+}
+
+/// A term found in the file name scores 1, elsewhere in the path relative to `root_path` 0.5.
+fn path_score(root_path: &Path, path: &Path, terms: &[String]) -> f64 {
+    let file_name = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+    let parent = path
+        .parent()
+        .and_then(|parent| parent.strip_prefix(root_path).ok())
+        .map(|parent| parent.to_string_lossy().to_lowercase())
+        .unwrap_or_default();
+
+    let total: f64 = terms
+        .iter()
+        .map(|term| {
+            if file_name.contains(term.as_str()) {
+                1.0
+            } else if parent.contains(term.as_str()) {
+                0.5
+            } else {
+                0.0
+            }
+        })
+        .sum();
+    total / terms.len() as f64
+}
+
+/// Number of matches, number of lines and terms found in the file, `None` for binary or
+/// unreadable files.
+fn content_matches(
+    matcher: &ContentMatcher,
+    content: Option<Vec<u8>>,
+    terms: &[String],
+) -> Option<(usize, usize, Vec<String>)> {
+    let content = content?;
+    if content.contains(&0) {
+        return None;
+    }
+    let lines = content.split(|byte| *byte == b'\n').count();
+
+    let mut matches = 0;
+    let mut found = vec![false; terms.len()];
+    matcher.find_iter(&content, |found_match| {
+        matches += 1;
+        // with a single term, the matcher doesn't tell which query matched. Terms are literals,
+        // the content after the match is not needed to tell which one matched.
+        let term = matcher.matched_query(&content[..found_match.end()], found_match);
+        let index = term
+            .and_then(|term| terms.iter().position(|t| *t == term))
+            .unwrap_or(0);
+        found[index] = true;
+    });
+
+    let matched_terms = terms
+        .iter()
+        .zip(found)
+        .filter(|(_, found)| *found)
+        .map(|(term, _)| term.clone())
+        .collect();
+    Some((matches, lines, matched_terms))
+}
+
+fn round_score(score: f64) -> f64 {
+    (score * 1000.0).round() / 1000.0
+}
@@ -0,0 +1,205 @@
+//! Directory sizes, cached per directory when enabled (`--cache-directory-sizes`).
+//!
+//! The modification time of a directory changes when entries are added, removed or renamed in it,
+//! so the size of its files and its list of subdirectories can be reused as long as it's unchanged.
+//! Each directory of the tree is still visited on every call, but only to read its modification
+//! time, which makes repeated queries over a mostly static tree fast.
+//!
+//! Rewriting a file in place does not change the modification time of its directory, such changes
+//! are only picked up when the cache is bypassed with `force_refresh`. This is why the cache is
+//! disabled unless the server is started for trees whose files are not rewritten in place.
+
+use crate::{
+    error::ServiceResult,
+    fs_service::{FileSystemService, ignore::IgnoreRules},
+};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
+
+/// Directories modified more recently are not cached, as further changes within the timestamp
+/// granularity of the filesystem (up to 2 seconds on FAT) would go unnoticed.
+const MIN_CACHED_DIRECTORY_AGE: Duration = Duration::from_secs(2);
+
+#[derive(Debug, Clone)]
+struct CachedDirectory {
+    modified: SystemTime,
+    /// Total size of the files directly within the directory.
+    files_size: u64,
+    subdirectories: Vec<PathBuf>,
+}
+
+/// Cached sizes of the directories visited by `calculate_directory_size`.
+/// Entries depend on the allowed directories and `.mcpignore` files, the cache is cleared when they change.
+#[derive(Debug, Default)]
+pub(crate) struct DirectorySizeCache {
+    allowed_directories: Vec<PathBuf>,
+    ignore_rules: IgnoreRules,
+    directories: HashMap<PathBuf, CachedDirectory>,
+}
+
+impl FileSystemService {
+    /// Caches the sizes of the directories visited by `calculate_directory_size` as long as they
+    /// are unchanged (default: false). Files rewritten in place are then only counted with their
+    /// new size when the cache is bypassed.
+    pub fn with_directory_size_cache(mut self, enabled: bool) -> Self {
+        self.directory_size_cache = enabled.then(Default::default);
+        self
+    }
+
+    /// Calculates the total size (in bytes) of all files within a directory tree.
+    ///
+    /// Symbolic links are followed, paths outside the allowed directories and paths excluded by
+    /// `.mcpignore` files are skipped. When the cache is enabled, results are cached per directory as
+    /// long as its modification time is unchanged, set `force_refresh` to recompute the size of the
+    /// whole tree. A tree changed by the overlay is measured as the overlay would write it, without
+    /// the cache.
+    pub async fn calculate_directory_size(
+        &self,
+        root_path: &Path,
+        force_refresh: bool,
+    ) -> ServiceResult<u64> {
+        let allowed_directories = self.allowed_directories().await;
+        let valid_path = self.validate_path(root_path, allowed_directories.clone())?;
+        let ignore_rules = IgnoreRules::load(&allowed_directories);
+
+        if self.overlay_changes_tree(&valid_path) {
+            return Ok(self
+                .walk_tree(&valid_path, |entry| {
+                    self.validate_path(entry.path(), allowed_directories.clone())
+                        .is_ok()
+                        && !ignore_rules.is_ignored(entry.path(), entry.is_dir())
+                })
+                .filter(|entry| entry.is_file())
+                .filter_map(|entry| entry.size().ok())
+                .sum());
+        }
+
+        // the cache is taken for the duration of the calculation, concurrent calls start from an empty cache
+        let mut cache = match &self.directory_size_cache {
+            Some(cache) => std::mem::take(&mut *cache.lock().unwrap()),
+            None => DirectorySizeCache::default(),
+        };
+        if force_refresh
+            || cache.allowed_directories != *allowed_directories
+            || cache.ignore_rules != ignore_rules
+        {
+            cache = DirectorySizeCache {
+                allowed_directories: allowed_directories.to_vec(),
+                ignore_rules,
+                directories: HashMap::new(),
+            };
+        }
+
+        let total_size =
+            self.cached_directory_size(&valid_path, &allowed_directories, &mut cache, &mut vec![]);
+
+        if let Some(directory_size_cache) = &self.directory_size_cache {
+            *directory_size_cache.lock().unwrap() = cache;
+        }
+        Ok(total_size)
+    }
+
+    /// Size of the files within `dir`, `ancestors` holds the canonical paths of the directories
+    /// being visited to detect symbolic link cycles.
+    fn cached_directory_size(
+        &self,
+        dir: &Path,
+        allowed_directories: &Arc<Vec<PathBuf>>,
+        cache: &mut DirectorySizeCache,
+        ancestors: &mut Vec<PathBuf>,
+    ) -> u64 {
+        // unreadable entries are skipped, as by the other traversal-based tools
+        let Ok(metadata) = std::fs::metadata(dir) else {
+            return 0;
+        };
+        if !metadata.is_dir() {
+            return 0;
+        }
+        let Ok(canonical_dir) = dir.canonicalize() else {
+            return 0;
+        };
+        if ancestors.contains(&canonical_dir) {
+            return 0;
+        }
+
+        let modified = metadata.modified().ok();
+        let cached = cache
+            .directories
+            .get(dir)
+            .filter(|cached| Some(cached.modified) == modified)
+            .cloned();
+        let (files_size, subdirectories) = match cached {
+            Some(cached) => (cached.files_size, cached.subdirectories),
+            None => {
+                let (files_size, subdirectories) =
+                    self.scan_directory(dir, allowed_directories, &cache.ignore_rules);
+                if let Some(modified) = modified
+                    && modified
+                        .elapsed()
+                        .is_ok_and(|age| age >= MIN_CACHED_DIRECTORY_AGE)
+                {
+                    cache.directories.insert(
+                        dir.to_path_buf(),
+                        CachedDirectory {
+                            modified,
+                            files_size,
+                            subdirectories: subdirectories.clone(),
+                        },
+                    );
+                }
+                (files_size, subdirectories)
+            }
+        };
+
+        ancestors.push(canonical_dir);
+        let subdirectories_size: u64 = subdirectories
+            .iter()
+            .map(|subdirectory| {
+                self.cached_directory_size(subdirectory, allowed_directories, cache, ancestors)
+            })
+            .sum();
+        ancestors.pop();
+
+        files_size + subdirectories_size
+    }
+
+    /// Lists `dir`, returning the total size of its files and its subdirectories.
+    fn scan_directory(
+        &self,
+        dir: &Path,
+        allowed_directories: &Arc<Vec<PathBuf>>,
+        ignore_rules: &IgnoreRules,
+    ) -> (u64, Vec<PathBuf>) {
+        let mut files_size = 0;
+        let mut subdirectories = vec![];
+
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return (files_size, subdirectories);
+        };
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            let path = entry.path();
+            if self
+                .validate_path(&path, allowed_directories.clone())
+                .is_err()
+            {
+                continue;
+            }
+            let Ok(metadata) = std::fs::metadata(&path) else {
+                continue;
+            };
+            if ignore_rules.is_ignored(&path, metadata.is_dir()) {
+                continue;
+            }
+            if metadata.is_file() {
+                files_size += metadata.len();
+            } else if metadata.is_dir() {
+                subdirectories.push(path);
+            }
+        }
+        (files_size, subdirectories)
+    }
+}
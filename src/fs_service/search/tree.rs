@@ -1,15 +1,14 @@
 use crate::{
     error::{ServiceError, ServiceResult},
-    fs_service::{FileSystemService, utils::is_system_metadata_file},
+    fs_service::{
+        FileSystemService, TreeEntry, ignore::IgnoreRules, utils::is_system_metadata_file,
+    },
 };
-use rayon::iter::{ParallelBridge, ParallelIterator};
 use serde_json::{Value, json};
 use std::{
-    fs::{self},
     path::{Path, PathBuf},
     sync::Arc,
 };
-use walkdir::WalkDir;
 
 impl FileSystemService {
     /// Generates a JSON representation of a directory tree starting at the given path.
@@ -34,10 +33,30 @@ impl FileSystemService {
         current_count: &mut usize,
         allowed_directories: Arc<Vec<PathBuf>>,
     ) -> ServiceResult<(Value, bool)> {
-        let valid_path = self.validate_path(root_path.as_ref(), allowed_directories.clone())?;
+        let ignore_rules = IgnoreRules::load(&allowed_directories);
+        self.build_directory_tree(
+            root_path.as_ref(),
+            max_depth,
+            max_files,
+            current_count,
+            allowed_directories,
+            &ignore_rules,
+        )
+    }
 
-        let metadata = fs::metadata(&valid_path)?;
-        if !metadata.is_dir() {
+    fn build_directory_tree(
+        &self,
+        root_path: &Path,
+        max_depth: Option<usize>,
+        max_files: Option<usize>,
+        current_count: &mut usize,
+        allowed_directories: Arc<Vec<PathBuf>>,
+        ignore_rules: &IgnoreRules,
+    ) -> ServiceResult<(Value, bool)> {
+        self.check_cancelled()?;
+        let valid_path = self.validate_path(root_path, allowed_directories.clone())?;
+
+        if !self.tree_entry(&valid_path)?.is_dir() {
             return Err(ServiceError::FromString(
                 "Root path must be a directory".into(),
             ));
@@ -47,15 +66,17 @@ impl FileSystemService {
         let mut reached_max_depth = false;
 
         if max_depth != Some(0) {
-            for entry in WalkDir::new(valid_path)
-                .min_depth(1)
-                .max_depth(1)
-                .follow_links(true)
+            // unreadable and broken entries are skipped
+            for entry in self
+                .read_tree_dir(&valid_path)
+                .unwrap_or_default()
                 .into_iter()
-                .filter_map(|e| e.ok())
+                .filter(|entry| entry.is_dir() || entry.is_file())
             {
                 let child_path = entry.path();
-                let metadata = fs::metadata(child_path)?;
+                if ignore_rules.is_ignored(child_path, entry.is_dir()) {
+                    continue;
+                }
 
                 let entry_name = child_path
                     .file_name()
@@ -75,17 +96,18 @@ impl FileSystemService {
 
                 let mut json_entry = json!({
                     "name": entry_name,
-                    "type": if metadata.is_dir() { "directory" } else { "file" }
+                    "type": if entry.is_dir() { "directory" } else { "file" }
                 });
 
-                if metadata.is_dir() {
+                if entry.is_dir() {
                     let next_depth = max_depth.map(|d| d - 1);
-                    let (child_children, child_reached_max_depth) = self.directory_tree(
+                    let (child_children, child_reached_max_depth) = self.build_directory_tree(
                         child_path,
                         next_depth,
                         max_files,
                         current_count,
                         allowed_directories.clone(),
+                        ignore_rules,
                     )?;
                     json_entry
                         .as_object_mut()
@@ -102,37 +124,6 @@ impl FileSystemService {
         Ok((Value::Array(children), reached_max_depth))
     }
 
-    /// Calculates the total size (in bytes) of all files within a directory tree.
-    ///
-    /// This function recursively searches the specified `root_path` for files,
-    /// filters out directories and non-file entries, and sums the sizes of all found files.
-    /// The size calculation is parallelized using Rayon for improved performance on large directories.
-    ///
-    /// # Arguments
-    /// * `root_path` - The root directory path to start the size calculation.
-    ///
-    /// # Returns
-    /// Returns a `ServiceResult<u64>` containing the total size in bytes of all files under the `root_path`.
-    ///
-    /// # Notes
-    /// - Only files are included in the size calculation; directories and other non-file entries are ignored.
-    /// - The search pattern is `"**/*"` (all files) and no exclusions are applied.
-    /// - Parallel iteration is used to speed up the metadata fetching and summation.
-    pub async fn calculate_directory_size(&self, root_path: &Path) -> ServiceResult<u64> {
-        let entries = self
-            .search_files_iter(root_path, "**/*".to_string(), vec![], None, None)
-            .await?
-            .filter(|e| e.file_type().is_file()); // Only process files
-
-        // Use rayon to parallelize size summation
-        let total_size: u64 = entries
-            .par_bridge() // Convert to parallel iterator
-            .filter_map(|entry| entry.metadata().ok().map(|meta| meta.len()))
-            .sum();
-
-        Ok(total_size)
-    }
-
     /// Recursively finds all empty directories within the given root path.
     ///
     /// A directory is considered empty if it contains no files in itself or any of its subdirectories
@@ -180,16 +171,15 @@ impl FileSystemService {
                 None,
             )
             .await?
-            .filter(|e| e.file_type().is_dir()); // Only directories
+            .filter(|e| e.is_dir()); // Only directories
 
         let mut empty_dirs = Vec::new();
 
         // Check each directory for emptiness
         for entry in walker {
-            let is_empty = WalkDir::new(entry.path())
-                .into_iter()
-                .filter_map(|e| e.ok())
-                .all(|e| !e.file_type().is_file() || is_system_metadata_file(e.file_name())); // Directory is empty if no files are found in it or subdirs, ".DS_Store" will be ignores on Mac
+            let is_empty = self
+                .walk_tree(entry.path(), |_| true)
+                .all(|e| !e.is_file() || is_system_metadata_file(e.file_name())); // Directory is empty if no files are found in it or subdirs, ".DS_Store" will be ignores on Mac
 
             if is_empty && let Some(path_str) = entry.path().to_str() {
                 empty_dirs.push(path_str.to_string());
@@ -199,18 +189,21 @@ impl FileSystemService {
         Ok(empty_dirs)
     }
 
-    pub async fn list_directory(&self, dir_path: &Path) -> ServiceResult<Vec<tokio::fs::DirEntry>> {
+    /// Lists the entries of a directory, through the overlay of the changes if any. When
+    /// `exclude_hidden` is true, hidden entries are skipped (hidden/system attributes on Windows,
+    /// dot-files elsewhere).
+    pub async fn list_directory(
+        &self,
+        dir_path: &Path,
+        exclude_hidden: bool,
+    ) -> ServiceResult<Vec<TreeEntry>> {
         let allowed_directories = self.allowed_directories().await;
 
         let valid_path = self.validate_path(dir_path, allowed_directories)?;
 
-        let mut dir = tokio::fs::read_dir(valid_path).await?;
-
-        let mut entries = Vec::new();
-
-        // Use a loop to collect the directory entries
-        while let Some(entry) = dir.next_entry().await? {
-            entries.push(entry);
+        let mut entries = self.read_tree_dir(&valid_path)?;
+        if exclude_hidden {
+            entries.retain(|entry| !entry.is_hidden());
         }
 
         Ok(entries)
@@ -0,0 +1,1108 @@
+//! Overlay of the changes, for the simulation mode (`--simulate`) and the overlay workspace
+//! (`--overlay-dir`).
+//!
+//! The mutating operations are validated as usual and return their usual results (diffs, paths,
+//! archive sizes) but nothing is written to the tree: their changes are kept in a copy-on-write
+//! overlay, in memory when simulating or in the overlay directory. Reads of the file contents go
+//! through the overlay, and so do the directory listings, searches, metadata and sizes, so a
+//! sequence of tool calls behaves as if the changes were made. The changes of an overlay directory
+//! are applied to the tree by `commit_overlay`, or discarded. Its index is saved along with the
+//! stored files, so the changes survive a restart of the server.
+
+use crate::{
+    error::{ServiceError, ServiceResult},
+    fs_service::{
+        FileSystemService,
+        utils::{create_parent_dirs, format_diff_block, is_hidden},
+    },
+};
+use std::{
+    collections::{BTreeSet, HashMap},
+    ffi::OsStr,
+    fmt::Display,
+    io,
+    path::{Path, PathBuf},
+};
+use walkdir::WalkDir;
+
+/// Name of the index of the changes, in the overlay directory.
+const OVERLAY_INDEX: &str = "overlay-index.json";
+
+/// A path changed by the overlay.
+#[derive(Debug, Clone, PartialEq, ::serde::Serialize, ::serde::Deserialize)]
+#[serde(rename_all = "snake_case")]
+enum OverlayEntry {
+    File(Vec<u8>),
+    /// A file whose content is stored in the overlay directory, at this path.
+    Stored(PathBuf),
+    Directory,
+    Removed,
+    /// Moved from a path on disk, whose content (or tree) it still refers to.
+    MovedFrom(PathBuf),
+}
+
+/// State of a path, as seen through the overlay.
+#[derive(Debug)]
+enum Resolved {
+    File(Vec<u8>),
+    Stored(PathBuf),
+    Directory,
+    /// Read from this path on disk.
+    Disk(PathBuf),
+    Missing,
+}
+
+/// The changes of the overlay, by path.
+#[derive(Debug, Default)]
+pub(crate) struct Overlay {
+    entries: HashMap<PathBuf, OverlayEntry>,
+    /// Where the content of the files is stored, in memory when `None`.
+    directory: Option<PathBuf>,
+    /// Number of the next file stored in the overlay directory.
+    next_file: u64,
+    /// Whether the changes are being committed, their stored files are then only deleted once the
+    /// commit ends.
+    committing: bool,
+    /// Stored files of the entries replaced or dropped during a commit.
+    unused_files: Vec<PathBuf>,
+}
+
+/// The entries of an overlay directory, as saved in its index.
+#[derive(Debug, Default, ::serde::Serialize, ::serde::Deserialize)]
+struct OverlayIndex {
+    next_file: u64,
+    entries: Vec<(PathBuf, OverlayEntry)>,
+}
+
+impl Overlay {
+    /// The overlay stored in `directory`, with the changes left by a previous run if any.
+    fn load(directory: PathBuf) -> io::Result<Self> {
+        let index: OverlayIndex = match std::fs::read(directory.join(OVERLAY_INDEX)) {
+            Ok(content) => serde_json::from_slice(&content).map_err(io::Error::other)?,
+            Err(err) if err.kind() == io::ErrorKind::NotFound => OverlayIndex::default(),
+            Err(err) => return Err(err),
+        };
+        Ok(Self {
+            entries: index.entries.into_iter().collect(),
+            directory: Some(directory),
+            next_file: index.next_file,
+            ..Default::default()
+        })
+    }
+
+    /// Saves the index of the overlay directory, which is removed once there are no changes.
+    fn save(&self) -> io::Result<()> {
+        let Some(directory) = &self.directory else {
+            return Ok(());
+        };
+        let index_path = directory.join(OVERLAY_INDEX);
+        if self.entries.is_empty() {
+            return match std::fs::remove_file(index_path) {
+                Err(err) if err.kind() != io::ErrorKind::NotFound => Err(err),
+                _ => Ok(()),
+            };
+        }
+        let index = OverlayIndex {
+            next_file: self.next_file,
+            entries: self
+                .entries
+                .iter()
+                .map(|(path, entry)| (path.clone(), entry.clone()))
+                .collect(),
+        };
+        std::fs::create_dir_all(directory)?;
+        // replaced at once, a crash leaves the previous index
+        let temp_path = directory.join(format!("{OVERLAY_INDEX}.tmp"));
+        std::fs::write(
+            &temp_path,
+            serde_json::to_vec(&index).map_err(io::Error::other)?,
+        )?;
+        std::fs::rename(temp_path, index_path)
+    }
+
+    /// Saves the index where a failure can't be reported, it is made up for by the next change
+    /// saving the whole index.
+    fn save_quietly(&self) {
+        let _ = self.save();
+    }
+
+    /// The nearest entry among `path` and its ancestors decides of its state.
+    fn resolve(&self, path: &Path) -> Resolved {
+        for ancestor in path.ancestors() {
+            let Some(entry) = self.entries.get(ancestor) else {
+                continue;
+            };
+            let rest = path.strip_prefix(ancestor).unwrap_or(Path::new(""));
+            return match entry {
+                OverlayEntry::MovedFrom(origin) => Resolved::Disk(origin.join(rest)),
+                OverlayEntry::Removed => Resolved::Missing,
+                OverlayEntry::File(content) if rest.as_os_str().is_empty() => {
+                    Resolved::File(content.clone())
+                }
+                OverlayEntry::Stored(stored_path) if rest.as_os_str().is_empty() => {
+                    Resolved::Stored(stored_path.clone())
+                }
+                OverlayEntry::File(_) | OverlayEntry::Stored(_) => Resolved::Missing,
+                OverlayEntry::Directory if rest.as_os_str().is_empty() => Resolved::Directory,
+                // a created directory, its other entries are those on disk if it existed already
+                OverlayEntry::Directory => Resolved::Disk(path.to_path_buf()),
+            };
+        }
+        Resolved::Disk(path.to_path_buf())
+    }
+
+    /// Records `entry` for `path`, deleting the stored content of the entry it replaces.
+    fn insert(&mut self, path: PathBuf, entry: OverlayEntry) {
+        if let Some(replaced) = self.entries.insert(path, entry) {
+            self.drop_content(replaced);
+        }
+    }
+
+    /// Deletes the stored content of an entry no longer in the overlay, once the commit in
+    /// progress (which may still read it) ends.
+    fn drop_content(&mut self, entry: OverlayEntry) {
+        if let OverlayEntry::Stored(stored_path) = entry {
+            if self.committing {
+                self.unused_files.push(stored_path);
+            } else {
+                let _ = std::fs::remove_file(stored_path);
+            }
+        }
+    }
+
+    /// Drops the entries of `path` and of the paths under it.
+    fn remove_tree(&mut self, path: &Path) {
+        let removed: Vec<PathBuf> = self
+            .entries
+            .keys()
+            .filter(|entry_path| entry_path.starts_with(path))
+            .cloned()
+            .collect();
+        for entry_path in removed {
+            if let Some(entry) = self.entries.remove(&entry_path) {
+                self.drop_content(entry);
+            }
+        }
+    }
+
+    /// Moves the entries under `src` (excluded) under `dest`.
+    fn move_children(&mut self, src: &Path, dest: &Path) {
+        let children: Vec<PathBuf> = self
+            .entries
+            .keys()
+            .filter(|path| path.starts_with(src) && *path != src)
+            .cloned()
+            .collect();
+        for child in children {
+            if let Some(entry) = self.entries.remove(&child) {
+                let relative_path = child.strip_prefix(src).unwrap_or(&child);
+                self.entries.insert(dest.join(relative_path), entry);
+            }
+        }
+    }
+
+    /// The entry of a file with `content`, stored in the overlay directory if any.
+    fn file_entry(&mut self, path: &Path, content: &[u8]) -> io::Result<OverlayEntry> {
+        let Some(directory) = &self.directory else {
+            return Ok(OverlayEntry::File(content.to_vec()));
+        };
+        std::fs::create_dir_all(directory)?;
+        self.next_file += 1;
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        let stored_path = directory.join(format!("{}-{file_name}", self.next_file));
+        std::fs::write(&stored_path, content)?;
+        Ok(OverlayEntry::Stored(stored_path))
+    }
+
+    /// The changes to apply to the tree, in the order they are applied.
+    fn changes(&self) -> Vec<OverlayChange> {
+        let mut moves = vec![];
+        let mut directories = vec![];
+        let mut writes = vec![];
+        let mut removals = vec![];
+        let origins: Vec<&PathBuf> = self
+            .entries
+            .values()
+            .filter_map(|entry| match entry {
+                OverlayEntry::MovedFrom(origin) => Some(origin),
+                _ => None,
+            })
+            .collect();
+        for (path, entry) in &self.entries {
+            match entry {
+                OverlayEntry::MovedFrom(origin) => moves.push(OverlayChange::Move {
+                    from: origin.clone(),
+                    path: path.clone(),
+                }),
+                OverlayEntry::Directory => {
+                    directories.push(OverlayChange::CreateDirectory { path: path.clone() })
+                }
+                OverlayEntry::File(_) | OverlayEntry::Stored(_) => {
+                    writes.push(OverlayChange::Write { path: path.clone() })
+                }
+                // only the paths existing on disk, other than the origins of the moves, are removed
+                OverlayEntry::Removed => {
+                    let disk_path = match path.parent().map(|parent| self.resolve(parent)) {
+                        Some(Resolved::Disk(parent)) => {
+                            parent.join(path.file_name().unwrap_or_default())
+                        }
+                        _ => continue,
+                    };
+                    if disk_path.symlink_metadata().is_ok() && !origins.contains(&&disk_path) {
+                        removals.push(OverlayChange::Remove { path: path.clone() })
+                    }
+                }
+            }
+        }
+        // parents before their children
+        moves.sort_by_key(|change| change.path().components().count());
+        directories.sort_by_key(|change| change.path().components().count());
+        writes.sort_by(|a, b| a.path().cmp(b.path()));
+        removals.sort_by(|a, b| a.path().cmp(b.path()));
+        [moves, directories, writes, removals].concat()
+    }
+
+    /// Drops all the entries, deleting their stored content.
+    fn clear(&mut self) {
+        let entries: Vec<OverlayEntry> = self.entries.drain().map(|(_, entry)| entry).collect();
+        for entry in entries {
+            self.drop_content(entry);
+        }
+    }
+
+    /// Ends a commit, dropping the `committed` entries unless they were changed in the meantime.
+    fn end_commit(&mut self, committed: Option<HashMap<PathBuf, OverlayEntry>>) {
+        self.committing = false;
+        for (path, entry) in committed.into_iter().flatten() {
+            if self.entries.get(&path) == Some(&entry) {
+                self.entries.remove(&path);
+                self.drop_content(entry);
+            }
+        }
+        for stored_path in std::mem::take(&mut self.unused_files) {
+            let _ = std::fs::remove_file(stored_path);
+        }
+        self.save_quietly();
+    }
+}
+
+/// A change of the overlay, applied to the tree when it is committed.
+#[derive(::serde::Serialize, Debug, Clone, PartialEq)]
+#[serde(tag = "change", rename_all = "snake_case")]
+pub enum OverlayChange {
+    Move { from: PathBuf, path: PathBuf },
+    CreateDirectory { path: PathBuf },
+    Write { path: PathBuf },
+    Remove { path: PathBuf },
+}
+
+impl OverlayChange {
+    pub fn path(&self) -> &Path {
+        match self {
+            OverlayChange::Move { path, .. }
+            | OverlayChange::CreateDirectory { path }
+            | OverlayChange::Write { path }
+            | OverlayChange::Remove { path } => path,
+        }
+    }
+}
+
+impl Display for OverlayChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            OverlayChange::Move { from, path } => {
+                write!(f, "move {} -> {}", from.display(), path.display())
+            }
+            OverlayChange::CreateDirectory { path } => {
+                write!(f, "create directory {}", path.display())
+            }
+            OverlayChange::Write { path } => write!(f, "write {}", path.display()),
+            OverlayChange::Remove { path } => write!(f, "remove {}", path.display()),
+        }
+    }
+}
+
+/// How a file differs between the tree and the overlay.
+#[derive(::serde::Serialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum FileChangeStatus {
+    Added,
+    Modified,
+    Deleted,
+}
+
+impl Display for FileChangeStatus {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            FileChangeStatus::Added => write!(f, "added"),
+            FileChangeStatus::Modified => write!(f, "modified"),
+            FileChangeStatus::Deleted => write!(f, "deleted"),
+        }
+    }
+}
+
+/// A file of the tree changed by the overlay.
+#[derive(::serde::Serialize, Debug, Clone)]
+pub struct WorkspaceFileChange {
+    pub path: PathBuf,
+    pub status: FileChangeStatus,
+    /// Unified diff between the file in the tree and in the overlay, `None` for a binary file.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub diff: Option<String>,
+}
+
+/// A file or directory of the tree as seen through the overlay, as returned by the listings and
+/// searches.
+#[derive(Debug, Clone)]
+pub struct TreeEntry {
+    path: PathBuf,
+    is_dir: bool,
+    is_file: bool,
+    /// Where the metadata of the entry are read from: its path on disk, the origin of a move or
+    /// the stored content of a changed file. `None` for a file changed in memory or a created
+    /// directory.
+    source: Option<PathBuf>,
+    /// The size of a file changed in memory.
+    size: u64,
+}
+
+impl TreeEntry {
+    fn on_disk(path: PathBuf, source: PathBuf, metadata: &std::fs::Metadata) -> Self {
+        Self {
+            path,
+            is_dir: metadata.is_dir(),
+            is_file: metadata.is_file(),
+            source: Some(source),
+            size: 0,
+        }
+    }
+
+    /// An entry of a walk of the disk, following symbolic links.
+    fn walked(entry: &walkdir::DirEntry) -> Self {
+        Self {
+            path: entry.path().to_path_buf(),
+            is_dir: entry.file_type().is_dir(),
+            is_file: entry.file_type().is_file(),
+            source: Some(entry.path().to_path_buf()),
+            size: 0,
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    pub fn into_path(self) -> PathBuf {
+        self.path
+    }
+
+    pub fn file_name(&self) -> &OsStr {
+        self.path.file_name().unwrap_or(self.path.as_os_str())
+    }
+
+    pub fn is_dir(&self) -> bool {
+        self.is_dir
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.is_file
+    }
+
+    /// The metadata of the entry on disk, or of the stored content of a changed file. Fails for a
+    /// file changed in memory or a directory created by the overlay.
+    pub fn metadata(&self) -> io::Result<std::fs::Metadata> {
+        match &self.source {
+            Some(source) => std::fs::metadata(source),
+            None => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("'{}' only exists in the overlay", self.path.display()),
+            )),
+        }
+    }
+
+    /// The size of the file, as it would be written by the overlay.
+    pub fn size(&self) -> io::Result<u64> {
+        match &self.source {
+            Some(_) => Ok(self.metadata()?.len()),
+            None => Ok(self.size),
+        }
+    }
+
+    /// Whether the entry is hidden (hidden/system attributes on Windows, dot-files elsewhere).
+    pub fn is_hidden(&self) -> bool {
+        is_hidden(&self.path, self.metadata().ok().as_ref())
+    }
+}
+
+fn removed_error(path: &Path) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::NotFound,
+        format!(
+            "'{}' was removed by a change of the overlay",
+            path.display()
+        ),
+    )
+}
+
+fn no_overlay_error() -> ServiceError {
+    ServiceError::FromString(
+        "No overlay is enabled. Start the server with --overlay-dir to keep the changes in an overlay.".to_string(),
+    )
+}
+
+/// The files under `path` on disk, or `path` itself if it is a file.
+fn disk_files(path: &Path) -> impl Iterator<Item = PathBuf> {
+    walkdir::WalkDir::new(path)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .map(walkdir::DirEntry::into_path)
+}
+
+/// A step of a commit, undone when a later one fails.
+#[derive(Debug)]
+enum Undo {
+    Rename { from: PathBuf, to: PathBuf },
+    RemoveFile(PathBuf),
+    RemoveDirectory(PathBuf),
+}
+
+/// Applies the overlay changes to the tree, keeping what they replace or remove aside until they
+/// are all applied so a failure leaves the tree as it was.
+#[derive(Debug, Default)]
+struct Commit {
+    journal: Vec<Undo>,
+    /// Replaced or removed paths, deleted once the commit succeeds.
+    trash: Vec<PathBuf>,
+    /// Origins of the moves, moved aside before any of them is placed.
+    staged: HashMap<PathBuf, PathBuf>,
+    next_name: u64,
+}
+
+impl Commit {
+    /// A path next to `path` for a hidden copy of it.
+    fn aside(&mut self, path: &Path) -> PathBuf {
+        self.next_name += 1;
+        let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+        path.with_file_name(format!(
+            ".{file_name}.overlay-{}-{}",
+            std::process::id(),
+            self.next_name
+        ))
+    }
+
+    async fn rename(&mut self, from: &Path, to: &Path) -> io::Result<()> {
+        tokio::fs::rename(from, to).await?;
+        self.journal.push(Undo::Rename {
+            from: to.to_path_buf(),
+            to: from.to_path_buf(),
+        });
+        Ok(())
+    }
+
+    /// Moves `path` aside, to be deleted once the commit succeeds.
+    async fn discard(&mut self, path: &Path) -> io::Result<()> {
+        if path.symlink_metadata().is_err() {
+            return Ok(());
+        }
+        let trash_path = self.aside(path);
+        self.rename(path, &trash_path).await?;
+        self.trash.push(trash_path);
+        Ok(())
+    }
+
+    /// Creates `path` and its missing parents.
+    async fn create_dir_all(&mut self, path: &Path) -> io::Result<()> {
+        let mut missing: Vec<&Path> = path
+            .ancestors()
+            .take_while(|ancestor| !ancestor.as_os_str().is_empty() && !ancestor.exists())
+            .collect();
+        missing.reverse();
+        for directory in missing {
+            tokio::fs::create_dir(directory).await?;
+            self.journal
+                .push(Undo::RemoveDirectory(directory.to_path_buf()));
+        }
+        Ok(())
+    }
+
+    async fn create_parent_dirs(&mut self, path: &Path) -> io::Result<()> {
+        match path.parent() {
+            Some(parent) => self.create_dir_all(parent).await,
+            None => Ok(()),
+        }
+    }
+
+    async fn undo(self) {
+        for undo in self.journal.into_iter().rev() {
+            let _ = match undo {
+                Undo::Rename { from, to } => tokio::fs::rename(from, to).await,
+                Undo::RemoveFile(path) => tokio::fs::remove_file(path).await,
+                Undo::RemoveDirectory(path) => tokio::fs::remove_dir(path).await,
+            };
+        }
+    }
+
+    async fn empty_trash(self) {
+        for path in self.trash {
+            let _ = match tokio::fs::symlink_metadata(&path).await {
+                Ok(metadata) if metadata.is_dir() => tokio::fs::remove_dir_all(path).await,
+                _ => tokio::fs::remove_file(path).await,
+            };
+        }
+    }
+}
+
+/// Ends the commit in progress when dropped, when it fails or is cancelled as well.
+struct CommitEnd<'a> {
+    simulation: &'a std::sync::Mutex<Overlay>,
+    /// The entries of the overlay applied to the tree, once they all are.
+    committed: Option<HashMap<PathBuf, OverlayEntry>>,
+}
+
+impl Drop for CommitEnd<'_> {
+    fn drop(&mut self) {
+        self.simulation
+            .lock()
+            .unwrap()
+            .end_commit(self.committed.take());
+    }
+}
+
+impl FileSystemService {
+    /// Enables the simulation mode: mutating operations are validated and return their results,
+    /// but their changes are kept in memory instead of being written to disk (default: false).
+    pub fn with_simulation(mut self, enabled: bool) -> Self {
+        self.simulation = enabled.then(Default::default);
+        self
+    }
+
+    /// Keeps the changes in an overlay whose files are stored in `directory`, until they are
+    /// committed to the tree or discarded with `commit_overlay`. The changes left in `directory`
+    /// by a previous run are reloaded.
+    pub fn with_overlay_directory(mut self, directory: PathBuf) -> ServiceResult<Self> {
+        let overlay = Overlay::load(directory.clone()).map_err(|err| {
+            ServiceError::FromString(format!(
+                "The changes of the overlay directory '{}' could not be loaded: {err}",
+                directory.display()
+            ))
+        })?;
+        self.simulation = Some(std::sync::Mutex::new(overlay));
+        Ok(self)
+    }
+
+    /// Whether the changes are kept in an overlay instead of being written to the tree, by the
+    /// simulation mode or an overlay directory.
+    pub fn is_simulation(&self) -> bool {
+        self.simulation.is_some()
+    }
+
+    pub fn overlay_directory(&self) -> Option<PathBuf> {
+        let simulation = self.simulation.as_ref()?;
+        simulation.lock().unwrap().directory.clone()
+    }
+
+    fn resolve_simulated(&self, path: &Path) -> Option<Resolved> {
+        let simulation = self.simulation.as_ref()?;
+        let resolved = simulation.lock().unwrap().resolve(path);
+        match resolved {
+            Resolved::Disk(disk_path) if disk_path == path => None,
+            resolved => Some(resolved),
+        }
+    }
+
+    /// The content of `path` when the overlay changed it, `None` if it is read from disk as
+    /// usual.
+    pub(crate) fn simulated_content(&self, path: &Path) -> Option<io::Result<Vec<u8>>> {
+        Some(match self.resolve_simulated(path)? {
+            Resolved::File(content) => Ok(content),
+            Resolved::Directory => Err(io::Error::new(
+                io::ErrorKind::IsADirectory,
+                format!("'{}' is a directory", path.display()),
+            )),
+            Resolved::Stored(disk_path) | Resolved::Disk(disk_path) => std::fs::read(disk_path),
+            Resolved::Missing => Err(removed_error(path)),
+        })
+    }
+
+    /// Reads the content of a validated path, through the overlay of the simulation.
+    pub(crate) async fn read_file_bytes(&self, path: &Path) -> io::Result<Vec<u8>> {
+        match self.simulated_content(path) {
+            Some(content) => content,
+            None => self.retry_io(|| tokio::fs::read(path)).await,
+        }
+    }
+
+    /// Reads the content of a validated path as UTF-8 text, through the overlay of the simulation.
+    pub(crate) async fn read_file_string(&self, path: &Path) -> io::Result<String> {
+        String::from_utf8(self.read_file_bytes(path).await?).map_err(|_| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            )
+        })
+    }
+
+    /// Reads the content of a validated path through the overlay, from a blocking context.
+    pub(crate) fn read_tree_file(&self, path: &Path) -> io::Result<Vec<u8>> {
+        self.simulated_content(path)
+            .unwrap_or_else(|| std::fs::read(path))
+    }
+
+    /// Whether `path` exists, through the overlay of the simulation.
+    pub(crate) fn path_exists(&self, path: &Path) -> bool {
+        match self.resolve_simulated(path) {
+            None => path.exists(),
+            Some(Resolved::File(_) | Resolved::Stored(_) | Resolved::Directory) => true,
+            Some(Resolved::Disk(disk_path)) => disk_path.exists(),
+            Some(Resolved::Missing) => false,
+        }
+    }
+
+    /// Whether the overlay changes `path`, one of its ancestors or a path under it, which is read
+    /// from disk as usual otherwise.
+    pub(crate) fn overlay_changes_tree(&self, path: &Path) -> bool {
+        let Some(simulation) = &self.simulation else {
+            return false;
+        };
+        simulation
+            .lock()
+            .unwrap()
+            .entries
+            .keys()
+            .any(|entry_path| entry_path.starts_with(path) || path.starts_with(entry_path))
+    }
+
+    /// The file or directory at `path` through the overlay, following symbolic links.
+    pub(crate) fn tree_entry(&self, path: &Path) -> io::Result<TreeEntry> {
+        let source = match self.resolve_simulated(path) {
+            None => path.to_path_buf(),
+            Some(Resolved::Disk(disk_path)) => disk_path,
+            Some(Resolved::File(content)) => {
+                return Ok(TreeEntry {
+                    path: path.to_path_buf(),
+                    is_dir: false,
+                    is_file: true,
+                    source: None,
+                    size: content.len() as u64,
+                });
+            }
+            Some(Resolved::Stored(stored_path)) => {
+                return Ok(TreeEntry {
+                    path: path.to_path_buf(),
+                    is_dir: false,
+                    is_file: true,
+                    source: Some(stored_path),
+                    size: 0,
+                });
+            }
+            Some(Resolved::Directory) => {
+                return Ok(TreeEntry {
+                    path: path.to_path_buf(),
+                    is_dir: true,
+                    is_file: false,
+                    source: path.is_dir().then(|| path.to_path_buf()),
+                    size: 0,
+                });
+            }
+            Some(Resolved::Missing) => return Err(removed_error(path)),
+        };
+        let metadata = std::fs::metadata(&source)?;
+        Ok(TreeEntry::on_disk(path.to_path_buf(), source, &metadata))
+    }
+
+    /// The entries of the directory `dir` through the overlay, in no particular order. Broken
+    /// symbolic links on disk are listed as neither files nor directories.
+    pub(crate) fn read_tree_dir(&self, dir: &Path) -> io::Result<Vec<TreeEntry>> {
+        if !self.overlay_changes_tree(dir) {
+            return Ok(std::fs::read_dir(dir)?
+                .filter_map(|entry| entry.ok())
+                .filter_map(|entry| {
+                    let path = entry.path();
+                    match std::fs::metadata(&path) {
+                        Ok(metadata) => Some(TreeEntry::on_disk(path.clone(), path, &metadata)),
+                        Err(_) => entry.metadata().ok().map(|_| TreeEntry {
+                            source: Some(path.clone()),
+                            path,
+                            is_dir: false,
+                            is_file: false,
+                            size: 0,
+                        }),
+                    }
+                })
+                .collect());
+        }
+
+        // the entries of the directory on disk, and the paths the overlay adds to it
+        let disk_entries = match self.resolve_simulated(dir) {
+            None => Some(std::fs::read_dir(dir)?),
+            Some(Resolved::Disk(disk_dir)) => Some(std::fs::read_dir(disk_dir)?),
+            // a created directory, its other entries are those on disk if it existed already
+            Some(Resolved::Directory) => std::fs::read_dir(dir).ok(),
+            Some(Resolved::Missing) => return Err(removed_error(dir)),
+            Some(Resolved::File(_) | Resolved::Stored(_)) => {
+                return Err(io::Error::new(
+                    io::ErrorKind::NotADirectory,
+                    format!("'{}' is not a directory", dir.display()),
+                ));
+            }
+        };
+        let mut paths: BTreeSet<PathBuf> = disk_entries
+            .into_iter()
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .map(|entry| dir.join(entry.file_name()))
+            .collect();
+        if let Some(simulation) = &self.simulation {
+            let overlay = simulation.lock().unwrap();
+            paths.extend(overlay.entries.keys().filter_map(|entry_path| {
+                let name = entry_path.strip_prefix(dir).ok()?.components().next()?;
+                Some(dir.join(name))
+            }));
+        }
+        Ok(paths
+            .iter()
+            .filter_map(|path| self.tree_entry(path).ok())
+            .collect())
+    }
+
+    /// Walks the tree under `root` (included) through the overlay, following symbolic links.
+    /// Like `WalkDir::filter_entry`, the entries `filter` rejects are skipped, directories along
+    /// with their content.
+    pub(crate) fn walk_tree<'a>(
+        &'a self,
+        root: &Path,
+        mut filter: impl FnMut(&TreeEntry) -> bool + Send + 'a,
+    ) -> Box<dyn Iterator<Item = TreeEntry> + Send + 'a> {
+        if !self.overlay_changes_tree(root) {
+            return Box::new(
+                WalkDir::new(root)
+                    .follow_links(true)
+                    .into_iter()
+                    .filter_entry(move |entry| filter(&TreeEntry::walked(entry)))
+                    .filter_map(|entry| entry.ok())
+                    .map(|entry| TreeEntry::walked(&entry)),
+            );
+        }
+        let mut entries = vec![];
+        if let Ok(root_entry) = self.tree_entry(root) {
+            self.walk_overlay(root_entry, &mut filter, &mut vec![], &mut entries);
+        }
+        Box::new(entries.into_iter())
+    }
+
+    /// Adds `entry` and the entries under it to `entries`, `ancestors` holds the canonical paths
+    /// of the directories being visited to skip symbolic link cycles.
+    fn walk_overlay(
+        &self,
+        entry: TreeEntry,
+        filter: &mut impl FnMut(&TreeEntry) -> bool,
+        ancestors: &mut Vec<PathBuf>,
+        entries: &mut Vec<TreeEntry>,
+    ) {
+        if !filter(&entry) {
+            return;
+        }
+        if !entry.is_dir() {
+            entries.push(entry);
+            return;
+        }
+        let canonical_dir = entry
+            .source
+            .as_ref()
+            .and_then(|source| source.canonicalize().ok());
+        if canonical_dir
+            .as_ref()
+            .is_some_and(|canonical_dir| ancestors.contains(canonical_dir))
+        {
+            return;
+        }
+        let mut children = self.read_tree_dir(entry.path()).unwrap_or_default();
+        children.sort_by(|a, b| a.path.cmp(&b.path));
+        entries.push(entry);
+        ancestors.extend(canonical_dir.clone());
+        for child in children {
+            self.walk_overlay(child, filter, ancestors, entries);
+        }
+        if canonical_dir.is_some() {
+            ancestors.pop();
+        }
+    }
+
+    /// Records `content` as the new content of `path` when simulating, returns false otherwise so
+    /// the caller writes it.
+    pub(crate) fn simulate_write(&self, path: &Path, content: &[u8]) -> io::Result<bool> {
+        let Some(simulation) = &self.simulation else {
+            return Ok(false);
+        };
+        let mut overlay = simulation.lock().unwrap();
+        let entry = overlay.file_entry(path, content)?;
+        overlay.insert(path.to_path_buf(), entry);
+        overlay.save()?;
+        Ok(true)
+    }
+
+    /// Records the creation of the directory `path` and of its missing parents when simulating,
+    /// returns false otherwise.
+    pub(crate) fn simulate_create_dir(&self, path: &Path) -> bool {
+        if self.simulation.is_none() {
+            return false;
+        }
+        let missing: Vec<PathBuf> = path
+            .ancestors()
+            .take_while(|ancestor| !ancestor.as_os_str().is_empty() && !self.path_exists(ancestor))
+            .map(Path::to_path_buf)
+            .collect();
+        if let Some(simulation) = &self.simulation {
+            let mut overlay = simulation.lock().unwrap();
+            for directory in missing {
+                overlay.insert(directory, OverlayEntry::Directory);
+            }
+            overlay.save_quietly();
+        }
+        true
+    }
+
+    /// Records the removal of `path` when simulating, returns false otherwise.
+    pub(crate) fn simulate_remove(&self, path: &Path) -> bool {
+        let Some(simulation) = &self.simulation else {
+            return false;
+        };
+        let mut overlay = simulation.lock().unwrap();
+        overlay.remove_tree(path);
+        overlay
+            .entries
+            .insert(path.to_path_buf(), OverlayEntry::Removed);
+        overlay.save_quietly();
+        true
+    }
+
+    /// Records the move of `src` to `dest` when simulating, returns `None` otherwise. Fails as a
+    /// rename would when `src` doesn't exist.
+    pub(crate) fn simulate_rename(&self, src: &Path, dest: &Path) -> Option<io::Result<()>> {
+        let simulation = self.simulation.as_ref()?;
+        let moved = match self.resolve_simulated(src) {
+            None => src
+                .exists()
+                .then(|| OverlayEntry::MovedFrom(src.to_path_buf())),
+            Some(Resolved::File(content)) => Some(OverlayEntry::File(content)),
+            Some(Resolved::Stored(stored_path)) => Some(OverlayEntry::Stored(stored_path)),
+            Some(Resolved::Directory) => Some(OverlayEntry::Directory),
+            Some(Resolved::Disk(disk_path)) => disk_path
+                .exists()
+                .then_some(OverlayEntry::MovedFrom(disk_path)),
+            Some(Resolved::Missing) => None,
+        };
+        let Some(moved) = moved else {
+            return Some(Err(io::Error::new(
+                io::ErrorKind::NotFound,
+                format!("'{}' does not exist", src.display()),
+            )));
+        };
+        let mut overlay = simulation.lock().unwrap();
+        overlay.remove_tree(dest);
+        overlay.move_children(src, dest);
+        // the stored content of `src`, if any, is now the one of `dest`
+        overlay.entries.insert(dest.to_path_buf(), moved);
+        overlay
+            .entries
+            .insert(src.to_path_buf(), OverlayEntry::Removed);
+        Some(overlay.save())
+    }
+
+    /// Creates the missing parent directories of `path`, in the overlay when simulating.
+    pub(crate) async fn create_parent_directories(&self, path: &Path) -> io::Result<()> {
+        match path.parent() {
+            Some(parent) if self.simulate_create_dir(parent) => Ok(()),
+            _ => create_parent_dirs(path).await,
+        }
+    }
+
+    /// Applies the changes of the overlay directory to the tree and removes them from the
+    /// overlay, or only returns them when `dry_run` is true.
+    ///
+    /// The changes are all applied or none is: the files and directories they replace or remove
+    /// are kept aside until the last one succeeds, and restored if one fails. Each file is
+    /// written to a temporary file renamed over the original, which is backed up first when
+    /// backups are enabled. The changes made to the overlay while the commit is in progress are
+    /// kept in the overlay.
+    pub async fn commit_overlay(&self, dry_run: bool) -> ServiceResult<Vec<OverlayChange>> {
+        let Some(simulation) = &self.simulation else {
+            return Err(no_overlay_error());
+        };
+        let (changes, entries) = {
+            let mut overlay = simulation.lock().unwrap();
+            if overlay.directory.is_none() && !dry_run {
+                return Err(ServiceError::FromString(
+                    "The changes of the simulation (--simulate) can only be discarded.".to_string(),
+                ));
+            }
+            let changes = overlay.changes();
+            if dry_run {
+                return Ok(changes);
+            }
+            if overlay.committing {
+                return Err(ServiceError::FromString(
+                    "The changes of the overlay are already being committed.".to_string(),
+                ));
+            }
+            overlay.committing = true;
+            (changes, overlay.entries.clone())
+        };
+        let mut commit_end = CommitEnd {
+            simulation,
+            committed: None,
+        };
+
+        for change in &changes {
+            self.ensure_modifiable(change.path()).await?;
+        }
+
+        let mut commit = Commit::default();
+        if let Err(err) = self.apply_changes(&mut commit, &changes, &entries).await {
+            commit.undo().await;
+            return Err(ServiceError::FromString(format!(
+                "The changes of the overlay could not be committed, the tree was left unchanged: {err}"
+            )));
+        }
+        commit.empty_trash().await;
+        commit_end.committed = Some(entries);
+        drop(commit_end);
+        for change in &changes {
+            if let OverlayChange::Write { path } = change {
+                self.record_own_write(path);
+            }
+        }
+        Ok(changes)
+    }
+
+    async fn apply_changes(
+        &self,
+        commit: &mut Commit,
+        changes: &[OverlayChange],
+        entries: &HashMap<PathBuf, OverlayEntry>,
+    ) -> ServiceResult<()> {
+        // the origins of the moves are moved aside first, the deepest first so a move out of a
+        // moved directory finds its origin
+        let mut origins: Vec<&Path> = changes
+            .iter()
+            .filter_map(|change| match change {
+                OverlayChange::Move { from, .. } => Some(from.as_path()),
+                _ => None,
+            })
+            .collect();
+        origins.sort_by_key(|origin| std::cmp::Reverse(origin.components().count()));
+        for origin in origins {
+            let staged_path = commit.aside(origin);
+            commit.rename(origin, &staged_path).await?;
+            commit.staged.insert(origin.to_path_buf(), staged_path);
+        }
+
+        for change in changes {
+            match change {
+                OverlayChange::Move { from, path } => {
+                    commit.create_parent_dirs(path).await?;
+                    commit.discard(path).await?;
+                    let staged_path = commit.staged[from].clone();
+                    commit.rename(&staged_path, path).await?;
+                }
+                OverlayChange::CreateDirectory { path } => commit.create_dir_all(path).await?,
+                OverlayChange::Write { path } => {
+                    commit.create_parent_dirs(path).await?;
+                    let temp_path = commit.aside(path);
+                    // removed on failure, even if it is only partly written
+                    commit.journal.push(Undo::RemoveFile(temp_path.clone()));
+                    match &entries[path] {
+                        OverlayEntry::Stored(stored_path) => {
+                            tokio::fs::copy(stored_path, &temp_path).await.map(|_| ())
+                        }
+                        OverlayEntry::File(content) => tokio::fs::write(&temp_path, content).await,
+                        _ => Ok(()),
+                    }?;
+                    self.backup_disk_file(path).await?;
+                    commit.discard(path).await?;
+                    commit.rename(&temp_path, path).await?;
+                }
+                OverlayChange::Remove { path } => commit.discard(path).await?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Discards the changes of the overlay, returning them.
+    pub fn discard_overlay(&self) -> ServiceResult<Vec<OverlayChange>> {
+        let Some(simulation) = &self.simulation else {
+            return Err(no_overlay_error());
+        };
+        let mut overlay = simulation.lock().unwrap();
+        let changes = overlay.changes();
+        overlay.clear();
+        overlay.save()?;
+        Ok(changes)
+    }
+
+    /// The files added, modified or deleted by the changes of the overlay, by path, with the
+    /// unified diff of their content. A moved file is deleted from its origin and added to its
+    /// destination.
+    pub async fn diff_overlay(&self) -> ServiceResult<Vec<WorkspaceFileChange>> {
+        let Some(simulation) = &self.simulation else {
+            return Err(no_overlay_error());
+        };
+        let changes = simulation.lock().unwrap().changes();
+
+        // the files whose content in the tree and in the overlay may differ
+        let mut paths = BTreeSet::new();
+        for change in &changes {
+            match change {
+                OverlayChange::Move { from, path } => {
+                    for file in disk_files(from) {
+                        let relative_path = file.strip_prefix(from).unwrap_or(&file);
+                        paths.insert(path.join(relative_path));
+                        paths.insert(file);
+                    }
+                }
+                OverlayChange::Write { path } => {
+                    paths.insert(path.clone());
+                }
+                OverlayChange::Remove { path } => paths.extend(disk_files(path)),
+                OverlayChange::CreateDirectory { .. } => {}
+            }
+        }
+
+        let file_changes = self
+            .batch_executor
+            .run(|| {
+                paths
+                    .into_iter()
+                    .filter_map(|path| {
+                        // unchanged unless the overlay changed it
+                        let after = self.simulated_content(&path)?.ok();
+                        let before = path.is_file().then(|| std::fs::read(&path).ok()).flatten();
+                        let status = match (&before, &after) {
+                            (None, Some(_)) => FileChangeStatus::Added,
+                            (Some(_), None) => FileChangeStatus::Deleted,
+                            (Some(before), Some(after)) if before != after => {
+                                FileChangeStatus::Modified
+                            }
+                            _ => return None,
+                        };
+                        let text = |content: Option<Vec<u8>>| {
+                            String::from_utf8(content.unwrap_or_default())
+                                .ok()
+                                .filter(|text| !text.contains('\0'))
+                        };
+                        let diff = text(before).zip(text(after)).map(|(before, after)| {
+                            format_diff_block(&self.create_unified_diff(
+                                &before,
+                                &after,
+                                Some(path.display().to_string()),
+                            ))
+                        });
+                        Some(WorkspaceFileChange { path, status, diff })
+                    })
+                    .collect()
+            })
+            .await;
+        Ok(file_changes)
+    }
+}
@@ -0,0 +1,139 @@
+//! Resolution of symbolic links for the `strict` validation profile.
+//!
+//! A path within an allowed directory may still point outside of it through a symbolic link, the
+//! strict profile resolves the links of the path and checks the resolved path as well. The
+//! canonical paths of directories are cached for a short time, so validating many paths in the
+//! same directories doesn't walk their ancestors again.
+//!
+//! `..` components are applied once the links before them are resolved, as the operating system
+//! does: `link/..` is the parent of the link target, not the directory containing the link.
+
+use std::{
+    collections::HashMap,
+    fs, io,
+    path::{Component, Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+/// How long the canonical path of a directory is reused before being resolved again.
+const SYMLINK_CACHE_TTL: Duration = Duration::from_secs(2);
+/// Number of cached directories from which expired entries are purged.
+const SYMLINK_CACHE_PURGE_THRESHOLD: usize = 1024;
+/// Maximum number of symbolic links followed while resolving a path, as most operating systems do.
+const MAX_SYMLINK_DEPTH: usize = 40;
+
+/// Canonical paths of directories, with the time they were resolved.
+#[derive(Default)]
+pub(crate) struct SymlinkCache {
+    directories: Mutex<HashMap<PathBuf, (PathBuf, Instant)>>,
+}
+
+impl SymlinkCache {
+    /// Resolves the symbolic links of the absolute `path`. Components that don't exist yet are
+    /// kept as is, links that don't resolve to an existing target are followed as well.
+    pub(crate) fn resolve(&self, path: &Path) -> io::Result<PathBuf> {
+        self.resolve_with_depth(path, 0)
+    }
+
+    /// Applies the `..` components of the absolute `path` the way the operating system does, the
+    /// links before the last `..` being resolved. The components after it are kept as is, so a
+    /// link at the end of the path still designates the link itself.
+    pub(crate) fn resolve_parent_components(&self, path: &Path) -> io::Result<PathBuf> {
+        let components: Vec<Component> = path.components().collect();
+        let Some(last) = components
+            .iter()
+            .rposition(|component| *component == Component::ParentDir)
+        else {
+            return Ok(path.to_path_buf());
+        };
+        let mut resolved = self.resolve(&components[..last].iter().collect::<PathBuf>())?;
+        resolved.pop();
+        Ok(components[last + 1..]
+            .iter()
+            .fold(resolved, |resolved, component| resolved.join(component)))
+    }
+
+    fn resolve_with_depth(&self, path: &Path, depth: usize) -> io::Result<PathBuf> {
+        if depth > MAX_SYMLINK_DEPTH {
+            return Err(io::Error::other(format!(
+                "Too many levels of symbolic links: {}",
+                path.display()
+            )));
+        }
+
+        // `..` applies to the resolved path preceding it
+        let components: Vec<Component> = path.components().collect();
+        if let Some(index) = components
+            .iter()
+            .position(|component| *component == Component::ParentDir)
+        {
+            let mut resolved =
+                self.resolve_with_depth(&components[..index].iter().collect::<PathBuf>(), depth)?;
+            resolved.pop();
+            let resolved = components[index + 1..]
+                .iter()
+                .fold(resolved, |resolved, component| resolved.join(component));
+            return self.resolve_with_depth(&resolved, depth);
+        }
+
+        // find the nearest existing ancestor, the missing components can't be links
+        let mut existing = path;
+        let mut missing: Vec<&std::ffi::OsStr> = vec![];
+        let metadata = loop {
+            match fs::symlink_metadata(existing) {
+                Ok(metadata) => break metadata,
+                Err(_) => match (existing.parent(), existing.file_name()) {
+                    (Some(parent), Some(name)) => {
+                        missing.push(name);
+                        existing = parent;
+                    }
+                    _ => return Ok(path.to_path_buf()),
+                },
+            }
+        };
+
+        let resolved = if metadata.file_type().is_symlink() {
+            let target = fs::read_link(existing)?;
+            let target = match existing.parent() {
+                Some(parent) => parent.join(target),
+                None => target,
+            };
+            self.resolve_with_depth(&target, depth + 1)?
+        } else if metadata.is_dir() {
+            self.canonical_directory(existing)?
+        } else {
+            match (existing.parent(), existing.file_name()) {
+                (Some(parent), Some(name)) => self.canonical_directory(parent)?.join(name),
+                _ => fs::canonicalize(existing)?,
+            }
+        };
+
+        Ok(missing
+            .iter()
+            .rev()
+            .fold(resolved, |resolved, name| resolved.join(name)))
+    }
+
+    fn canonical_directory(&self, dir: &Path) -> io::Result<PathBuf> {
+        let now = Instant::now();
+        let mut directories = self.directories.lock().unwrap();
+        if let Some((canonical, resolved_at)) = directories.get(dir)
+            && now.duration_since(*resolved_at) < SYMLINK_CACHE_TTL
+        {
+            return Ok(canonical.clone());
+        }
+        if directories.len() >= SYMLINK_CACHE_PURGE_THRESHOLD {
+            directories
+                .retain(|_, (_, resolved_at)| now.duration_since(*resolved_at) < SYMLINK_CACHE_TTL);
+        }
+        drop(directories);
+
+        let canonical = fs::canonicalize(dir)?;
+        self.directories
+            .lock()
+            .unwrap()
+            .insert(dir.to_path_buf(), (canonical.clone(), now));
+        Ok(canonical)
+    }
+}
@@ -1,14 +1,17 @@
 use crate::error::{ServiceError, ServiceResult};
-use async_zip::{Compression, ZipEntryBuilder, error::ZipError, tokio::write::ZipFileWriter};
+use crate::fs_service::platform::PortableMetadata;
+use crate::metrics::record_bytes;
+use async_zip::{
+    Compression, ZipDateTime, ZipDateTimeBuilder, ZipEntry, ZipEntryBuilder, error::ZipError,
+    tokio::write::ZipFileWriter,
+};
 use base64::{engine::general_purpose, write::EncoderWriter};
-use chrono::{DateTime, Local};
+use chrono::{DateTime, Datelike, Local, Utc};
 use dirs::home_dir;
+use glob_match::glob_match;
 use rust_mcp_sdk::macros::JsonSchema;
-use std::io::Write;
-#[cfg(unix)]
-use std::os::unix::fs::PermissionsExt;
-#[cfg(windows)]
-use std::os::windows::fs::MetadataExt;
+use sha2::{Digest, Sha256};
+use std::io::{Read, Write};
 use std::{
     ffi::OsStr,
     fs::{self},
@@ -20,13 +23,14 @@ use tokio::{
     fs::{File, metadata},
     io::BufReader,
 };
+use url::Url;
 
 #[cfg(windows)]
 pub const OS_LINE_ENDING: &str = "\r\n";
 #[cfg(not(windows))]
 pub const OS_LINE_ENDING: &str = "\n";
 
-#[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, JsonSchema)]
+#[derive(::serde::Deserialize, ::serde::Serialize, Clone, Copy, Debug, JsonSchema)]
 pub enum OutputFormat {
     #[serde(rename = "text")]
     Text,
@@ -34,6 +38,196 @@ pub enum OutputFormat {
     Json,
 }
 
+/// Determines how `write_file` behaves when the target file already exists.
+#[derive(
+    ::serde::Deserialize,
+    ::serde::Serialize,
+    Clone,
+    Copy,
+    Debug,
+    Default,
+    PartialEq,
+    Eq,
+    JsonSchema,
+    clap::ValueEnum,
+)]
+pub enum WriteIfExists {
+    /// Refuse to write and return an error.
+    #[serde(rename = "fail")]
+    Fail,
+    /// Replace the existing content.
+    #[default]
+    #[serde(rename = "overwrite")]
+    Overwrite,
+    /// Append the content to the end of the existing file.
+    #[serde(rename = "append")]
+    Append,
+}
+
+/// How strictly paths are checked against the allowed directories.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ValidationProfile {
+    /// Resolve symbolic links and reject paths pointing outside of the allowed directories.
+    #[default]
+    Strict,
+    /// Only check the requested paths, without resolving symbolic links (trusted environments).
+    Relaxed,
+}
+
+/// Line endings of the content written by `write_file` and `edit_file`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum LineEndingPolicy {
+    /// Keep the line endings of the written content, or of the edited file.
+    #[default]
+    Preserve,
+    /// Convert to LF.
+    Lf,
+    /// Convert to CRLF.
+    Crlf,
+    /// Convert to the line endings of the platform the server runs on.
+    Os,
+}
+
+impl LineEndingPolicy {
+    /// The enforced line ending, `None` to preserve the existing ones.
+    pub fn line_ending(self) -> Option<&'static str> {
+        match self {
+            LineEndingPolicy::Preserve => None,
+            LineEndingPolicy::Lf => Some("\n"),
+            LineEndingPolicy::Crlf => Some("\r\n"),
+            LineEndingPolicy::Os if cfg!(windows) => Some("\r\n"),
+            LineEndingPolicy::Os => Some("\n"),
+        }
+    }
+}
+
+/// Timestamp of a file used to determine its age.
+#[derive(
+    ::serde::Deserialize, ::serde::Serialize, Clone, Copy, Debug, Default, PartialEq, Eq, JsonSchema,
+)]
+pub enum FileTimestamp {
+    /// Last modification time.
+    #[default]
+    #[serde(rename = "modified")]
+    Modified,
+    /// Last access time, not updated by filesystems mounted with `noatime`.
+    #[serde(rename = "accessed")]
+    Accessed,
+    /// Creation time, not available on all platforms and filesystems.
+    #[serde(rename = "created")]
+    Created,
+}
+
+impl FileTimestamp {
+    /// Reads the timestamp from the `metadata` of a file.
+    pub fn of(self, metadata: &fs::Metadata) -> std::io::Result<SystemTime> {
+        match self {
+            FileTimestamp::Modified => metadata.modified(),
+            FileTimestamp::Accessed => metadata.accessed(),
+            FileTimestamp::Created => metadata.created(),
+        }
+    }
+}
+
+/// Length of the periods files are grouped by in a timeline.
+#[derive(
+    ::serde::Deserialize, ::serde::Serialize, Clone, Copy, Debug, Default, PartialEq, Eq, JsonSchema,
+)]
+pub enum TimelineInterval {
+    #[serde(rename = "day")]
+    Day,
+    /// ISO 8601 weeks, starting on Monday.
+    #[serde(rename = "week")]
+    Week,
+    #[default]
+    #[serde(rename = "month")]
+    Month,
+}
+
+#[derive(
+    ::serde::Deserialize, ::serde::Serialize, Clone, Copy, Debug, PartialEq, Eq, JsonSchema,
+)]
+pub enum MimeCategory {
+    #[serde(rename = "image")]
+    Image,
+    #[serde(rename = "audio")]
+    Audio,
+    #[serde(rename = "video")]
+    Video,
+    #[serde(rename = "text")]
+    Text,
+    #[serde(rename = "archive")]
+    Archive,
+    #[serde(rename = "binary")]
+    Binary,
+}
+
+impl From<infer::MatcherType> for MimeCategory {
+    fn from(matcher_type: infer::MatcherType) -> Self {
+        match matcher_type {
+            infer::MatcherType::Image => MimeCategory::Image,
+            infer::MatcherType::Audio => MimeCategory::Audio,
+            infer::MatcherType::Video => MimeCategory::Video,
+            infer::MatcherType::Archive => MimeCategory::Archive,
+            infer::MatcherType::Text => MimeCategory::Text,
+            _ => MimeCategory::Binary,
+        }
+    }
+}
+
+impl std::fmt::Display for MimeCategory {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            MimeCategory::Image => "image",
+            MimeCategory::Audio => "audio",
+            MimeCategory::Video => "video",
+            MimeCategory::Text => "text",
+            MimeCategory::Archive => "archive",
+            MimeCategory::Binary => "binary",
+        };
+        write!(f, "{name}")
+    }
+}
+
+/// SHA-256 digest and size of a file's content.
+#[derive(Debug, Clone, PartialEq, Eq, ::serde::Serialize)]
+pub struct FileDigest {
+    /// Lowercase hex encoded SHA-256 of the content.
+    pub sha256: String,
+    /// Number of bytes hashed.
+    pub bytes: u64,
+}
+
+impl FileDigest {
+    /// Digest of `content`, held in memory.
+    pub fn of(content: &[u8]) -> Self {
+        Self {
+            sha256: format!("{:x}", Sha256::digest(content)),
+            bytes: content.len() as u64,
+        }
+    }
+}
+
+/// Computes the SHA-256 digest of a file by streaming its content.
+pub async fn sha256_file(path: &Path) -> std::io::Result<FileDigest> {
+    let mut reader = BufReader::new(File::open(path).await?);
+    let mut hasher = Sha256::new();
+    let mut buffer = vec![0u8; 8192];
+    let mut bytes = 0u64;
+    loop {
+        let bytes_read = reader.read(&mut buffer).await?;
+        if bytes_read == 0 {
+            break;
+        }
+        hasher.update(&buffer[..bytes_read]);
+        bytes += bytes_read as u64;
+    }
+    Ok(FileDigest {
+        sha256: format!("{:x}", hasher.finalize()),
+        bytes,
+    })
+}
+
 pub fn format_system_time(system_time: SystemTime) -> String {
     // Convert SystemTime to DateTime<Local>
     let datetime: DateTime<Local> = system_time.into();
@@ -41,39 +235,209 @@ pub fn format_system_time(system_time: SystemTime) -> String {
 }
 
 pub fn format_permissions(metadata: &fs::Metadata) -> String {
-    #[cfg(unix)]
-    {
-        let permissions = metadata.permissions();
-        let mode = permissions.mode();
-        format!("0{:o}", mode & 0o777) // Octal representation
+    if cfg!(unix) {
+        return format!("0{:o}", metadata.permission_bits() & 0o777); // Octal representation
     }
 
-    #[cfg(windows)]
-    {
-        let attributes = metadata.file_attributes();
-        let read_only = (attributes & 0x1) != 0; // FILE_ATTRIBUTE_READONLY
-        let directory = metadata.is_dir();
+    // Unix mode bits are synthesized elsewhere, report the type and read-only flag instead
+    let mut result = String::new();
 
-        let mut result = String::new();
+    if metadata.is_dir() {
+        result.push('d');
+    } else {
+        result.push('-');
+    }
 
-        if directory {
-            result.push('d');
-        } else {
-            result.push('-');
+    if metadata.permissions().readonly() {
+        result.push('r');
+    } else {
+        result.push('w');
+    }
+
+    result
+}
+
+/// Lists the Windows attributes set on a file, e.g. `hidden, system`.
+#[cfg(windows)]
+pub fn format_file_attributes(metadata: &fs::Metadata) -> String {
+    use windows_sys::Win32::Storage::FileSystem::{
+        FILE_ATTRIBUTE_ARCHIVE, FILE_ATTRIBUTE_COMPRESSED, FILE_ATTRIBUTE_ENCRYPTED,
+        FILE_ATTRIBUTE_HIDDEN, FILE_ATTRIBUTE_OFFLINE, FILE_ATTRIBUTE_READONLY,
+        FILE_ATTRIBUTE_REPARSE_POINT, FILE_ATTRIBUTE_SYSTEM, FILE_ATTRIBUTE_TEMPORARY,
+    };
+
+    let attributes = metadata.windows_attributes().unwrap_or_default();
+    let names: Vec<&str> = [
+        (FILE_ATTRIBUTE_READONLY, "readonly"),
+        (FILE_ATTRIBUTE_HIDDEN, "hidden"),
+        (FILE_ATTRIBUTE_SYSTEM, "system"),
+        (FILE_ATTRIBUTE_ARCHIVE, "archive"),
+        (FILE_ATTRIBUTE_TEMPORARY, "temporary"),
+        (FILE_ATTRIBUTE_REPARSE_POINT, "reparse_point"),
+        (FILE_ATTRIBUTE_COMPRESSED, "compressed"),
+        (FILE_ATTRIBUTE_OFFLINE, "offline"),
+        (FILE_ATTRIBUTE_ENCRYPTED, "encrypted"),
+    ]
+    .into_iter()
+    .filter(|(flag, _)| attributes & flag != 0)
+    .map(|(_, name)| name)
+    .collect();
+
+    if names.is_empty() {
+        "normal".to_string()
+    } else {
+        names.join(", ")
+    }
+}
+
+/// Summarizes the rights the file's ACL grants to the current user, e.g. `read, write`.
+/// Returns `None` on platforms without ACLs or if the security information can't be read.
+#[cfg(windows)]
+pub fn effective_access_summary(path: &Path) -> Option<String> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::{
+        Foundation::{ERROR_SUCCESS, LocalFree},
+        Security::Authorization::{
+            BuildTrusteeWithNameW, GetEffectiveRightsFromAclW, GetNamedSecurityInfoW,
+            SE_FILE_OBJECT, TRUSTEE_W,
+        },
+        Security::{ACL, DACL_SECURITY_INFORMATION, PSECURITY_DESCRIPTOR},
+        Storage::FileSystem::{
+            FILE_ALL_ACCESS, FILE_GENERIC_EXECUTE, FILE_GENERIC_READ, FILE_GENERIC_WRITE,
+        },
+    };
+
+    let wide_path: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let current_user: Vec<u16> = "CURRENT_USER".encode_utf16().chain(Some(0)).collect();
+
+    let mut dacl: *mut ACL = std::ptr::null_mut();
+    let mut security_descriptor: PSECURITY_DESCRIPTOR = std::ptr::null_mut();
+
+    // SAFETY: all pointers passed to the Win32 calls are valid for the duration of the calls,
+    // the strings are null-terminated and the security descriptor is released with LocalFree.
+    let access_mask = unsafe {
+        let status = GetNamedSecurityInfoW(
+            wide_path.as_ptr(),
+            SE_FILE_OBJECT,
+            DACL_SECURITY_INFORMATION,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+            &mut dacl,
+            std::ptr::null_mut(),
+            &mut security_descriptor,
+        );
+        if status != ERROR_SUCCESS {
+            return None;
         }
 
-        if read_only {
-            result.push('r');
+        // a null DACL grants full access to everyone
+        let access_mask = if dacl.is_null() {
+            Some(FILE_ALL_ACCESS)
         } else {
-            result.push('w');
-        }
+            let mut trustee: TRUSTEE_W = std::mem::zeroed();
+            BuildTrusteeWithNameW(&mut trustee, current_user.as_ptr() as _);
+            let mut access_mask: u32 = 0;
+            (GetEffectiveRightsFromAclW(dacl, &trustee, &mut access_mask) == ERROR_SUCCESS)
+                .then_some(access_mask)
+        };
+        LocalFree(security_descriptor as _);
+        access_mask?
+    };
+
+    if access_mask & FILE_ALL_ACCESS == FILE_ALL_ACCESS {
+        return Some("full control".to_string());
+    }
 
-        result
+    let rights: Vec<&str> = [
+        (FILE_GENERIC_READ, "read"),
+        (FILE_GENERIC_WRITE, "write"),
+        (FILE_GENERIC_EXECUTE, "execute"),
+    ]
+    .into_iter()
+    .filter(|(right, _)| access_mask & right == *right)
+    .map(|(_, name)| name)
+    .collect();
+
+    if rights.is_empty() {
+        Some("none".to_string())
+    } else {
+        Some(rights.join(", "))
+    }
+}
+
+#[cfg(not(windows))]
+pub fn effective_access_summary(_path: &Path) -> Option<String> {
+    None
+}
+
+/// Returns `true` for entries that are normally hidden from listings.
+/// On Windows this checks the hidden and system attributes of `metadata`, elsewhere dot-files are considered hidden.
+pub fn is_hidden(path: &Path, metadata: Option<&fs::Metadata>) -> bool {
+    #[cfg(windows)]
+    {
+        use windows_sys::Win32::Storage::FileSystem::{
+            FILE_ATTRIBUTE_HIDDEN, FILE_ATTRIBUTE_SYSTEM,
+        };
+        let _ = path;
+        metadata.is_some_and(|metadata| {
+            metadata.windows_attributes().unwrap_or_default()
+                & (FILE_ATTRIBUTE_HIDDEN | FILE_ATTRIBUTE_SYSTEM)
+                != 0
+        })
+    }
+
+    #[cfg(not(windows))]
+    {
+        let _ = metadata;
+        path.file_name()
+            .and_then(|name| name.to_str())
+            .is_some_and(|name| name.starts_with('.'))
     }
 }
 
 pub fn normalize_path(path: &Path) -> PathBuf {
-    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+    path.canonicalize().unwrap_or_else(|_| clean_path(path))
+}
+
+/// Resolves `.` and `..` components of an absolute path without accessing the filesystem,
+/// for paths that don't exist and can't be canonicalized.
+pub fn clean_path(path: &Path) -> PathBuf {
+    let mut cleaned = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                cleaned.pop();
+            }
+            component => cleaned.push(component),
+        }
+    }
+    cleaned
+}
+
+/// Computes the relative path leading from the directory `from` to `to`, both being absolute and normalized.
+/// Returns `None` when the paths have no common root, e.g. on different Windows drives.
+pub fn relative_path(from: &Path, to: &Path) -> Option<PathBuf> {
+    let mut from_components = from.components().peekable();
+    let mut to_components = to.components().peekable();
+
+    if from_components.peek() != to_components.peek() {
+        return None;
+    }
+    while from_components.peek().is_some() && from_components.peek() == to_components.peek() {
+        from_components.next();
+        to_components.next();
+    }
+
+    let relative: PathBuf = from_components
+        .map(|_| Component::ParentDir)
+        .chain(to_components)
+        .collect();
+    if relative.as_os_str().is_empty() {
+        Some(PathBuf::from("."))
+    } else {
+        Some(relative)
+    }
 }
 
 pub fn expand_home(path: PathBuf) -> PathBuf {
@@ -102,10 +466,10 @@ pub fn format_bytes(bytes: u64) -> String {
     format!("{bytes} bytes")
 }
 
-pub async fn write_zip_entry(
+pub async fn write_zip_entry<W: tokio::io::AsyncWrite + Unpin>(
     filename: &str,
     input_path: &Path,
-    zip_writer: &mut ZipFileWriter<File>,
+    zip_writer: &mut ZipFileWriter<W>,
 ) -> Result<(), ZipError> {
     let mut input_file = File::open(input_path).await?;
     let input_file_size = input_file.metadata().await?.len() as usize;
@@ -119,10 +483,109 @@ pub async fn write_zip_entry(
     Ok(())
 }
 
+/// Reads and deflates a file into a zip entry ready for `write_entry_whole_precompressed`.
+///
+/// Compression is CPU-bound and performed synchronously, so this is meant to run on a worker
+/// thread (e.g. the rayon pool) rather than on the async runtime.
+pub fn compress_zip_entry(
+    filename: &str,
+    input_path: &Path,
+    deterministic: bool,
+) -> ServiceResult<(ZipEntry, Vec<u8>)> {
+    let metadata = fs::metadata(input_path)?;
+    let data = fs::read(input_path)?;
+    let builder = ZipEntryBuilder::new(filename.into(), Compression::Deflate);
+    let entry = with_zip_entry_attributes(builder, &metadata, deterministic)
+        .crc32(async_zip::base::write::crc32(&data))
+        .uncompressed_size(data.len() as u64)
+        .build();
+    // the encoder writes to memory, so the future completes without waiting on I/O
+    let compressed = futures::executor::block_on(async_zip::base::write::compress(&entry, &data));
+    Ok((entry, compressed))
+}
+
+/// Builds the entry of a directory, which has no content and a name ending with `/`.
+pub fn zip_directory_entry(
+    dirname: &str,
+    input_path: &Path,
+    deterministic: bool,
+) -> ServiceResult<ZipEntry> {
+    let metadata = fs::metadata(input_path)?;
+    let builder = ZipEntryBuilder::new(format!("{dirname}/").into(), Compression::Stored);
+    Ok(with_zip_entry_attributes(builder, &metadata, deterministic).build())
+}
+
+/// Records the modification time and permissions of a file in its zip entry.
+///
+/// When `deterministic` is set, a fixed timestamp (1980-01-01 00:00:00, the earliest date a zip
+/// entry can hold) and fixed permissions are used instead, so the entry only depends on the file content.
+fn with_zip_entry_attributes(
+    builder: ZipEntryBuilder,
+    metadata: &fs::Metadata,
+    deterministic: bool,
+) -> ZipEntryBuilder {
+    let (file_type_bits, fixed_permissions) = if metadata.is_dir() {
+        (0o040000, 0o755)
+    } else {
+        (0o100000, 0o644)
+    };
+
+    let zip_epoch = ZipDateTimeBuilder::new().year(1980).month(1).day(1).build();
+    let (modified, permissions) = if deterministic {
+        (zip_epoch, fixed_permissions)
+    } else {
+        let modified = metadata
+            .modified()
+            .ok()
+            .map(DateTime::<Utc>::from)
+            // dates outside of the range supported by zip (1980-2107) fall back to the epoch
+            .filter(|modified| (1980..=2107).contains(&modified.year()))
+            .map(|modified| ZipDateTime::from_chrono(&modified))
+            .unwrap_or(zip_epoch);
+        (modified, metadata.permission_bits())
+    };
+
+    builder
+        .last_modification_date(modified)
+        .unix_permissions((file_type_bits | permissions) as u16)
+}
+
+/// Creates any missing parent directories of `path`.
+pub async fn create_parent_dirs(path: &Path) -> std::io::Result<()> {
+    match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => tokio::fs::create_dir_all(parent).await,
+        _ => Ok(()),
+    }
+}
+
+/// Wraps a unified diff in a fenced `diff` code block, using enough backticks
+/// so that backticks inside the diff cannot terminate the block early.
+pub fn format_diff_block(diff: &str) -> String {
+    let mut num_backticks = 3;
+    while diff.contains(&"`".repeat(num_backticks)) {
+        num_backticks += 1;
+    }
+    format!(
+        "{}diff\n{}{}\n\n",
+        "`".repeat(num_backticks),
+        diff,
+        "`".repeat(num_backticks)
+    )
+}
+
 pub fn normalize_line_endings(text: &str) -> String {
     text.replace("\r\n", "\n").replace('\r', "\n")
 }
 
+/// Anchor identifying the content of a line: its 1-based number and the first 8 hex digits of the
+/// SHA-256 of the line without its line ending, e.g. `12:9f86d081`.
+pub fn line_anchor(line_number: usize, line: &str) -> String {
+    let line = line.trim_end_matches(['\n', '\r']);
+    let hash = Sha256::digest(line.as_bytes());
+    let hex: String = hash[..4].iter().map(|byte| format!("{byte:02x}")).collect();
+    format!("{line_number}:{hex}")
+}
+
 // checks if path component is a  Prefix::VerbatimDisk
 fn is_verbatim_disk(component: &Component) -> bool {
     match component {
@@ -185,6 +648,7 @@ pub async fn read_file_as_base64(file_path: &Path) -> ServiceResult<String> {
             if n == 0 {
                 break;
             }
+            record_bytes(n as u64);
             // Write raw bytes to the Base64 encoder
             encoder.write_all(&buffer[..n])?;
         }
@@ -229,6 +693,76 @@ pub fn mime_from_path(path: &Path) -> ServiceResult<infer::Type> {
     Ok(kind)
 }
 
+/// Maps well-known file extensions to a `MimeCategory` without touching the file content.
+pub fn mime_category_from_extension(path: &Path) -> Option<MimeCategory> {
+    let extension = path.extension()?.to_str()?.to_lowercase();
+    let category = match extension.as_str() {
+        "png" | "jpg" | "jpeg" | "gif" | "bmp" | "webp" | "svg" | "ico" | "tif" | "tiff"
+        | "heic" | "avif" => MimeCategory::Image,
+        "mp3" | "wav" | "flac" | "ogg" | "oga" | "m4a" | "aac" | "opus" | "aiff" => {
+            MimeCategory::Audio
+        }
+        "mp4" | "mkv" | "mov" | "avi" | "webm" | "wmv" | "flv" | "m4v" | "mpeg" | "mpg" => {
+            MimeCategory::Video
+        }
+        "zip" | "tar" | "gz" | "tgz" | "bz2" | "xz" | "7z" | "rar" | "zst" => MimeCategory::Archive,
+        "txt" | "md" | "rs" | "py" | "js" | "ts" | "json" | "toml" | "yaml" | "yml" | "xml"
+        | "html" | "htm" | "css" | "csv" | "log" | "sh" | "c" | "h" | "cpp" | "java" | "go" => {
+            MimeCategory::Text
+        }
+        "exe" | "dll" | "so" | "dylib" | "bin" | "o" | "a" | "class" | "wasm" => {
+            MimeCategory::Binary
+        }
+        _ => return None,
+    };
+    Some(category)
+}
+
+/// Detects the `MimeCategory` of a file by sniffing its leading bytes.
+///
+/// Content that `infer` does not recognize is classified as `Text` when it is valid UTF-8
+/// without NUL bytes, and `Binary` otherwise.
+pub fn mime_category_from_content(path: &Path) -> std::io::Result<MimeCategory> {
+    let mut buffer = Vec::with_capacity(MIME_SNIFF_BYTES);
+    fs::File::open(path)?
+        .take(MIME_SNIFF_BYTES as u64)
+        .read_to_end(&mut buffer)?;
+    Ok(mime_category_from_bytes(&buffer))
+}
+
+/// Number of leading bytes of a file sniffed to detect its `MimeCategory`.
+const MIME_SNIFF_BYTES: usize = 8192;
+
+/// Detects the `MimeCategory` of a file from its content, see `mime_category_from_content`.
+pub fn mime_category_from_bytes(content: &[u8]) -> MimeCategory {
+    let buffer = &content[..content.len().min(MIME_SNIFF_BYTES)];
+    if let Some(kind) = infer::get(buffer) {
+        return kind.matcher_type().into();
+    }
+
+    let is_text = !buffer.contains(&0)
+        && match std::str::from_utf8(buffer) {
+            Ok(_) => true,
+            // the sample may end in the middle of a multi-byte character
+            Err(err) => err.error_len().is_none(),
+        };
+
+    if is_text {
+        MimeCategory::Text
+    } else {
+        MimeCategory::Binary
+    }
+}
+
+/// Checks whether a file belongs to the given `MimeCategory`.
+/// A matching extension is accepted right away, otherwise the file header is inspected.
+pub fn matches_mime_category(path: &Path, category: MimeCategory) -> bool {
+    if mime_category_from_extension(path) == Some(category) {
+        return true;
+    }
+    mime_category_from_content(path).is_ok_and(|detected| detected == category)
+}
+
 pub fn escape_regex(text: &str) -> String {
     // Covers special characters in regex engines (RE2, PCRE, JS, Python)
     const SPECIAL_CHARS: &[char] = &[
@@ -247,6 +781,20 @@ pub fn escape_regex(text: &str) -> String {
     escaped
 }
 
+/// Checks whether `relative_path` matches any of the `exclude_patterns`.
+/// Patterns without a `*` match any path containing them, a leading `/` anchors the pattern to the root.
+pub fn matches_exclude_patterns(relative_path: &Path, exclude_patterns: &[String]) -> bool {
+    exclude_patterns.iter().any(|pattern| {
+        let glob_pattern = if pattern.contains('*') {
+            pattern.strip_prefix("/").unwrap_or(pattern).to_owned()
+        } else {
+            format!("*{pattern}*")
+        };
+
+        glob_match(&glob_pattern, relative_path.to_str().unwrap_or(""))
+    })
+}
+
 pub fn filesize_in_range(file_size: u64, min_bytes: Option<u64>, max_bytes: Option<u64>) -> bool {
     if min_bytes.is_none() && max_bytes.is_none() {
         return true;
@@ -276,9 +824,28 @@ pub async fn validate_file_size<P: AsRef<Path>>(
     }
 }
 
-/// Converts a string to a `PathBuf`, supporting both raw paths and `file://` URIs.
+/// Converts a string to a `PathBuf`, supporting both raw paths and `file:` URIs (RFC 8089).
+///
+/// URIs are parsed by the `url` crate: they may have an empty or `localhost` host
+/// (`file:///home/user`, `file://localhost/home/user`, `file:/home/user`) and percent-encoded
+/// characters (`%20`). On Windows, drive letters (`file:///C:/Users`) and other hosts, as UNC paths
+/// (`file://server/share`), are supported as well.
 pub fn parse_file_path(input: &str) -> ServiceResult<PathBuf> {
-    Ok(PathBuf::from(
-        input.strip_prefix("file://").unwrap_or(input).trim(),
-    ))
+    let input = input.trim();
+    // only `file:` URIs are parsed, a Windows path such as `C:\dir` is not a URI of scheme `c`
+    if !input
+        .get(..5)
+        .is_some_and(|scheme| scheme.eq_ignore_ascii_case("file:"))
+    {
+        return Ok(PathBuf::from(input));
+    }
+
+    let invalid_file_uri = || ServiceError::FromString(format!("Invalid file URI '{input}'."));
+    let url = Url::parse(input).map_err(|_| invalid_file_uri())?;
+    url.to_file_path().map_err(|_| match url.host_str() {
+        Some(host) if !host.eq_ignore_ascii_case("localhost") => ServiceError::FromString(format!(
+            "Unsupported file URI '{input}': files on remote hosts ('{host}') are not supported."
+        )),
+        _ => invalid_file_uri(),
+    })
 }
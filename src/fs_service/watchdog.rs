@@ -0,0 +1,57 @@
+//! Detection of allowed directories disappearing and reappearing at runtime.
+//!
+//! Volumes can be unmounted and folders deleted while a long-lived server is running. Allowed
+//! directories that no longer exist are excluded from `allowed_directories` until they are back,
+//! so they don't take part in path validation, resolution of relative paths or traversals.
+//! Excluded directories are allowed again as soon as they exist, the next check reports them.
+
+use crate::fs_service::FileSystemService;
+use std::path::PathBuf;
+
+/// Change of the availability of an allowed directory since the previous check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DirectoryAvailabilityChange {
+    /// The directory no longer exists and is excluded from the allowed directories.
+    Lost(PathBuf),
+    /// The directory exists again and is allowed again.
+    Restored(PathBuf),
+}
+
+impl std::fmt::Display for DirectoryAvailabilityChange {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            DirectoryAvailabilityChange::Lost(path) => write!(
+                f,
+                "Warning: allowed directory '{}' is no longer available, it is excluded until it returns.",
+                path.display()
+            ),
+            DirectoryAvailabilityChange::Restored(path) => write!(
+                f,
+                "Allowed directory '{}' is available again.",
+                path.display()
+            ),
+        }
+    }
+}
+
+impl FileSystemService {
+    /// Checks whether the configured allowed directories still exist, updating the set of excluded
+    /// directories. Returns the changes since the previous check.
+    pub async fn check_allowed_directories(&self) -> Vec<DirectoryAvailabilityChange> {
+        let configured_directories = self.configured_directories().await;
+        let mut unavailable = self.unavailable_directories.write().unwrap();
+
+        let mut changes = vec![];
+        for dir in configured_directories.iter() {
+            let available = dir.is_dir();
+            if !available && unavailable.insert(dir.clone()) {
+                changes.push(DirectoryAvailabilityChange::Lost(dir.clone()));
+            } else if available && unavailable.remove(dir) {
+                changes.push(DirectoryAvailabilityChange::Restored(dir.clone()));
+            }
+        }
+        // directories removed from the configuration are no longer tracked
+        unavailable.retain(|dir| configured_directories.contains(dir));
+        changes
+    }
+}
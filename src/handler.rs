@@ -1,99 +1,456 @@
 use crate::cli::CommandArguments;
-use crate::error::ServiceError;
+use crate::debug_info::{error_counters_path, record_tool_error};
+use crate::i18n::Message;
+use crate::idempotency::IdempotencyCache;
 use crate::invoke_tools;
-use crate::{error::ServiceResult, fs_service::FileSystemService, tools::*};
+use crate::metrics::measure;
+use crate::progress::ProgressReporter;
+use crate::prompts;
+use crate::recording::SessionRecorder;
+use crate::response_cache::{DEFAULT_RESPONSE_CACHE_CAPACITY, ResponseCache};
+use crate::{
+    error::{ServiceResult, ToolError},
+    fs_service::{
+        AllowedDirectoriesDelta, BackupConfig, BatchLimits, FileSystemService, RetryPolicy,
+    },
+    tools::*,
+};
 use async_trait::async_trait;
 use rust_mcp_sdk::McpServer;
 use rust_mcp_sdk::mcp_server::ServerHandler;
 use rust_mcp_sdk::schema::{
-    CallToolRequestParams, InitializeRequestParams, NotificationParams, PaginatedRequestParams,
+    CallToolRequestParams, GetPromptRequestParams, GetPromptResult, InitializeRequestParams,
+    ListPromptsResult, LoggingLevel, LoggingMessageNotificationParams, NotificationParams,
+    PaginatedRequestParams, SetLevelRequestParams,
 };
 use rust_mcp_sdk::schema::{
     CallToolResult, InitializeResult, ListToolsResult, RpcError, schema_utils::CallToolError,
 };
+use serde_json::json;
 use std::cmp::Ordering;
 use std::collections::HashSet;
+use std::path::PathBuf;
 use std::sync::Arc;
+use std::sync::atomic::{self, AtomicBool};
+use std::time::Duration;
+
+/// Features of the server enabled by its command line options.
+#[derive(::serde::Serialize, Debug)]
+pub struct StartupCapabilities {
+    pub write: bool,
+    pub mcp_roots: bool,
+    pub simulation: bool,
+    pub overlay: bool,
+    pub backups: bool,
+    pub response_cache: bool,
+    pub ascii_output: bool,
+}
+
+/// Effective configuration of the server, sent to the client as an `info` log message of the
+/// `startup` logger when the session is initialized, once the roots of the client are applied.
+#[derive(::serde::Serialize, Debug)]
+pub struct StartupBanner {
+    /// One-line summary of the configuration.
+    pub message: String,
+    /// `readonly`, `read/write`, `simulated read/write` or `overlay read/write`.
+    pub mode: &'static str,
+    /// Where the allowed directories come from: `command_line`, `mcp_roots` once the roots of the
+    /// client replaced them, or `none` while waiting for the client to provide roots.
+    pub roots_source: &'static str,
+    pub allowed_directories: Vec<PathBuf>,
+    /// Allowed directories which don't exist yet.
+    pub unavailable_directories: Vec<PathBuf>,
+    pub disabled_tools: Vec<String>,
+    pub capabilities: StartupCapabilities,
+    /// The file the session is recorded to, if any.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub recording: Option<PathBuf>,
+}
 
 pub struct FileSystemHandler {
     readonly: bool,
     mcp_roots_support: bool,
+    /// Whether the allowed directories were replaced by the roots of the client.
+    roots_applied: AtomicBool,
     fs_service: Arc<FileSystemService>,
     disabled_tools: HashSet<String>,
+    idempotency_cache: IdempotencyCache,
+    /// Recent results of the read-only tools, when started with `--response-cache-ttl`.
+    response_cache: ResponseCache,
+    /// Whether the non-ASCII characters of the results are escaped (`--ascii-output`).
+    ascii_output: bool,
+    watchdog_interval: Duration,
+    /// Minimum level of the log messages sent to the client, set by `logging/setLevel`.
+    log_level: std::sync::RwLock<Option<LoggingLevel>>,
+    /// Recorder of the session, when started with `--record`.
+    recorder: Option<SessionRecorder>,
+    /// File counting the tool errors, when started with `--count-errors`.
+    error_counters_path: Option<PathBuf>,
 }
 
 impl FileSystemHandler {
     pub fn new(args: CommandArguments) -> ServiceResult<Self> {
-        let fs_service = FileSystemService::try_new(&args.allowed_directories)?;
+        let fs_service = if args.lazy_validation {
+            FileSystemService::new_lazy(&args.allowed_directories)
+        } else {
+            FileSystemService::try_new(&args.allowed_directories)?
+        };
+        let mut fs_service = fs_service
+            .with_write_if_exists(args.write_if_exists)
+            .with_write_line_endings(args.write_line_endings)
+            .with_locale(args.locale)
+            .with_validation_profile(args.validation_profile)
+            .with_editorconfig(!args.no_editorconfig)
+            .with_simulation(args.simulate)
+            .with_directory_size_cache(args.cache_directory_sizes)
+            .with_retry_policy(RetryPolicy {
+                retries: args.io_retries,
+                initial_backoff: Duration::from_millis(args.io_retry_backoff),
+            })
+            .with_batch_limits(BatchLimits {
+                concurrency: args.batch_concurrency,
+                threads: match args.batch_threads {
+                    0 => BatchLimits::default().threads,
+                    threads => threads,
+                },
+            });
+        if !args.pinned_paths.is_empty() {
+            fs_service =
+                fs_service.with_pinned_paths(args.pinned_paths.iter().map(PathBuf::from).collect());
+        }
+        if let Some(overlay_dir) = &args.overlay_dir {
+            fs_service = fs_service.with_overlay_directory(PathBuf::from(overlay_dir))?;
+        }
+        if let Some(base_dir) = &args.base_dir {
+            fs_service = fs_service.with_base_dir(PathBuf::from(base_dir));
+        }
+        if args.enable_backups {
+            fs_service = fs_service.with_backups(BackupConfig {
+                dir: PathBuf::from(&args.backup_dir),
+                retention: args.backup_retention,
+            });
+        }
+        let recorder = args
+            .record_dir
+            .as_ref()
+            .map(|dir| SessionRecorder::create(&PathBuf::from(dir), args.record_content))
+            .transpose()?;
         Ok(Self {
             fs_service: Arc::new(fs_service),
-            readonly: !args.allow_write,
+            // nothing is written when simulating, changes are written to the overlay directory
+            readonly: !args.allow_write && !args.simulate && args.overlay_dir.is_none(),
             mcp_roots_support: args.enable_roots,
+            roots_applied: AtomicBool::new(false),
             disabled_tools: args
                 .disabled_tool_names
                 .unwrap_or_default()
                 .into_iter()
                 .collect(),
+            idempotency_cache: IdempotencyCache::default(),
+            response_cache: ResponseCache::new(
+                Duration::from_secs(args.response_cache_ttl),
+                DEFAULT_RESPONSE_CACHE_CAPACITY,
+            ),
+            ascii_output: args.ascii_output,
+            watchdog_interval: Duration::from_secs(args.watchdog_interval),
+            log_level: Default::default(),
+            recorder,
+            error_counters_path: args.count_errors.then(error_counters_path).flatten(),
         })
     }
 
+    /// The filesystem service shared by the tools.
+    pub fn fs_service(&self) -> &Arc<FileSystemService> {
+        &self.fs_service
+    }
+
     pub fn assert_write_access(&self) -> std::result::Result<(), CallToolError> {
         if self.readonly {
-            Err(CallToolError::new(ServiceError::NoWriteAccess))
+            Err(CallToolError::new(
+                Message::NoWriteAccess.error(self.fs_service.locale()),
+            ))
         } else {
             Ok(())
         }
     }
 
-    pub async fn startup_message(&self) -> String {
-        let common_message = format!(
-            "Secure MCP Filesystem Server running in \"{}\" mode {} \"MCP Roots\" support.",
-            if !self.readonly {
-                "read/write"
-            } else {
-                "readonly"
-            },
+    /// Calls a tool, as requested by a client or by the replay of a recorded session.
+    pub async fn call_tool(
+        &self,
+        params: CallToolRequestParams,
+        progress_reporter: Option<ProgressReporter>,
+    ) -> std::result::Result<CallToolResult, CallToolError> {
+        // check if tool is disabled
+        if self.disabled_tools.contains(&params.name) {
+            return Err(CallToolError::from_message(format!(
+                "Error: The tool '{}' is disabled. Check the 'disable-tools' list in your configuration and ensure it's enabled before trying again.",
+                &params.name
+            )));
+        }
+
+        let tool_name = params.name.clone();
+        let arguments = params.arguments.clone();
+        let tool_params: FileSystemTools =
+            FileSystemTools::try_from(params).map_err(CallToolError::new)?;
+
+        // Verify write access for tools that modify the file system
+        if tool_params.require_write_access() {
+            self.assert_write_access()?;
+        }
+
+        // Retried requests with a known idempotency key return the original result, repeated
+        // read-only calls a recent one
+        let cacheable = self.response_cache.is_enabled() && tool_params.is_cacheable();
+        let ((result, cached), metrics) = measure(async {
+            match tool_params.idempotency_key() {
+                Some(key) => {
+                    let key = format!("{tool_name}:{key}");
+                    let result = self
+                        .idempotency_cache
+                        .run(key, self.invoke_tool(tool_params, progress_reporter))
+                        .await;
+                    (result, false)
+                }
+                None if cacheable => {
+                    let key =
+                        ResponseCache::key(&tool_name, arguments.as_ref(), &self.fs_service).await;
+                    self.response_cache
+                        .run(key, self.invoke_tool(tool_params, progress_reporter))
+                        .await
+                }
+                None => (
+                    self.invoke_tool(tool_params, progress_reporter).await,
+                    false,
+                ),
+            }
+        })
+        .await;
+        // the results cached before a change made by a tool may be outdated
+        if !cacheable && self.response_cache.is_enabled() {
+            self.response_cache.clear();
+        }
+
+        if let (Err(err), Some(counters_path)) = (&result, &self.error_counters_path) {
+            let (counters_path, tool_name, error) =
+                (counters_path.clone(), tool_name.clone(), err.to_string());
+            tokio::task::spawn_blocking(move || {
+                record_tool_error(&counters_path, &tool_name, &error)
+            });
+        }
+        let mut result = result.map_err(|err| {
+            let mut error = ToolError::from_call_error(&err);
+            if self.ascii_output {
+                error.message = escape_non_ascii(&error.message);
+            }
+            CallToolError::new(error)
+        })?;
+        if self.ascii_output {
+            escape_result_text(&mut result);
+        }
+        metrics.attach_to(&mut result);
+        if cached {
+            result
+                .meta
+                .get_or_insert_with(Default::default)
+                .insert("cached".to_string(), json!(true));
+        }
+        Ok(result)
+    }
+
+    /// Builds the messages of a prompt, as requested by a client.
+    pub async fn get_prompt(
+        &self,
+        params: GetPromptRequestParams,
+    ) -> std::result::Result<GetPromptResult, RpcError> {
+        prompts::get_prompt(params, &self.fs_service).await
+    }
+
+    async fn invoke_tool(
+        &self,
+        tool_params: FileSystemTools,
+        progress_reporter: Option<ProgressReporter>,
+    ) -> std::result::Result<CallToolResult, CallToolError> {
+        // long-running tools that support progress notifications
+        let tool_params = match (tool_params, progress_reporter) {
+            (FileSystemTools::ZipDirectory(params), Some(progress_reporter)) => {
+                return ZipDirectory::run_tool_with_progress(
+                    params,
+                    &self.fs_service,
+                    Some(progress_reporter),
+                )
+                .await;
+            }
+            (tool_params, _) => tool_params,
+        };
+
+        invoke_tools!(
+            tool_params,
+            &self.fs_service,
+            ReadMediaFile,
+            ReadMultipleMediaFiles,
+            ReadTextFile,
+            ReadMultipleTextFiles,
+            WriteFile,
+            EditFile,
+            CreateDirectory,
+            ListDirectory,
+            DirectoryTree,
+            MoveFile,
+            SearchFiles,
+            GetFileInfo,
+            ListAllowedDirectories,
+            ZipFiles,
+            UnzipFile,
+            ZipDirectory,
+            SearchFilesContent,
+            ListDirectoryWithSizes,
+            HeadFile,
+            TailFile,
+            ReadFileLines,
+            FindEmptyDirectories,
+            CalculateDirectorySize,
+            FindDuplicateFiles,
+            FindFileTypeMismatches,
+            ListBackups,
+            RestoreBackup,
+            UpdateFinderTags,
+            ReadArchiveEntry,
+            CheckPathLengths,
+            ResolvePath,
+            SetDefaultRoot,
+            FindOldestFiles,
+            UsageByExtension,
+            FileTimeline,
+            FindRelevantFiles,
+            GetFileProfile,
+            CleanupWhitespace,
+            GetFileLockState,
+            CommitOverlay,
+            DiffWorkspace,
+            FindProjectRoot,
+            CountLinesOfCode,
+            FindDependencyManifests,
+            CompareSimilarity
+        )
+    }
+
+    /// The effective configuration of the server, reported when a client initializes the session.
+    pub async fn startup_banner(&self) -> StartupBanner {
+        let mode = if self.fs_service.overlay_directory().is_some() {
+            "overlay read/write"
+        } else if self.fs_service.is_simulation() {
+            "simulated read/write"
+        } else if !self.readonly {
+            "read/write"
+        } else {
+            "readonly"
+        };
+        let allowed_directories = self.fs_service.allowed_directories().await.to_vec();
+        let unavailable_directories = self.fs_service.unavailable_directories();
+        let mut disabled_tools: Vec<String> = self.disabled_tools.iter().cloned().collect();
+        disabled_tools.sort();
+        let recording = self
+            .recorder
+            .as_ref()
+            .map(|recorder| recorder.path().to_path_buf());
+
+        let plural = |count: usize, singular: &str, plural: &str| {
+            format!("{count} {}", if count == 1 { singular } else { plural })
+        };
+        let mut message = format!(
+            "Secure MCP Filesystem Server running in \"{mode}\" mode {} \"MCP Roots\" support, ",
             if self.mcp_roots_support {
                 "with"
             } else {
                 "without"
             },
         );
-
-        let disabled_tool_message = if !self.disabled_tools.is_empty() {
-            let count = self.disabled_tools.len();
-            let plural = if count > 1 { "s" } else { "" };
-            let verb = if count > 1 { "are" } else { "is" };
-            format!(
-                "{} tool{} {} disabled: {}",
-                count,
-                plural,
-                verb,
-                self.disabled_tools
-                    .iter()
-                    .cloned()
-                    .collect::<Vec<String>>()
-                    .join(", ")
-            )
+        if allowed_directories.is_empty() && self.mcp_roots_support {
+            message.push_str("waiting for the client to provide roots");
         } else {
-            "No tools are disabled 👍".to_string()
-        };
+            message.push_str(&plural(
+                allowed_directories.len(),
+                "allowed directory",
+                "allowed directories",
+            ));
+        }
+        if !unavailable_directories.is_empty() {
+            message.push_str(&format!(
+                ", {} not available yet",
+                unavailable_directories.len()
+            ));
+        }
+        if !disabled_tools.is_empty() {
+            message.push_str(&format!(
+                ", {} disabled",
+                plural(disabled_tools.len(), "tool", "tools")
+            ));
+        }
+        if let Some(recording) = &recording {
+            message.push_str(&format!(
+                ", recording the session to {}",
+                recording.display()
+            ));
+        }
+        message.push('.');
 
-        let allowed_directories = self.fs_service.allowed_directories().await;
-        let sub_message: String = if allowed_directories.is_empty() && self.mcp_roots_support {
-            "No allowed directories is set - waiting for client to provide roots via MCP protocol...".to_string()
-        } else {
-            format!(
-                "Allowed directories:\n{}",
-                allowed_directories
-                    .iter()
-                    .map(|p| p.display().to_string())
-                    .collect::<Vec<String>>()
-                    .join(",\n")
-            )
-        };
+        StartupBanner {
+            message,
+            mode,
+            roots_source: if self.roots_applied.load(atomic::Ordering::Relaxed) {
+                "mcp_roots"
+            } else if allowed_directories.is_empty() {
+                "none"
+            } else {
+                "command_line"
+            },
+            allowed_directories,
+            unavailable_directories,
+            disabled_tools,
+            capabilities: StartupCapabilities {
+                write: !self.readonly,
+                mcp_roots: self.mcp_roots_support,
+                simulation: self.fs_service.is_simulation(),
+                overlay: self.fs_service.overlay_directory().is_some(),
+                backups: self.fs_service.backup_config().is_some(),
+                response_cache: self.response_cache.is_enabled(),
+                ascii_output: self.ascii_output,
+            },
+            recording,
+        }
+    }
 
-        format!("{common_message}\n{disabled_tool_message}\n{sub_message}")
+    /// Whether log messages of the `info` level are sent to the client.
+    fn info_logging_enabled(&self) -> bool {
+        matches!(
+            *self.log_level.read().unwrap(),
+            None | Some(LoggingLevel::Debug | LoggingLevel::Info)
+        )
+    }
+
+    /// Periodically checks that the allowed directories still exist, reporting directories that
+    /// disappear or come back.
+    fn spawn_directory_watchdog(&self, runtime: Arc<dyn McpServer>) {
+        if self.watchdog_interval.is_zero() {
+            return;
+        }
+        let fs_service = self.fs_service.clone();
+        let mut interval = tokio::time::interval(self.watchdog_interval);
+        tokio::spawn(async move {
+            loop {
+                interval.tick().await;
+                for change in fs_service.check_allowed_directories().await {
+                    let _ = runtime.stderr_message(change.to_string()).await;
+                }
+            }
+        });
+    }
+
+    /// Replaces the allowed directories with the valid roots provided by the client.
+    pub async fn apply_client_roots(&self, valid_roots: Vec<PathBuf>) -> AllowedDirectoriesDelta {
+        let delta = self.fs_service.update_allowed_paths(valid_roots).await;
+        self.roots_applied.store(true, atomic::Ordering::Relaxed);
+        delta
     }
 
     pub(crate) async fn update_allowed_directories(&self, runtime: Arc<dyn McpServer>) {
@@ -153,11 +510,30 @@ impl FileSystemHandler {
                 let _ = runtime.stderr_message(message.to_string()).await;
             } else {
                 let num_valid_roots = valid_roots.len();
-                fs_service.update_allowed_paths(valid_roots).await;
+                let delta = self.apply_client_roots(valid_roots).await;
+                let allowed_directories = fs_service.configured_directories().await;
                 let message = format!(
-                    "Updated allowed directories from MCP roots: {num_valid_roots} valid directories",
+                    "Updated allowed directories from MCP roots: {num_valid_roots} valid directories\n{}",
+                    delta.summary(&allowed_directories)
                 );
-                let _ = runtime.stderr_message(message.to_string()).await;
+                let _ = runtime.stderr_message(message.clone()).await;
+
+                // let the client know why paths that used to work may now be rejected
+                if self.info_logging_enabled() {
+                    let _ = runtime
+                        .notify_log_message(LoggingMessageNotificationParams {
+                            data: json!({
+                                "message": message,
+                                "added": delta.added,
+                                "removed": delta.removed,
+                                "allowed_directories": *allowed_directories,
+                            }),
+                            level: LoggingLevel::Info,
+                            logger: Some("roots".to_string()),
+                            meta: None,
+                        })
+                        .await;
+                }
             }
         }
     }
@@ -165,8 +541,23 @@ impl FileSystemHandler {
 #[async_trait]
 impl ServerHandler for FileSystemHandler {
     async fn on_initialized(&self, runtime: Arc<dyn McpServer>) {
-        let _ = runtime.stderr_message(self.startup_message().await).await;
-        self.update_allowed_directories(runtime).await;
+        // the banner reports the directories actually allowed, those of the roots if any
+        self.update_allowed_directories(runtime.clone()).await;
+        let banner = self.startup_banner().await;
+        let _ = runtime.stderr_message(banner.message.clone()).await;
+        if self.info_logging_enabled()
+            && let Ok(data) = serde_json::to_value(&banner)
+        {
+            let _ = runtime
+                .notify_log_message(LoggingMessageNotificationParams {
+                    data,
+                    level: LoggingLevel::Info,
+                    logger: Some("startup".to_string()),
+                    meta: None,
+                })
+                .await;
+        }
+        self.spawn_directory_watchdog(runtime);
     }
 
     async fn handle_roots_list_changed_notification(
@@ -185,6 +576,15 @@ impl ServerHandler for FileSystemHandler {
         Ok(())
     }
 
+    async fn handle_set_level_request(
+        &self,
+        params: SetLevelRequestParams,
+        _: Arc<dyn McpServer>,
+    ) -> std::result::Result<rust_mcp_sdk::schema::Result, RpcError> {
+        *self.log_level.write().unwrap() = Some(params.level);
+        Ok(Default::default())
+    }
+
     async fn handle_list_tools_request(
         &self,
         _params: Option<PaginatedRequestParams>,
@@ -200,6 +600,26 @@ impl ServerHandler for FileSystemHandler {
         })
     }
 
+    async fn handle_list_prompts_request(
+        &self,
+        _params: Option<PaginatedRequestParams>,
+        _: Arc<dyn McpServer>,
+    ) -> std::result::Result<ListPromptsResult, RpcError> {
+        Ok(ListPromptsResult {
+            prompts: prompts::prompts(),
+            meta: None,
+            next_cursor: None,
+        })
+    }
+
+    async fn handle_get_prompt_request(
+        &self,
+        params: GetPromptRequestParams,
+        _: Arc<dyn McpServer>,
+    ) -> std::result::Result<GetPromptResult, RpcError> {
+        self.get_prompt(params).await
+    }
+
     async fn handle_initialize_request(
         &self,
         params: InitializeRequestParams,
@@ -213,7 +633,14 @@ impl ServerHandler for FileSystemHandler {
         let mut server_info = runtime.server_info().to_owned();
         // Provide compatibility for clients using older MCP protocol versions.
         if server_info.protocol_version.cmp(&params.protocol_version) == Ordering::Greater {
-            server_info.protocol_version = params.protocol_version;
+            server_info.protocol_version = params.protocol_version.clone();
+        }
+        if let Some(recorder) = &self.recorder {
+            recorder.record(
+                "initialize",
+                serde_json::to_value(&params).unwrap_or_default(),
+                Ok(serde_json::to_value(&server_info).unwrap_or_default()),
+            );
         }
         Ok(server_info)
     }
@@ -221,51 +648,24 @@ impl ServerHandler for FileSystemHandler {
     async fn handle_call_tool_request(
         &self,
         params: CallToolRequestParams,
-        _: Arc<dyn McpServer>,
+        runtime: Arc<dyn McpServer>,
     ) -> std::result::Result<CallToolResult, CallToolError> {
-        // check if tool is disabled
-        if self.disabled_tools.contains(&params.name) {
-            return Err(CallToolError::from_message(format!(
-                "Error: The tool '{}' is disabled. Check the 'disable-tools' list in your configuration and ensure it's enabled before trying again.",
-                &params.name
-            )));
-        }
-
-        let tool_params: FileSystemTools =
-            FileSystemTools::try_from(params).map_err(CallToolError::new)?;
-
-        // Verify write access for tools that modify the file system
-        if tool_params.require_write_access() {
-            self.assert_write_access()?;
-        }
+        let progress_reporter = params
+            .meta
+            .as_ref()
+            .and_then(|meta| meta.progress_token.clone())
+            .map(|progress_token| ProgressReporter::new(runtime, progress_token));
 
-        invoke_tools!(
-            tool_params,
-            &self.fs_service,
-            ReadMediaFile,
-            ReadMultipleMediaFiles,
-            ReadTextFile,
-            ReadMultipleTextFiles,
-            WriteFile,
-            EditFile,
-            CreateDirectory,
-            ListDirectory,
-            DirectoryTree,
-            MoveFile,
-            SearchFiles,
-            GetFileInfo,
-            ListAllowedDirectories,
-            ZipFiles,
-            UnzipFile,
-            ZipDirectory,
-            SearchFilesContent,
-            ListDirectoryWithSizes,
-            HeadFile,
-            TailFile,
-            ReadFileLines,
-            FindEmptyDirectories,
-            CalculateDirectorySize,
-            FindDuplicateFiles
-        )
+        let result = match &self.recorder {
+            Some(recorder) => {
+                let recorded_params = params.clone();
+                let result = self.call_tool(params, progress_reporter).await;
+                recorder.record_tool_call(&recorded_params, &result);
+                result
+            }
+            None => self.call_tool(params, progress_reporter).await,
+        };
+        // failed calls carry the stable code of their error
+        Ok(result.unwrap_or_else(|err| ToolError::from_call_error(&err).into_result()))
     }
 }
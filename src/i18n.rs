@@ -0,0 +1,414 @@
+//! Localized user-facing messages.
+//!
+//! Messages are typed so every locale has to provide all of them. Each message has a stable `code`
+//! which does not depend on the locale, so clients can identify a message without parsing its text.
+
+use crate::error::ToolError;
+use std::str::FromStr;
+
+/// Language of the messages returned to clients.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+    Es,
+    Fr,
+}
+
+impl FromStr for Locale {
+    type Err = String;
+
+    /// Parses a language tag, accepting POSIX locale names such as `de_DE.UTF-8` as well.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let language = value
+            .split(['_', '-', '.'])
+            .next()
+            .unwrap_or_default()
+            .to_ascii_lowercase();
+        match language.as_str() {
+            "en" | "c" | "posix" => Ok(Locale::En),
+            "de" => Ok(Locale::De),
+            "es" => Ok(Locale::Es),
+            "fr" => Ok(Locale::Fr),
+            _ => Err(format!(
+                "unsupported locale '{value}', supported locales are: en, de, es, fr"
+            )),
+        }
+    }
+}
+
+/// A user-facing message.
+pub enum Message<'a> {
+    AccessDenied {
+        is_symlink_target: bool,
+        path: &'a str,
+        allowed_directories: &'a str,
+    },
+    NoWriteAccess,
+    ReadOnlyFilesystem {
+        path: &'a str,
+    },
+    PinnedPath {
+        path: &'a str,
+        pinned_path: &'a str,
+    },
+    DirectoryCreated {
+        path: &'a str,
+    },
+    FileMoved {
+        source: &'a str,
+        destination: &'a str,
+    },
+    FileWritten {
+        path: &'a str,
+    },
+    BackupRestored {
+        path: &'a str,
+        backup_path: &'a str,
+    },
+    AllowedDirectories {
+        directories: &'a str,
+    },
+    NoAllowedDirectories,
+    NoMatchesFound,
+    FilesExtracted {
+        files: usize,
+        directories: usize,
+        size: &'a str,
+        target_dir: &'a str,
+    },
+    DirectoryCompressed {
+        input_dir: &'a str,
+        target: &'a str,
+        files: usize,
+        size: &'a str,
+    },
+    FilesCompressed {
+        files: usize,
+        target: &'a str,
+        size: &'a str,
+    },
+}
+
+/// `count` followed by the singular or plural noun.
+fn count(count: usize, singular: &str, plural: &str) -> String {
+    format!("{count} {}", if count == 1 { singular } else { plural })
+}
+
+/// ` <and> <count> <directories>`, empty without directories.
+fn and_count(and: &str, directories: usize, singular: &str, plural: &str) -> String {
+    match directories {
+        0 => String::new(),
+        directories => format!(" {and} {}", count(directories, singular, plural)),
+    }
+}
+
+impl Message<'_> {
+    /// Stable identifier of the message, identical in all locales.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Message::AccessDenied { .. } => "access_denied",
+            Message::NoWriteAccess => "no_write_access",
+            Message::ReadOnlyFilesystem { .. } => "read_only_filesystem",
+            Message::PinnedPath { .. } => "pinned_path",
+            Message::DirectoryCreated { .. } => "directory_created",
+            Message::FileMoved { .. } => "file_moved",
+            Message::FileWritten { .. } => "file_written",
+            Message::BackupRestored { .. } => "backup_restored",
+            Message::AllowedDirectories { .. } => "allowed_directories",
+            Message::NoAllowedDirectories => "no_allowed_directories",
+            Message::NoMatchesFound => "no_matches_found",
+            Message::FilesExtracted { .. } => "files_extracted",
+            Message::DirectoryCompressed { .. } => "directory_compressed",
+            Message::FilesCompressed { .. } => "files_compressed",
+        }
+    }
+
+    /// The message as a tool error, keeping its code.
+    pub fn error(&self, locale: Locale) -> ToolError {
+        ToolError {
+            code: self.code(),
+            message: self.localize(locale),
+        }
+    }
+
+    pub fn localize(&self, locale: Locale) -> String {
+        match locale {
+            Locale::En => self.en(),
+            Locale::De => self.de(),
+            Locale::Es => self.es(),
+            Locale::Fr => self.fr(),
+        }
+    }
+
+    fn en(&self) -> String {
+        match self {
+            Message::AccessDenied {
+                is_symlink_target,
+                path,
+                allowed_directories,
+            } => format!(
+                "Access denied - {} is outside allowed directories: {path} not in {allowed_directories}",
+                if *is_symlink_target {
+                    "a symlink target path"
+                } else {
+                    "path"
+                }
+            ),
+            Message::NoWriteAccess => "Service is running in read-only mode. To enable write access, please run with the --allow-write flag.".to_string(),
+            Message::ReadOnlyFilesystem { path } => format!(
+                "Cannot write to {path}: the filesystem is mounted read-only (e.g. a Docker volume mounted with ':ro'). Remount it read-write to allow changes."
+            ),
+            Message::PinnedPath { path, pinned_path } => format!(
+                "Cannot modify {path}: {pinned_path} is pinned and can't be modified through this server."
+            ),
+            Message::DirectoryCreated { path } => format!("Successfully created directory {path}"),
+            Message::FileMoved {
+                source,
+                destination,
+            } => format!("Successfully moved {source} to {destination}"),
+            Message::FileWritten { path } => format!("Successfully wrote to {path}"),
+            Message::BackupRestored { path, backup_path } => {
+                format!("Successfully restored {path} from {backup_path}")
+            }
+            Message::AllowedDirectories { directories } => {
+                format!("Allowed directories:\n{directories}")
+            }
+            Message::NoAllowedDirectories => "Allowed directories list is empty!".to_string(),
+            Message::NoMatchesFound => "No matches found".to_string(),
+            Message::FilesExtracted {
+                files,
+                directories,
+                size,
+                target_dir,
+            } => format!(
+                "Successfully extracted {}{} ({size}) into '{target_dir}'.",
+                count(*files, "file", "files"),
+                and_count("and", *directories, "directory", "directories")
+            ),
+            Message::DirectoryCompressed {
+                input_dir,
+                target,
+                files,
+                size,
+            } => format!(
+                "Successfully compressed '{input_dir}' directory into '{target}' ({}, {size}).",
+                count(*files, "file", "files")
+            ),
+            Message::FilesCompressed {
+                files,
+                target,
+                size,
+            } => format!(
+                "Successfully compressed {} into '{target}' ({size}).",
+                count(*files, "file", "files")
+            ),
+        }
+    }
+
+    fn de(&self) -> String {
+        match self {
+            Message::AccessDenied {
+                is_symlink_target,
+                path,
+                allowed_directories,
+            } => format!(
+                "Zugriff verweigert - {} liegt außerhalb der erlaubten Verzeichnisse: {path} nicht in {allowed_directories}",
+                if *is_symlink_target {
+                    "ein Symlink-Zielpfad"
+                } else {
+                    "der Pfad"
+                }
+            ),
+            Message::NoWriteAccess => "Der Dienst läuft im schreibgeschützten Modus. Um Schreibzugriff zu aktivieren, starten Sie ihn mit dem Flag --allow-write.".to_string(),
+            Message::ReadOnlyFilesystem { path } => format!(
+                "{path} kann nicht geschrieben werden: das Dateisystem ist schreibgeschützt eingehängt (z. B. ein mit ':ro' eingebundenes Docker-Volume). Hängen Sie es mit Schreibzugriff ein, um Änderungen zu ermöglichen."
+            ),
+            Message::PinnedPath { path, pinned_path } => format!(
+                "{path} kann nicht geändert werden: {pinned_path} ist fixiert und kann über diesen Server nicht geändert werden."
+            ),
+            Message::DirectoryCreated { path } => {
+                format!("Verzeichnis {path} wurde erfolgreich erstellt")
+            }
+            Message::FileMoved {
+                source,
+                destination,
+            } => format!("{source} wurde erfolgreich nach {destination} verschoben"),
+            Message::FileWritten { path } => format!("Erfolgreich in {path} geschrieben"),
+            Message::BackupRestored { path, backup_path } => {
+                format!("{path} wurde erfolgreich aus {backup_path} wiederhergestellt")
+            }
+            Message::AllowedDirectories { directories } => {
+                format!("Erlaubte Verzeichnisse:\n{directories}")
+            }
+            Message::NoAllowedDirectories => {
+                "Die Liste der erlaubten Verzeichnisse ist leer!".to_string()
+            }
+            Message::NoMatchesFound => "Keine Treffer gefunden".to_string(),
+            Message::FilesExtracted {
+                files,
+                directories,
+                size,
+                target_dir,
+            } => format!(
+                "{}{} ({size}) wurden erfolgreich nach '{target_dir}' entpackt.",
+                count(*files, "Datei", "Dateien"),
+                and_count("und", *directories, "Verzeichnis", "Verzeichnisse")
+            ),
+            Message::DirectoryCompressed {
+                input_dir,
+                target,
+                files,
+                size,
+            } => format!(
+                "Verzeichnis '{input_dir}' wurde erfolgreich in '{target}' komprimiert ({}, {size}).",
+                count(*files, "Datei", "Dateien")
+            ),
+            Message::FilesCompressed {
+                files,
+                target,
+                size,
+            } => format!(
+                "{} wurden erfolgreich in '{target}' komprimiert ({size}).",
+                count(*files, "Datei", "Dateien")
+            ),
+        }
+    }
+
+    fn es(&self) -> String {
+        match self {
+            Message::AccessDenied {
+                is_symlink_target,
+                path,
+                allowed_directories,
+            } => format!(
+                "Acceso denegado: {} está fuera de los directorios permitidos: {path} no está en {allowed_directories}",
+                if *is_symlink_target {
+                    "la ruta de destino de un enlace simbólico"
+                } else {
+                    "la ruta"
+                }
+            ),
+            Message::NoWriteAccess => "El servicio se está ejecutando en modo de solo lectura. Para habilitar el acceso de escritura, ejecútelo con la opción --allow-write.".to_string(),
+            Message::ReadOnlyFilesystem { path } => format!(
+                "No se puede escribir en {path}: el sistema de archivos está montado en modo de solo lectura (p. ej., un volumen de Docker montado con ':ro'). Vuelva a montarlo en modo de lectura y escritura para permitir cambios."
+            ),
+            Message::PinnedPath { path, pinned_path } => format!(
+                "No se puede modificar {path}: {pinned_path} está fijado y no se puede modificar a través de este servidor."
+            ),
+            Message::DirectoryCreated { path } => {
+                format!("Directorio {path} creado correctamente")
+            }
+            Message::FileMoved {
+                source,
+                destination,
+            } => format!("{source} se movió correctamente a {destination}"),
+            Message::FileWritten { path } => format!("Se escribió correctamente en {path}"),
+            Message::BackupRestored { path, backup_path } => {
+                format!("{path} se restauró correctamente desde {backup_path}")
+            }
+            Message::AllowedDirectories { directories } => {
+                format!("Directorios permitidos:\n{directories}")
+            }
+            Message::NoAllowedDirectories => {
+                "¡La lista de directorios permitidos está vacía!".to_string()
+            }
+            Message::NoMatchesFound => "No se encontraron coincidencias".to_string(),
+            Message::FilesExtracted {
+                files,
+                directories,
+                size,
+                target_dir,
+            } => format!(
+                "Se extrajeron correctamente {}{} ({size}) en '{target_dir}'.",
+                count(*files, "archivo", "archivos"),
+                and_count("y", *directories, "directorio", "directorios")
+            ),
+            Message::DirectoryCompressed {
+                input_dir,
+                target,
+                files,
+                size,
+            } => format!(
+                "El directorio '{input_dir}' se comprimió correctamente en '{target}' ({}, {size}).",
+                count(*files, "archivo", "archivos")
+            ),
+            Message::FilesCompressed {
+                files,
+                target,
+                size,
+            } => format!(
+                "Se comprimieron correctamente {} en '{target}' ({size}).",
+                count(*files, "archivo", "archivos")
+            ),
+        }
+    }
+
+    fn fr(&self) -> String {
+        match self {
+            Message::AccessDenied {
+                is_symlink_target,
+                path,
+                allowed_directories,
+            } => format!(
+                "Accès refusé - {} se trouve en dehors des répertoires autorisés : {path} n'est pas dans {allowed_directories}",
+                if *is_symlink_target {
+                    "le chemin cible d'un lien symbolique"
+                } else {
+                    "le chemin"
+                }
+            ),
+            Message::NoWriteAccess => "Le service fonctionne en mode lecture seule. Pour activer l'accès en écriture, lancez-le avec l'option --allow-write.".to_string(),
+            Message::ReadOnlyFilesystem { path } => format!(
+                "Impossible d'écrire dans {path} : le système de fichiers est monté en lecture seule (par ex. un volume Docker monté avec ':ro'). Remontez-le en lecture-écriture pour autoriser les modifications."
+            ),
+            Message::PinnedPath { path, pinned_path } => format!(
+                "Impossible de modifier {path} : {pinned_path} est épinglé et ne peut pas être modifié via ce serveur."
+            ),
+            Message::DirectoryCreated { path } => format!("Répertoire {path} créé avec succès"),
+            Message::FileMoved {
+                source,
+                destination,
+            } => format!("{source} a été déplacé avec succès vers {destination}"),
+            Message::FileWritten { path } => format!("Écriture réussie dans {path}"),
+            Message::BackupRestored { path, backup_path } => {
+                format!("{path} a été restauré avec succès depuis {backup_path}")
+            }
+            Message::AllowedDirectories { directories } => {
+                format!("Répertoires autorisés :\n{directories}")
+            }
+            Message::NoAllowedDirectories => {
+                "La liste des répertoires autorisés est vide !".to_string()
+            }
+            Message::NoMatchesFound => "Aucune correspondance trouvée".to_string(),
+            Message::FilesExtracted {
+                files,
+                directories,
+                size,
+                target_dir,
+            } => format!(
+                "Extraction réussie de {}{} ({size}) dans '{target_dir}'.",
+                count(*files, "fichier", "fichiers"),
+                and_count("et", *directories, "répertoire", "répertoires")
+            ),
+            Message::DirectoryCompressed {
+                input_dir,
+                target,
+                files,
+                size,
+            } => format!(
+                "Répertoire '{input_dir}' compressé avec succès dans '{target}' ({}, {size}).",
+                count(*files, "fichier", "fichiers")
+            ),
+            Message::FilesCompressed {
+                files,
+                target,
+                size,
+            } => format!(
+                "Compression réussie de {} dans '{target}' ({size}).",
+                count(*files, "fichier", "fichiers")
+            ),
+        }
+    }
+}
@@ -0,0 +1,83 @@
+use rust_mcp_sdk::schema::{CallToolResult, schema_utils::CallToolError};
+use std::{
+    collections::{HashMap, VecDeque},
+    sync::Arc,
+};
+use tokio::sync::Mutex;
+
+/// Default number of idempotency keys remembered by the server.
+pub const DEFAULT_IDEMPOTENCY_CAPACITY: usize = 256;
+
+type ResultSlot = Arc<Mutex<Option<CallToolResult>>>;
+
+#[derive(Default)]
+struct CacheEntries {
+    slots: HashMap<String, ResultSlot>,
+    // insertion order, used to evict the oldest keys first
+    order: VecDeque<String>,
+}
+
+/// Remembers the results of recent operations by their idempotency key, so a request retried by
+/// the client returns the original result instead of being executed again.
+///
+/// Only successful results are remembered, a failed operation is executed again when retried.
+/// Concurrent requests with the same key wait for the first one to complete.
+pub struct IdempotencyCache {
+    capacity: usize,
+    entries: Mutex<CacheEntries>,
+}
+
+impl IdempotencyCache {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            entries: Mutex::new(CacheEntries::default()),
+        }
+    }
+
+    async fn slot(&self, key: String) -> ResultSlot {
+        let mut entries = self.entries.lock().await;
+        if let Some(slot) = entries.slots.get(&key) {
+            return slot.clone();
+        }
+
+        while entries.order.len() >= self.capacity {
+            if let Some(oldest) = entries.order.pop_front() {
+                entries.slots.remove(&oldest);
+            }
+        }
+
+        let slot = ResultSlot::default();
+        entries.slots.insert(key.clone(), slot.clone());
+        entries.order.push_back(key);
+        slot
+    }
+
+    /// Runs `operation` unless a result for `key` is already known, in which case that result is returned.
+    pub async fn run<F>(
+        &self,
+        key: String,
+        operation: F,
+    ) -> std::result::Result<CallToolResult, CallToolError>
+    where
+        F: Future<Output = std::result::Result<CallToolResult, CallToolError>>,
+    {
+        let slot = self.slot(key).await;
+        let mut cached_result = slot.lock().await;
+        if let Some(result) = cached_result.as_ref() {
+            return Ok(result.clone());
+        }
+
+        let result = operation.await;
+        if let Ok(result) = result.as_ref() {
+            *cached_result = Some(result.clone());
+        }
+        result
+    }
+}
+
+impl Default for IdempotencyCache {
+    fn default() -> Self {
+        Self::new(DEFAULT_IDEMPOTENCY_CAPACITY)
+    }
+}
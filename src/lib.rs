@@ -1,7 +1,21 @@
 pub mod cli;
+pub mod debug_info;
 pub mod error;
 pub mod fs_service;
 pub mod handler;
+pub mod i18n;
+pub mod idempotency;
 pub mod macros;
+pub mod metrics;
+pub mod progress;
+pub mod prompts;
+pub mod recording;
+pub mod response_cache;
 pub mod server;
+pub mod shutdown;
+pub mod synthetic_tree;
+#[cfg(feature = "test-support")]
+pub mod test_support;
 pub mod tools;
+
+pub use error::{ErrorKind, ServiceError, ServiceResult};
@@ -1,15 +1,81 @@
 use clap::Parser;
-use rust_mcp_filesystem::{cli, server};
+use rust_mcp_filesystem::handler::FileSystemHandler;
+use rust_mcp_filesystem::recording::{read_recording, replay, replay_arguments};
+use rust_mcp_filesystem::synthetic_tree::{SyntheticTreeOptions, generate_tree};
+use rust_mcp_filesystem::{cli, debug_info, server};
 
 #[tokio::main]
 async fn main() {
     let mut arguments = cli::CommandArguments::parse();
 
-    if let Err(err) = arguments.validate() {
+    let validation = arguments.validate();
+
+    if let Some(cli::Command::DebugInfo { redact_paths }) = arguments.command {
+        let info = debug_info::debug_info(
+            &arguments,
+            validation.as_ref().err().map(String::as_str),
+            redact_paths,
+            debug_info::error_counters_path().as_deref(),
+        );
+        println!(
+            "{}",
+            serde_json::to_string_pretty(&info).unwrap_or_default()
+        );
+        return;
+    }
+
+    if let Some(cli::Command::GenerateTree {
+        target_dir,
+        files,
+        depth,
+        fan_out,
+        file_size,
+        duplicate_percent,
+        seed,
+    }) = &arguments.command
+    {
+        let options = SyntheticTreeOptions {
+            files: *files,
+            depth: *depth,
+            fan_out: *fan_out,
+            file_size: *file_size,
+            duplicate_percent: *duplicate_percent,
+            seed: *seed,
+        };
+        match generate_tree(target_dir, &options) {
+            Ok(summary) => println!(
+                "{}",
+                serde_json::to_string_pretty(&summary).unwrap_or_default()
+            ),
+            Err(err) => eprintln!("Error: {err}"),
+        }
+        return;
+    }
+
+    if let Err(err) = validation {
         eprintln!("Error: {err}");
         return;
     };
 
+    if let Some(cli::Command::Replay { recording, apply }) = arguments.command.clone() {
+        let recording = match read_recording(&recording) {
+            Ok(recording) => recording,
+            Err(err) => {
+                eprintln!("Error: {err}");
+                return;
+            }
+        };
+        match FileSystemHandler::new(replay_arguments(arguments, apply)) {
+            Ok(handler) => println!(
+                "{}",
+                serde_json::to_string_pretty(&replay(&handler, recording).await)
+                    .unwrap_or_default()
+            ),
+            Err(err) => eprintln!("Error: {err}"),
+        }
+        return;
+    }
+
     if let Err(error) = server::start_server(arguments).await {
         eprintln!("{error}");
     }
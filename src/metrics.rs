@@ -0,0 +1,50 @@
+use rust_mcp_sdk::schema::CallToolResult;
+use serde_json::json;
+use std::{cell::Cell, future::Future, time::Instant};
+
+tokio::task_local! {
+    static BYTES_PROCESSED: Cell<Option<u64>>;
+}
+
+/// Adds `bytes` to the number of bytes read or written by the tool call being measured.
+/// Does nothing outside of `measure`.
+pub fn record_bytes(bytes: u64) {
+    let _ = BYTES_PROCESSED.try_with(|processed| {
+        processed.set(Some(processed.get().unwrap_or_default() + bytes));
+    });
+}
+
+/// Execution metrics of a tool call, attached to its result as `_meta`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ToolMetrics {
+    pub duration_ms: u64,
+    /// Bytes of file content read or written, `None` for tools that don't process file content.
+    pub bytes_processed: Option<u64>,
+}
+
+impl ToolMetrics {
+    /// Adds `duration_ms` and `bytes_processed` to the `_meta` of `result`, keeping existing fields.
+    pub fn attach_to(&self, result: &mut CallToolResult) {
+        let meta = result.meta.get_or_insert_with(Default::default);
+        meta.insert("duration_ms".to_string(), json!(self.duration_ms));
+        if let Some(bytes_processed) = self.bytes_processed {
+            meta.insert("bytes_processed".to_string(), json!(bytes_processed));
+        }
+    }
+}
+
+/// Runs `future`, measuring its duration and the bytes recorded by `record_bytes` while it runs.
+pub async fn measure<F: Future>(future: F) -> (F::Output, ToolMetrics) {
+    let started = Instant::now();
+    let (output, bytes_processed) = BYTES_PROCESSED
+        .scope(Cell::new(None), async {
+            let output = future.await;
+            (output, BYTES_PROCESSED.with(|processed| processed.get()))
+        })
+        .await;
+    let metrics = ToolMetrics {
+        duration_ms: started.elapsed().as_millis() as u64,
+        bytes_processed,
+    };
+    (output, metrics)
+}
@@ -0,0 +1,43 @@
+use rust_mcp_sdk::McpServer;
+use rust_mcp_sdk::schema::{ProgressNotificationParams, ProgressToken};
+use std::sync::Arc;
+use tokio::sync::mpsc::{UnboundedSender, unbounded_channel};
+
+/// Sends `notifications/progress` to the client for a request that provided a progress token.
+///
+/// Reporting is synchronous so it can be called from within service operations, notifications
+/// are delivered in order by a background task. When updates are produced faster than they can
+/// be sent, intermediate updates are dropped in favor of the latest one.
+pub struct ProgressReporter {
+    sender: UnboundedSender<ProgressNotificationParams>,
+    progress_token: ProgressToken,
+}
+
+impl ProgressReporter {
+    pub fn new(runtime: Arc<dyn McpServer>, progress_token: ProgressToken) -> Self {
+        let (sender, mut receiver) = unbounded_channel::<ProgressNotificationParams>();
+        tokio::spawn(async move {
+            while let Some(mut params) = receiver.recv().await {
+                while let Ok(latest) = receiver.try_recv() {
+                    params = latest;
+                }
+                let _ = runtime.notify_progress(params).await;
+            }
+        });
+
+        Self {
+            sender,
+            progress_token,
+        }
+    }
+
+    pub fn report(&self, progress: f64, total: Option<f64>, message: Option<String>) {
+        let _ = self.sender.send(ProgressNotificationParams {
+            message,
+            meta: None,
+            progress,
+            progress_token: self.progress_token.clone(),
+            total,
+        });
+    }
+}
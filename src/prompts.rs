@@ -0,0 +1,144 @@
+//! Prompts offered to the clients, turning common sequences of tool calls into a single request.
+
+use crate::fs_service::{FileSystemService, RelevantFile};
+use crate::tools::SearchFiles;
+use rust_mcp_sdk::schema::{
+    ContentBlock, GetPromptRequestParams, GetPromptResult, Prompt, PromptArgument, PromptMessage,
+    Role, RpcError, TextContent,
+};
+use std::fmt::Write;
+use std::path::Path;
+
+pub const LOCATE_AND_LOAD_CONTEXT: &str = "locate_and_load_context";
+
+/// Number of files embedded by `locate_and_load_context` unless set by the `limit` argument.
+const DEFAULT_CONTEXT_FILES: usize = 5;
+/// Maximum number of bytes embedded from each file.
+const CONTEXT_FILE_BYTES: u64 = 16 * 1024;
+/// Maximum number of bytes embedded from all the files.
+const CONTEXT_BUDGET: u64 = 64 * 1024;
+
+/// The prompts listed to the clients.
+pub fn prompts() -> Vec<Prompt> {
+    vec![Prompt {
+        name: LOCATE_AND_LOAD_CONTEXT.to_string(),
+        title: Some("Locate and load context".to_string()),
+        description: Some(concat!(
+            "Finds the files most relevant to a topic, by searching it in both the file paths and the file contents, ",
+            "and embeds their content as resources, to start a task with its context in a single step."
+        ).to_string()),
+        arguments: vec![
+            PromptArgument {
+                name: "topic".to_string(),
+                title: None,
+                description: Some(
+                    "Terms to look for in the file paths and contents, separated by whitespace."
+                        .to_string(),
+                ),
+                required: Some(true),
+            },
+            PromptArgument {
+                name: "root_path".to_string(),
+                title: None,
+                description: Some(
+                    "The directory to search, the default root or the first allowed directory if omitted."
+                        .to_string(),
+                ),
+                required: Some(false),
+            },
+            PromptArgument {
+                name: "limit".to_string(),
+                title: None,
+                description: Some(format!(
+                    "Maximum number of files to embed (default: {DEFAULT_CONTEXT_FILES})."
+                )),
+                required: Some(false),
+            },
+        ],
+        icons: vec![],
+        meta: None,
+    }]
+}
+
+/// Builds the messages of the prompt `params.name` with its arguments.
+pub async fn get_prompt(
+    params: GetPromptRequestParams,
+    context: &FileSystemService,
+) -> std::result::Result<GetPromptResult, RpcError> {
+    if params.name != LOCATE_AND_LOAD_CONTEXT {
+        return Err(RpcError::invalid_params().with_message(format!(
+            "Unknown prompt '{}', available prompts: {LOCATE_AND_LOAD_CONTEXT}.",
+            params.name
+        )));
+    }
+    let arguments = params.arguments.unwrap_or_default();
+    let topic = arguments
+        .get("topic")
+        .map(|topic| topic.trim())
+        .filter(|topic| !topic.is_empty())
+        .ok_or(
+            RpcError::invalid_params()
+                .with_message("The 'topic' argument is required.".to_string()),
+        )?;
+    let root_path = arguments.get("root_path").map_or(".", |path| path.as_str());
+    let limit = match arguments.get("limit") {
+        Some(limit) => limit.parse::<usize>().map_err(|_| {
+            RpcError::invalid_params()
+                .with_message(format!("Invalid limit '{limit}', expected a number."))
+        })?,
+        None => DEFAULT_CONTEXT_FILES,
+    };
+
+    let files = context
+        .find_relevant_files(Path::new(root_path), "*", topic, None, limit)
+        .await
+        .map_err(|err| RpcError::invalid_params().with_message(err.to_string()))?;
+
+    let paths: Vec<&Path> = files.iter().map(|file| file.path.as_path()).collect();
+    let resources =
+        SearchFiles::collect_previews(&paths, CONTEXT_FILE_BYTES, CONTEXT_BUDGET, context).await;
+
+    let mut messages = vec![PromptMessage {
+        content: TextContent::new(context_introduction(topic, &files), None, None).into(),
+        role: Role::User,
+    }];
+    messages.extend(
+        resources
+            .into_iter()
+            .map(|content: ContentBlock| PromptMessage {
+                content,
+                role: Role::User,
+            }),
+    );
+
+    Ok(GetPromptResult {
+        description: Some(format!("Files relevant to '{topic}'")),
+        messages,
+        meta: None,
+    })
+}
+
+fn context_introduction(topic: &str, files: &[RelevantFile]) -> String {
+    if files.is_empty() {
+        return format!(
+            "No file relevant to '{topic}' was found. Search the allowed directories with other terms before starting the task."
+        );
+    }
+    let mut text = format!(
+        "The files most relevant to '{topic}' are listed below, ranked by relevance, their content follows (truncated for large files):\n"
+    );
+    for (index, file) in files.iter().enumerate() {
+        let _ = writeln!(
+            text,
+            "{}. {} ({} matches in {} lines)",
+            index + 1,
+            file.path.display(),
+            file.matches,
+            file.lines
+        );
+    }
+    text.push_str(
+        "Use them as the context of the task, and read other files only if they are not enough.",
+    );
+    text
+}
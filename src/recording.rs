@@ -0,0 +1,312 @@
+//! Recording of MCP sessions with `--record`, and their replay with the `replay` subcommand.
+//!
+//! Each server session is written to its own JSON Lines file in the recording directory, one line
+//! per handled request with its result or error. Recordings are sanitized: request metadata such as
+//! progress tokens is dropped, media data is replaced with its size and long text content is
+//! truncated. Unless `--record-content` is set, file contents are redacted as well: the arguments
+//! holding them (the content of `write_file`, the edits of `edit_file`) and the text, resources and
+//! structured content of the results are replaced with their size.
+//!
+//! Replaying a recording calls the tools again through the handler, without a client, and compares
+//! the results with the recorded ones. Calls whose arguments were redacted are skipped.
+
+use crate::{cli::CommandArguments, handler::FileSystemHandler};
+use chrono::Utc;
+use rust_mcp_sdk::schema::{CallToolRequestParams, CallToolResult, schema_utils::CallToolError};
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+/// Text content longer than this is truncated in recordings.
+const RECORDED_TEXT_MAX_CHARS: usize = 64 * 1024;
+
+/// Arguments of the tool calls holding file contents, redacted unless `--record-content` is set.
+const CONTENT_ARGUMENTS: [&str; 3] = ["content", "edits", "expected_anchors"];
+
+/// Method name of the recorded tool calls.
+pub const TOOLS_CALL_METHOD: &str = "tools/call";
+
+/// A request handled by the server, as written to a recording.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecordedRequest {
+    /// Position of the request in the session, starting at 1.
+    pub sequence: u64,
+    /// When the request was handled, as RFC 3339.
+    pub timestamp: String,
+    /// MCP method of the request, e.g. `tools/call`.
+    pub method: String,
+    pub params: Value,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// Whether the file contents were redacted from the parameters and the result.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub redacted: bool,
+}
+
+/// Writes the requests of a session to a new file of the recording directory.
+pub struct SessionRecorder {
+    path: PathBuf,
+    file: Mutex<File>,
+    sequence: AtomicU64,
+    /// Whether the file contents are kept in the recording instead of being redacted.
+    keep_content: bool,
+}
+
+impl SessionRecorder {
+    /// Creates the recording file of a new session in `dir`, creating `dir` if needed. The file is
+    /// only readable by the user, and the file contents are redacted from the recording unless
+    /// `keep_content` is true.
+    pub fn create(dir: &Path, keep_content: bool) -> io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let path = dir.join(format!(
+            "session-{}-{}.jsonl",
+            Utc::now().format("%Y%m%dT%H%M%S%.3fZ"),
+            std::process::id()
+        ));
+        let mut options = OpenOptions::new();
+        options.write(true).create_new(true);
+        #[cfg(unix)]
+        std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+        let file = options.open(&path)?;
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+            sequence: AtomicU64::new(0),
+            keep_content,
+        })
+    }
+
+    /// Path of the recording file.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Records a request of `method` and its outcome. Recording is best-effort, failures to write
+    /// the file are ignored so they never affect the session.
+    pub fn record(&self, method: &str, params: Value, outcome: Result<Value, String>) {
+        let redacted = !self.keep_content;
+        let (result, error) = match outcome {
+            Ok(result) => (Some(sanitize_result(result, redacted)), None),
+            Err(error) => (None, Some(error)),
+        };
+        let request = RecordedRequest {
+            sequence: self.sequence.fetch_add(1, Ordering::Relaxed) + 1,
+            timestamp: Utc::now().to_rfc3339(),
+            method: method.to_string(),
+            params: sanitize_params(params, redacted),
+            result,
+            error,
+            redacted,
+        };
+        if let Ok(mut line) = serde_json::to_string(&request) {
+            line.push('\n');
+            let _ = self.file.lock().unwrap().write_all(line.as_bytes());
+        }
+    }
+
+    /// Records a tool call and its result.
+    pub fn record_tool_call(
+        &self,
+        params: &CallToolRequestParams,
+        result: &Result<CallToolResult, CallToolError>,
+    ) {
+        let outcome = match result {
+            Ok(result) => Ok(serde_json::to_value(result).unwrap_or_default()),
+            Err(err) => Err(err.to_string()),
+        };
+        self.record(
+            TOOLS_CALL_METHOD,
+            serde_json::to_value(params).unwrap_or_default(),
+            outcome,
+        );
+    }
+}
+
+/// Replaces `value` with its size in bytes.
+fn redact(value: &mut Value, kind: &str) {
+    let size = match &*value {
+        Value::String(text) => text.len(),
+        other => other.to_string().len(),
+    };
+    *value = Value::String(format!("<{size} bytes of {kind} redacted>"));
+}
+
+/// The arguments of the tool call `params` holding file contents.
+fn content_arguments(params: &mut Value) -> impl Iterator<Item = &mut Value> {
+    params
+        .get_mut("arguments")
+        .and_then(Value::as_object_mut)
+        .into_iter()
+        .flat_map(|arguments| arguments.iter_mut())
+        .filter(|(name, _)| CONTENT_ARGUMENTS.contains(&name.as_str()))
+        .map(|(_, value)| value)
+}
+
+fn sanitize_params(mut params: Value, redact_content: bool) -> Value {
+    if let Some(params) = params.as_object_mut() {
+        params.remove("_meta");
+    }
+    if redact_content {
+        for argument in content_arguments(&mut params) {
+            redact(argument, "content");
+        }
+    }
+    params
+}
+
+fn sanitize_result(mut result: Value, redact_content: bool) -> Value {
+    if redact_content && let Some(structured_content) = result.get_mut("structuredContent") {
+        redact(structured_content, "structured content");
+    }
+    let Some(contents) = result.get_mut("content").and_then(Value::as_array_mut) else {
+        return result;
+    };
+    for content in contents {
+        if let Some(Value::String(data)) = content.get_mut("data") {
+            *data = format!("<{} bytes of base64 data omitted>", data.len());
+        }
+        if redact_content {
+            if let Some(text) = content.get_mut("text") {
+                redact(text, "text");
+            }
+            if let Some(resource) = content.get_mut("resource") {
+                for field in ["text", "blob"] {
+                    if let Some(value) = resource.get_mut(field) {
+                        redact(value, field);
+                    }
+                }
+            }
+        }
+        if let Some(Value::String(text)) = content.get_mut("text")
+            && let Some((index, _)) = text.char_indices().nth(RECORDED_TEXT_MAX_CHARS)
+        {
+            let omitted = text.len() - index;
+            text.truncate(index);
+            text.push_str(&format!("<{omitted} bytes omitted>"));
+        }
+    }
+    result
+}
+
+/// Reads the requests of the recording at `path`.
+pub fn read_recording(path: &Path) -> io::Result<Vec<RecordedRequest>> {
+    BufReader::new(File::open(path)?)
+        .lines()
+        .filter(|line| !line.as_ref().is_ok_and(|line| line.trim().is_empty()))
+        .map(|line| {
+            serde_json::from_str(&line?)
+                .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+        })
+        .collect()
+}
+
+/// How a replayed request compares with its recording.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ReplayStatus {
+    /// The tool returned the recorded result or error.
+    Match,
+    /// The tool returned a different result or error.
+    Differs,
+    /// The request isn't a tool call, or its parameters couldn't be read or were redacted.
+    Skipped,
+}
+
+/// Outcome of a replayed request.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ReplayedRequest {
+    pub sequence: u64,
+    pub method: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tool: Option<String>,
+    pub status: ReplayStatus,
+    /// The replayed result or error, when it differs from the recording.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual: Option<Value>,
+}
+
+/// The options of the server replaying a session started with `args`: the replayed session is not
+/// recorded again, and the tools modifying files are simulated unless `apply` is true, so a replay
+/// doesn't change the tree by default.
+pub fn replay_arguments(mut args: CommandArguments, apply: bool) -> CommandArguments {
+    args.record_dir = None;
+    args.record_content = false;
+    if !apply && (args.allow_write || args.overlay_dir.is_some()) {
+        args.simulate = true;
+        args.overlay_dir = None;
+    }
+    args
+}
+
+/// Calls the tools of `recording` again through `handler`, in order, comparing their results with
+/// the recorded ones. Execution metadata such as durations isn't compared, nor the redacted file
+/// contents other than by their size. The calls whose arguments were redacted are skipped.
+pub async fn replay(
+    handler: &FileSystemHandler,
+    recording: Vec<RecordedRequest>,
+) -> Vec<ReplayedRequest> {
+    let mut replayed = Vec::with_capacity(recording.len());
+    for mut request in recording {
+        let replayable = request.method == TOOLS_CALL_METHOD
+            && !(request.redacted && content_arguments(&mut request.params).next().is_some());
+        let params = replayable
+            .then(|| serde_json::from_value::<CallToolRequestParams>(request.params.clone()).ok())
+            .flatten();
+        let Some(params) = params else {
+            replayed.push(ReplayedRequest {
+                sequence: request.sequence,
+                method: request.method,
+                tool: None,
+                status: ReplayStatus::Skipped,
+                actual: None,
+            });
+            continue;
+        };
+
+        let tool = params.name.clone();
+        let (expected, actual) = match handler.call_tool(params, None).await {
+            Ok(result) => (
+                request.result.map(comparable_result),
+                comparable_result(sanitize_result(
+                    serde_json::to_value(result).unwrap_or_default(),
+                    request.redacted,
+                )),
+            ),
+            Err(err) => (
+                request.error.map(|error| json!({ "error": error })),
+                json!({ "error": err.to_string() }),
+            ),
+        };
+        let status = if expected.as_ref() == Some(&actual) {
+            ReplayStatus::Match
+        } else {
+            ReplayStatus::Differs
+        };
+        replayed.push(ReplayedRequest {
+            sequence: request.sequence,
+            method: request.method,
+            tool: Some(tool),
+            status,
+            actual: (status == ReplayStatus::Differs).then_some(actual),
+        });
+    }
+    replayed
+}
+
+/// The result without its metadata, which holds execution metrics.
+fn comparable_result(mut result: Value) -> Value {
+    if let Some(result) = result.as_object_mut() {
+        result.remove("_meta");
+    }
+    result
+}
@@ -0,0 +1,149 @@
+use crate::fs_service::FileSystemService;
+use rust_mcp_sdk::schema::{CallToolResult, schema_utils::CallToolError};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use std::{
+    collections::{HashMap, VecDeque},
+    path::Path,
+    sync::Arc,
+    time::{Duration, Instant, UNIX_EPOCH},
+};
+use tokio::sync::Mutex;
+
+/// Default number of responses remembered by the server.
+pub const DEFAULT_RESPONSE_CACHE_CAPACITY: usize = 64;
+
+type ResponseSlot = Arc<Mutex<Option<(Instant, CallToolResult)>>>;
+
+#[derive(Default)]
+struct CacheEntries {
+    slots: HashMap<Vec<u8>, ResponseSlot>,
+    // insertion order, used to evict the oldest keys first
+    order: VecDeque<Vec<u8>>,
+}
+
+/// Remembers the results of the read-only tools for a few seconds, so an agent repeating the same
+/// expensive call in a loop gets the previous result instead of running it again.
+///
+/// Results are keyed by tool, arguments, allowed directories and modification time of the paths in
+/// the arguments, so a call on a path modified since is executed again. Only the named paths are
+/// checked, not the files within a named directory: a file rewritten in place deeper in the tree
+/// goes unnoticed until the result expires. Only successful results are remembered, and concurrent
+/// identical calls wait for the first one to complete.
+pub struct ResponseCache {
+    ttl: Duration,
+    capacity: usize,
+    entries: std::sync::Mutex<CacheEntries>,
+}
+
+impl ResponseCache {
+    pub fn new(ttl: Duration, capacity: usize) -> Self {
+        Self {
+            ttl,
+            capacity: capacity.max(1),
+            entries: Default::default(),
+        }
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        !self.ttl.is_zero()
+    }
+
+    /// The key of a call of `tool_name` with `arguments`, changing when one of the paths it names
+    /// is modified.
+    pub async fn key(
+        tool_name: &str,
+        arguments: Option<&Map<String, Value>>,
+        fs_service: &FileSystemService,
+    ) -> Vec<u8> {
+        let allowed_directories = fs_service.allowed_directories().await;
+        let mut hasher = Sha256::new();
+        hasher.update(tool_name.as_bytes());
+        hasher.update([0]);
+        if let Some(arguments) = arguments {
+            hasher.update(Value::Object(arguments.clone()).to_string().as_bytes());
+        }
+        for directory in allowed_directories.iter() {
+            hasher.update([0]);
+            hasher.update(directory.as_os_str().as_encoded_bytes());
+        }
+        for path in arguments.into_iter().flat_map(argument_paths) {
+            let Ok(valid_path) =
+                fs_service.validate_path(Path::new(path), allowed_directories.clone())
+            else {
+                continue;
+            };
+            let modified = std::fs::metadata(&valid_path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+                .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+                .map_or(0, |modified| modified.as_nanos());
+            hasher.update([0]);
+            hasher.update(valid_path.as_os_str().as_encoded_bytes());
+            hasher.update(modified.to_le_bytes());
+        }
+        hasher.finalize().to_vec()
+    }
+
+    fn slot(&self, key: Vec<u8>) -> ResponseSlot {
+        let mut entries = self.entries.lock().unwrap();
+        if let Some(slot) = entries.slots.get(&key) {
+            return slot.clone();
+        }
+
+        while entries.order.len() >= self.capacity {
+            if let Some(oldest) = entries.order.pop_front() {
+                entries.slots.remove(&oldest);
+            }
+        }
+
+        let slot = ResponseSlot::default();
+        entries.slots.insert(key.clone(), slot.clone());
+        entries.order.push_back(key);
+        slot
+    }
+
+    /// Runs `operation` unless a result for `key` was returned less than the time to live ago, in
+    /// which case that result is returned. The flag tells whether the result comes from the cache.
+    pub async fn run<F>(
+        &self,
+        key: Vec<u8>,
+        operation: F,
+    ) -> (std::result::Result<CallToolResult, CallToolError>, bool)
+    where
+        F: Future<Output = std::result::Result<CallToolResult, CallToolError>>,
+    {
+        let slot = self.slot(key);
+        let mut cached_result = slot.lock().await;
+        if let Some((created, result)) = cached_result.as_ref()
+            && created.elapsed() < self.ttl
+        {
+            return (Ok(result.clone()), true);
+        }
+
+        let result = operation.await;
+        *cached_result = result
+            .as_ref()
+            .ok()
+            .map(|result| (Instant::now(), result.clone()));
+        (result, false)
+    }
+
+    /// Forgets all the results, after a change made by the server itself.
+    pub fn clear(&self) {
+        let mut entries = self.entries.lock().unwrap();
+        entries.slots.clear();
+        entries.order.clear();
+    }
+}
+
+/// The paths named by the arguments of a tool: the values of `path`, `paths` and `*_path`.
+fn argument_paths(arguments: &Map<String, Value>) -> impl Iterator<Item = &str> {
+    arguments
+        .iter()
+        .filter(|(name, _)| *name == "path" || *name == "paths" || name.ends_with("_path"))
+        .flat_map(|(_, value)| match value {
+            Value::Array(values) => values.iter().filter_map(Value::as_str).collect(),
+            value => value.as_str().into_iter().collect::<Vec<_>>(),
+        })
+}
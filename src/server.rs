@@ -1,8 +1,10 @@
 use crate::handler::FileSystemHandler;
+use crate::shutdown::{SHUTDOWN_GRACE_PERIOD, wait_for_shutdown};
 use crate::{cli::CommandArguments, error::ServiceResult};
 use rust_mcp_sdk::mcp_server::McpServerOptions;
 use rust_mcp_sdk::schema::{
-    Implementation, InitializeResult, ProtocolVersion, ServerCapabilities, ServerCapabilitiesTools,
+    Implementation, InitializeResult, ProtocolVersion, ServerCapabilities,
+    ServerCapabilitiesPrompts, ServerCapabilitiesTools,
 };
 use rust_mcp_sdk::{McpServer, StdioTransport, TransportOptions, mcp_server::server_runtime};
 use rust_mcp_sdk::{ToMcpServerHandler, mcp_icon};
@@ -25,8 +27,8 @@ pub fn server_details() -> InitializeResult {
         },
         capabilities: ServerCapabilities {
             experimental: None,
-            logging: None,
-            prompts: None,
+            logging: Some(Default::default()),
+            prompts: Some(ServerCapabilitiesPrompts { list_changed: None }),
             resources: None,
             tools: Some(ServerCapabilitiesTools { list_changed: None }),
             completions: None,
@@ -42,6 +44,7 @@ pub async fn start_server(args: CommandArguments) -> ServiceResult<()> {
     let transport = StdioTransport::new(TransportOptions::default())?;
 
     let handler = FileSystemHandler::new(args)?;
+    let fs_service = handler.fs_service().clone();
     let server = server_runtime::create_server(McpServerOptions {
         server_details: server_details(),
         handler: handler.to_mcp_server_handler(),
@@ -50,7 +53,24 @@ pub async fn start_server(args: CommandArguments) -> ServiceResult<()> {
         transport,
     });
 
-    server.start().await?;
+    let running = server.start();
+    tokio::pin!(running);
+    tokio::select! {
+        result = &mut running => result?,
+        reason = wait_for_shutdown() => {
+            // stop the operations in progress, they remove their partial output on the way out
+            eprintln!("Shutting down, {reason}: cancelling the operations in progress.");
+            fs_service.cancel_operations();
+            // the transport is gone, errors sending the last responses are expected
+            match tokio::time::timeout(SHUTDOWN_GRACE_PERIOD, running).await {
+                Ok(_) => eprintln!("The operations in progress were stopped, server exited cleanly."),
+                Err(_) => eprintln!(
+                    "Some operations were still running after {}s, server exited.",
+                    SHUTDOWN_GRACE_PERIOD.as_secs()
+                ),
+            }
+        }
+    }
 
     Ok(())
 }
@@ -0,0 +1,105 @@
+use std::{fmt::Display, time::Duration};
+
+/// How often the standard input is checked for a hang-up of the client.
+const TRANSPORT_POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Time given to the operations in progress to stop and clean up after a shutdown.
+pub const SHUTDOWN_GRACE_PERIOD: Duration = Duration::from_secs(5);
+
+/// Why the server is shutting down.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ShutdownReason {
+    /// The client closed the stdio transport.
+    TransportClosed,
+    /// The process received an interrupt or termination signal.
+    Signal,
+}
+
+impl Display for ShutdownReason {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ShutdownReason::TransportClosed => write!(f, "the client closed the transport"),
+            ShutdownReason::Signal => write!(f, "a termination signal was received"),
+        }
+    }
+}
+
+/// Whether the client closed its end of the stdio transport: the standard input hung up with no
+/// message left to read, or nothing reads the standard output anymore. Unlike a read, this
+/// doesn't consume the pending messages.
+#[cfg(any(target_os = "macos", target_os = "linux"))]
+fn transport_closed() -> bool {
+    use rustix::event::{PollFd, PollFlags, Timespec, poll};
+
+    let (stdin, stdout) = (std::io::stdin(), std::io::stdout());
+    // hang-ups and errors are reported whatever the requested events
+    let mut fds = [
+        PollFd::new(&stdin, PollFlags::IN),
+        PollFd::new(&stdout, PollFlags::empty()),
+    ];
+    if !matches!(poll(&mut fds, Some(&Timespec::default())), Ok(ready) if ready > 0) {
+        return false;
+    }
+    let (input, output) = (fds[0].revents(), fds[1].revents());
+    (input.contains(PollFlags::HUP) && !input.contains(PollFlags::IN))
+        || input.intersects(PollFlags::ERR | PollFlags::NVAL)
+        || output.intersects(PollFlags::HUP | PollFlags::ERR | PollFlags::NVAL)
+}
+
+async fn wait_for_transport_closed() {
+    #[cfg(any(target_os = "macos", target_os = "linux"))]
+    {
+        let mut interval = tokio::time::interval(TRANSPORT_POLL_INTERVAL);
+        loop {
+            interval.tick().await;
+            if transport_closed() {
+                return;
+            }
+        }
+    }
+
+    // the closure is only noticed once the pending messages are handled
+    #[cfg(not(any(target_os = "macos", target_os = "linux")))]
+    {
+        let _ = TRANSPORT_POLL_INTERVAL;
+        std::future::pending::<()>().await
+    }
+}
+
+/// Resolves on an interrupt, never when the handler can't be installed.
+async fn ctrl_c() {
+    if tokio::signal::ctrl_c().await.is_err() {
+        std::future::pending::<()>().await
+    }
+}
+
+async fn wait_for_signal() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{SignalKind, signal};
+
+        let (Ok(mut terminate), Ok(mut hangup)) = (
+            signal(SignalKind::terminate()),
+            signal(SignalKind::hangup()),
+        ) else {
+            return ctrl_c().await;
+        };
+        tokio::select! {
+            _ = ctrl_c() => {}
+            _ = terminate.recv() => {}
+            _ = hangup.recv() => {}
+        }
+    }
+
+    #[cfg(not(unix))]
+    ctrl_c().await
+}
+
+/// Resolves when the client goes away while the server is running: the stdio transport was
+/// closed or the process was asked to terminate.
+pub async fn wait_for_shutdown() -> ShutdownReason {
+    tokio::select! {
+        _ = wait_for_transport_closed() => ShutdownReason::TransportClosed,
+        _ = wait_for_signal() => ShutdownReason::Signal,
+    }
+}
@@ -0,0 +1,149 @@
+//! Generator of synthetic directory trees used by the benchmarks (`benches/traversal.rs`) and the
+//! hidden `generate-tree` subcommand, so benchmark numbers can be reproduced on other hardware.
+//!
+//! Trees are deterministic for a given seed: the same options always produce the same directories,
+//! file names and contents.
+
+use serde::Serialize;
+use std::{
+    collections::HashSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// Word contained in about a tenth of the generated files, to be searched for by content benchmarks.
+pub const SYNTHETIC_TREE_NEEDLE: &str = "needle";
+
+const WORDS: &[&str] = &[
+    "alpha", "bravo", "charlie", "delta", "echo", "foxtrot", "golf", "hotel", "india", "juliet",
+    "kilo", "lima", "mike", "november", "oscar", "papa", "quebec", "romeo", "sierra", "tango",
+];
+const EXTENSIONS: &[&str] = &["rs", "txt", "md", "json", "log"];
+
+/// Shape of a synthetic tree.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SyntheticTreeOptions {
+    /// Number of files.
+    pub files: usize,
+    /// Maximum nesting level of the directories.
+    pub depth: usize,
+    /// Number of subdirectories of each directory.
+    pub fan_out: usize,
+    /// Approximate size of each file in bytes.
+    pub file_size: usize,
+    /// Percentage of files that are copies of another file.
+    pub duplicate_percent: u8,
+    /// Seed of the generated names and contents.
+    pub seed: u64,
+}
+
+impl Default for SyntheticTreeOptions {
+    fn default() -> Self {
+        Self {
+            files: 10_000,
+            depth: 4,
+            fan_out: 8,
+            file_size: 4096,
+            duplicate_percent: 10,
+            seed: 42,
+        }
+    }
+}
+
+/// What was generated.
+#[derive(Debug, Default, Clone, PartialEq, Eq, Serialize)]
+pub struct SyntheticTreeSummary {
+    pub files: usize,
+    pub directories: usize,
+    pub bytes: u64,
+    pub duplicates: usize,
+    pub files_with_needle: usize,
+}
+
+/// SplitMix64, good enough for generating test data without an additional dependency.
+struct SplitMix64(u64);
+
+impl SplitMix64 {
+    fn next(&mut self) -> u64 {
+        self.0 = self.0.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.0;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    fn below(&mut self, bound: usize) -> usize {
+        (self.next() % bound.max(1) as u64) as usize
+    }
+}
+
+/// Generates a tree shaped by `options` in `root`, which is created if needed and must be empty.
+pub fn generate_tree(
+    root: &Path,
+    options: &SyntheticTreeOptions,
+) -> io::Result<SyntheticTreeSummary> {
+    fs::create_dir_all(root)?;
+    if fs::read_dir(root)?.next().is_some() {
+        return Err(io::Error::new(
+            io::ErrorKind::AlreadyExists,
+            format!("'{}' is not empty", root.display()),
+        ));
+    }
+
+    let mut rng = SplitMix64(options.seed);
+    let mut summary = SyntheticTreeSummary::default();
+    let mut contents: Vec<String> = Vec::with_capacity(options.files);
+    let mut directories: HashSet<PathBuf> = HashSet::new();
+
+    for index in 0..options.files {
+        // random path of up to `depth` levels, within `fan_out` directories per level
+        let mut dir = root.to_path_buf();
+        for _ in 0..rng.below(options.depth + 1) {
+            dir.push(format!("dir_{}", rng.below(options.fan_out)));
+            if directories.insert(dir.clone()) {
+                fs::create_dir(&dir)?;
+            }
+        }
+
+        let content = if !contents.is_empty() && rng.below(100) < options.duplicate_percent as usize
+        {
+            summary.duplicates += 1;
+            contents[rng.below(contents.len())].clone()
+        } else {
+            let content = generate_content(&mut rng, options.file_size, index % 10 == 0);
+            contents.push(content.clone());
+            content
+        };
+        if content.contains(SYNTHETIC_TREE_NEEDLE) {
+            summary.files_with_needle += 1;
+        }
+
+        let extension = EXTENSIONS[rng.below(EXTENSIONS.len())];
+        fs::write(dir.join(format!("file_{index}.{extension}")), &content)?;
+        summary.files += 1;
+        summary.bytes += content.len() as u64;
+    }
+
+    summary.directories = directories.len();
+    Ok(summary)
+}
+
+/// Lines of random words adding up to about `size` bytes, with the needle in the middle if requested.
+fn generate_content(rng: &mut SplitMix64, size: usize, with_needle: bool) -> String {
+    let mut content = String::with_capacity(size + 16);
+    let mut needle_pending = with_needle;
+    while content.len() < size {
+        let word = if needle_pending && content.len() >= size / 2 {
+            needle_pending = false;
+            SYNTHETIC_TREE_NEEDLE
+        } else {
+            WORDS[rng.below(WORDS.len())]
+        };
+        content.push_str(word);
+        content.push(if rng.below(8) == 0 { '\n' } else { ' ' });
+    }
+    if needle_pending {
+        content.push_str(SYNTHETIC_TREE_NEEDLE);
+    }
+    content
+}
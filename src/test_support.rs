@@ -0,0 +1,149 @@
+//! Helpers for the integration tests of applications embedding the server, enabled by the
+//! `test-support` feature.
+//!
+//! `TestServer` runs the handler in process, on a temporary directory removed when the server is
+//! dropped, so tools can be called directly without spawning the binary and speaking MCP over
+//! stdio:
+//!
+//! ```ignore
+//! let server = TestServer::with_args(&["--allow-write"])?;
+//! server.create_file("notes.txt", "hello");
+//! let text = server
+//!     .call_tool_text("read_text_file", json!({ "path": server.path("notes.txt") }))
+//!     .await?;
+//! assert_eq!(text, "hello");
+//! ```
+
+use crate::cli::CommandArguments;
+use crate::error::{ServiceError, ServiceResult};
+use crate::handler::FileSystemHandler;
+use clap::Parser;
+use rust_mcp_sdk::schema::{
+    CallToolRequestParams, CallToolResult, ContentBlock, schema_utils::CallToolError,
+};
+use serde_json::Value;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+use tempfile::TempDir;
+
+/// The server handler, allowed to access a temporary directory only.
+pub struct TestServer {
+    handler: FileSystemHandler,
+    root: PathBuf,
+    // removes the directory when the server is dropped
+    _temp_dir: TempDir,
+}
+
+impl TestServer {
+    /// A server in read-only mode, with the default options.
+    pub fn new() -> ServiceResult<Self> {
+        Self::with_args(&[])
+    }
+
+    /// A server started with the command line options `args`, e.g. `["--allow-write"]`. The
+    /// temporary directory is added as the allowed directory.
+    pub fn with_args(args: &[&str]) -> ServiceResult<Self> {
+        let temp_dir = TempDir::new()?;
+        // canonical path, as returned by the tools (e.g. /private/var on macOS)
+        let root = temp_dir.path().canonicalize()?;
+        let root_arg = root.display().to_string();
+        let args = std::iter::once(env!("CARGO_PKG_NAME"))
+            .chain(args.iter().copied())
+            .chain([root_arg.as_str()]);
+        let mut args = CommandArguments::try_parse_from(args)
+            .map_err(|err| ServiceError::InvalidConfig(err.to_string()))?;
+        args.validate().map_err(ServiceError::InvalidConfig)?;
+        Ok(Self {
+            handler: FileSystemHandler::new(args)?,
+            root,
+            _temp_dir: temp_dir,
+        })
+    }
+
+    /// The handler, for tests that need it directly.
+    pub fn handler(&self) -> &FileSystemHandler {
+        &self.handler
+    }
+
+    /// The allowed directory.
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+
+    /// Absolute path of `relative_path` in the allowed directory.
+    pub fn path(&self, relative_path: impl AsRef<Path>) -> PathBuf {
+        self.root.join(relative_path)
+    }
+
+    /// Creates a file with `content` in the allowed directory, along with its parent directories.
+    ///
+    /// # Panics
+    /// If the file can't be written, as it is meant for test setup.
+    pub fn create_file(
+        &self,
+        relative_path: impl AsRef<Path>,
+        content: impl AsRef<[u8]>,
+    ) -> PathBuf {
+        let path = self.path(relative_path);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    /// Creates a directory in the allowed directory, along with its parents.
+    ///
+    /// # Panics
+    /// If the directory can't be created, as it is meant for test setup.
+    pub fn create_dir(&self, relative_path: impl AsRef<Path>) -> PathBuf {
+        let path = self.path(relative_path);
+        fs::create_dir_all(&path).unwrap();
+        path
+    }
+
+    /// Calls the tool `name` with `arguments`, a JSON object, as an MCP client would.
+    pub async fn call_tool(
+        &self,
+        name: &str,
+        arguments: Value,
+    ) -> Result<CallToolResult, CallToolError> {
+        let params = CallToolRequestParams {
+            name: name.to_string(),
+            arguments: match arguments {
+                Value::Object(arguments) => Some(arguments),
+                Value::Null => None,
+                _ => {
+                    return Err(CallToolError::from_message(
+                        "Tool arguments must be a JSON object",
+                    ));
+                }
+            },
+            meta: None,
+            task: None,
+        };
+        // the futures of the tools are large, keep them off the stack of the test
+        Box::pin(self.handler.call_tool(params, None)).await
+    }
+
+    /// Calls the tool `name` with `arguments` and returns its text content, the text blocks being
+    /// joined with new lines.
+    pub async fn call_tool_text(
+        &self,
+        name: &str,
+        arguments: Value,
+    ) -> Result<String, CallToolError> {
+        let result = self.call_tool(name, arguments).await?;
+        Ok(result
+            .content
+            .iter()
+            .filter_map(|content| match content {
+                ContentBlock::TextContent(text_content) => Some(text_content.text.as_str()),
+                _ => None,
+            })
+            .collect::<Vec<_>>()
+            .join("\n"))
+    }
+}
@@ -1,47 +1,89 @@
+mod backups;
 mod calculate_directory_size;
+mod check_path_lengths;
+mod cleanup_whitespace;
+mod commit_overlay;
+mod compare_similarity;
+mod count_lines_of_code;
 mod create_directory;
+mod diff_workspace;
 mod directory_tree;
 mod edit_file;
+mod file_timeline;
+mod find_dependency_manifests;
 mod find_duplicate_files;
 mod find_empty_directories;
+mod find_file_type_mismatches;
+mod find_oldest_files;
+mod find_project_root;
+mod find_relevant_files;
 mod get_file_info;
+mod get_file_lock_state;
+mod get_file_profile;
 mod head_file;
 mod list_allowed_directories;
 mod list_directory;
 mod list_directory_with_sizes;
 mod move_file;
+mod output;
+mod read_archive_entry;
 mod read_file_lines;
 mod read_media_file;
 mod read_multiple_media_files;
 mod read_multiple_text_files;
 mod read_text_file;
+mod resolve_path;
 mod search_file;
 mod search_files_content;
+mod set_default_root;
 mod tail_file;
+mod update_finder_tags;
+mod usage_by_extension;
 mod write_file;
 mod zip_unzip;
 
+pub use backups::{ListBackups, RestoreBackup};
 pub use calculate_directory_size::{CalculateDirectorySize, FileSizeOutputFormat};
+pub use check_path_lengths::CheckPathLengths;
+pub use cleanup_whitespace::CleanupWhitespace;
+pub use commit_overlay::CommitOverlay;
+pub use compare_similarity::CompareSimilarity;
+pub use count_lines_of_code::CountLinesOfCode;
 pub use create_directory::CreateDirectory;
-pub use directory_tree::DirectoryTree;
+pub use diff_workspace::DiffWorkspace;
+pub use directory_tree::{DirectoryTree, TreeOutputFormat};
 pub use edit_file::{EditFile, EditOperation};
+pub use file_timeline::FileTimeline;
+pub use find_dependency_manifests::FindDependencyManifests;
 pub use find_duplicate_files::FindDuplicateFiles;
 pub use find_empty_directories::FindEmptyDirectories;
+pub use find_file_type_mismatches::FindFileTypeMismatches;
+pub use find_oldest_files::FindOldestFiles;
+pub use find_project_root::FindProjectRoot;
+pub use find_relevant_files::FindRelevantFiles;
 pub use get_file_info::GetFileInfo;
+pub use get_file_lock_state::GetFileLockState;
+pub use get_file_profile::GetFileProfile;
 pub use head_file::HeadFile;
 pub use list_allowed_directories::ListAllowedDirectories;
 pub use list_directory::ListDirectory;
 pub use list_directory_with_sizes::ListDirectoryWithSizes;
 pub use move_file::MoveFile;
+pub use output::{escape_non_ascii, escape_result_text};
+pub use read_archive_entry::ReadArchiveEntry;
 pub use read_file_lines::ReadFileLines;
 pub use read_media_file::ReadMediaFile;
 pub use read_multiple_media_files::ReadMultipleMediaFiles;
 pub use read_multiple_text_files::ReadMultipleTextFiles;
 pub use read_text_file::ReadTextFile;
+pub use resolve_path::ResolvePath;
 pub use rust_mcp_sdk::tool_box;
 pub use search_file::SearchFiles;
 pub use search_files_content::SearchFilesContent;
+pub use set_default_root::SetDefaultRoot;
 pub use tail_file::TailFile;
+pub use update_finder_tags::UpdateFinderTags;
+pub use usage_by_extension::UsageByExtension;
 pub use write_file::WriteFile;
 pub use zip_unzip::{UnzipFile, ZipDirectory, ZipFiles};
 //Generate FileSystemTools enum , tools() function, and TryFrom<CallToolRequestParams> trait implementation
@@ -71,7 +113,28 @@ tool_box!(
         ReadFileLines,
         FindEmptyDirectories,
         CalculateDirectorySize,
-        FindDuplicateFiles
+        FindDuplicateFiles,
+        FindFileTypeMismatches,
+        ListBackups,
+        RestoreBackup,
+        UpdateFinderTags,
+        ReadArchiveEntry,
+        CheckPathLengths,
+        ResolvePath,
+        SetDefaultRoot,
+        FindOldestFiles,
+        UsageByExtension,
+        FileTimeline,
+        FindRelevantFiles,
+        GetFileProfile,
+        CleanupWhitespace,
+        GetFileLockState,
+        CommitOverlay,
+        DiffWorkspace,
+        FindProjectRoot,
+        CountLinesOfCode,
+        FindDependencyManifests,
+        CompareSimilarity
     ]
 );
 
@@ -86,7 +149,11 @@ impl FileSystemTools {
             | FileSystemTools::EditFile(_)
             | FileSystemTools::ZipFiles(_)
             | FileSystemTools::UnzipFile(_)
-            | FileSystemTools::ZipDirectory(_) => true,
+            | FileSystemTools::ZipDirectory(_)
+            | FileSystemTools::RestoreBackup(_)
+            | FileSystemTools::UpdateFinderTags(_)
+            | FileSystemTools::CleanupWhitespace(_)
+            | FileSystemTools::CommitOverlay(_) => true,
             FileSystemTools::ReadTextFile(_)
             | FileSystemTools::DirectoryTree(_)
             | FileSystemTools::GetFileInfo(_)
@@ -103,7 +170,54 @@ impl FileSystemTools {
             | FileSystemTools::FindEmptyDirectories(_)
             | FileSystemTools::CalculateDirectorySize(_)
             | FileSystemTools::FindDuplicateFiles(_)
-            | FileSystemTools::SearchFiles(_) => false,
+            | FileSystemTools::SearchFiles(_)
+            | FileSystemTools::FindFileTypeMismatches(_)
+            | FileSystemTools::ListBackups(_)
+            | FileSystemTools::ReadArchiveEntry(_)
+            | FileSystemTools::CheckPathLengths(_)
+            | FileSystemTools::ResolvePath(_)
+            | FileSystemTools::SetDefaultRoot(_)
+            | FileSystemTools::FindOldestFiles(_)
+            | FileSystemTools::UsageByExtension(_)
+            | FileSystemTools::FileTimeline(_)
+            | FileSystemTools::FindRelevantFiles(_)
+            | FileSystemTools::GetFileProfile(_)
+            | FileSystemTools::GetFileLockState(_)
+            | FileSystemTools::DiffWorkspace(_)
+            | FileSystemTools::FindProjectRoot(_)
+            | FileSystemTools::CountLinesOfCode(_)
+            | FileSystemTools::FindDependencyManifests(_)
+            | FileSystemTools::CompareSimilarity(_) => false,
+        }
+    }
+
+    // Determines whether the result of the tool may be served from the response cache: the tools
+    // that only read the file system, except those depending on the state of the server or time.
+    pub fn is_cacheable(&self) -> bool {
+        !self.require_write_access()
+            && !matches!(
+                self,
+                FileSystemTools::ListAllowedDirectories(_)
+                    | FileSystemTools::SetDefaultRoot(_)
+                    | FileSystemTools::GetFileLockState(_)
+            )
+    }
+
+    // Returns the idempotency key provided by the client for tools that modify the file system.
+    pub fn idempotency_key(&self) -> Option<&str> {
+        match self {
+            FileSystemTools::CreateDirectory(params) => params.idempotency_key.as_deref(),
+            FileSystemTools::MoveFile(params) => params.idempotency_key.as_deref(),
+            FileSystemTools::WriteFile(params) => params.idempotency_key.as_deref(),
+            FileSystemTools::EditFile(params) => params.idempotency_key.as_deref(),
+            FileSystemTools::ZipFiles(params) => params.idempotency_key.as_deref(),
+            FileSystemTools::UnzipFile(params) => params.idempotency_key.as_deref(),
+            FileSystemTools::ZipDirectory(params) => params.idempotency_key.as_deref(),
+            FileSystemTools::RestoreBackup(params) => params.idempotency_key.as_deref(),
+            FileSystemTools::UpdateFinderTags(params) => params.idempotency_key.as_deref(),
+            FileSystemTools::CleanupWhitespace(params) => params.idempotency_key.as_deref(),
+            FileSystemTools::CommitOverlay(params) => params.idempotency_key.as_deref(),
+            _ => None,
         }
     }
 }
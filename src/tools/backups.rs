@@ -0,0 +1,118 @@
+use rust_mcp_sdk::{
+    macros::{JsonSchema, mcp_tool},
+    schema::{CallToolResult, schema_utils::CallToolError},
+};
+use serde_json::json;
+use std::fmt::Write;
+use std::path::Path;
+
+use super::output::tool_result;
+use crate::fs_service::{BackupEntry, FileSystemService, utils::OutputFormat};
+use crate::i18n::Message;
+
+#[mcp_tool(
+    name = "list_backups",
+    title="List backups",
+    description = concat!("Lists backups created by the server before files were modified by `write_file` or `edit_file`, newest first. ",
+    "Each entry includes the backup path, the original file path, the time the backup was taken and its size. ",
+    "Optional `path` argument limits the results to backups of a single file. ",
+    "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
+    "Only available when the server runs with backups enabled."),
+    destructive_hint = false,
+    idempotent_hint = true,
+    open_world_hint = false,
+    read_only_hint = true
+)]
+#[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, JsonSchema)]
+pub struct ListBackups {
+    /// Optional path of a file to list the backups for. When omitted, backups of all files are listed.
+    pub path: Option<String>,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+}
+
+impl ListBackups {
+    fn format_text(backups: &[BackupEntry]) -> std::result::Result<String, CallToolError> {
+        if backups.is_empty() {
+            return Ok("No backups were found.".to_string());
+        }
+
+        let mut output = String::new();
+        for backup in backups {
+            writeln!(
+                output,
+                "{} (original: {}, created: {}, size: {} bytes)",
+                backup.backup_path.display(),
+                backup.original_path.display(),
+                backup.created,
+                backup.size
+            )
+            .map_err(CallToolError::new)?;
+        }
+        Ok(output)
+    }
+
+    pub async fn run_tool(
+        params: Self,
+        context: &FileSystemService,
+    ) -> std::result::Result<CallToolResult, CallToolError> {
+        let backups = context
+            .list_backups(params.path.as_ref().map(Path::new))
+            .await
+            .map_err(CallToolError::new)?;
+
+        tool_result(params.output_format, &backups, || {
+            Self::format_text(&backups)
+        })
+    }
+}
+
+#[mcp_tool(
+    name = "restore_backup",
+    title="Restore backup",
+    description = concat!("Restores a backup created by the server, replacing the current content of the original file. ",
+    "Use `list_backups` to find the path of the backup to restore. ",
+    "The current content of the original file is backed up before it is replaced, so a restore can be undone. ",
+    "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
+    "Only available when the server runs with backups enabled."),
+    destructive_hint = true,
+    idempotent_hint = false,
+    open_world_hint = false,
+    read_only_hint = false
+)]
+#[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, JsonSchema)]
+pub struct RestoreBackup {
+    /// Path of the backup to restore, as returned by `list_backups`.
+    pub backup_path: String,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+    /// Optional client-generated key identifying this operation. When a request is retried with the same key,
+    /// the server returns the original result instead of executing the operation again.
+    pub idempotency_key: Option<String>,
+}
+
+impl RestoreBackup {
+    pub async fn run_tool(
+        params: Self,
+        context: &FileSystemService,
+    ) -> std::result::Result<CallToolResult, CallToolError> {
+        let original_path = context
+            .restore_backup(Path::new(&params.backup_path))
+            .await
+            .map_err(CallToolError::new)?;
+
+        let output = json!({
+            "path": original_path.display().to_string(),
+            "backup_path": params.backup_path,
+        });
+        tool_result(params.output_format, &output, || {
+            Ok(Message::BackupRestored {
+                path: &original_path.display().to_string(),
+                backup_path: &params.backup_path,
+            }
+            .localize(context.locale()))
+        })
+    }
+}
@@ -1,8 +1,13 @@
-use crate::fs_service::{FileSystemService, utils::format_bytes};
+use super::output::tool_result;
+use crate::fs_service::{
+    FileSystemService,
+    utils::{OutputFormat, format_bytes},
+};
 use rust_mcp_sdk::{
     macros::{JsonSchema, mcp_tool},
-    schema::{CallToolResult, TextContent, schema_utils::CallToolError},
+    schema::{CallToolResult, schema_utils::CallToolError},
 };
+use serde_json::json;
 use std::path::Path;
 
 #[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, JsonSchema)]
@@ -11,6 +16,8 @@ pub enum FileSizeOutputFormat {
     HumanReadable,
     #[serde(rename = "bytes")]
     Bytes,
+    #[serde(rename = "json")]
+    Json,
 }
 
 #[mcp_tool(
@@ -18,7 +25,9 @@ pub enum FileSizeOutputFormat {
     title="Calculate directory size",
     description = concat!("Calculates the total size of a directory specified by `root_path`.",
     "It recursively searches for files and sums their sizes. ",
-    "The result can be returned in either a `human-readable` format or as `bytes`, depending on the specified `output_format` argument.",
+    "The result can be returned in either a `human-readable` format, as `bytes` or as `json`, depending on the specified `output_format` argument.",
+    "When the server runs with --cache-directory-sizes, sizes are cached per directory until the directory is modified, so repeated queries are fast. ",
+    "Files rewritten in place are then not detected by the cache, set `force_refresh` to true to recompute the size of the whole tree. ",
     "Only works within allowed directories."),
     destructive_hint = false,
     idempotent_hint = false,
@@ -34,9 +43,12 @@ pub enum FileSizeOutputFormat {
 pub struct CalculateDirectorySize {
     /// The root directory path to start the size calculation.
     pub root_path: String,
-    /// Defines the output format, which can be either `human-readable` or `bytes`.
+    /// Defines the output format, which can be either `human-readable`, `bytes` or `json`.
     #[json_schema(default = "human-readable")]
     pub output_format: Option<FileSizeOutputFormat>,
+    /// Bypass the cached sizes, if enabled, and recompute the size of the whole tree (default: false).
+    #[json_schema(default = "false")]
+    pub force_refresh: Option<bool>,
 }
 
 impl CalculateDirectorySize {
@@ -45,20 +57,31 @@ impl CalculateDirectorySize {
         context: &FileSystemService,
     ) -> std::result::Result<CallToolResult, CallToolError> {
         let total_bytes = context
-            .calculate_directory_size(Path::new(&params.root_path))
+            .calculate_directory_size(
+                Path::new(&params.root_path),
+                params.force_refresh.unwrap_or(false),
+            )
             .await
             .map_err(CallToolError::new)?;
 
-        let output_content = match params
+        let output_format = params
             .output_format
-            .unwrap_or(FileSizeOutputFormat::HumanReadable)
-        {
-            FileSizeOutputFormat::HumanReadable => format_bytes(total_bytes),
-            FileSizeOutputFormat::Bytes => format!("{total_bytes}"),
-        };
+            .unwrap_or(FileSizeOutputFormat::HumanReadable);
+        let output = json!({
+            "root_path": params.root_path,
+            "bytes": total_bytes,
+            "human_readable": format_bytes(total_bytes),
+        });
 
-        Ok(CallToolResult::text_content(vec![TextContent::from(
-            output_content,
-        )]))
+        tool_result(
+            matches!(output_format, FileSizeOutputFormat::Json).then_some(OutputFormat::Json),
+            &output,
+            || {
+                Ok(match output_format {
+                    FileSizeOutputFormat::Bytes => format!("{total_bytes}"),
+                    _ => format_bytes(total_bytes),
+                })
+            },
+        )
     }
 }
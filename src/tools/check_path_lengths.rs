@@ -0,0 +1,75 @@
+use rust_mcp_sdk::{
+    macros::{JsonSchema, mcp_tool},
+    schema::{CallToolResult, schema_utils::CallToolError},
+};
+use std::fmt::Write;
+
+use super::output::tool_result;
+use crate::fs_service::{FileSystemService, PathLengthReport, PathPlatform, utils::OutputFormat};
+
+#[mcp_tool(
+    name = "check_path_lengths",
+    title="Check path lengths",
+    description = concat!("Checks whether proposed paths would exceed the path length limits of a platform, ",
+    "before creating deep directory structures or renaming files. ",
+    "Paths don't need to exist. Each path is resolved to its absolute form and checked against the maximum length ",
+    "of a single component (255 bytes on Linux and macOS, 255 characters on Windows) and of the full path ",
+    "(4095 bytes on Linux, 1023 bytes on macOS, 259 characters on Windows, or 32767 characters where long paths are enabled). ",
+    "The `platform` argument accepts `linux`, `macos` or `windows` and defaults to the platform the server runs on. ",
+    "The result lists the problematic components of each path. ",
+    "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
+    "Only works within allowed directories."),
+    destructive_hint = false,
+    idempotent_hint = true,
+    open_world_hint = false,
+    read_only_hint = true
+)]
+#[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, JsonSchema)]
+pub struct CheckPathLengths {
+    /// The paths to check.
+    pub paths: Vec<String>,
+    /// The platform whose limits are checked, accepts `linux`, `macos` or `windows` (default: the platform of the server).
+    pub platform: Option<PathPlatform>,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+}
+
+#[derive(::serde::Serialize, Debug)]
+struct PathLengthsOutput {
+    platform: PathPlatform,
+    reports: Vec<PathLengthReport>,
+}
+
+impl CheckPathLengths {
+    fn format_text(reports: &[PathLengthReport]) -> std::result::Result<String, CallToolError> {
+        let mut output = String::new();
+        for report in reports {
+            if report.valid {
+                writeln!(output, "OK: {}", report.path).map_err(CallToolError::new)?;
+                continue;
+            }
+            writeln!(output, "TOO LONG: {}", report.path).map_err(CallToolError::new)?;
+            for issue in &report.issues {
+                writeln!(output, "  - {}", issue.message).map_err(CallToolError::new)?;
+            }
+        }
+        Ok(output.trim_end().to_string())
+    }
+
+    pub async fn run_tool(
+        params: Self,
+        context: &FileSystemService,
+    ) -> std::result::Result<CallToolResult, CallToolError> {
+        let platform = params.platform.unwrap_or_else(PathPlatform::current);
+        let reports = context
+            .check_path_lengths(&params.paths, platform)
+            .await
+            .map_err(CallToolError::new)?;
+
+        let output = PathLengthsOutput { platform, reports };
+        tool_result(params.output_format, &output, || {
+            Self::format_text(&output.reports)
+        })
+    }
+}
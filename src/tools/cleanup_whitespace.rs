@@ -0,0 +1,96 @@
+use rust_mcp_sdk::{
+    macros::{JsonSchema, mcp_tool},
+    schema::{CallToolResult, schema_utils::CallToolError},
+};
+use std::fmt::Write;
+use std::path::Path;
+
+use super::output::tool_result;
+use crate::fs_service::{FileSystemService, WhitespaceCleanup, utils::OutputFormat};
+
+#[mcp_tool(
+    name = "cleanup_whitespace",
+    title="Clean up whitespace",
+    description = concat!("Cleans up the whitespace of the text files under a directory matching a glob pattern: ",
+    "strips trailing whitespace, converts a mix of tabs and spaces to the indentation style of the file ",
+    "(or the one set by its `.editorconfig`) and leaves a single final newline. ",
+    "Returns a git-style diff for each modified file. Set 'dry_run' to true to preview the changes without applying them. ",
+    "Binary and non UTF-8 files are skipped. ",
+    "The optional exclude_patterns argument accepts glob-style patterns to exclude specific paths. ",
+    "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
+    "Only works within allowed directories."),
+    destructive_hint = false,
+    idempotent_hint = true,
+    open_world_hint = false,
+    read_only_hint = false
+)]
+#[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, JsonSchema)]
+pub struct CleanupWhitespace {
+    /// The directory containing the files to clean up.
+    pub path: String,
+    /// Glob pattern matching the names of the files to clean up, e.g. `*.rs` (default: all files).
+    pub pattern: Option<String>,
+    /// Optional list of glob patterns to exclude, matched against paths relative to the directory.
+    pub exclude_patterns: Option<Vec<String>>,
+    /// Preview the changes without modifying the files (default: false).
+    pub dry_run: Option<bool>,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+    /// Optional client-generated key identifying this operation. When a request is retried with the same key,
+    /// the server returns the original result instead of executing the operation again.
+    pub idempotency_key: Option<String>,
+}
+
+impl CleanupWhitespace {
+    pub async fn run_tool(
+        params: Self,
+        context: &FileSystemService,
+    ) -> std::result::Result<CallToolResult, CallToolError> {
+        let dry_run = params.dry_run.unwrap_or(false);
+        let cleanups = context
+            .cleanup_whitespace(
+                Path::new(&params.path),
+                params.pattern.unwrap_or("**/*".to_string()),
+                params.exclude_patterns.unwrap_or_default(),
+                dry_run,
+            )
+            .await
+            .map_err(CallToolError::new)?;
+
+        tool_result(params.output_format, &cleanups, || {
+            Self::format_text(&cleanups, dry_run)
+        })
+    }
+
+    fn format_text(
+        cleanups: &[WhitespaceCleanup],
+        dry_run: bool,
+    ) -> std::result::Result<String, CallToolError> {
+        if cleanups.is_empty() {
+            return Ok("No file needs a whitespace cleanup.".to_string());
+        }
+        let mut output = format!(
+            "{} {} {}:\n",
+            if dry_run {
+                "Would clean up"
+            } else {
+                "Cleaned up"
+            },
+            cleanups.len(),
+            if cleanups.len() == 1 { "file" } else { "files" }
+        );
+        for cleanup in cleanups {
+            if let Some(error) = &cleanup.error {
+                writeln!(
+                    output,
+                    "\n{}: not modified, {error}",
+                    cleanup.path.display()
+                )
+                .map_err(CallToolError::new)?;
+            }
+            writeln!(output, "\n{}", cleanup.diff).map_err(CallToolError::new)?;
+        }
+        Ok(output)
+    }
+}
@@ -0,0 +1,85 @@
+use rust_mcp_sdk::{
+    macros::{JsonSchema, mcp_tool},
+    schema::{CallToolResult, schema_utils::CallToolError},
+};
+use std::fmt::Write;
+
+use super::output::tool_result;
+use crate::fs_service::{FileSystemService, OverlayChange, utils::OutputFormat};
+
+#[mcp_tool(
+    name = "commit_overlay",
+    title="Commit overlay",
+    description = concat!("Applies the changes kept in the overlay to the real files, or discards them. ",
+    "Only available when the server runs with an overlay directory: the tools modifying files then write their changes to the overlay, ",
+    "and reading a file returns its content in the overlay, until they are committed. ",
+    "The changes are applied all at once: if one fails, the files are left unchanged and the changes stay in the overlay. ",
+    "Set 'discard' to true to drop the changes instead, or 'dry_run' to true to list them without applying them. ",
+    "Use `diff_workspace` to review the changes with the diff of each file. ",
+    "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text)."),
+    destructive_hint = true,
+    idempotent_hint = false,
+    open_world_hint = false,
+    read_only_hint = false
+)]
+#[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, JsonSchema)]
+pub struct CommitOverlay {
+    /// Drop the changes of the overlay instead of applying them (default: false).
+    pub discard: Option<bool>,
+    /// List the changes of the overlay without applying them (default: false).
+    pub dry_run: Option<bool>,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+    /// Optional client-generated key identifying this operation. When a request is retried with the same key,
+    /// the server returns the original result instead of executing the operation again.
+    pub idempotency_key: Option<String>,
+}
+
+impl CommitOverlay {
+    pub async fn run_tool(
+        params: Self,
+        context: &FileSystemService,
+    ) -> std::result::Result<CallToolResult, CallToolError> {
+        let dry_run = params.dry_run.unwrap_or(false);
+        let discard = params.discard.unwrap_or(false);
+        let changes = if discard && !dry_run {
+            context.discard_overlay()
+        } else {
+            context.commit_overlay(dry_run).await
+        }
+        .map_err(CallToolError::new)?;
+
+        tool_result(params.output_format, &changes, || {
+            Self::format_text(&changes, dry_run, discard)
+        })
+    }
+
+    fn format_text(
+        changes: &[OverlayChange],
+        dry_run: bool,
+        discard: bool,
+    ) -> std::result::Result<String, CallToolError> {
+        if changes.is_empty() {
+            return Ok("The overlay has no changes.".to_string());
+        }
+        let mut output = format!(
+            "{} {} {}:\n",
+            match (dry_run, discard) {
+                (true, _) => "The overlay has",
+                (false, true) => "Discarded",
+                (false, false) => "Committed",
+            },
+            changes.len(),
+            if changes.len() == 1 {
+                "change"
+            } else {
+                "changes"
+            }
+        );
+        for change in changes {
+            writeln!(output, "{change}").map_err(CallToolError::new)?;
+        }
+        Ok(output)
+    }
+}
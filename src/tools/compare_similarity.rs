@@ -0,0 +1,107 @@
+use rust_mcp_sdk::{
+    macros::{JsonSchema, mcp_tool},
+    schema::{CallToolResult, schema_utils::CallToolError},
+};
+use std::fmt::Write;
+use std::path::PathBuf;
+
+use super::output::tool_result;
+use crate::fs_service::{FileSystemService, SimilarityReport, utils::OutputFormat};
+
+const DEFAULT_THRESHOLD: u64 = 80;
+
+#[mcp_tool(
+    name = "compare_similarity",
+    title="Compare file similarity",
+    description = concat!("Computes how similar text files are, as the percentage of their lines they have in common. ",
+    "With two files, reports their similarity. With more files (up to 50), every pair is compared ",
+    "and the files are grouped into clusters of files at least 'threshold' percent similar to another file of the cluster. ",
+    "Useful to spot copy-pasted configuration files or diverging copies of the same file. ",
+    "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
+    "Only works within allowed directories."),
+    destructive_hint = false,
+    idempotent_hint = true,
+    open_world_hint = false,
+    read_only_hint = true
+)]
+#[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, JsonSchema)]
+pub struct CompareSimilarity {
+    /// The files to compare, at least two.
+    pub paths: Vec<String>,
+    /// Minimum similarity percentage of files clustered together (default: 80).
+    #[json_schema(default = "80")]
+    pub threshold: Option<u64>,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+}
+
+impl CompareSimilarity {
+    fn format_text(report: &SimilarityReport) -> std::result::Result<String, CallToolError> {
+        let mut output = String::new();
+        if let [pair] = report.pairs.as_slice() {
+            writeln!(
+                output,
+                "{} and {} are {}% similar ({} common lines, {} and {} lines).",
+                pair.path_a.display(),
+                pair.path_b.display(),
+                pair.similarity,
+                pair.common_lines,
+                pair.lines_a,
+                pair.lines_b
+            )
+            .map_err(CallToolError::new)?;
+            return Ok(output);
+        }
+
+        if report.clusters.is_empty() {
+            writeln!(
+                output,
+                "No files are at least {}% similar.",
+                report.threshold
+            )
+        } else {
+            writeln!(
+                output,
+                "Clusters of files at least {}% similar:",
+                report.threshold
+            )
+        }
+        .map_err(CallToolError::new)?;
+        for (index, cluster) in report.clusters.iter().enumerate() {
+            let paths: Vec<String> = cluster
+                .iter()
+                .map(|path| path.display().to_string())
+                .collect();
+            writeln!(output, "  {}. {}", index + 1, paths.join(", "))
+                .map_err(CallToolError::new)?;
+        }
+
+        writeln!(output, "\nPairs, most similar first:").map_err(CallToolError::new)?;
+        for pair in &report.pairs {
+            writeln!(
+                output,
+                "  {:>5}%  {} <-> {} ({} common lines)",
+                pair.similarity,
+                pair.path_a.display(),
+                pair.path_b.display(),
+                pair.common_lines
+            )
+            .map_err(CallToolError::new)?;
+        }
+        Ok(output)
+    }
+
+    pub async fn run_tool(
+        params: Self,
+        context: &FileSystemService,
+    ) -> std::result::Result<CallToolResult, CallToolError> {
+        let paths: Vec<PathBuf> = params.paths.iter().map(PathBuf::from).collect();
+        let report = context
+            .compare_similarity(&paths, params.threshold.unwrap_or(DEFAULT_THRESHOLD) as f64)
+            .await
+            .map_err(CallToolError::new)?;
+
+        tool_result(params.output_format, &report, || Self::format_text(&report))
+    }
+}
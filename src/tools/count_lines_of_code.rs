@@ -0,0 +1,127 @@
+use rust_mcp_sdk::{
+    macros::{JsonSchema, mcp_tool},
+    schema::{CallToolResult, schema_utils::CallToolError},
+};
+use serde::Serialize;
+use std::fmt::Write;
+use std::path::Path;
+
+use super::output::tool_result;
+use crate::fs_service::{FileSystemService, LanguageLines, utils::OutputFormat};
+
+#[mcp_tool(
+    name = "count_lines_of_code",
+    title="Count lines of code",
+    description = concat!("Reports the number of files and lines under a directory grouped by language, ",
+    "sorted by number of lines, largest first. Languages are detected from file extensions, ",
+    "files of other extensions are grouped by extension and binary files are skipped. ",
+    "Set 'classify_lines' to true to also count code, comment and blank lines, based on the comment syntax of each language. ",
+    "Useful to get an overview of a codebase. ",
+    "Optional `pattern` argument can be used to narrow down the search to specific glob pattern. ",
+    "Optional `exclude_patterns` can be used to exclude certain files matching a glob. ",
+    "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
+    "Only works within allowed directories."),
+    destructive_hint = false,
+    idempotent_hint = true,
+    open_world_hint = false,
+    read_only_hint = true
+)]
+#[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, JsonSchema)]
+pub struct CountLinesOfCode {
+    /// The root directory path to start the search.
+    pub root_path: String,
+    /// Optional glob pattern can be used to match target files.
+    pub pattern: Option<String>,
+    /// Optional list of glob patterns to exclude from the search. File matching these patterns will be ignored.
+    pub exclude_patterns: Option<Vec<String>>,
+    /// Count code, comment and blank lines as well (default: false).
+    pub classify_lines: Option<bool>,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+}
+
+#[derive(Serialize, Debug)]
+struct LinesOutput {
+    languages: Vec<LanguageLines>,
+    total: LanguageLines,
+}
+
+impl CountLinesOfCode {
+    fn total(languages: &[LanguageLines], classify: bool) -> LanguageLines {
+        let sum = |field: fn(&LanguageLines) -> Option<usize>| {
+            classify.then(|| languages.iter().filter_map(field).sum())
+        };
+        LanguageLines {
+            language: "total".to_string(),
+            files: languages.iter().map(|language| language.files).sum(),
+            lines: languages.iter().map(|language| language.lines).sum(),
+            code: sum(|language| language.code),
+            comments: sum(|language| language.comments),
+            blanks: sum(|language| language.blanks),
+        }
+    }
+
+    fn format_text(output: &LinesOutput) -> std::result::Result<String, CallToolError> {
+        if output.languages.is_empty() {
+            return Ok("No text files were found.".to_string());
+        }
+
+        let mut text = String::new();
+        let classified = output.total.code.is_some();
+        if classified {
+            writeln!(
+                text,
+                "  {:<14} {:>8} {:>10} {:>10} {:>10} {:>10}",
+                "language", "files", "lines", "code", "comments", "blanks"
+            )
+        } else {
+            writeln!(text, "  {:<14} {:>8} {:>10}", "language", "files", "lines")
+        }
+        .map_err(CallToolError::new)?;
+
+        for language in output.languages.iter().chain([&output.total]) {
+            write!(
+                text,
+                "  {:<14} {:>8} {:>10}",
+                language.language, language.files, language.lines
+            )
+            .map_err(CallToolError::new)?;
+            if classified {
+                write!(
+                    text,
+                    " {:>10} {:>10} {:>10}",
+                    language.code.unwrap_or_default(),
+                    language.comments.unwrap_or_default(),
+                    language.blanks.unwrap_or_default()
+                )
+                .map_err(CallToolError::new)?;
+            }
+            writeln!(text).map_err(CallToolError::new)?;
+        }
+        Ok(text)
+    }
+
+    pub async fn run_tool(
+        params: Self,
+        context: &FileSystemService,
+    ) -> std::result::Result<CallToolResult, CallToolError> {
+        let classify = params.classify_lines.unwrap_or(false);
+        let languages = context
+            .count_lines_of_code(
+                Path::new(&params.root_path),
+                params.pattern,
+                params.exclude_patterns,
+                classify,
+            )
+            .await
+            .map_err(CallToolError::new)?;
+
+        let output = LinesOutput {
+            total: Self::total(&languages, classify),
+            languages,
+        };
+
+        tool_result(params.output_format, &output, || Self::format_text(&output))
+    }
+}
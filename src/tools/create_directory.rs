@@ -1,10 +1,12 @@
 use std::path::Path;
 
 use rust_mcp_sdk::macros::{JsonSchema, mcp_tool};
-use rust_mcp_sdk::schema::TextContent;
 use rust_mcp_sdk::schema::{CallToolResult, schema_utils::CallToolError};
+use serde_json::json;
 
-use crate::fs_service::FileSystemService;
+use super::output::tool_result;
+use crate::fs_service::{FileSystemService, utils::OutputFormat};
+use crate::i18n::Message;
 
 #[mcp_tool(
     name = "create_directory",
@@ -13,6 +15,7 @@ use crate::fs_service::FileSystemService;
     "Can create multiple nested directories in one operation. ",
     "If the directory already exists, this operation will succeed silently. ",
     "Perfect for setting up directory structures for projects or ensuring required paths exist. ",
+    "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
     "Only works within allowed directories."),
     destructive_hint = false,
     idempotent_hint = false,
@@ -28,6 +31,12 @@ use crate::fs_service::FileSystemService;
 pub struct CreateDirectory {
     /// The path where the directory will be created.
     pub path: String,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+    /// Optional client-generated key identifying this operation. When a request is retried with the same key,
+    /// the server returns the original result instead of executing the operation again.
+    pub idempotency_key: Option<String>,
 }
 
 impl CreateDirectory {
@@ -40,8 +49,10 @@ impl CreateDirectory {
             .await
             .map_err(CallToolError::new)?;
 
-        Ok(CallToolResult::text_content(vec![TextContent::from(
-            format!("Successfully created directory {}", &params.path),
-        )]))
+        tool_result(
+            params.output_format,
+            &json!({ "path": params.path }),
+            || Ok(Message::DirectoryCreated { path: &params.path }.localize(context.locale())),
+        )
     }
 }
@@ -0,0 +1,84 @@
+use rust_mcp_sdk::{
+    macros::{JsonSchema, mcp_tool},
+    schema::{CallToolResult, schema_utils::CallToolError},
+};
+use std::fmt::Write;
+
+use super::output::tool_result;
+use crate::fs_service::{
+    FileChangeStatus, FileSystemService, WorkspaceFileChange, utils::OutputFormat,
+};
+
+#[mcp_tool(
+    name = "diff_workspace",
+    title="Diff workspace",
+    description = concat!("Shows every file the pending changes of the overlay add, modify or delete, with a git-style diff of each text file. ",
+    "A moved file appears deleted from its origin and added to its destination. ",
+    "Use this to review everything done in the session before applying it with `commit_overlay`. ",
+    "Only available when the server runs with an overlay directory or in simulation mode. ",
+    "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text)."),
+    destructive_hint = false,
+    idempotent_hint = true,
+    open_world_hint = false,
+    read_only_hint = true
+)]
+#[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, JsonSchema)]
+pub struct DiffWorkspace {
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+}
+
+impl DiffWorkspace {
+    pub async fn run_tool(
+        params: Self,
+        context: &FileSystemService,
+    ) -> std::result::Result<CallToolResult, CallToolError> {
+        let file_changes = context.diff_overlay().await.map_err(CallToolError::new)?;
+
+        tool_result(params.output_format, &file_changes, || {
+            Self::format_text(&file_changes)
+        })
+    }
+
+    fn format_text(
+        file_changes: &[WorkspaceFileChange],
+    ) -> std::result::Result<String, CallToolError> {
+        if file_changes.is_empty() {
+            return Ok("The overlay has no pending file changes.".to_string());
+        }
+        let count = |status| {
+            file_changes
+                .iter()
+                .filter(|file_change| file_change.status == status)
+                .count()
+        };
+        let mut output = format!(
+            "{} {} changed ({} added, {} modified, {} deleted):\n",
+            file_changes.len(),
+            if file_changes.len() == 1 {
+                "file"
+            } else {
+                "files"
+            },
+            count(FileChangeStatus::Added),
+            count(FileChangeStatus::Modified),
+            count(FileChangeStatus::Deleted)
+        );
+        for file_change in file_changes {
+            writeln!(
+                output,
+                "\n{} {}",
+                file_change.status,
+                file_change.path.display()
+            )
+            .map_err(CallToolError::new)?;
+            match &file_change.diff {
+                Some(diff) => writeln!(output, "{diff}"),
+                None => writeln!(output, "(binary file)"),
+            }
+            .map_err(CallToolError::new)?;
+        }
+        Ok(output)
+    }
+}
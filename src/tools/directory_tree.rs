@@ -1,11 +1,23 @@
 use rust_mcp_sdk::macros::{JsonSchema, mcp_tool};
-use rust_mcp_sdk::schema::TextContent;
 use rust_mcp_sdk::schema::{CallToolResult, schema_utils::CallToolError};
 use serde_json::{Map, Value, json};
+use std::fmt::Write;
+use std::path::Path;
 
+use super::output::{Audience, annotated_text};
 use crate::error::ServiceError;
 use crate::fs_service::FileSystemService;
 
+#[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, JsonSchema)]
+pub enum TreeOutputFormat {
+    #[serde(rename = "json")]
+    Json,
+    #[serde(rename = "mermaid")]
+    Mermaid,
+    #[serde(rename = "dot")]
+    Dot,
+}
+
 #[mcp_tool(
     name = "directory_tree",
     title= "Directory tree",
@@ -14,7 +26,9 @@ use crate::fs_service::FileSystemService;
     "Files have no children array, while directories always have a children array (which may be empty). ",
     "If the 'max_depth' parameter is provided, the traversal will be limited to the specified depth. ",
     "As a result, the returned directory structure may be incomplete or provide a skewed representation of the full directory tree, since deeper-level files and subdirectories beyond the specified depth will be excluded. ",
-    "The output is formatted with 2-space indentation for readability. Only works within allowed directories."),
+    "The optional 'max_entries' parameter caps the total number of files and directories included in the output. ",
+    "The 'output_format' argument accepts `json` (default), `mermaid` or `dot` to return a diagram definition of the structure instead. ",
+    "The JSON output is formatted with 2-space indentation for readability. Only works within allowed directories."),
     destructive_hint = false,
     idempotent_hint = false,
     open_world_hint = false,
@@ -31,8 +45,90 @@ pub struct DirectoryTree {
     pub path: String,
     /// Limits the depth of directory traversal
     pub max_depth: Option<u64>,
+    /// Limits the total number of entries (files and directories) included in the tree.
+    pub max_entries: Option<u64>,
+    /// Specify the output format, accepts `json`, `mermaid` or `dot` (default: json).
+    #[json_schema(default = "json")]
+    pub output_format: Option<TreeOutputFormat>,
 }
 impl DirectoryTree {
+    fn root_label(path: &str) -> String {
+        Path::new(path)
+            .file_name()
+            .map(|name| name.to_string_lossy().into_owned())
+            .unwrap_or(path.to_string())
+    }
+
+    fn entry_label(entry: &Value) -> &str {
+        entry.get("name").and_then(|v| v.as_str()).unwrap_or("")
+    }
+
+    fn is_directory(entry: &Value) -> bool {
+        entry.get("type").and_then(|v| v.as_str()) == Some("directory")
+    }
+
+    /// Renders the tree as a mermaid flowchart definition.
+    fn to_mermaid(root_label: &str, entries: &Value) -> String {
+        fn walk(parent_id: usize, entries: &Value, next_id: &mut usize, output: &mut String) {
+            for entry in entries.as_array().into_iter().flatten() {
+                let id = *next_id;
+                *next_id += 1;
+                let label = DirectoryTree::entry_label(entry).replace('"', "#quot;");
+                let _ = if DirectoryTree::is_directory(entry) {
+                    writeln!(output, "    n{parent_id} --> n{id}[\"{label}/\"]")
+                } else {
+                    writeln!(output, "    n{parent_id} --> n{id}(\"{label}\")")
+                };
+                if let Some(children) = entry.get("children") {
+                    walk(id, children, next_id, output);
+                }
+            }
+        }
+
+        let mut output = String::from("graph TD\n");
+        let _ = writeln!(output, "    n0[\"{}/\"]", root_label.replace('"', "#quot;"));
+        walk(0, entries, &mut 1, &mut output);
+        output
+    }
+
+    /// Renders the tree as a graphviz (DOT) digraph definition.
+    fn to_dot(root_label: &str, entries: &Value) -> String {
+        fn escape(label: &str) -> String {
+            label.replace('\\', "\\\\").replace('"', "\\\"")
+        }
+
+        fn walk(parent_id: usize, entries: &Value, next_id: &mut usize, output: &mut String) {
+            for entry in entries.as_array().into_iter().flatten() {
+                let id = *next_id;
+                *next_id += 1;
+                let shape = if DirectoryTree::is_directory(entry) {
+                    "folder"
+                } else {
+                    "note"
+                };
+                let _ = writeln!(
+                    output,
+                    "    n{id} [label=\"{}\", shape={shape}];",
+                    escape(DirectoryTree::entry_label(entry))
+                );
+                let _ = writeln!(output, "    n{parent_id} -> n{id};");
+                if let Some(children) = entry.get("children") {
+                    walk(id, children, next_id, output);
+                }
+            }
+        }
+
+        let mut output = String::from("digraph tree {\n    rankdir=LR;\n");
+        let _ = writeln!(
+            output,
+            "    n0 [label=\"{}\", shape=folder];",
+            escape(root_label)
+        );
+        walk(0, entries, &mut 1, &mut output);
+        output.push_str("}\n");
+        output
+    }
+
     pub async fn run_tool(
         params: Self,
         context: &FileSystemService,
@@ -41,11 +137,12 @@ impl DirectoryTree {
 
         let allowed_directories = context.allowed_directories().await;
 
+        let max_entries = params.max_entries.map(|v| v as usize);
         let (entries, reached_max_depth) = context
             .directory_tree(
-                params.path,
+                &params.path,
                 params.max_depth.map(|v| v as usize),
-                None,
+                max_entries,
                 &mut entry_counter,
                 allowed_directories,
             )
@@ -57,23 +154,40 @@ impl DirectoryTree {
             )));
         }
 
-        let json_str = serde_json::to_string_pretty(&json!(entries)).map_err(CallToolError::new)?;
+        let output_format = params.output_format.unwrap_or(TreeOutputFormat::Json);
+        let output = match output_format {
+            TreeOutputFormat::Json => {
+                serde_json::to_string_pretty(&json!(entries)).map_err(CallToolError::new)?
+            }
+            TreeOutputFormat::Mermaid => {
+                Self::to_mermaid(&Self::root_label(&params.path), &entries)
+            }
+            TreeOutputFormat::Dot => Self::to_dot(&Self::root_label(&params.path), &entries),
+        };
 
-        // Include meta flag to denote that max depth was hit; some files and directories might be omitted
-        let meta = if reached_max_depth {
-            let mut meta = Map::new();
-            meta.insert(
-                "warning".to_string(),
-                Value::String(
-                    "Incomplete listing: subdirectories beyond the maximum depth were skipped."
-                        .to_string(),
-                ),
-            );
-            Some(meta)
-        } else {
-            None
+        let reached_max_entries = max_entries.is_some_and(|max| entry_counter > max);
+
+        // Include meta flag to denote that max depth or max entries was hit; some files and directories might be omitted
+        let warning = match (reached_max_depth, reached_max_entries) {
+            (true, _) => {
+                Some("Incomplete listing: subdirectories beyond the maximum depth were skipped.")
+            }
+            (false, true) => Some(
+                "Incomplete listing: entries beyond the maximum number of entries were skipped.",
+            ),
+            (false, false) => None,
         };
+        let meta = warning.map(|warning| {
+            let mut meta = Map::new();
+            meta.insert("warning".to_string(), Value::String(warning.to_string()));
+            meta
+        });
 
-        Ok(CallToolResult::text_content(vec![TextContent::from(json_str)]).with_meta(meta))
+        // diagrams are meant to be rendered
+        let audience = match output_format {
+            TreeOutputFormat::Json => Audience::Assistant,
+            TreeOutputFormat::Mermaid | TreeOutputFormat::Dot => Audience::Everyone,
+        };
+        Ok(CallToolResult::text_content(vec![annotated_text(output, audience)]).with_meta(meta))
     }
 }
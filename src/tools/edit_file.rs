@@ -1,10 +1,11 @@
 use std::path::Path;
 
 use rust_mcp_sdk::macros::{JsonSchema, mcp_tool};
-use rust_mcp_sdk::schema::TextContent;
 use rust_mcp_sdk::schema::{CallToolResult, schema_utils::CallToolError};
+use serde_json::{Map, Value};
 
-use crate::fs_service::FileSystemService;
+use super::output::tool_result;
+use crate::fs_service::{FileSystemService, utils::OutputFormat};
 
 #[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, JsonSchema)]
 /// Represents a text replacement operation.
@@ -23,6 +24,12 @@ pub struct EditOperation {
     description = concat!("Make line-based edits to a text file. ",
     "Each edit replaces exact line sequences with new content. ",
     "Returns a git-style diff showing the changes made. ",
+    "Optional `expected_anchors` are line anchors returned by `read_file_lines`, ",
+    "the edit is rejected if any of these lines has changed since it was read. ",
+    "The new text follows the indentation and trailing whitespace settings of the `.editorconfig` files, unless the server disables it. ",
+    "The result includes a warning when the file appears to be in use: locked by another process or modified by it within the last few seconds. ",
+    "Unless it is a dry run, the structured result includes the SHA-256 and byte count of the final on-disk content. ",
+    "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
     "Only works within allowed directories."),
     destructive_hint = false,
     idempotent_hint = false,
@@ -59,6 +66,15 @@ pub struct EditFile {
         skip_serializing_if = "std::option::Option::is_none"
     )]
     pub replace_all: Option<bool>,
+    /// Optional line anchors (`<line number>:<hash>`) returned by `read_file_lines`.
+    /// The edit fails if any of these lines has changed since it was read.
+    pub expected_anchors: Option<Vec<String>>,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+    /// Optional client-generated key identifying this operation. When a request is retried with the same key,
+    /// the server returns the original result instead of executing the operation again.
+    pub idempotency_key: Option<String>,
 }
 
 impl EditFile {
@@ -66,6 +82,19 @@ impl EditFile {
         params: Self,
         context: &FileSystemService,
     ) -> std::result::Result<CallToolResult, CallToolError> {
+        if let Some(anchors) = &params.expected_anchors {
+            context
+                .verify_line_anchors(Path::new(&params.path), anchors)
+                .await
+                .map_err(CallToolError::new)?;
+        }
+
+        let warning = context
+            .file_lock_state(Path::new(&params.path))
+            .await
+            .ok()
+            .and_then(|lock_state| lock_state.warning());
+
         let diff = context
             .apply_file_edits(
                 Path::new(&params.path),
@@ -77,6 +106,36 @@ impl EditFile {
             .await
             .map_err(CallToolError::new)?;
 
-        Ok(CallToolResult::text_content(vec![TextContent::from(diff)]))
+        let mut structured_content = Map::new();
+        structured_content.insert("path".to_string(), Value::String(params.path.clone()));
+
+        let dry_run = params.dry_run.unwrap_or(false);
+        if !dry_run {
+            let digest = context
+                .file_digest(Path::new(&params.path))
+                .await
+                .map_err(CallToolError::new)?;
+            structured_content.insert("sha256".to_string(), Value::String(digest.sha256));
+            structured_content.insert("bytes".to_string(), Value::from(digest.bytes));
+        }
+
+        let mut output = structured_content.clone();
+        output.insert("diff".to_string(), Value::String(diff.clone()));
+        if let Some(warning) = &warning {
+            output.insert("warning".to_string(), Value::String(warning.clone()));
+        }
+
+        let result = tool_result(params.output_format, &output, || {
+            Ok(match warning {
+                Some(warning) => format!("Warning: {warning}\n{diff}"),
+                None => diff,
+            })
+        })?;
+        // unless it is a dry run, text results carry the checksum as structured content
+        Ok(match params.output_format {
+            Some(OutputFormat::Json) => result,
+            _ if dry_run => result,
+            _ => result.with_structured_content(structured_content),
+        })
     }
 }
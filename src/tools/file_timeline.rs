@@ -0,0 +1,115 @@
+use rust_mcp_sdk::{
+    macros::{JsonSchema, mcp_tool},
+    schema::{CallToolResult, schema_utils::CallToolError},
+};
+use serde::Serialize;
+use std::fmt::Write;
+use std::path::Path;
+
+use super::output::tool_result;
+use crate::fs_service::{
+    FileSystemService, TimelineBucket,
+    utils::{FileTimestamp, OutputFormat, TimelineInterval, format_bytes},
+};
+
+#[mcp_tool(
+    name = "file_timeline",
+    title="File timeline",
+    description = concat!("Groups the files under a directory by the day, week or month they were last modified (or created) ",
+    "and returns the number of files and total bytes of each period, in chronological order. ",
+    "Useful to find out when a project actually changed without retrieving the metadata of every file. ",
+    "The `interval` argument accepts `day`, `week` or `month` (default: month), weeks start on Monday. ",
+    "The `timestamp` argument accepts `modified`, `created` or `accessed` (default: modified). ",
+    "Periods without files are omitted. Dates are in the local time zone of the server. ",
+    "Optional `pattern` argument can be used to narrow down the search to specific glob pattern. ",
+    "Optional `exclude_patterns` can be used to exclude certain files matching a glob. ",
+    "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
+    "Only works within allowed directories."),
+    destructive_hint = false,
+    idempotent_hint = false,
+    open_world_hint = false,
+    read_only_hint = true
+)]
+#[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, JsonSchema)]
+pub struct FileTimeline {
+    /// The root directory path to start the search.
+    pub root_path: String,
+    /// Length of the periods, accepts `day`, `week` or `month` (default: month).
+    #[json_schema(default = "month")]
+    pub interval: Option<TimelineInterval>,
+    /// The timestamp files are grouped by, accepts `modified`, `created` or `accessed` (default: modified).
+    #[json_schema(default = "modified")]
+    pub timestamp: Option<FileTimestamp>,
+    /// Optional glob pattern can be used to match target files.
+    pub pattern: Option<String>,
+    /// Optional list of glob patterns to exclude from the search. File matching these patterns will be ignored.
+    pub exclude_patterns: Option<Vec<String>>,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+}
+
+#[derive(Serialize, Debug)]
+struct TimelineOutput {
+    interval: TimelineInterval,
+    timestamp: FileTimestamp,
+    periods: Vec<TimelineBucket>,
+    total_files: usize,
+    total_bytes: u64,
+}
+
+impl FileTimeline {
+    fn format_text(output: &TimelineOutput) -> std::result::Result<String, CallToolError> {
+        if output.periods.is_empty() {
+            return Ok("No files were found.".to_string());
+        }
+
+        let mut text = String::new();
+        writeln!(
+            text,
+            "{} files, {} in total:",
+            output.total_files,
+            format_bytes(output.total_bytes)
+        )
+        .map_err(CallToolError::new)?;
+        for period in &output.periods {
+            writeln!(
+                text,
+                "  {:<10} {:>6} files {:>10}",
+                period.period,
+                period.files,
+                format_bytes(period.bytes)
+            )
+            .map_err(CallToolError::new)?;
+        }
+        Ok(text)
+    }
+
+    pub async fn run_tool(
+        params: Self,
+        context: &FileSystemService,
+    ) -> std::result::Result<CallToolResult, CallToolError> {
+        let interval = params.interval.unwrap_or_default();
+        let timestamp = params.timestamp.unwrap_or_default();
+        let periods = context
+            .file_timeline(
+                Path::new(&params.root_path),
+                params.pattern,
+                params.exclude_patterns,
+                timestamp,
+                interval,
+            )
+            .await
+            .map_err(CallToolError::new)?;
+
+        let output = TimelineOutput {
+            interval,
+            timestamp,
+            total_files: periods.iter().map(|period| period.files).sum(),
+            total_bytes: periods.iter().map(|period| period.bytes).sum(),
+            periods,
+        };
+
+        tool_result(params.output_format, &output, || Self::format_text(&output))
+    }
+}
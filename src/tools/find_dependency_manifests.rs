@@ -0,0 +1,107 @@
+use rust_mcp_sdk::{
+    macros::{JsonSchema, mcp_tool},
+    schema::{CallToolResult, schema_utils::CallToolError},
+};
+use serde::Serialize;
+use std::fmt::Write;
+use std::path::Path;
+
+use super::output::tool_result;
+use crate::fs_service::{EcosystemManifests, FileSystemService, utils::OutputFormat};
+
+const DEFAULT_MAX_BYTES: u64 = 16384;
+
+#[mcp_tool(
+    name = "find_dependency_manifests",
+    title="Find dependency manifests",
+    description = concat!("Finds the dependency manifests under a directory and returns their content grouped by ecosystem: ",
+    "`Cargo.toml` for rust, `package.json` for node, `requirements.txt` and `pyproject.toml` for python, and `go.mod` for go. ",
+    "Manifests of installed packages within `node_modules` are skipped. ",
+    "The content of each manifest is limited to 'max_bytes' bytes, longer manifests are cut at a line boundary. ",
+    "Use this to map the projects and dependencies of a repository in a single call. ",
+    "Optional `exclude_patterns` can be used to exclude certain files matching a glob. ",
+    "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
+    "Only works within allowed directories."),
+    destructive_hint = false,
+    idempotent_hint = true,
+    open_world_hint = false,
+    read_only_hint = true
+)]
+#[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, JsonSchema)]
+pub struct FindDependencyManifests {
+    /// The root directory path to start the search.
+    pub root_path: String,
+    /// Optional list of glob patterns to exclude from the search. File matching these patterns will be ignored.
+    pub exclude_patterns: Option<Vec<String>>,
+    /// Maximum number of bytes of content returned for each manifest (default: 16384).
+    #[json_schema(default = "16384")]
+    pub max_bytes: Option<u64>,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+}
+
+#[derive(Serialize, Debug)]
+struct ManifestsOutput {
+    ecosystems: Vec<EcosystemManifests>,
+}
+
+impl FindDependencyManifests {
+    fn format_text(groups: &[EcosystemManifests]) -> std::result::Result<String, CallToolError> {
+        if groups.is_empty() {
+            return Ok("No dependency manifests were found.".to_string());
+        }
+
+        let mut output = String::new();
+        for group in groups {
+            writeln!(
+                output,
+                "## {} ({} {})",
+                group.ecosystem,
+                group.manifests.len(),
+                if group.manifests.len() == 1 {
+                    "manifest"
+                } else {
+                    "manifests"
+                }
+            )
+            .map_err(CallToolError::new)?;
+            for manifest in &group.manifests {
+                let shown = if manifest.truncated {
+                    format!(", {} shown", manifest.content.len())
+                } else {
+                    String::new()
+                };
+                writeln!(
+                    output,
+                    "\n{} ({} bytes{shown}):\n{}",
+                    manifest.path.display(),
+                    manifest.size,
+                    manifest.content.trim_end()
+                )
+                .map_err(CallToolError::new)?;
+            }
+            writeln!(output).map_err(CallToolError::new)?;
+        }
+        Ok(output)
+    }
+
+    pub async fn run_tool(
+        params: Self,
+        context: &FileSystemService,
+    ) -> std::result::Result<CallToolResult, CallToolError> {
+        let ecosystems = context
+            .find_dependency_manifests(
+                Path::new(&params.root_path),
+                params.exclude_patterns,
+                params.max_bytes.unwrap_or(DEFAULT_MAX_BYTES) as usize,
+            )
+            .await
+            .map_err(CallToolError::new)?;
+
+        let output = ManifestsOutput { ecosystems };
+        tool_result(params.output_format, &output, || {
+            Self::format_text(&output.ecosystems)
+        })
+    }
+}
@@ -1,10 +1,15 @@
-use crate::fs_service::{FileSystemService, utils::OutputFormat};
+use super::output::tool_result;
+use crate::fs_service::{
+    FileSystemService,
+    utils::{OutputFormat, format_system_time, matches_exclude_patterns},
+};
 use rust_mcp_sdk::{
     macros::{JsonSchema, mcp_tool},
-    schema::{CallToolResult, TextContent, schema_utils::CallToolError},
+    schema::{CallToolResult, schema_utils::CallToolError},
 };
+use serde::Serialize;
+use std::fmt::Write;
 use std::path::Path;
-use std::{collections::BTreeMap, fmt::Write};
 
 #[mcp_tool(
     name = "find_duplicate_files",
@@ -13,7 +18,11 @@ use std::{collections::BTreeMap, fmt::Write};
     "Optional `pattern` argument can be used to narrow down the file search to specific glob pattern.",
     "Optional `exclude_patterns` can be used to exclude certain files matching a glob.",
     "`min_bytes` and `max_bytes` are optional arguments that can be used to restrict the search to files with sizes within a specified range.",
-    "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text).",
+    "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
+    "Groups are numbered and sorted by reclaimable space, largest first. ",
+    "Optional `prefer_keep_paths` globs mark the copy to keep in each group, e.g. `Photos/` to keep originals and remove copies found elsewhere. ",
+    "Without a matching copy the first path of the group is kept. ",
+    "In `json` format each group reports its file count, the size and modification time of each file, and the bytes wasted by the extra copies. ",
     "Only works within allowed directories."),
     destructive_hint = false,
     idempotent_hint = false,
@@ -41,45 +50,127 @@ pub struct FindDuplicateFiles {
     /// Specify the output format, accepts either `text` or `json` (default: text).
     #[json_schema(default = "text")]
     pub output_format: Option<OutputFormat>,
+    /// Optional list of glob patterns for paths holding the canonical copies. In each group the first file
+    /// matching one of them is marked to keep and the other copies to remove.
+    pub prefer_keep_paths: Option<Vec<String>>,
+}
+
+#[derive(Serialize, Debug)]
+struct DuplicateFile {
+    path: String,
+    size: u64,
+    modified: Option<String>,
+    /// Whether this is the copy to keep, the other copies of the group can be removed.
+    keep: bool,
+}
+
+#[derive(Serialize, Debug)]
+struct DuplicateGroup {
+    /// 1-based position of the group in the sorted output.
+    id: usize,
+    file_count: usize,
+    /// Bytes that can be reclaimed by keeping a single copy.
+    wasted_bytes: u64,
+    files: Vec<DuplicateFile>,
+}
+
+#[derive(Serialize, Debug)]
+struct DuplicateFilesOutput {
+    groups: Vec<DuplicateGroup>,
+    total_wasted_bytes: u64,
 }
 
 impl FindDuplicateFiles {
-    fn format_output(
+    /// Builds the duplicate groups, sorted by reclaimable space descending.
+    /// Ties are ordered by the first path so the output is stable between runs.
+    fn build_groups(
         duplicate_files: Vec<Vec<String>>,
-        output_format: OutputFormat,
-    ) -> std::result::Result<String, CallToolError> {
-        match output_format {
-            OutputFormat::Text => {
-                let mut output = String::new();
+        root_path: &Path,
+        prefer_keep_paths: &[String],
+    ) -> Vec<DuplicateGroup> {
+        let mut groups: Vec<DuplicateGroup> = duplicate_files
+            .into_iter()
+            .map(|mut paths| {
+                paths.sort();
+                let keep_index = paths
+                    .iter()
+                    .position(|path| {
+                        let path = Path::new(path);
+                        let relative_path = path.strip_prefix(root_path).unwrap_or(path);
+                        matches_exclude_patterns(relative_path, prefer_keep_paths)
+                    })
+                    .unwrap_or(0);
+                let files: Vec<DuplicateFile> = paths
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, path)| {
+                        let metadata = std::fs::metadata(&path).ok();
+                        DuplicateFile {
+                            size: metadata.as_ref().map_or(0, |m| m.len()),
+                            modified: metadata
+                                .and_then(|m| m.modified().ok())
+                                .map(format_system_time),
+                            keep: i == keep_index,
+                            path,
+                        }
+                    })
+                    .collect();
+                // all files of a group share the same content, hence the same size
+                let size = files.first().map_or(0, |file| file.size);
+                DuplicateGroup {
+                    id: 0,
+                    file_count: files.len(),
+                    wasted_bytes: size * (files.len() as u64).saturating_sub(1),
+                    files,
+                }
+            })
+            .collect();
 
-                let header = if duplicate_files.is_empty() {
-                    "No duplicate files were found.".to_string()
-                } else {
-                    format!("Found {} sets of duplicate files:\n", duplicate_files.len(),)
-                };
-                output.push_str(&header);
+        groups.sort_by(|a, b| {
+            b.wasted_bytes
+                .cmp(&a.wasted_bytes)
+                .then_with(|| a.files[0].path.cmp(&b.files[0].path))
+        });
+        for (i, group) in groups.iter_mut().enumerate() {
+            group.id = i + 1;
+        }
+        groups
+    }
 
-                for (i, group) in duplicate_files.iter().enumerate() {
-                    writeln!(output, "\nDuplicated Group {}:", i + 1)
-                        .map_err(CallToolError::new)?;
-                    for file in group {
-                        writeln!(output, "  {file}").map_err(CallToolError::new)?;
-                    }
-                }
-                Ok(output)
-            }
-            OutputFormat::Json => {
-                // Use a map to hold string keys and array values
-                let mut map = BTreeMap::new();
+    fn format_text(
+        output: &DuplicateFilesOutput,
+        show_actions: bool,
+    ) -> std::result::Result<String, CallToolError> {
+        let mut text = String::new();
 
-                for (i, group) in duplicate_files.into_iter().enumerate() {
-                    map.insert(i.to_string(), group);
-                }
+        let header = if output.groups.is_empty() {
+            "No duplicate files were found.".to_string()
+        } else {
+            format!(
+                "Found {} sets of duplicate files ({} bytes reclaimable):\n",
+                output.groups.len(),
+                output.total_wasted_bytes
+            )
+        };
+        text.push_str(&header);
 
-                // Serialize the map to a pretty JSON string
-                Ok(serde_json::to_string_pretty(&map).map_err(CallToolError::new)?)
+        for group in &output.groups {
+            writeln!(
+                text,
+                "\nDuplicated Group {} ({} bytes reclaimable):",
+                group.id, group.wasted_bytes
+            )
+            .map_err(CallToolError::new)?;
+            for file in &group.files {
+                if show_actions {
+                    let action = if file.keep { "keep" } else { "remove" };
+                    writeln!(text, "  [{action}] {}", file.path).map_err(CallToolError::new)?;
+                } else {
+                    writeln!(text, "  {}", file.path).map_err(CallToolError::new)?;
+                }
             }
         }
+        Ok(text)
     }
 
     pub async fn run_tool(
@@ -97,14 +188,19 @@ impl FindDuplicateFiles {
             .await
             .map_err(CallToolError::new)?;
 
-        let result_content = Self::format_output(
+        let prefer_keep_paths = params.prefer_keep_paths.unwrap_or_default();
+        let groups = Self::build_groups(
             duplicate_files,
-            params.output_format.unwrap_or(OutputFormat::Text),
-        )
-        .map_err(CallToolError::new)?;
+            Path::new(&params.root_path),
+            &prefer_keep_paths,
+        );
+        let output = DuplicateFilesOutput {
+            total_wasted_bytes: groups.iter().map(|group| group.wasted_bytes).sum(),
+            groups,
+        };
 
-        Ok(CallToolResult::text_content(vec![TextContent::from(
-            result_content,
-        )]))
+        tool_result(params.output_format, &output, || {
+            Self::format_text(&output, !prefer_keep_paths.is_empty())
+        })
     }
 }
@@ -1,10 +1,11 @@
 use rust_mcp_sdk::{
     macros::{JsonSchema, mcp_tool},
-    schema::{CallToolResult, TextContent, schema_utils::CallToolError},
+    schema::{CallToolResult, schema_utils::CallToolError},
 };
 use std::fmt::Write;
 use std::path::Path;
 
+use super::output::tool_result;
 use crate::fs_service::{FileSystemService, utils::OutputFormat};
 
 // find_empty_directories
@@ -46,47 +47,30 @@ impl FindEmptyDirectories {
             .await
             .map_err(CallToolError::new)?;
 
-        let content =
-            Self::format_output(result, params.output_format.unwrap_or(OutputFormat::Text))
-                .map_err(CallToolError::new)?;
-
-        Ok(CallToolResult::text_content(vec![TextContent::from(
-            content,
-        )]))
+        tool_result(params.output_format, &result, || Self::format_text(&result))
     }
 
-    fn format_output(
-        empty_dirs: Vec<String>,
-        output_format: OutputFormat,
-    ) -> std::result::Result<String, CallToolError> {
-        let output = match output_format {
-            OutputFormat::Text => {
-                let mut output = String::new();
+    fn format_text(empty_dirs: &[String]) -> std::result::Result<String, CallToolError> {
+        let mut output = String::new();
 
-                let header = if empty_dirs.is_empty() {
-                    "No empty directories were found.".to_string()
+        let header = if empty_dirs.is_empty() {
+            "No empty directories were found.".to_string()
+        } else {
+            format!(
+                "Found {} empty {}:\n",
+                empty_dirs.len(),
+                (if empty_dirs.len() == 1 {
+                    "directory"
                 } else {
-                    format!(
-                        "Found {} empty {}:\n",
-                        empty_dirs.len(),
-                        (if empty_dirs.len() == 1 {
-                            "directory"
-                        } else {
-                            "directories"
-                        }),
-                    )
-                };
-                output.push_str(&header);
-
-                for dir in empty_dirs {
-                    writeln!(output, "  {dir}").map_err(CallToolError::new)?;
-                }
-                output
-            }
-            OutputFormat::Json => {
-                serde_json::to_string_pretty(&empty_dirs).map_err(CallToolError::new)?
-            }
+                    "directories"
+                }),
+            )
         };
+        output.push_str(&header);
+
+        for dir in empty_dirs {
+            writeln!(output, "  {dir}").map_err(CallToolError::new)?;
+        }
 
         Ok(output)
     }
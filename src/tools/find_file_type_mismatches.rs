@@ -0,0 +1,94 @@
+use rust_mcp_sdk::{
+    macros::{JsonSchema, mcp_tool},
+    schema::{CallToolResult, schema_utils::CallToolError},
+};
+use std::fmt::Write;
+use std::path::Path;
+
+use super::output::tool_result;
+use crate::fs_service::{FileSystemService, FileTypeMismatch, utils::OutputFormat};
+
+#[mcp_tool(
+    name = "find_file_type_mismatches",
+    title="Find file type mismatches",
+    description = concat!("Scans a directory recursively and reports files whose content (magic bytes) does not match their extension, ",
+    "for example a `.jpg` that is actually an HTML error page or a `.zip` that is actually an executable. ",
+    "Useful for validating downloads and spotting suspicious content in shared directories. ",
+    "Optional `pattern` argument can be used to narrow down the scan to specific glob pattern.",
+    "Optional `exclude_patterns` can be used to exclude certain files matching a glob.",
+    "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text).",
+    "Only works within allowed directories."),
+    destructive_hint = false,
+    idempotent_hint = false,
+    open_world_hint = false,
+    read_only_hint = true
+)]
+#[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, JsonSchema)]
+pub struct FindFileTypeMismatches {
+    /// The root directory path to start the scan.
+    pub root_path: String,
+    /// Optional glob pattern can be used to match target files.
+    pub pattern: Option<String>,
+    /// Optional list of glob patterns to exclude from the scan. File matching these patterns will be ignored.
+    pub exclude_patterns: Option<Vec<String>>,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+}
+
+impl FindFileTypeMismatches {
+    fn format_text(mismatches: &[FileTypeMismatch]) -> std::result::Result<String, CallToolError> {
+        let mut output = String::new();
+
+        if mismatches.is_empty() {
+            output.push_str("No file type mismatches were found.");
+        } else {
+            writeln!(
+                output,
+                "Found {} {} with mismatched content:",
+                mismatches.len(),
+                if mismatches.len() == 1 {
+                    "file"
+                } else {
+                    "files"
+                }
+            )
+            .map_err(CallToolError::new)?;
+        }
+
+        for mismatch in mismatches {
+            writeln!(
+                output,
+                "  {} (expected {}, detected {}{})",
+                mismatch.path.display(),
+                mismatch.expected,
+                mismatch.detected,
+                mismatch
+                    .detected_mime_type
+                    .as_ref()
+                    .map(|mime| format!(": {mime}"))
+                    .unwrap_or_default()
+            )
+            .map_err(CallToolError::new)?;
+        }
+        Ok(output)
+    }
+
+    pub async fn run_tool(
+        params: Self,
+        context: &FileSystemService,
+    ) -> std::result::Result<CallToolResult, CallToolError> {
+        let mismatches = context
+            .find_file_type_mismatches(
+                Path::new(&params.root_path),
+                params.pattern,
+                params.exclude_patterns,
+            )
+            .await
+            .map_err(CallToolError::new)?;
+
+        tool_result(params.output_format, &mismatches, || {
+            Self::format_text(&mismatches)
+        })
+    }
+}
@@ -0,0 +1,143 @@
+use rust_mcp_sdk::{
+    macros::{JsonSchema, mcp_tool},
+    schema::{CallToolResult, schema_utils::CallToolError},
+};
+use serde::Serialize;
+use std::fmt::Write;
+use std::path::Path;
+
+use super::output::tool_result;
+use crate::fs_service::{
+    FileSystemService,
+    utils::{FileTimestamp, OutputFormat, format_bytes, format_system_time},
+};
+
+const DEFAULT_LIMIT: u64 = 20;
+
+#[mcp_tool(
+    name = "find_oldest_files",
+    title="Find oldest files",
+    description = concat!("Lists the files under a directory that were least recently modified or accessed, oldest first, ",
+    "along with their size and the total size of the listed files. ",
+    "Useful to decide which files can be archived, moved to cold storage or cleaned up. ",
+    "The `sort_by` argument accepts `modified`, `accessed` or `created` (default: modified), ",
+    "note that access times are not maintained on filesystems mounted with `noatime` and creation times are not available everywhere. ",
+    "`limit` sets the number of files returned (default: 20). ",
+    "Optional `pattern` argument can be used to narrow down the search to specific glob pattern. ",
+    "Optional `exclude_patterns` can be used to exclude certain files matching a glob. ",
+    "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
+    "Only works within allowed directories."),
+    destructive_hint = false,
+    idempotent_hint = false,
+    open_world_hint = false,
+    read_only_hint = true
+)]
+#[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, JsonSchema)]
+pub struct FindOldestFiles {
+    /// The root directory path to start the search.
+    pub root_path: String,
+    /// The timestamp the files are sorted by, accepts `modified`, `accessed` or `created` (default: modified).
+    #[json_schema(default = "modified")]
+    pub sort_by: Option<FileTimestamp>,
+    /// Maximum number of files to return (default: 20).
+    #[json_schema(default = "20")]
+    pub limit: Option<u64>,
+    /// Optional glob pattern can be used to match target files.
+    pub pattern: Option<String>,
+    /// Optional list of glob patterns to exclude from the search. File matching these patterns will be ignored.
+    pub exclude_patterns: Option<Vec<String>>,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+}
+
+#[derive(Serialize, Debug)]
+struct OldestFile {
+    path: String,
+    size: u64,
+    modified: Option<String>,
+    accessed: Option<String>,
+    created: Option<String>,
+}
+
+#[derive(Serialize, Debug)]
+struct OldestFilesOutput {
+    sort_by: FileTimestamp,
+    files: Vec<OldestFile>,
+    total_files: usize,
+    total_size: u64,
+}
+
+impl FindOldestFiles {
+    fn format_text(output: &OldestFilesOutput) -> std::result::Result<String, CallToolError> {
+        if output.files.is_empty() {
+            return Ok("No files were found.".to_string());
+        }
+
+        let mut text = String::new();
+        writeln!(
+            text,
+            "{} least recently {} files, {} in total:",
+            output.total_files,
+            match output.sort_by {
+                FileTimestamp::Modified => "modified",
+                FileTimestamp::Accessed => "accessed",
+                FileTimestamp::Created => "created",
+            },
+            format_bytes(output.total_size)
+        )
+        .map_err(CallToolError::new)?;
+        for file in &output.files {
+            let time = match output.sort_by {
+                FileTimestamp::Modified => &file.modified,
+                FileTimestamp::Accessed => &file.accessed,
+                FileTimestamp::Created => &file.created,
+            };
+            writeln!(
+                text,
+                "  {}  {:>10}  {}",
+                time.as_deref().unwrap_or_default(),
+                format_bytes(file.size),
+                file.path
+            )
+            .map_err(CallToolError::new)?;
+        }
+        Ok(text)
+    }
+
+    pub async fn run_tool(
+        params: Self,
+        context: &FileSystemService,
+    ) -> std::result::Result<CallToolResult, CallToolError> {
+        let sort_by = params.sort_by.unwrap_or_default();
+        let files = context
+            .find_oldest_files(
+                Path::new(&params.root_path),
+                params.pattern,
+                params.exclude_patterns,
+                sort_by,
+                params.limit.unwrap_or(DEFAULT_LIMIT) as usize,
+            )
+            .await
+            .map_err(CallToolError::new)?;
+
+        let files: Vec<OldestFile> = files
+            .into_iter()
+            .map(|file| OldestFile {
+                path: file.path.display().to_string(),
+                size: file.size,
+                modified: file.modified.map(format_system_time),
+                accessed: file.accessed.map(format_system_time),
+                created: file.created.map(format_system_time),
+            })
+            .collect();
+        let output = OldestFilesOutput {
+            sort_by,
+            total_files: files.len(),
+            total_size: files.iter().map(|file| file.size).sum(),
+            files,
+        };
+
+        tool_result(params.output_format, &output, || Self::format_text(&output))
+    }
+}
@@ -0,0 +1,81 @@
+use rust_mcp_sdk::{
+    macros::{JsonSchema, mcp_tool},
+    schema::{CallToolResult, schema_utils::CallToolError},
+};
+use std::fmt::Write;
+use std::path::Path;
+
+use super::output::tool_result;
+use crate::fs_service::{FileSystemService, PROJECT_MARKERS, ProjectRoot, utils::OutputFormat};
+
+#[mcp_tool(
+    name = "find_project_root",
+    title="Find project root",
+    description = concat!("Finds the root of the project a file or directory belongs to: walks up from the path, ",
+    "without leaving its allowed directory, to the nearest directory containing a project marker ",
+    "(`Cargo.toml` for rust, `package.json` for node, `pyproject.toml` for python or `.git`). ",
+    "Returns the project root, its kinds and markers, and the root of the git repository containing it if any. ",
+    "Use this instead of listing parent directories to locate a project. ",
+    "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
+    "Only works within allowed directories."),
+    destructive_hint = false,
+    idempotent_hint = true,
+    open_world_hint = false,
+    read_only_hint = true
+)]
+#[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, JsonSchema)]
+pub struct FindProjectRoot {
+    /// The file or directory to find the project of.
+    pub path: String,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+}
+
+impl FindProjectRoot {
+    fn format_text(project_root: &ProjectRoot) -> std::result::Result<String, CallToolError> {
+        let Some(root) = &project_root.root else {
+            let markers: Vec<&str> = PROJECT_MARKERS.iter().map(|(marker, _)| *marker).collect();
+            return Ok(format!(
+                "No project marker ({}) was found from {} up to its allowed directory.",
+                markers.join(", "),
+                project_root.path.display()
+            ));
+        };
+
+        let mut output = String::new();
+        let kinds: Vec<String> = project_root
+            .kinds
+            .iter()
+            .map(|kind| kind.to_string())
+            .collect();
+        writeln!(
+            output,
+            "project root: {} ({})",
+            root.display(),
+            kinds.join(", ")
+        )
+        .map_err(CallToolError::new)?;
+        writeln!(output, "markers: {}", project_root.markers.join(", "))
+            .map_err(CallToolError::new)?;
+        if let Some(repository_root) = &project_root.repository_root {
+            writeln!(output, "repository root: {}", repository_root.display())
+                .map_err(CallToolError::new)?;
+        }
+        Ok(output)
+    }
+
+    pub async fn run_tool(
+        params: Self,
+        context: &FileSystemService,
+    ) -> std::result::Result<CallToolResult, CallToolError> {
+        let project_root = context
+            .find_project_root(Path::new(&params.path))
+            .await
+            .map_err(CallToolError::new)?;
+
+        tool_result(params.output_format, &project_root, || {
+            Self::format_text(&project_root)
+        })
+    }
+}
@@ -0,0 +1,93 @@
+use rust_mcp_sdk::{
+    macros::{JsonSchema, mcp_tool},
+    schema::{CallToolResult, schema_utils::CallToolError},
+};
+use serde_json::json;
+use std::fmt::Write;
+use std::path::Path;
+
+use super::output::tool_result;
+use crate::fs_service::{FileSystemService, RelevantFile, utils::OutputFormat};
+
+const DEFAULT_LIMIT: u64 = 10;
+
+#[mcp_tool(
+    name = "find_relevant_files",
+    title="Find relevant files",
+    description = concat!("Finds the files most relevant to a query by searching it in both the file paths and the file contents, ",
+    "and returns them ranked by a combined score: path relevance (query terms found in the file name, or elsewhere in the path) ",
+    "plus content relevance (share of the query terms found in the content and density of the matches). ",
+    "The query is a list of terms separated by whitespace, matched case-insensitively as plain text. ",
+    "A single entry point to \"find the file about X\" without chaining search_files and search_files_content. ",
+    "Optional `pattern` argument narrows down the candidates to files matching a glob pattern (e.g., \"*.rs\"). ",
+    "Optional `exclude_patterns` can be used to exclude certain files matching a glob. ",
+    "Optional `limit` sets the maximum number of files returned (default: 10). ",
+    "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
+    "Only works within allowed directories."),
+    destructive_hint = false,
+    idempotent_hint = false,
+    open_world_hint = false,
+    read_only_hint = true
+)]
+#[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, JsonSchema)]
+pub struct FindRelevantFiles {
+    /// The root directory path to start the search.
+    pub root_path: String,
+    /// Terms to look for in the file paths and contents, separated by whitespace.
+    pub query: String,
+    /// Optional glob pattern can be used to match target files.
+    pub pattern: Option<String>,
+    /// Optional list of glob patterns to exclude from the search. File matching these patterns will be ignored.
+    pub exclude_patterns: Option<Vec<String>>,
+    /// Maximum number of files to return (default: 10).
+    #[json_schema(default = "10")]
+    pub limit: Option<u64>,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+}
+
+impl FindRelevantFiles {
+    fn format_text(files: &[RelevantFile]) -> std::result::Result<String, CallToolError> {
+        if files.is_empty() {
+            return Ok("No relevant files were found.".to_string());
+        }
+
+        let mut text = String::new();
+        for (index, file) in files.iter().enumerate() {
+            writeln!(
+                text,
+                "{}. {} (score: {:.3}, path: {:.3}, content: {:.3}, {} matches in {} lines)",
+                index + 1,
+                file.path.display(),
+                file.score,
+                file.path_score,
+                file.content_score,
+                file.matches,
+                file.lines
+            )
+            .map_err(CallToolError::new)?;
+        }
+        Ok(text)
+    }
+
+    pub async fn run_tool(
+        params: Self,
+        context: &FileSystemService,
+    ) -> std::result::Result<CallToolResult, CallToolError> {
+        let files = context
+            .find_relevant_files(
+                Path::new(&params.root_path),
+                params.pattern.as_deref().unwrap_or("*"),
+                &params.query,
+                params.exclude_patterns,
+                params.limit.unwrap_or(DEFAULT_LIMIT) as usize,
+            )
+            .await
+            .map_err(CallToolError::new)?;
+
+        tool_result(params.output_format, &json!({ "files": files }), || {
+            Self::format_text(&files)
+        })
+    }
+}
@@ -1,10 +1,12 @@
 use std::path::Path;
 
 use rust_mcp_sdk::macros::{JsonSchema, mcp_tool};
-use rust_mcp_sdk::schema::TextContent;
 use rust_mcp_sdk::schema::{CallToolResult, schema_utils::CallToolError};
+use serde_json::{Map, Value};
 
-use crate::fs_service::FileSystemService;
+use super::output::tool_result;
+use crate::fs_service::utils::{OutputFormat, format_permissions, format_system_time};
+use crate::fs_service::{FileInfo, FileSystemService, FinderMetadata};
 
 #[mcp_tool(
     name = "get_file_info",
@@ -12,8 +14,15 @@ use crate::fs_service::FileSystemService;
     description = concat!("Retrieve detailed metadata about a file or directory. ",
     "Returns comprehensive information including size, creation time, ",
     "last modified time, permissions, and type. ",
+    "On Windows, file attributes (hidden, system, readonly, reparse point, ...) and ",
+    "the effective access rights of the current user are also reported. ",
+    "On macOS, Finder tags and the quarantine attribute of downloaded files are included. ",
+    "Set `advanced` to true to also get the raw platform metadata (all `stat` fields, Windows attributes, ",
+    "extended attribute names) for debugging filesystem issues, the result is then returned in `json` format. ",
     "This tool is perfect for understanding file characteristics without ",
-    "reading the actual content. Only works within allowed directories."),
+    "reading the actual content. ",
+    "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
+    "Only works within allowed directories."),
     destructive_hint = false,
     idempotent_hint = false,
     open_world_hint = false,
@@ -28,6 +37,63 @@ use crate::fs_service::FileSystemService;
 pub struct GetFileInfo {
     /// The path of the file to get information for.
     pub path: String,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+    /// Include the raw platform metadata, implies the `json` output format (default: false).
+    #[json_schema(default = "false")]
+    pub advanced: Option<bool>,
+}
+
+#[derive(::serde::Serialize)]
+struct FileInfoOutput<'a> {
+    path: &'a str,
+    size: u64,
+    created: Option<String>,
+    modified: Option<String>,
+    accessed: Option<String>,
+    is_directory: bool,
+    is_file: bool,
+    permissions: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    attributes: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    access: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    finder_metadata: Option<&'a FinderMetadata>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    raw_metadata: Option<Map<String, Value>>,
+}
+
+impl<'a> FileInfoOutput<'a> {
+    fn new(path: &'a str, info: &'a FileInfo) -> Self {
+        #[cfg(windows)]
+        let attributes = info
+            .metadata
+            .as_ref()
+            .map(crate::fs_service::utils::format_file_attributes);
+        #[cfg(not(windows))]
+        let attributes = None;
+
+        Self {
+            path,
+            size: info.size,
+            created: info.created.map(format_system_time),
+            modified: info.modified.map(format_system_time),
+            accessed: info.accessed.map(format_system_time),
+            is_directory: info.is_directory,
+            is_file: info.is_file,
+            permissions: info
+                .metadata
+                .as_ref()
+                .map(format_permissions)
+                .unwrap_or_default(),
+            attributes,
+            access: info.access.as_deref(),
+            finder_metadata: info.finder_metadata.as_ref(),
+            raw_metadata: None,
+        }
+    }
 }
 
 impl GetFileInfo {
@@ -39,8 +105,17 @@ impl GetFileInfo {
             .get_file_stats(Path::new(&params.path))
             .await
             .map_err(CallToolError::new)?;
-        Ok(CallToolResult::text_content(vec![TextContent::from(
-            stats.to_string(),
-        )]))
+        let mut output = FileInfoOutput::new(&params.path, &stats);
+        let mut output_format = params.output_format;
+        if params.advanced.unwrap_or(false) {
+            output.raw_metadata = Some(
+                context
+                    .get_raw_metadata(Path::new(&params.path))
+                    .await
+                    .map_err(CallToolError::new)?,
+            );
+            output_format = Some(OutputFormat::Json);
+        }
+        tool_result(output_format, &output, || Ok(stats.to_string()))
     }
 }
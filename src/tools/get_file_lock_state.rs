@@ -0,0 +1,46 @@
+use std::path::Path;
+
+use rust_mcp_sdk::macros::{JsonSchema, mcp_tool};
+use rust_mcp_sdk::schema::{CallToolResult, schema_utils::CallToolError};
+
+use super::output::tool_result;
+use crate::fs_service::{FileSystemService, utils::OutputFormat};
+
+#[mcp_tool(
+    name = "get_file_lock_state",
+    title="Get file lock state",
+    description = concat!("Reports whether another process appears to be using a file: ",
+    "whether it holds a lock on it (flock on macOS and Linux, opened without sharing on Windows, with the processes holding it when they can be listed), ",
+    "and whether it modified the file within the last few seconds. ",
+    "Use this before modifying a file another program may be editing, to avoid overwriting its changes. ",
+    "The state is advisory: write_file and edit_file include the same warning in their result but still modify the file. ",
+    "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
+    "Only works within allowed directories."),
+    destructive_hint = false,
+    idempotent_hint = true,
+    open_world_hint = false,
+    read_only_hint = true
+)]
+#[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, JsonSchema)]
+pub struct GetFileLockState {
+    /// The path of the file to check.
+    pub path: String,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+}
+
+impl GetFileLockState {
+    pub async fn run_tool(
+        params: Self,
+        context: &FileSystemService,
+    ) -> std::result::Result<CallToolResult, CallToolError> {
+        let lock_state = context
+            .file_lock_state(Path::new(&params.path))
+            .await
+            .map_err(CallToolError::new)?;
+        tool_result(params.output_format, &lock_state, || {
+            Ok(lock_state.to_string())
+        })
+    }
+}
@@ -0,0 +1,43 @@
+use std::path::Path;
+
+use rust_mcp_sdk::macros::{JsonSchema, mcp_tool};
+use rust_mcp_sdk::schema::{CallToolResult, schema_utils::CallToolError};
+
+use super::output::tool_result;
+use crate::fs_service::{FileSystemService, utils::OutputFormat};
+
+#[mcp_tool(
+    name = "get_file_profile",
+    title="Get file profile",
+    description = concat!("Detects the conventions of a text file: its encoding (UTF-8, with or without BOM, or UTF-16), ",
+    "its line endings (lf, crlf or cr, and whether they are mixed), whether it ends with a final newline, ",
+    "and its indentation style (tabs or spaces, with the width of one level). ",
+    "Use this before editing or writing a file to produce content matching its style. ",
+    "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
+    "Only works within allowed directories."),
+    destructive_hint = false,
+    idempotent_hint = true,
+    open_world_hint = false,
+    read_only_hint = true
+)]
+#[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, JsonSchema)]
+pub struct GetFileProfile {
+    /// The path of the file to profile.
+    pub path: String,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+}
+
+impl GetFileProfile {
+    pub async fn run_tool(
+        params: Self,
+        context: &FileSystemService,
+    ) -> std::result::Result<CallToolResult, CallToolError> {
+        let profile = context
+            .get_file_profile(Path::new(&params.path))
+            .await
+            .map_err(CallToolError::new)?;
+        tool_result(params.output_format, &profile, || Ok(profile.to_string()))
+    }
+}
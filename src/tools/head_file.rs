@@ -2,9 +2,10 @@ use std::path::Path;
 
 use rust_mcp_sdk::{
     macros::{JsonSchema, mcp_tool},
-    schema::{CallToolResult, TextContent, schema_utils::CallToolError},
+    schema::{CallToolResult, schema_utils::CallToolError},
 };
 
+use super::output::{Audience, annotated_text};
 use crate::fs_service::FileSystemService;
 
 // head_file
@@ -43,8 +44,9 @@ impl HeadFile {
             .await
             .map_err(CallToolError::new)?;
 
-        Ok(CallToolResult::text_content(vec![TextContent::from(
+        Ok(CallToolResult::text_content(vec![annotated_text(
             result,
+            Audience::Assistant,
         )]))
     }
 }
@@ -1,8 +1,10 @@
 use rust_mcp_sdk::macros::{JsonSchema, mcp_tool};
-use rust_mcp_sdk::schema::TextContent;
 use rust_mcp_sdk::schema::{CallToolResult, schema_utils::CallToolError};
+use serde_json::json;
 
-use crate::fs_service::FileSystemService;
+use super::output::tool_result;
+use crate::fs_service::{FileSystemService, utils::OutputFormat};
+use crate::i18n::Message;
 
 #[mcp_tool(
     name = "list_allowed_directories",
@@ -10,7 +12,8 @@ use crate::fs_service::FileSystemService;
     description = concat!("Returns a list of directories that the server has permission ",
     "to access Subdirectories within these allowed directories are also accessible. ",
     "Use this to identify which directories and their nested paths are available ",
-    "before attempting to access files."),
+    "before attempting to access files. ",
+    "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text)."),
     destructive_hint = false,
     idempotent_hint = false,
     open_world_hint = false,
@@ -22,29 +25,34 @@ use crate::fs_service::FileSystemService;
     ],
 )]
 #[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, JsonSchema)]
-pub struct ListAllowedDirectories {}
+pub struct ListAllowedDirectories {
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+}
 
 impl ListAllowedDirectories {
     pub async fn run_tool(
-        _: Self,
+        params: Self,
         context: &FileSystemService,
     ) -> std::result::Result<CallToolResult, CallToolError> {
-        let allowed_directories = context.allowed_directories().await;
+        let allowed_directories: Vec<_> = context
+            .allowed_directories()
+            .await
+            .iter()
+            .map(|entry| entry.display().to_string())
+            .collect();
 
-        let result = if allowed_directories.is_empty() {
-            "Allowed directories list is empty!".to_string()
-        } else {
-            format!(
-                "Allowed directories:\n{}",
-                allowed_directories
-                    .iter()
-                    .map(|entry| entry.display().to_string())
-                    .collect::<Vec<_>>()
-                    .join("\n")
-            )
-        };
-        Ok(CallToolResult::text_content(vec![TextContent::from(
-            result,
-        )]))
+        let output = json!({ "allowed_directories": allowed_directories });
+        tool_result(params.output_format, &output, || {
+            let message = if allowed_directories.is_empty() {
+                Message::NoAllowedDirectories
+            } else {
+                Message::AllowedDirectories {
+                    directories: &allowed_directories.join("\n"),
+                }
+            };
+            Ok(message.localize(context.locale()))
+        })
     }
 }
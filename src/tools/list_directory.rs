@@ -1,10 +1,11 @@
 use std::path::Path;
 
 use rust_mcp_sdk::macros::{JsonSchema, mcp_tool};
-use rust_mcp_sdk::schema::TextContent;
 use rust_mcp_sdk::schema::{CallToolResult, schema_utils::CallToolError};
+use serde_json::json;
 
-use crate::fs_service::FileSystemService;
+use super::output::{DirectoryEntryOutput, EntryKind, tool_result};
+use crate::fs_service::{FileSystemService, utils::OutputFormat};
 
 #[mcp_tool(
     name = "list_directory",
@@ -12,7 +13,10 @@ use crate::fs_service::FileSystemService;
     description = concat!("Get a detailed listing of all files and directories in a specified path. ",
 "Results clearly distinguish between files and directories with [FILE] and [DIR] ",
 "prefixes. This tool is essential for understanding directory structure and ",
-"finding specific files within a directory. Only works within allowed directories."),
+"finding specific files within a directory. ",
+"Set 'exclude_hidden' to true to skip hidden and system files. ",
+"The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
+"Only works within allowed directories."),
     destructive_hint = false,
     idempotent_hint = false,
     open_world_hint = false,
@@ -27,6 +31,11 @@ use crate::fs_service::FileSystemService;
 pub struct ListDirectory {
     /// The path of the directory to list.
     pub path: String,
+    /// Skip hidden entries: files with the hidden or system attribute on Windows, dot-files on other platforms (default: false).
+    pub exclude_hidden: Option<bool>,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
 }
 
 impl ListDirectory {
@@ -35,27 +44,42 @@ impl ListDirectory {
         context: &FileSystemService,
     ) -> std::result::Result<CallToolResult, CallToolError> {
         let entries = context
-            .list_directory(Path::new(&params.path))
+            .list_directory(
+                Path::new(&params.path),
+                params.exclude_hidden.unwrap_or(false),
+            )
             .await
             .map_err(CallToolError::new)?;
 
-        let formatted: Vec<_> = entries
+        let entries: Vec<_> = entries
             .iter()
-            .map(|entry| {
-                format!(
-                    "{} {}",
-                    if entry.path().is_dir() {
-                        "[DIR]"
-                    } else {
-                        "[FILE]"
-                    },
-                    entry.file_name().to_str().unwrap_or_default()
-                )
+            .map(|entry| DirectoryEntryOutput {
+                name: entry.file_name().to_str().unwrap_or_default().to_string(),
+                kind: if entry.is_dir() {
+                    EntryKind::Directory
+                } else {
+                    EntryKind::File
+                },
+                size: None,
             })
             .collect();
 
-        Ok(CallToolResult::text_content(vec![TextContent::from(
-            formatted.join("\n"),
-        )]))
+        let output = json!({ "path": params.path, "entries": entries });
+        tool_result(params.output_format, &output, || {
+            let formatted: Vec<_> = entries
+                .iter()
+                .map(|entry| {
+                    format!(
+                        "{} {}",
+                        match entry.kind {
+                            EntryKind::Directory => "[DIR]",
+                            EntryKind::File => "[FILE]",
+                        },
+                        entry.name
+                    )
+                })
+                .collect();
+            Ok(formatted.join("\n"))
+        })
     }
 }
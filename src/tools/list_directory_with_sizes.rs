@@ -1,11 +1,11 @@
 use rust_mcp_sdk::macros::{JsonSchema, mcp_tool};
-use rust_mcp_sdk::schema::TextContent;
 use rust_mcp_sdk::schema::{CallToolResult, schema_utils::CallToolError};
 use std::fmt::Write;
 use std::path::Path;
 
-use crate::fs_service::FileSystemService;
-use crate::fs_service::utils::format_bytes;
+use super::output::{DirectoryEntryOutput, EntryKind, tool_result};
+use crate::fs_service::utils::{OutputFormat, format_bytes};
+use crate::fs_service::{FileSystemService, TreeEntry};
 
 #[mcp_tool(
     name = "list_directory_with_sizes",
@@ -13,7 +13,10 @@ use crate::fs_service::utils::format_bytes;
     description = concat!("Get a detailed listing of all files and directories in a specified path, including sizes. " ,
         "Results clearly distinguish between files and directories with [FILE] and [DIR] prefixes. " ,
         "This tool is useful for understanding directory structure and " ,
-        "finding specific files within a directory. Only works within allowed directories."),
+        "finding specific files within a directory. " ,
+        "Set 'exclude_hidden' to true to skip hidden and system files. ",
+        "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
+        "Only works within allowed directories."),
     destructive_hint = false,
     idempotent_hint = false,
     open_world_hint = false,
@@ -28,54 +31,96 @@ use crate::fs_service::utils::format_bytes;
 pub struct ListDirectoryWithSizes {
     /// The path of the directory to list.
     pub path: String,
+    /// Skip hidden entries: files with the hidden or system attribute on Windows, dot-files on other platforms (default: false).
+    pub exclude_hidden: Option<bool>,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+}
+
+#[derive(::serde::Serialize)]
+struct DirectoryListing {
+    path: String,
+    entries: Vec<DirectoryEntryOutput>,
+    files: usize,
+    directories: usize,
+    total_size: u64,
 }
 
 impl ListDirectoryWithSizes {
-    async fn format_directory_entries(
+    fn directory_listing(
         &self,
-        mut entries: Vec<tokio::fs::DirEntry>,
-    ) -> std::result::Result<String, CallToolError> {
-        let mut file_count = 0;
-        let mut dir_count = 0;
-        let mut total_size: u64 = 0;
-
-        // Estimate initial capacity: assume ~50 bytes per entry + summary
-        let mut output = String::with_capacity(entries.len() * 50 + 120);
+        mut entries: Vec<TreeEntry>,
+    ) -> std::result::Result<DirectoryListing, CallToolError> {
+        let mut listing = DirectoryListing {
+            path: self.path.clone(),
+            entries: Vec::with_capacity(entries.len()),
+            files: 0,
+            directories: 0,
+            total_size: 0,
+        };
 
         // Sort entries by file name
-        entries.sort_by_key(|a| a.file_name());
+        entries.sort_by(|a, b| a.file_name().cmp(b.file_name()));
 
-        // build the output string
         for entry in &entries {
-            let file_name = entry.file_name();
-            let file_name = file_name.to_string_lossy();
+            let name = entry.file_name().to_string_lossy().to_string();
+
+            if entry.is_dir() {
+                listing.entries.push(DirectoryEntryOutput {
+                    name,
+                    kind: EntryKind::Directory,
+                    size: None,
+                });
+                listing.directories += 1;
+            } else if entry.is_file() {
+                let file_size = entry.size().map_err(CallToolError::new)?;
+                listing.entries.push(DirectoryEntryOutput {
+                    name,
+                    kind: EntryKind::File,
+                    size: Some(file_size),
+                });
+                listing.files += 1;
+                listing.total_size += file_size;
+            }
+        }
 
-            if entry.path().is_dir() {
-                writeln!(output, "[DIR]  {file_name:<30}").map_err(CallToolError::new)?;
-                dir_count += 1;
-            } else if entry.path().is_file() {
-                let metadata = entry.metadata().await.map_err(CallToolError::new)?;
+        Ok(listing)
+    }
 
-                let file_size = metadata.len();
-                writeln!(
-                    output,
-                    "[FILE] {:<30} {:>10}",
-                    file_name,
-                    format_bytes(file_size)
-                )
-                .map_err(CallToolError::new)?;
-                file_count += 1;
-                total_size += file_size;
+    fn format_directory_listing(
+        listing: &DirectoryListing,
+    ) -> std::result::Result<String, CallToolError> {
+        // Estimate initial capacity: assume ~50 bytes per entry + summary
+        let mut output = String::with_capacity(listing.entries.len() * 50 + 120);
+
+        // build the output string
+        for entry in &listing.entries {
+            match entry.kind {
+                EntryKind::Directory => {
+                    writeln!(output, "[DIR]  {:<30}", entry.name).map_err(CallToolError::new)?;
+                }
+                EntryKind::File => {
+                    writeln!(
+                        output,
+                        "[FILE] {:<30} {:>10}",
+                        entry.name,
+                        format_bytes(entry.size.unwrap_or_default())
+                    )
+                    .map_err(CallToolError::new)?;
+                }
             }
         }
 
         // Append summary
         writeln!(
             output,
-            "\nTotal: {file_count} files, {dir_count} directories"
+            "\nTotal: {} files, {} directories",
+            listing.files, listing.directories
         )
         .map_err(CallToolError::new)?;
-        writeln!(output, "Total size: {}", format_bytes(total_size)).map_err(CallToolError::new)?;
+        writeln!(output, "Total size: {}", format_bytes(listing.total_size))
+            .map_err(CallToolError::new)?;
 
         Ok(output)
     }
@@ -85,16 +130,16 @@ impl ListDirectoryWithSizes {
         context: &FileSystemService,
     ) -> std::result::Result<CallToolResult, CallToolError> {
         let entries = context
-            .list_directory(Path::new(&params.path))
+            .list_directory(
+                Path::new(&params.path),
+                params.exclude_hidden.unwrap_or(false),
+            )
             .await
             .map_err(CallToolError::new)?;
 
-        let output = params
-            .format_directory_entries(entries)
-            .await
-            .map_err(CallToolError::new)?;
-        Ok(CallToolResult::text_content(vec![TextContent::from(
-            output,
-        )]))
+        let listing = params.directory_listing(entries)?;
+        tool_result(params.output_format, &listing, || {
+            Self::format_directory_listing(&listing)
+        })
     }
 }
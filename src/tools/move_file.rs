@@ -1,10 +1,12 @@
 use std::path::Path;
 
 use rust_mcp_sdk::macros::{JsonSchema, mcp_tool};
-use rust_mcp_sdk::schema::TextContent;
 use rust_mcp_sdk::schema::{CallToolResult, schema_utils::CallToolError};
+use serde_json::json;
 
-use crate::fs_service::FileSystemService;
+use super::output::tool_result;
+use crate::fs_service::{FileSystemService, utils::OutputFormat};
+use crate::i18n::Message;
 
 #[mcp_tool(
     name = "move_file",
@@ -13,6 +15,8 @@ use crate::fs_service::FileSystemService;
 "and rename them in a single operation. If the destination exists, the ",
 "operation will fail. Works across different directories and can be used ",
 "for simple renaming within the same directory. ",
+"Set 'create_parents' to true to create any missing parent directories of the destination. ",
+"The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
 "Both source and destination must be within allowed directories."),
     destructive_hint = false,
     idempotent_hint = false,
@@ -30,6 +34,14 @@ pub struct MoveFile {
     pub source: String,
     /// The destination path to move the file to.
     pub destination: String,
+    /// Create any missing parent directories of the destination (default: false).
+    pub create_parents: Option<bool>,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+    /// Optional client-generated key identifying this operation. When a request is retried with the same key,
+    /// the server returns the original result instead of executing the operation again.
+    pub idempotency_key: Option<String>,
 }
 
 impl MoveFile {
@@ -38,15 +50,21 @@ impl MoveFile {
         context: &FileSystemService,
     ) -> std::result::Result<CallToolResult, CallToolError> {
         context
-            .move_file(Path::new(&params.source), Path::new(&params.destination))
+            .move_file(
+                Path::new(&params.source),
+                Path::new(&params.destination),
+                params.create_parents.unwrap_or(false),
+            )
             .await
             .map_err(CallToolError::new)?;
 
-        Ok(CallToolResult::text_content(vec![TextContent::from(
-            format!(
-                "Successfully moved {} to {}",
-                &params.source, &params.destination
-            ),
-        )]))
+        let output = json!({ "source": params.source, "destination": params.destination });
+        tool_result(params.output_format, &output, || {
+            Ok(Message::FileMoved {
+                source: &params.source,
+                destination: &params.destination,
+            }
+            .localize(context.locale()))
+        })
     }
 }
@@ -0,0 +1,180 @@
+//! Shared formatting of tool results for the `output_format` argument.
+//!
+//! In `json` format a tool returns its result serialized as pretty-printed JSON, objects are also
+//! attached as structured content. Field names are snake_case, paths are returned as strings and
+//! sizes in bytes, so the output can be consumed without knowledge of the text format.
+//!
+//! Content blocks are annotated with their audience and priority, so clients can tell what to
+//! render from what to feed to the model: JSON output and file contents are meant for the
+//! assistant, text summaries for both the user and the assistant, and large blocks have a lower
+//! priority than small ones.
+
+use rust_mcp_sdk::schema::{
+    Annotations, CallToolResult, ContentBlock, EmbeddedResourceResource, Role, TextContent,
+    schema_utils::CallToolError,
+};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::fs_service::utils::OutputFormat;
+
+/// Kind of an entry returned by listing tools.
+#[derive(Serialize, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum EntryKind {
+    File,
+    Directory,
+}
+
+/// An entry of a directory listing.
+#[derive(Serialize, Debug)]
+pub struct DirectoryEntryOutput {
+    pub name: String,
+    #[serde(rename = "type")]
+    pub kind: EntryKind,
+    /// Size in bytes, only reported for files by tools that include sizes.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<u64>,
+}
+
+/// Content blocks up to this size have the highest priority.
+const SMALL_CONTENT_BYTES: usize = 4 * 1024;
+/// Content blocks above this size have the lowest priority.
+const LARGE_CONTENT_BYTES: usize = 64 * 1024;
+
+/// Who a content block is meant for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Audience {
+    /// Machine-oriented content, such as JSON output or file contents.
+    Assistant,
+    /// Human-readable content, useful to the user as much as to the assistant.
+    Everyone,
+}
+
+/// The annotations of a content block of `size` bytes, its priority decreasing with its size.
+pub fn content_annotations(audience: Audience, size: usize) -> Annotations {
+    let priority = if size <= SMALL_CONTENT_BYTES {
+        1.0
+    } else if size <= LARGE_CONTENT_BYTES {
+        0.5
+    } else {
+        0.2
+    };
+    Annotations {
+        audience: match audience {
+            Audience::Assistant => vec![Role::Assistant],
+            Audience::Everyone => vec![Role::User, Role::Assistant],
+        },
+        last_modified: None,
+        priority: Some(priority),
+    }
+}
+
+/// A text content block annotated for `audience`.
+pub fn annotated_text(text: String, audience: Audience) -> TextContent {
+    let annotations = content_annotations(audience, text.len());
+    TextContent::new(text, Some(annotations), None)
+}
+
+/// Builds the result of a tool in the requested output format (default: text).
+///
+/// `to_text` is only evaluated when the text format is requested.
+pub fn tool_result<T: Serialize>(
+    output_format: Option<OutputFormat>,
+    value: &T,
+    to_text: impl FnOnce() -> std::result::Result<String, CallToolError>,
+) -> std::result::Result<CallToolResult, CallToolError> {
+    match output_format.unwrap_or(OutputFormat::Text) {
+        OutputFormat::Text => Ok(CallToolResult::text_content(vec![annotated_text(
+            to_text()?,
+            Audience::Everyone,
+        )])),
+        OutputFormat::Json => {
+            let json = serde_json::to_value(value).map_err(CallToolError::new)?;
+            let text = serde_json::to_string_pretty(&json).map_err(CallToolError::new)?;
+            let result =
+                CallToolResult::text_content(vec![annotated_text(text, Audience::Assistant)]);
+            Ok(match json {
+                Value::Object(structured_content) => {
+                    result.with_structured_content(structured_content)
+                }
+                _ => result,
+            })
+        }
+    }
+}
+
+/// `text` with its non-ASCII characters escaped as `\uXXXX` sequences of UTF-16 code units, as in
+/// JSON strings.
+pub fn escape_non_ascii(text: &str) -> String {
+    if text.is_ascii() {
+        return text.to_string();
+    }
+    let mut escaped = String::with_capacity(text.len() * 2);
+    for character in text.chars() {
+        if character.is_ascii() {
+            escaped.push(character);
+        } else {
+            for unit in character.encode_utf16(&mut [0; 2]) {
+                escaped.push_str(&format!("\\u{unit:04x}"));
+            }
+        }
+    }
+    escaped
+}
+
+fn escape_value(value: &mut Value) {
+    match value {
+        Value::String(text) => *text = escape_non_ascii(text),
+        Value::Array(values) => values.iter_mut().for_each(escape_value),
+        Value::Object(object) => escape_object(object),
+        _ => {}
+    }
+}
+
+fn escape_object(object: &mut serde_json::Map<String, Value>) {
+    *object = std::mem::take(object)
+        .into_iter()
+        .map(|(key, mut value)| {
+            escape_value(&mut value);
+            (escape_non_ascii(&key), value)
+        })
+        .collect();
+}
+
+/// Escapes the non-ASCII characters of the texts of `result`, for clients failing to parse
+/// responses containing them: its text contents, resources and structured content.
+pub fn escape_result_text(result: &mut CallToolResult) {
+    for content in &mut result.content {
+        match content {
+            ContentBlock::TextContent(text_content) => {
+                text_content.text = escape_non_ascii(&text_content.text)
+            }
+            ContentBlock::ResourceLink(resource_link) => {
+                resource_link.uri = escape_non_ascii(&resource_link.uri);
+                resource_link.name = escape_non_ascii(&resource_link.name);
+                for text in [&mut resource_link.title, &mut resource_link.description]
+                    .into_iter()
+                    .flatten()
+                {
+                    *text = escape_non_ascii(text);
+                }
+            }
+            ContentBlock::EmbeddedResource(embedded_resource) => {
+                match &mut embedded_resource.resource {
+                    EmbeddedResourceResource::TextResourceContents(resource) => {
+                        resource.uri = escape_non_ascii(&resource.uri);
+                        resource.text = escape_non_ascii(&resource.text);
+                    }
+                    EmbeddedResourceResource::BlobResourceContents(resource) => {
+                        resource.uri = escape_non_ascii(&resource.uri)
+                    }
+                }
+            }
+            ContentBlock::ImageContent(_) | ContentBlock::AudioContent(_) => {}
+        }
+    }
+    if let Some(structured_content) = &mut result.structured_content {
+        escape_object(structured_content);
+    }
+}
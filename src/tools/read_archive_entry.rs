@@ -0,0 +1,76 @@
+use base64::{Engine, engine::general_purpose};
+use rust_mcp_sdk::{
+    macros::{JsonSchema, mcp_tool},
+    schema::{BlobResourceContents, CallToolResult, EmbeddedResource, schema_utils::CallToolError},
+};
+use std::path::Path;
+
+use super::output::{Audience, annotated_text, content_annotations};
+use crate::fs_service::FileSystemService;
+
+const DEFAULT_MAX_ENTRY_BYTES: u64 = 1024 * 1024;
+
+#[mcp_tool(
+    name = "read_archive_entry",
+    title="Read archive entry",
+    description = concat!("Reads the content of a single entry of a ZIP archive without extracting the archive to disk. ",
+    "Useful to inspect packaged artifacts or the internals of ZIP based formats such as .docx, .xlsx or .jar files. ",
+    "The `entry` argument is the full name of the entry within the archive, e.g. `word/document.xml`. ",
+    "Text entries are returned as text, other entries are returned Base64-encoded along with their MIME type when it can be detected. ",
+    "Entries larger than `max_bytes` (default: 1048576) are rejected. ",
+    "Only works within allowed directories."),
+    destructive_hint = false,
+    idempotent_hint = true,
+    open_world_hint = false,
+    read_only_hint = true
+)]
+#[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, JsonSchema)]
+pub struct ReadArchiveEntry {
+    /// The path of the ZIP archive.
+    pub path: String,
+    /// The name of the entry to read, as stored in the archive.
+    pub entry: String,
+    /// Maximum allowed uncompressed size (in bytes) of the entry (default: 1048576).
+    #[json_schema(default = "1048576")]
+    pub max_bytes: Option<u64>,
+}
+
+impl ReadArchiveEntry {
+    pub async fn run_tool(
+        params: Self,
+        context: &FileSystemService,
+    ) -> std::result::Result<CallToolResult, CallToolError> {
+        let entry = context
+            .read_archive_entry(
+                Path::new(&params.path),
+                &params.entry,
+                params.max_bytes.unwrap_or(DEFAULT_MAX_ENTRY_BYTES) as usize,
+            )
+            .await
+            .map_err(CallToolError::new)?;
+
+        let call_result = match String::from_utf8(entry.content) {
+            Ok(text) => {
+                CallToolResult::text_content(vec![annotated_text(text, Audience::Assistant)])
+            }
+            Err(err) => {
+                let annotations = content_annotations(Audience::Assistant, err.as_bytes().len());
+                let resource = BlobResourceContents {
+                    blob: general_purpose::STANDARD.encode(err.into_bytes()),
+                    meta: None,
+                    mime_type: Some(
+                        entry
+                            .mime_type
+                            .unwrap_or("application/octet-stream".to_string()),
+                    ),
+                    uri: format!("file://{}!/{}", params.path, entry.name),
+                };
+                CallToolResult::from_content(vec![
+                    EmbeddedResource::new(resource.into(), Some(annotations), None).into(),
+                ])
+            }
+        };
+
+        Ok(call_result)
+    }
+}
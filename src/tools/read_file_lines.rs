@@ -2,10 +2,11 @@ use std::path::Path;
 
 use rust_mcp_sdk::{
     macros::{JsonSchema, mcp_tool},
-    schema::{CallToolResult, TextContent, schema_utils::CallToolError},
+    schema::{CallToolResult, schema_utils::CallToolError},
 };
 
-use crate::fs_service::FileSystemService;
+use super::output::{Audience, annotated_text};
+use crate::fs_service::{FileSystemService, utils::line_anchor};
 
 // read_file_lines
 #[mcp_tool(
@@ -14,6 +15,8 @@ use crate::fs_service::FileSystemService;
     description = concat!("Reads lines from a text file starting at a specified line offset (0-based) and continues for the specified number of lines if a limit is provided.",
     "This function skips the first 'offset' lines and then reads up to 'limit' lines if specified, or reads until the end of the file otherwise.",
     "It's useful for partial reads, pagination, or previewing sections of large text files.",
+    "Set `with_anchors` to true to prefix each line with an anchor `<line number>:<hash>` (line numbers are 1-based), ",
+    "which can be passed to `edit_file` as `expected_anchors` to make sure the lines are unchanged when the edit is applied. ",
     "Only works within allowed directories."),
     destructive_hint = false,
     idempotent_hint = false,
@@ -33,6 +36,9 @@ pub struct ReadFileLines {
     pub offset: u64,
     ///  Optional maximum number of lines to read after the offset.
     pub limit: Option<u64>,
+    /// Prefix each line with its anchor, formatted as `<anchor> | <line>` (default: false).
+    #[json_schema(default = "false")]
+    pub with_anchors: Option<bool>,
 }
 
 impl ReadFileLines {
@@ -49,8 +55,23 @@ impl ReadFileLines {
             .await
             .map_err(CallToolError::new)?;
 
-        Ok(CallToolResult::text_content(vec![TextContent::from(
+        let result = if params.with_anchors.unwrap_or(false) {
+            result
+                .lines()
+                .enumerate()
+                .map(|(i, line)| {
+                    let anchor = line_anchor(params.offset as usize + i + 1, line);
+                    format!("{anchor} | {line}")
+                })
+                .collect::<Vec<_>>()
+                .join("\n")
+        } else {
+            result
+        };
+
+        Ok(CallToolResult::text_content(vec![annotated_text(
             result,
+            Audience::Assistant,
         )]))
     }
 }
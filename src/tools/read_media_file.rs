@@ -4,6 +4,7 @@ use rust_mcp_sdk::macros::{JsonSchema, mcp_tool};
 use rust_mcp_sdk::schema::{AudioContent, ImageContent};
 use rust_mcp_sdk::schema::{CallToolResult, schema_utils::CallToolError};
 
+use super::output::{Audience, content_annotations};
 use crate::error::ServiceError;
 use crate::fs_service::FileSystemService;
 
@@ -45,13 +46,16 @@ impl ReadMediaFile {
             .await
             .map_err(CallToolError::new)?;
         let mime_type = kind.mime_type().to_string();
+        let annotations = Some(content_annotations(Audience::Everyone, content.len()));
         let call_result = match kind.matcher_type() {
             infer::MatcherType::Image => {
-                let image_content: ImageContent = ImageContent::new(content, mime_type, None, None);
+                let image_content: ImageContent =
+                    ImageContent::new(content, mime_type, annotations, None);
                 CallToolResult::image_content(vec![image_content])
             }
             infer::MatcherType::Audio => {
-                let audio_content: AudioContent = AudioContent::new(content, mime_type, None, None);
+                let audio_content: AudioContent =
+                    AudioContent::new(content, mime_type, annotations, None);
                 CallToolResult::audio_content(vec![audio_content])
             }
             _ => {
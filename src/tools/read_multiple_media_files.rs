@@ -1,3 +1,4 @@
+use super::output::{Audience, content_annotations};
 use crate::fs_service::FileSystemService;
 use rust_mcp_sdk::macros::{JsonSchema, mcp_tool};
 use rust_mcp_sdk::schema::{AudioContent, ContentBlock, ImageContent};
@@ -43,13 +44,14 @@ impl ReadMultipleMediaFiles {
             .into_iter()
             .filter_map(|(kind, content)| {
                 let mime_type = kind.mime_type().to_string();
+                let annotations = Some(content_annotations(Audience::Everyone, content.len()));
 
                 match kind.matcher_type() {
                     infer::MatcherType::Image => Some(ContentBlock::ImageContent(
-                        ImageContent::new(content, mime_type, None, None),
+                        ImageContent::new(content, mime_type, annotations, None),
                     )),
                     infer::MatcherType::Audio => Some(ContentBlock::AudioContent(
-                        AudioContent::new(content, mime_type, None, None),
+                        AudioContent::new(content, mime_type, annotations, None),
                     )),
                     _ => None,
                 }
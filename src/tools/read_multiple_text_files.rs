@@ -1,9 +1,10 @@
+use super::output::{Audience, annotated_text};
 use crate::fs_service::FileSystemService;
-use futures::future::join_all;
 use rust_mcp_sdk::macros::{JsonSchema, mcp_tool};
-use rust_mcp_sdk::schema::TextContent;
 use rust_mcp_sdk::schema::{CallToolResult, schema_utils::CallToolError};
-use std::path::Path;
+use std::path::PathBuf;
+
+const DEFAULT_BYTE_BUDGET: u64 = 262144;
 
 #[mcp_tool(
     name = "read_multiple_text_files",
@@ -11,8 +12,11 @@ use std::path::Path;
     description = concat!("Read the contents of multiple text files simultaneously as text. ",
     "This is more efficient than reading files one by one when you need to analyze ",
     "or compare multiple files. Each file's content is returned with its ",
-    "path as a reference. Failed reads for individual files won't stop ",
-    "the entire operation. Only works within allowed directories."),
+    "path and size in bytes as a reference. Failed reads for individual files won't stop ",
+    "the entire operation. ",
+    "When the files exceed 'byte_budget' bytes in total, the smaller files are returned fully and the larger ones ",
+    "are truncated to their beginning and end, with a marker giving the omitted bytes and lines: ",
+    "use read_file_lines to read them. Only works within allowed directories."),
     destructive_hint = false,
     idempotent_hint = false,
     open_world_hint = false,
@@ -27,6 +31,9 @@ use std::path::Path;
 pub struct ReadMultipleTextFiles {
     /// The list of file paths to read.
     pub paths: Vec<String>,
+    /// Total number of bytes of file content that may be returned in a single response (default: 262144).
+    #[json_schema(default = "262144")]
+    pub byte_budget: Option<u64>,
 }
 
 impl ReadMultipleTextFiles {
@@ -34,28 +41,98 @@ impl ReadMultipleTextFiles {
         params: Self,
         context: &FileSystemService,
     ) -> std::result::Result<CallToolResult, CallToolError> {
-        let content_futures: Vec<_> = params
+        let paths: Vec<PathBuf> = params.paths.iter().map(PathBuf::from).collect();
+        let results = context.read_text_files(&paths, false).await;
+
+        let sizes: Vec<usize> = results
+            .iter()
+            .map(|content| content.as_ref().map_or(0, String::len))
+            .collect();
+        let byte_budget = params.byte_budget.unwrap_or(DEFAULT_BYTE_BUDGET) as usize;
+        let allowances = allocate_byte_budget(&sizes, byte_budget);
+
+        let contents: Vec<_> = params
             .paths
             .iter()
-            .map(|path| async move {
-                {
-                    let content = context
-                        .read_text_file(Path::new(&path), false)
-                        .await
-                        .map_err(CallToolError::new);
-
-                    content.map_or_else(
-                        |err| format!("{path}: Error - {err}"),
-                        |value| format!("{path}:\n{value}\n"),
-                    )
-                }
+            .zip(results)
+            .zip(allowances)
+            .map(|((path, content), allowance)| {
+                content.map_or_else(
+                    |err| format!("{path}: Error - {err}"),
+                    |value| match truncate_middle(&value, allowance) {
+                        Some((truncated, shown_bytes)) => format!(
+                            "{path} ({} bytes, {shown_bytes} shown):\n{truncated}\n",
+                            value.len()
+                        ),
+                        None => format!("{path} ({} bytes):\n{value}\n", value.len()),
+                    },
+                )
             })
             .collect();
 
-        let contents = join_all(content_futures).await;
-
-        Ok(CallToolResult::text_content(vec![TextContent::from(
+        Ok(CallToolResult::text_content(vec![annotated_text(
             contents.join("\n---\n"),
+            Audience::Assistant,
         )]))
     }
 }
+
+/// Shares `budget` between files of `sizes` bytes: from the smallest, each file gets its size if
+/// it fits in an equal share of what remains, the larger files get that share.
+fn allocate_byte_budget(sizes: &[usize], budget: usize) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..sizes.len()).collect();
+    order.sort_by_key(|&index| sizes[index]);
+    let mut allowances = vec![0; sizes.len()];
+    let mut remaining_budget = budget;
+    for (position, index) in order.into_iter().enumerate() {
+        let share = remaining_budget / (sizes.len() - position);
+        allowances[index] = sizes[index].min(share);
+        remaining_budget -= allowances[index];
+    }
+    allowances
+}
+
+/// Keeps at most `max_bytes` of `content`, half from its beginning and half from its end, cut at
+/// line boundaries when it doesn't drop more than half of them, with a marker in place of the
+/// rest. Returns the truncated content and the number of bytes kept, `None` if `content` fits.
+fn truncate_middle(content: &str, max_bytes: usize) -> Option<(String, usize)> {
+    if content.len() <= max_bytes {
+        return None;
+    }
+    let mut head_end = max_bytes / 2;
+    while !content.is_char_boundary(head_end) {
+        head_end -= 1;
+    }
+    if let Some(line_end) = content[..head_end]
+        .rfind('\n')
+        .filter(|line_end| line_end + 1 >= head_end / 2)
+    {
+        head_end = line_end + 1;
+    }
+    let tail_bytes = max_bytes - max_bytes / 2;
+    let mut tail_start = content.len() - tail_bytes;
+    while !content.is_char_boundary(tail_start) {
+        tail_start += 1;
+    }
+    if let Some(line_start) = content[tail_start..]
+        .find('\n')
+        .map(|offset| tail_start + offset + 1)
+        .filter(|line_start| content.len() - line_start >= tail_bytes / 2)
+        .filter(|_| !content[..tail_start].ends_with('\n'))
+    {
+        tail_start = line_start;
+    }
+
+    let first_omitted_line = content[..head_end].matches('\n').count() + 1;
+    // the line the tail starts in is partly omitted unless the tail starts at its beginning
+    let last_omitted_line = (content[..tail_start].matches('\n').count()
+        + usize::from(!content[..tail_start].ends_with('\n')))
+    .max(first_omitted_line);
+    let truncated = format!(
+        "{}\n[... {} bytes omitted (lines {first_omitted_line}-{last_omitted_line}), use read_file_lines to read them ...]\n{}",
+        &content[..head_end],
+        tail_start - head_end,
+        &content[tail_start..]
+    );
+    Some((truncated, head_end + content.len() - tail_start))
+}
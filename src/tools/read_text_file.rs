@@ -1,9 +1,9 @@
 use std::path::Path;
 
 use rust_mcp_sdk::macros::{JsonSchema, mcp_tool};
-use rust_mcp_sdk::schema::TextContent;
 use rust_mcp_sdk::schema::{CallToolResult, schema_utils::CallToolError};
 
+use super::output::{Audience, annotated_text};
 use crate::fs_service::FileSystemService;
 
 #[mcp_tool(
@@ -48,8 +48,9 @@ impl ReadTextFile {
             .await
             .map_err(CallToolError::new)?;
 
-        Ok(CallToolResult::text_content(vec![TextContent::from(
+        Ok(CallToolResult::text_content(vec![annotated_text(
             content,
+            Audience::Assistant,
         )]))
     }
 }
@@ -0,0 +1,84 @@
+use rust_mcp_sdk::{
+    macros::{JsonSchema, mcp_tool},
+    schema::{CallToolResult, schema_utils::CallToolError},
+};
+use std::fmt::Write;
+use std::path::Path;
+
+use super::output::tool_result;
+use crate::fs_service::{FileSystemService, ResolvedPath, utils::OutputFormat};
+
+#[mcp_tool(
+    name = "resolve_path",
+    title="Resolve path",
+    description = concat!("Resolves a path to its absolute, normalized form and reports whether it exists ",
+    "and which allowed directory it falls under, along with the path relative to that directory. ",
+    "When `relative_to` is provided, also computes the relative path leading from that directory to the path. ",
+    "Use this to build correct paths before calling other tools. Paths don't need to exist. ",
+    "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
+    "Only works within allowed directories."),
+    destructive_hint = false,
+    idempotent_hint = true,
+    open_world_hint = false,
+    read_only_hint = true
+)]
+#[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, JsonSchema)]
+pub struct ResolvePath {
+    /// The path to resolve.
+    pub path: String,
+    /// Optional base directory to compute the relative path from.
+    pub relative_to: Option<String>,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+}
+
+impl ResolvePath {
+    fn format_text(
+        resolved: &ResolvedPath,
+        relative_to: Option<&str>,
+    ) -> std::result::Result<String, CallToolError> {
+        let mut output = String::new();
+        let kind = match (resolved.exists, resolved.is_directory) {
+            (false, _) => "no",
+            (true, true) => "yes (directory)",
+            (true, false) => "yes (file)",
+        };
+        writeln!(output, "resolved: {}", resolved.resolved.display())
+            .map_err(CallToolError::new)?;
+        writeln!(output, "exists: {kind}").map_err(CallToolError::new)?;
+        if let Some(root) = &resolved.allowed_root {
+            writeln!(output, "allowed root: {}", root.display()).map_err(CallToolError::new)?;
+        }
+        if let Some(path) = &resolved.relative_to_root {
+            writeln!(output, "relative to root: {}", path.display()).map_err(CallToolError::new)?;
+        }
+        if let Some(base) = relative_to {
+            let path = resolved
+                .relative_path
+                .as_ref()
+                .map_or("(no common root)".to_string(), |path| {
+                    path.display().to_string()
+                });
+            writeln!(output, "relative to {base}: {path}").map_err(CallToolError::new)?;
+        }
+        Ok(output.trim_end().to_string())
+    }
+
+    pub async fn run_tool(
+        params: Self,
+        context: &FileSystemService,
+    ) -> std::result::Result<CallToolResult, CallToolError> {
+        let resolved = context
+            .resolve_path(
+                Path::new(&params.path),
+                params.relative_to.as_deref().map(Path::new),
+            )
+            .await
+            .map_err(CallToolError::new)?;
+
+        tool_result(params.output_format, &resolved, || {
+            Self::format_text(&resolved, params.relative_to.as_deref())
+        })
+    }
+}
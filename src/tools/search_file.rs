@@ -1,10 +1,26 @@
 use std::path::Path;
 
+use base64::{Engine, engine::general_purpose};
 use rust_mcp_sdk::macros::{JsonSchema, mcp_tool};
-use rust_mcp_sdk::schema::TextContent;
+use rust_mcp_sdk::schema::{
+    BlobResourceContents, ContentBlock, EmbeddedResource, TextResourceContents,
+};
 use rust_mcp_sdk::schema::{CallToolResult, schema_utils::CallToolError};
+use serde_json::json;
+use url::Url;
+
+use super::output::{Audience, content_annotations, tool_result};
+use crate::fs_service::{
+    FilePreview, FileSystemService,
+    utils::{MimeCategory, OutputFormat, matches_mime_category},
+};
+use crate::i18n::Message;
+
+const DEFAULT_PREVIEW_BYTES: u64 = 512;
+const DEFAULT_PREVIEW_BUDGET: u64 = 4096;
+/// Maximum number of previews in a single response, whatever their size.
+const MAX_PREVIEWS: usize = 20;
 
-use crate::fs_service::FileSystemService;
 #[mcp_tool(
     name = "search_files",
     title="Search files",
@@ -14,6 +30,11 @@ use crate::fs_service::FileSystemService;
   "Optional 'min_bytes' and 'max_bytes' arguments can be used to filter files by size, ",
   "ensuring that only files within the specified byte range are included in the search. ",
   "This tool is great for finding files when you don't know their exact location or find files by their size.",
+  "Optional 'mime_category' (image, audio, video, text, archive, binary) restricts results to files of that kind, ",
+  "detected from the file header so that files with missing or misleading extensions are found too. ",
+  "Set 'include_previews' to true to embed the first 'preview_bytes' of the top matching files as resources, ",
+  "limited to a total of 'preview_budget' bytes and 20 files across all previews.",
+  "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
   "Only searches within allowed directories."),
     destructive_hint = false,
     idempotent_hint = false,
@@ -40,8 +61,91 @@ pub struct SearchFiles {
     pub min_bytes: Option<u64>,
     /// Maximum file size (in bytes) to include in the search (optional).
     pub max_bytes: Option<u64>,
+    /// Only include files of the given kind: `image`, `audio`, `video`, `text`, `archive` or `binary` (optional).
+    pub mime_category: Option<MimeCategory>,
+    /// Whether to embed previews of the top matching files as resources, at most 20 (default: false).
+    pub include_previews: Option<bool>,
+    /// Maximum number of bytes to read from the start of each previewed file, 0 embeds no preview (default: 512).
+    #[json_schema(default = "512")]
+    pub preview_bytes: Option<u64>,
+    /// Total number of bytes that may be spent on previews in a single response (default: 4096).
+    #[json_schema(default = "4096")]
+    pub preview_budget: Option<u64>,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
 }
 impl SearchFiles {
+    /// The preview of `path` as an embedded resource, `None` if the path has no `file` URI.
+    pub(crate) fn preview_resource(path: &Path, preview: FilePreview) -> Option<EmbeddedResource> {
+        let uri = Url::from_file_path(path).ok()?.to_string();
+        let annotations = content_annotations(Audience::Assistant, preview.content.len());
+
+        // a preview may cut a multi-byte character in half, only the trailing partial character is dropped
+        let text = match String::from_utf8(preview.content) {
+            Ok(text) => Ok(text),
+            Err(err) if err.utf8_error().error_len().is_none() => {
+                let valid_up_to = err.utf8_error().valid_up_to();
+                let mut bytes = err.into_bytes();
+                bytes.truncate(valid_up_to);
+                Ok(String::from_utf8(bytes).unwrap_or_default())
+            }
+            Err(err) => Err(err.into_bytes()),
+        };
+
+        let resource = match text {
+            Ok(text) => TextResourceContents {
+                meta: None,
+                mime_type: Some(preview.mime_type.unwrap_or("text/plain".to_string())),
+                text,
+                uri,
+            }
+            .into(),
+            Err(bytes) => BlobResourceContents {
+                blob: general_purpose::STANDARD.encode(bytes),
+                meta: None,
+                mime_type: Some(
+                    preview
+                        .mime_type
+                        .unwrap_or("application/octet-stream".to_string()),
+                ),
+                uri,
+            }
+            .into(),
+        };
+
+        Some(EmbeddedResource::new(resource, Some(annotations), None))
+    }
+
+    pub(crate) async fn collect_previews(
+        paths: &[&Path],
+        preview_bytes: u64,
+        preview_budget: u64,
+        context: &FileSystemService,
+    ) -> Vec<ContentBlock> {
+        let mut previews = vec![];
+        let mut remaining_budget = preview_budget;
+
+        // empty previews would not use the budget, there would be one for each path
+        if preview_bytes == 0 {
+            return previews;
+        }
+        for path in paths {
+            if remaining_budget == 0 || previews.len() == MAX_PREVIEWS {
+                break;
+            }
+            let max_bytes = preview_bytes.min(remaining_budget) as usize;
+            if let Ok(preview) = context.read_file_preview(path, max_bytes).await {
+                let preview_size = preview.content.len() as u64;
+                if let Some(resource) = Self::preview_resource(path, preview) {
+                    remaining_budget = remaining_budget.saturating_sub(preview_size);
+                    previews.push(ContentBlock::EmbeddedResource(resource));
+                }
+            }
+        }
+        previews
+    }
+
     pub async fn run_tool(
         params: Self,
         context: &FileSystemService,
@@ -57,16 +161,44 @@ impl SearchFiles {
             .await
             .map_err(CallToolError::new)?;
 
-        let result = if !list.is_empty() {
-            list.iter()
-                .map(|entry| entry.path().display().to_string())
-                .collect::<Vec<_>>()
-                .join("\n")
-        } else {
-            "No matches found".to_string()
+        let list: Vec<_> = match params.mime_category {
+            Some(category) => list
+                .into_iter()
+                .filter(|entry| entry.is_file() && matches_mime_category(entry.path(), category))
+                .collect(),
+            None => list,
         };
-        Ok(CallToolResult::text_content(vec![TextContent::from(
-            result,
-        )]))
+
+        let matches: Vec<_> = list
+            .iter()
+            .map(|entry| entry.path().display().to_string())
+            .collect();
+
+        let mut result = tool_result(params.output_format, &json!({ "matches": matches }), || {
+            Ok(if !matches.is_empty() {
+                matches.join("\n")
+            } else {
+                Message::NoMatchesFound.localize(context.locale())
+            })
+        })?;
+
+        if params.include_previews.unwrap_or(false) {
+            let files: Vec<_> = list
+                .iter()
+                .filter(|entry| entry.is_file())
+                .map(|entry| entry.path())
+                .collect();
+            result.content.extend(
+                Self::collect_previews(
+                    &files,
+                    params.preview_bytes.unwrap_or(DEFAULT_PREVIEW_BYTES),
+                    params.preview_budget.unwrap_or(DEFAULT_PREVIEW_BUDGET),
+                    context,
+                )
+                .await,
+            );
+        }
+
+        Ok(result)
     }
 }
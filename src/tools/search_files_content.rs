@@ -1,17 +1,25 @@
+use super::output::tool_result;
 use crate::error::ServiceError;
-use crate::fs_service::{FileSearchResult, FileSystemService};
+use crate::fs_service::{FileSearchResult, FileSystemService, utils::OutputFormat};
 use rust_mcp_sdk::macros::{JsonSchema, mcp_tool};
-use rust_mcp_sdk::schema::TextContent;
 use rust_mcp_sdk::schema::{CallToolResult, schema_utils::CallToolError};
+use serde_json::json;
 use std::fmt::Write;
 #[mcp_tool(
     name = "search_files_content",
     title="Search files content",
     description = concat!("Searches for text or regex patterns in the content of files matching matching a GLOB pattern.",
-                          "Returns detailed matches with file path, line number, column number and a preview of matched text.",
+                          "Returns detailed matches with file path, line number, column number and a preview of matched text, reporting every occurrence on a line separately.",
                           "By default, it performs a literal text search; if the 'is_regex' parameter is set to true, it performs a regular expression (regex) search instead.",
+                          "Several queries can be searched for in a single pass with the 'queries' argument, in which case each match reports the query that matched.",
+                          "If 'invert' is set to true, it instead lists the files that do NOT contain any of the queries (e.g., files lacking a license header).",
                           "Optional 'min_bytes' and 'max_bytes' arguments can be used to filter files by size, ",
                           "ensuring that only files within the specified byte range are included in the search. ",
+                          "Instead of 'path' and 'pattern', an explicit 'paths' list of files (e.g., the output of a previous search_files call) can be provided ",
+                          "to search only those files without traversing directories again, which is much faster for narrowing down searches on large trees. ",
+                          "Optional 'snippet_max_chars' and 'snippet_context_chars' control the length of the preview returned for each match ",
+                          "and how many characters are shown before the match (defaults: 200 and 30). ",
+                          "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
                           "Ideal for finding specific code, comments, or text when you don’t know their exact location."),
     destructive_hint = false,
     idempotent_hint = false,
@@ -27,12 +35,19 @@ use std::fmt::Write;
 
 /// A tool for searching content of one or more files based on a path and pattern.
 pub struct SearchFilesContent {
-    /// The file or directory path to search in.
-    pub path: String,
-    /// The file glob pattern to match (e.g., "*.rs").
-    pub pattern: String,
-    /// Text or regex pattern to find in file contents (e.g., 'TODO' or '^function\\s+').
-    pub query: String,
+    /// The file or directory path to search in. Required unless `paths` is provided.
+    pub path: Option<String>,
+    /// The file glob pattern to match (e.g., "*.rs"). Required unless `paths` is provided.
+    pub pattern: Option<String>,
+    /// Explicit list of files to search (e.g., the output of a previous `search_files` call).
+    /// When provided, `path`, `pattern` and `excludePatterns` are ignored and no directory is traversed.
+    pub paths: Option<Vec<String>>,
+    /// Text or regex pattern to find in file contents (e.g., 'TODO' or '^function\\s+'). Required unless `queries` is provided.
+    pub query: Option<String>,
+    /// Several text or regex patterns to find in a single pass, each match reports the query that matched.
+    pub queries: Option<Vec<String>>,
+    /// List the files that do NOT contain any of the queries instead of the matches. (Default : false)
+    pub invert: Option<bool>,
     /// Whether the query is a regular expression. If false, the query as plain text. (Default : false)
     pub is_regex: Option<bool>,
     #[serde(rename = "excludePatterns")]
@@ -42,10 +57,19 @@ pub struct SearchFilesContent {
     pub min_bytes: Option<u64>,
     /// Maximum file size (in bytes) to include in the search (optional).
     pub max_bytes: Option<u64>,
+    /// Maximum number of characters of the matched line returned for each match (default: 200).
+    #[json_schema(default = "200")]
+    pub snippet_max_chars: Option<u64>,
+    /// Number of characters to include before the match in each snippet (default: 30).
+    #[json_schema(default = "30")]
+    pub snippet_context_chars: Option<u64>,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
 }
 
 impl SearchFilesContent {
-    fn format_result(&self, results: Vec<FileSearchResult>) -> String {
+    fn format_result(&self, results: &[FileSearchResult]) -> String {
         // TODO: improve capacity estimation
         let estimated_capacity = 2048;
 
@@ -57,12 +81,19 @@ impl SearchFilesContent {
 
             // Push each match line
             for m in &file_result.matches {
-                // Format: "  line:col: text snippet"
-                let _ = writeln!(
-                    output,
-                    "  {}:{}: {}",
-                    m.line_number, m.start_pos, m.line_text
-                );
+                // Format: "  line:col: text snippet", or "  line:col: [query] text snippet" with several queries
+                let _ = match &m.query {
+                    Some(query) => writeln!(
+                        output,
+                        "  {}:{}: [{}] {}",
+                        m.line_number, m.start_pos, query, m.line_text
+                    ),
+                    None => writeln!(
+                        output,
+                        "  {}:{}: {}",
+                        m.line_number, m.start_pos, m.line_text
+                    ),
+                };
             }
 
             // double spacing
@@ -76,27 +107,122 @@ impl SearchFilesContent {
         context: &FileSystemService,
     ) -> std::result::Result<CallToolResult, CallToolError> {
         let is_regex = params.is_regex.unwrap_or_default();
-        match context
-            .search_files_content(
-                &params.path,
-                &params.pattern,
-                &params.query,
-                is_regex,
-                params.exclude_patterns.to_owned(),
-                params.min_bytes,
-                params.max_bytes,
-            )
-            .await
-        {
+        let snippet_max_chars = params.snippet_max_chars.map(|v| v as usize);
+        let snippet_context_chars = params.snippet_context_chars.map(|v| v as usize);
+        let queries: Vec<&str> = params
+            .query
+            .iter()
+            .chain(params.queries.iter().flatten())
+            .map(String::as_str)
+            .collect();
+        if queries.is_empty() {
+            return Ok(CallToolResult::with_error(CallToolError::new(
+                ServiceError::FromString("Either 'query' or 'queries' must be provided.".into()),
+            )));
+        }
+        if params.invert.unwrap_or_default() {
+            return Self::run_inverted(&params, &queries, context).await;
+        }
+        let results = match (&params.paths, &params.path, &params.pattern) {
+            (Some(paths), _, _) => {
+                context
+                    .search_paths_content(
+                        paths,
+                        &queries,
+                        is_regex,
+                        params.min_bytes,
+                        params.max_bytes,
+                        snippet_max_chars,
+                        snippet_context_chars,
+                    )
+                    .await
+            }
+            (None, Some(path), Some(pattern)) => {
+                context
+                    .search_files_content(
+                        path,
+                        pattern,
+                        &queries,
+                        is_regex,
+                        params.exclude_patterns.to_owned(),
+                        params.min_bytes,
+                        params.max_bytes,
+                        snippet_max_chars,
+                        snippet_context_chars,
+                    )
+                    .await
+            }
+            _ => Err(ServiceError::FromString(
+                "Either 'paths' or both 'path' and 'pattern' must be provided.".into(),
+            )),
+        };
+        match results {
             Ok(results) => {
                 if results.is_empty() {
                     return Ok(CallToolResult::with_error(CallToolError::new(
                         ServiceError::FromString("No matches found in the files content.".into()),
                     )));
                 }
-                Ok(CallToolResult::text_content(vec![TextContent::from(
-                    params.format_result(results),
-                )]))
+                tool_result(params.output_format, &json!({ "results": results }), || {
+                    Ok(params.format_result(&results))
+                })
+            }
+            Err(err) => Ok(CallToolResult::with_error(CallToolError::new(err))),
+        }
+    }
+    /// Lists the files that contain none of the `queries`.
+    async fn run_inverted(
+        params: &Self,
+        queries: &[&str],
+        context: &FileSystemService,
+    ) -> std::result::Result<CallToolResult, CallToolError> {
+        let is_regex = params.is_regex.unwrap_or_default();
+        let files = match (&params.paths, &params.path, &params.pattern) {
+            (Some(paths), _, _) => {
+                context
+                    .search_paths_without_content(
+                        paths,
+                        queries,
+                        is_regex,
+                        params.min_bytes,
+                        params.max_bytes,
+                    )
+                    .await
+            }
+            (None, Some(path), Some(pattern)) => {
+                context
+                    .search_files_without_content(
+                        path,
+                        pattern,
+                        queries,
+                        is_regex,
+                        params.exclude_patterns.to_owned(),
+                        params.min_bytes,
+                        params.max_bytes,
+                    )
+                    .await
+            }
+            _ => Err(ServiceError::FromString(
+                "Either 'paths' or both 'path' and 'pattern' must be provided.".into(),
+            )),
+        };
+        match files {
+            Ok(files) => {
+                if files.is_empty() {
+                    return Ok(CallToolResult::with_error(CallToolError::new(
+                        ServiceError::FromString(
+                            "All the files contain the query, no file without a match found."
+                                .into(),
+                        ),
+                    )));
+                }
+                tool_result(params.output_format, &json!({ "files": files }), || {
+                    Ok(files
+                        .iter()
+                        .map(|file| file.display().to_string())
+                        .collect::<Vec<_>>()
+                        .join("\n"))
+                })
             }
             Err(err) => Ok(CallToolResult::with_error(CallToolError::new(err))),
         }
@@ -0,0 +1,52 @@
+use rust_mcp_sdk::{
+    macros::{JsonSchema, mcp_tool},
+    schema::{CallToolResult, schema_utils::CallToolError},
+};
+use serde_json::json;
+use std::path::Path;
+
+use super::output::tool_result;
+use crate::fs_service::{FileSystemService, utils::OutputFormat};
+
+#[mcp_tool(
+    name = "set_default_root",
+    title="Set default root",
+    description = concat!("Sets the directory that relative paths passed to other tools are resolved against for the rest of the session, ",
+    "so subsequent calls can use short relative paths instead of repeating long absolute paths. ",
+    "The directory must exist within the allowed directories, a relative `path` is resolved against the current default root. ",
+    "By default relative paths are resolved against the first allowed directory. ",
+    "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
+    "Only works within allowed directories."),
+    destructive_hint = false,
+    idempotent_hint = true,
+    open_world_hint = false,
+    read_only_hint = true
+)]
+#[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, JsonSchema)]
+pub struct SetDefaultRoot {
+    /// The directory to resolve relative paths against.
+    pub path: String,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+}
+
+impl SetDefaultRoot {
+    pub async fn run_tool(
+        params: Self,
+        context: &FileSystemService,
+    ) -> std::result::Result<CallToolResult, CallToolError> {
+        let default_root = context
+            .set_base_dir(Path::new(&params.path))
+            .await
+            .map_err(CallToolError::new)?;
+
+        let output = json!({ "default_root": default_root });
+        tool_result(params.output_format, &output, || {
+            Ok(format!(
+                "Relative paths are now resolved against {}",
+                default_root.display()
+            ))
+        })
+    }
+}
@@ -0,0 +1,66 @@
+use rust_mcp_sdk::{
+    macros::{JsonSchema, mcp_tool},
+    schema::{CallToolResult, schema_utils::CallToolError},
+};
+use serde_json::json;
+use std::path::Path;
+
+use super::output::tool_result;
+use crate::fs_service::{FileSystemService, FinderTag, FinderTagsUpdate, utils::OutputFormat};
+
+#[mcp_tool(
+    name = "update_finder_tags",
+    title="Update Finder tags",
+    description = concat!("Adds, removes or replaces the macOS Finder tags of a file or directory, the same tags shown and managed by Finder. ",
+    "Each tag has a name and an optional color: none, gray, green, purple, blue, yellow, red or orange. ",
+    "The `mode` argument accepts `add` (default), `remove` (tags are matched by name) or `replace`. ",
+    "Returns the resulting list of tags. Current tags are reported by `get_file_info`. ",
+    "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
+    "Only supported on macOS and only works within allowed directories."),
+    destructive_hint = false,
+    idempotent_hint = true,
+    open_world_hint = false,
+    read_only_hint = false
+)]
+#[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, JsonSchema)]
+pub struct UpdateFinderTags {
+    /// The path of the file or directory to tag.
+    pub path: String,
+    /// The tags to add, remove or set.
+    pub tags: Vec<FinderTag>,
+    /// How the tags are applied: `add`, `remove` or `replace` (default: add).
+    #[json_schema(default = "add")]
+    pub mode: Option<FinderTagsUpdate>,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+    /// Optional client-generated key identifying this operation. When a request is retried with the same key,
+    /// the server returns the original result instead of executing the operation again.
+    pub idempotency_key: Option<String>,
+}
+
+impl UpdateFinderTags {
+    pub async fn run_tool(
+        params: Self,
+        context: &FileSystemService,
+    ) -> std::result::Result<CallToolResult, CallToolError> {
+        let tags = context
+            .update_finder_tags(
+                Path::new(&params.path),
+                params.tags,
+                params.mode.unwrap_or_default(),
+            )
+            .await
+            .map_err(CallToolError::new)?;
+
+        let output = json!({ "path": params.path, "tags": tags });
+        tool_result(params.output_format, &output, || {
+            Ok(if tags.is_empty() {
+                format!("{} has no Finder tags.", params.path)
+            } else {
+                let tags: Vec<String> = tags.iter().map(|t| t.to_string()).collect();
+                format!("Finder tags of {}: {}", params.path, tags.join(", "))
+            })
+        })
+    }
+}
@@ -0,0 +1,149 @@
+use rust_mcp_sdk::{
+    macros::{JsonSchema, mcp_tool},
+    schema::{CallToolResult, schema_utils::CallToolError},
+};
+use serde::Serialize;
+use std::fmt::Write;
+use std::path::Path;
+
+use super::output::tool_result;
+use crate::fs_service::{
+    ExtensionUsage, FileSystemService,
+    utils::{MimeCategory, OutputFormat, format_bytes},
+};
+
+#[mcp_tool(
+    name = "usage_by_extension",
+    title="Disk usage by extension",
+    description = concat!("Reports the disk usage of the files under a directory grouped by file extension, ",
+    "with the number of files and total bytes of each extension, sorted by size, largest first. ",
+    "Totals by file kind (image, audio, video, text, archive, binary) are reported as well, based on well-known extensions. ",
+    "Useful to find out what fills up a volume. ",
+    "Optional `pattern` argument can be used to narrow down the search to specific glob pattern. ",
+    "Optional `exclude_patterns` can be used to exclude certain files matching a glob. ",
+    "The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
+    "Only works within allowed directories."),
+    destructive_hint = false,
+    idempotent_hint = false,
+    open_world_hint = false,
+    read_only_hint = true
+)]
+#[derive(::serde::Deserialize, ::serde::Serialize, Clone, Debug, JsonSchema)]
+pub struct UsageByExtension {
+    /// The root directory path to start the search.
+    pub root_path: String,
+    /// Optional glob pattern can be used to match target files.
+    pub pattern: Option<String>,
+    /// Optional list of glob patterns to exclude from the search. File matching these patterns will be ignored.
+    pub exclude_patterns: Option<Vec<String>>,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+}
+
+#[derive(Serialize, Debug)]
+struct CategoryUsage {
+    /// `None` for extensions which aren't well-known.
+    category: Option<MimeCategory>,
+    files: usize,
+    bytes: u64,
+}
+
+#[derive(Serialize, Debug)]
+struct UsageOutput {
+    extensions: Vec<ExtensionUsage>,
+    categories: Vec<CategoryUsage>,
+    total_files: usize,
+    total_bytes: u64,
+}
+
+impl UsageByExtension {
+    fn categories(extensions: &[ExtensionUsage]) -> Vec<CategoryUsage> {
+        let mut categories: Vec<CategoryUsage> = vec![];
+        for usage in extensions {
+            match categories
+                .iter_mut()
+                .find(|category| category.category == usage.category)
+            {
+                Some(category) => {
+                    category.files += usage.files;
+                    category.bytes += usage.bytes;
+                }
+                None => categories.push(CategoryUsage {
+                    category: usage.category,
+                    files: usage.files,
+                    bytes: usage.bytes,
+                }),
+            }
+        }
+        categories.sort_by(|a, b| b.bytes.cmp(&a.bytes));
+        categories
+    }
+
+    fn format_text(output: &UsageOutput) -> std::result::Result<String, CallToolError> {
+        if output.extensions.is_empty() {
+            return Ok("No files were found.".to_string());
+        }
+
+        let mut text = String::new();
+        writeln!(
+            text,
+            "{} files, {} in total.\n\nBy extension:",
+            output.total_files,
+            format_bytes(output.total_bytes)
+        )
+        .map_err(CallToolError::new)?;
+        for usage in &output.extensions {
+            let extension = if usage.extension.is_empty() {
+                "(none)".to_string()
+            } else {
+                format!(".{}", usage.extension)
+            };
+            writeln!(
+                text,
+                "  {extension:<12} {:>10}  {} files",
+                format_bytes(usage.bytes),
+                usage.files
+            )
+            .map_err(CallToolError::new)?;
+        }
+
+        writeln!(text, "\nBy kind:").map_err(CallToolError::new)?;
+        for usage in &output.categories {
+            let category = usage
+                .category
+                .map_or("other".to_string(), |category| category.to_string());
+            writeln!(
+                text,
+                "  {category:<12} {:>10}  {} files",
+                format_bytes(usage.bytes),
+                usage.files
+            )
+            .map_err(CallToolError::new)?;
+        }
+        Ok(text)
+    }
+
+    pub async fn run_tool(
+        params: Self,
+        context: &FileSystemService,
+    ) -> std::result::Result<CallToolResult, CallToolError> {
+        let extensions = context
+            .usage_by_extension(
+                Path::new(&params.root_path),
+                params.pattern,
+                params.exclude_patterns,
+            )
+            .await
+            .map_err(CallToolError::new)?;
+
+        let output = UsageOutput {
+            categories: Self::categories(&extensions),
+            total_files: extensions.iter().map(|usage| usage.files).sum(),
+            total_bytes: extensions.iter().map(|usage| usage.bytes).sum(),
+            extensions,
+        };
+
+        tool_result(params.output_format, &output, || Self::format_text(&output))
+    }
+}
@@ -1,17 +1,27 @@
-use rust_mcp_sdk::{
-    macros::{JsonSchema, mcp_tool},
-    schema::TextContent,
-};
+use rust_mcp_sdk::macros::{JsonSchema, mcp_tool};
+use serde_json::{Map, Value};
 use std::path::Path;
 
 use rust_mcp_sdk::schema::{CallToolResult, schema_utils::CallToolError};
 
-use crate::fs_service::FileSystemService;
+use super::output::tool_result;
+use crate::fs_service::{
+    FileSystemService,
+    utils::{OutputFormat, WriteIfExists},
+};
+use crate::i18n::Message;
 #[mcp_tool(
     name = "write_file",
     title="Write file",
     description = concat!("Create a new file or completely overwrite an existing file with new content. ",
-"Use with caution as it will overwrite existing files without warning. ",
+"Use with caution as it will overwrite existing files without warning unless 'if_exists' is set. ",
+"Set 'if_exists' to `fail` to refuse clobbering an existing file, or to `append` to add the content to the end of it. ",
+"Set 'return_diff' to true to get a unified diff of the replaced content when an existing file is modified. ",
+"The structured result includes the SHA-256 and byte count of the final on-disk content. ",
+"Set 'create_parents' to true to create any missing parent directories in the same step. ",
+"The content follows the indentation, line endings, final newline and trailing whitespace settings of the `.editorconfig` files, unless the server disables it. ",
+"The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
+"The result includes a warning when the file appears to be in use: locked by another process or modified by it within the last few seconds. ",
 "Handles text content with proper encoding. Only works within allowed directories."),
     destructive_hint = false,
     idempotent_hint = false,
@@ -29,6 +39,18 @@ pub struct WriteFile {
     pub path: String,
     /// The content to write to the file.
     pub content: String,
+    /// Create any missing parent directories of the file (default: false).
+    pub create_parents: Option<bool>,
+    /// What to do if the file already exists: `fail`, `overwrite` or `append` (default is set by the server, usually `overwrite`).
+    pub if_exists: Option<WriteIfExists>,
+    /// Return a unified diff of the previous and new content when an existing file is modified (default: false).
+    pub return_diff: Option<bool>,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+    /// Optional client-generated key identifying this operation. When a request is retried with the same key,
+    /// the server returns the original result instead of executing the operation again.
+    pub idempotency_key: Option<String>,
 }
 
 impl WriteFile {
@@ -36,13 +58,53 @@ impl WriteFile {
         params: Self,
         context: &FileSystemService,
     ) -> std::result::Result<CallToolResult, CallToolError> {
-        context
-            .write_file(Path::new(&params.path), &params.content)
+        // checked before writing, the file is not expected to be in use afterwards
+        let warning = context
+            .file_lock_state(Path::new(&params.path))
+            .await
+            .ok()
+            .and_then(|lock_state| lock_state.warning());
+
+        let outcome = context
+            .write_file(
+                Path::new(&params.path),
+                &params.content,
+                params.create_parents.unwrap_or(false),
+                params.if_exists,
+                params.return_diff.unwrap_or(false),
+            )
             .await
             .map_err(CallToolError::new)?;
 
-        Ok(CallToolResult::text_content(vec![TextContent::from(
-            format!("Successfully wrote to {}", &params.path),
-        )]))
+        let message = Message::FileWritten { path: &params.path }.localize(context.locale());
+        let message = match &outcome.diff {
+            Some(diff) => format!("{message}\n\n{diff}"),
+            None => message,
+        };
+        let message = match &warning {
+            Some(warning) => format!("Warning: {warning}\n{message}"),
+            None => message,
+        };
+
+        let digest = outcome.digest;
+        let mut structured_content = Map::new();
+        structured_content.insert("path".to_string(), Value::String(params.path));
+        structured_content.insert("sha256".to_string(), Value::String(digest.sha256));
+        structured_content.insert("bytes".to_string(), Value::from(digest.bytes));
+
+        let mut output = structured_content.clone();
+        if let Some(diff) = outcome.diff {
+            output.insert("diff".to_string(), Value::String(diff));
+        }
+        if let Some(warning) = warning {
+            output.insert("warning".to_string(), Value::String(warning));
+        }
+
+        let result = tool_result(params.output_format, &output, || Ok(message))?;
+        // text results carry the checksum as structured content
+        Ok(match params.output_format {
+            Some(OutputFormat::Json) => result,
+            _ => result.with_structured_content(structured_content),
+        })
     }
 }
@@ -1,14 +1,27 @@
 use rust_mcp_sdk::macros::{JsonSchema, mcp_tool};
-use rust_mcp_sdk::schema::TextContent;
 use rust_mcp_sdk::schema::{CallToolResult, schema_utils::CallToolError};
+use serde_json::json;
 
-use crate::fs_service::FileSystemService;
+use super::output::tool_result;
+use crate::fs_service::{
+    FileSystemService, OverwrittenFile, TwoPhaseOutcome, ZipProgress,
+    utils::{OutputFormat, format_bytes},
+};
+use crate::progress::ProgressReporter;
+
+/// Adds the result message of an archive operation to its JSON output.
+fn with_message(mut output: serde_json::Value, message: &str) -> serde_json::Value {
+    output["message"] = message.into();
+    output
+}
 
 #[mcp_tool(
     name = "zip_files",
     title="Zip files",
     description = concat!("Creates a ZIP archive by compressing files. ",
 "It takes a list of files to compress and a target path for the resulting ZIP file. ",
+"Set 'create_parents' to true to create any missing parent directories of the target ZIP file. ",
+"The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text). ",
 "Both the source files and the target ZIP file should reside within allowed directories."),
     destructive_hint = false,
     idempotent_hint = false,
@@ -26,6 +39,14 @@ pub struct ZipFiles {
     pub input_files: Vec<String>,
     /// Path to save the resulting ZIP file, including filename and .zip extension
     pub target_zip_file: String,
+    /// Create any missing parent directories of the target ZIP file (default: false).
+    pub create_parents: Option<bool>,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+    /// Optional client-generated key identifying this operation. When a request is retried with the same key,
+    /// the server returns the original result instead of executing the operation again.
+    pub idempotency_key: Option<String>,
 }
 
 impl ZipFiles {
@@ -33,14 +54,24 @@ impl ZipFiles {
         params: Self,
         context: &FileSystemService,
     ) -> std::result::Result<CallToolResult, CallToolError> {
+        let output = json!({
+            "input_files": params.input_files,
+            "target_zip_file": params.target_zip_file,
+        });
         let result_content = context
-            .zip_files(params.input_files, params.target_zip_file)
+            .zip_files(
+                params.input_files,
+                params.target_zip_file,
+                params.create_parents.unwrap_or(false),
+            )
             .await
             .map_err(CallToolError::new)?;
         //TODO: return resource?
-        Ok(CallToolResult::text_content(vec![TextContent::from(
-            result_content,
-        )]))
+        tool_result(
+            params.output_format,
+            &with_message(output, &result_content),
+            || Ok(result_content),
+        )
     }
 }
 
@@ -49,7 +80,11 @@ impl ZipFiles {
     title = "Unzip Files",
     description = "Extracts the contents of a ZIP archive to a specified target directory.
 It takes a source ZIP file path and a target extraction directory.
-The tool decompresses all files and directories stored in the ZIP, recreating their structure in the target location.
+The tool decompresses all files and directories stored in the ZIP, recreating their structure in the target location, including empty directories.
+The result summarizes what was extracted: the number of files and directories, their total size and the top-level entries of the target directory.
+By default the extraction stops at the first entry that can't be extracted and the target directory is removed. Set 'continue_on_error' to true to skip unreadable or corrupt entries instead, they are listed in the result.
+The target directory must not exist, unless 'merge' is true: the archive is then extracted into the existing directory. When this would overwrite existing files, nothing is extracted at first: the result lists exactly which files would be replaced, along with a confirmation token. Call the tool again with the same arguments and the 'confirmation_token' to extract the archive.
+The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text).
 Both the source ZIP file and the target directory should reside within allowed directories.",
 icons = [
     (src = "https://rust-mcp-stack.github.io/rust-mcp-filesystem/_media/tool_icons/unzip_file.png",
@@ -63,21 +98,130 @@ pub struct UnzipFile {
     pub zip_file: String,
     /// Path to the target directory where the contents of the ZIP file will be extracted.
     pub target_path: String,
+    /// Skip the entries that can't be extracted instead of aborting the extraction (default: false).
+    pub continue_on_error: Option<bool>,
+    /// Extract into the target directory even if it exists, replacing the files of the same paths (default: false).
+    pub merge: Option<bool>,
+    /// The token returned by the preview of the files a merge would overwrite, to confirm the extraction.
+    /// Use a new idempotency key, if any, when confirming.
+    pub confirmation_token: Option<String>,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+    /// Optional client-generated key identifying this operation. When a request is retried with the same key,
+    /// the server returns the original result instead of executing the operation again.
+    pub idempotency_key: Option<String>,
 }
 
 impl UnzipFile {
+    fn format_preview(
+        params: &Self,
+        overwritten: &[OverwrittenFile],
+        confirmation_token: &str,
+        expires_at: &str,
+    ) -> String {
+        let mut message = format!(
+            "Extracting '{}' into '{}' would overwrite {} existing {}:",
+            params.zip_file,
+            params.target_path,
+            overwritten.len(),
+            if overwritten.len() == 1 {
+                "file"
+            } else {
+                "files"
+            }
+        );
+        for file in overwritten {
+            message.push_str(&format!(
+                "\n  {} ({} -> {})",
+                file.path.display(),
+                format_bytes(file.size),
+                format_bytes(file.new_size)
+            ));
+        }
+        message.push_str(&format!(
+            "\nNothing was extracted. To proceed, call unzip_file again with the same arguments and confirmation_token \"{confirmation_token}\" (expires at {expires_at})."
+        ));
+        message
+    }
+
     pub async fn run_tool(
         params: Self,
         context: &FileSystemService,
     ) -> std::result::Result<CallToolResult, CallToolError> {
-        let result_content = context
-            .unzip_file(&params.zip_file, &params.target_path)
-            .await
-            .map_err(CallToolError::new)?;
+        let continue_on_error = params.continue_on_error.unwrap_or(false);
+        let merge = params.merge.unwrap_or(false);
+        let overwritten = if merge {
+            context
+                .unzip_overwrites(&params.zip_file, &params.target_path)
+                .await
+                .map_err(CallToolError::new)?
+        } else {
+            vec![]
+        };
+
+        let extract = |_| {
+            context.unzip_file_with_report(
+                &params.zip_file,
+                &params.target_path,
+                continue_on_error,
+                merge,
+            )
+        };
+        let report = if overwritten.is_empty() {
+            extract(overwritten).await
+        } else {
+            let arguments = json!({
+                "zip_file": params.zip_file,
+                "target_path": params.target_path,
+                "continue_on_error": continue_on_error,
+                "merge": merge,
+            });
+            let outcome = context
+                .preview_or_apply(
+                    "unzip_file",
+                    &arguments,
+                    overwritten,
+                    params.confirmation_token.as_deref(),
+                    extract,
+                )
+                .await
+                .map_err(CallToolError::new)?;
+            match outcome {
+                TwoPhaseOutcome::Applied { result } => Ok(result),
+                TwoPhaseOutcome::Preview {
+                    ref preview,
+                    ref confirmation_token,
+                    ref expires_at,
+                } => {
+                    return tool_result(params.output_format, &outcome, || {
+                        Ok(Self::format_preview(
+                            &params,
+                            preview,
+                            confirmation_token,
+                            expires_at,
+                        ))
+                    });
+                }
+            }
+        }
+        .map_err(CallToolError::new)?;
+        let result_content = report.message(context.locale());
+        let output = json!({
+            "zip_file": params.zip_file,
+            "target_path": params.target_path,
+            "files": report.files,
+            "directories": report.directories,
+            "bytes": report.bytes,
+            "top_level": report.top_level,
+            "skipped": report.skipped,
+        });
         //TODO: return resource?
-        Ok(CallToolResult::text_content(vec![TextContent::from(
-            result_content,
-        )]))
+        tool_result(
+            params.output_format,
+            &with_message(output, &result_content),
+            || Ok(result_content),
+        )
     }
 }
 
@@ -86,6 +230,12 @@ impl UnzipFile {
     title = "Zip Directory",
     description = "Creates a ZIP archive by compressing a directory , including files and subdirectories matching a specified glob pattern.
 It takes a path to the folder and a glob pattern to identify files to compress and a target path for the resulting ZIP file.
+Set 'create_parents' to true to create any missing parent directories of the target ZIP file.
+Paths matching any of the optional 'exclude_patterns' are skipped, e.g. [\"target\", \".git\", \"node_modules\"] skips those directories entirely.
+Set 'include_empty_dirs' to true to store empty directories, so they are recreated when the archive is extracted.
+Set 'deterministic' to true to write entries in sorted order with fixed timestamps and permissions, so archiving the same tree twice produces byte-identical archives.
+When the directory and the options are unchanged since its last archive (same files, sizes and modification times), that archive is copied instead of compressing the files again. Set 'force' to true to always compress the files.
+The output_format argument specifies the format of the output and accepts either `text` or `json` (default: text).
 Both the source directory and the target ZIP file should reside within allowed directories.",
 icons = [
     (src = "https://rust-mcp-stack.github.io/rust-mcp-filesystem/_media/tool_icons/zip_directory.png",
@@ -99,23 +249,80 @@ pub struct ZipDirectory {
     pub input_directory: String,
     /// A optional glob pattern to match files and subdirectories to zip, defaults to **/*"
     pub pattern: Option<String>,
+    /// Optional list of glob patterns to exclude, matched against paths relative to the input directory.
+    pub exclude_patterns: Option<Vec<String>>,
     /// Path to save the resulting ZIP file, including filename and .zip extension
     pub target_zip_file: String,
+    /// Create any missing parent directories of the target ZIP file (default: false).
+    pub create_parents: Option<bool>,
+    /// Store empty directories as directory entries (default: false).
+    pub include_empty_dirs: Option<bool>,
+    /// Write entries in sorted order with fixed timestamps and permissions (default: false).
+    pub deterministic: Option<bool>,
+    /// Compress the files even if the directory is unchanged since its last archive, which is
+    /// otherwise copied (default: false).
+    pub force: Option<bool>,
+    /// Specify the output format, accepts either `text` or `json` (default: text).
+    #[json_schema(default = "text")]
+    pub output_format: Option<OutputFormat>,
+    /// Optional client-generated key identifying this operation. When a request is retried with the same key,
+    /// the server returns the original result instead of executing the operation again.
+    pub idempotency_key: Option<String>,
 }
 
 impl ZipDirectory {
     pub async fn run_tool(
         params: Self,
         context: &FileSystemService,
+    ) -> std::result::Result<CallToolResult, CallToolError> {
+        Self::run_tool_with_progress(params, context, None).await
+    }
+
+    /// Runs the tool, sending a progress notification as each file is added to the archive.
+    pub async fn run_tool_with_progress(
+        params: Self,
+        context: &FileSystemService,
+        progress_reporter: Option<ProgressReporter>,
     ) -> std::result::Result<CallToolResult, CallToolError> {
         let pattern = params.pattern.unwrap_or("**/*".to_string());
+        let on_progress = progress_reporter.map(|reporter| {
+            move |progress: ZipProgress| {
+                reporter.report(
+                    progress.files as f64,
+                    None,
+                    Some(format!(
+                        "Compressed {} files ({})",
+                        progress.files,
+                        format_bytes(progress.bytes)
+                    )),
+                )
+            }
+        });
+        let output = json!({
+            "input_directory": params.input_directory,
+            "target_zip_file": params.target_zip_file,
+        });
         let result_content = context
-            .zip_directory(params.input_directory, pattern, params.target_zip_file)
+            .zip_directory(
+                params.input_directory,
+                pattern,
+                params.exclude_patterns.unwrap_or_default(),
+                params.target_zip_file,
+                params.create_parents.unwrap_or(false),
+                params.include_empty_dirs.unwrap_or(false),
+                params.deterministic.unwrap_or(false),
+                params.force.unwrap_or(false),
+                on_progress
+                    .as_ref()
+                    .map(|f| f as &(dyn Fn(ZipProgress) + Send + Sync)),
+            )
             .await
             .map_err(CallToolError::new)?;
         //TODO: return resource?
-        Ok(CallToolResult::text_content(vec![TextContent::from(
-            result_content,
-        )]))
+        tool_result(
+            params.output_format,
+            &with_message(output, &result_content),
+            || Ok(result_content),
+        )
     }
 }
@@ -72,7 +72,9 @@ pub fn create_temp_file_info(content: &[u8]) -> (PathBuf, FileInfo) {
         accessed: metadata.accessed().ok(),
         is_directory: metadata.is_dir(),
         is_file: metadata.is_file(),
-        metadata,
+        access: None,
+        finder_metadata: None,
+        metadata: Some(metadata),
     };
     (dir, file_info)
 }
@@ -88,7 +90,9 @@ pub fn create_temp_dir() -> (TempDir, FileInfo) {
         accessed: metadata.accessed().ok(),
         is_directory: metadata.is_dir(),
         is_file: metadata.is_file(),
-        metadata,
+        access: None,
+        finder_metadata: None,
+        metadata: Some(metadata),
     };
     (dir, file_info)
 }
@@ -2,6 +2,13 @@
 pub mod common;
 
 use common::parse_args;
+use rust_mcp_filesystem::cli::Command;
+use rust_mcp_filesystem::debug_info::{debug_info, record_tool_error};
+use rust_mcp_filesystem::fs_service::utils::{LineEndingPolicy, ValidationProfile, WriteIfExists};
+use rust_mcp_filesystem::i18n::Locale;
+use rust_mcp_filesystem::recording::replay_arguments;
+use rust_mcp_filesystem::synthetic_tree::{SyntheticTreeOptions, generate_tree};
+use std::path::PathBuf;
 
 #[test]
 fn test_parse_with_single_directory() {
@@ -184,3 +191,457 @@ fn test_disable_tools_long_flag() {
         Some(vec!["read_text_file".to_string()])
     );
 }
+
+#[test]
+fn test_write_if_exists_default() {
+    let args = ["mcp-server", "/path/to/dir"];
+    let result = parse_args(&args).unwrap();
+    assert_eq!(result.write_if_exists, WriteIfExists::Overwrite);
+}
+
+#[test]
+fn test_write_if_exists_fail() {
+    let args = ["mcp-server", "--write-if-exists", "fail", "/path/to/dir"];
+    let result = parse_args(&args).unwrap();
+    assert_eq!(result.write_if_exists, WriteIfExists::Fail);
+}
+
+#[test]
+fn test_locale() {
+    let result = parse_args(&["mcp-server", "/path/to/dir"]).unwrap();
+    assert_eq!(result.locale, Locale::En);
+
+    let args = ["mcp-server", "--locale", "de_DE.UTF-8", "/path/to/dir"];
+    let result = parse_args(&args).unwrap();
+    assert_eq!(result.locale, Locale::De);
+
+    let args = ["mcp-server", "--locale", "xx", "/path/to/dir"];
+    assert!(parse_args(&args).is_err());
+}
+
+#[test]
+fn test_base_dir() {
+    let result = parse_args(&["mcp-server", "/path/to/dir"]).unwrap();
+    assert_eq!(result.base_dir, None);
+
+    let args = [
+        "mcp-server",
+        "--base-dir",
+        "/path/to/dir/src",
+        "/path/to/dir",
+    ];
+    let result = parse_args(&args).unwrap();
+    assert_eq!(result.base_dir, Some("/path/to/dir/src".to_string()));
+}
+
+#[test]
+fn test_io_retries() {
+    let result = parse_args(&["mcp-server", "/path/to/dir"]).unwrap();
+    assert_eq!(result.io_retries, 3);
+    assert_eq!(result.io_retry_backoff, 100);
+
+    let args = [
+        "mcp-server",
+        "--io-retries",
+        "0",
+        "--io-retry-backoff",
+        "250",
+        "/path/to/dir",
+    ];
+    let result = parse_args(&args).unwrap();
+    assert_eq!(result.io_retries, 0);
+    assert_eq!(result.io_retry_backoff, 250);
+}
+
+#[test]
+fn test_watchdog_interval() {
+    let result = parse_args(&["mcp-server", "/path/to/dir"]).unwrap();
+    assert_eq!(result.watchdog_interval, 10);
+
+    let args = ["mcp-server", "--watchdog-interval", "0", "/path/to/dir"];
+    let result = parse_args(&args).unwrap();
+    assert_eq!(result.watchdog_interval, 0);
+}
+
+#[test]
+fn test_debug_info_subcommand() {
+    let result = parse_args(&["mcp-server", "/path/to/dir"]).unwrap();
+    assert_eq!(result.command, None);
+
+    let args = [
+        "mcp-server",
+        "-w",
+        "/path/to/dir",
+        "debug-info",
+        "--redact-paths",
+    ];
+    let result = parse_args(&args).unwrap();
+    assert_eq!(result.allowed_directories, vec!["/path/to/dir"]);
+    assert_eq!(
+        result.command,
+        Some(Command::DebugInfo { redact_paths: true })
+    );
+
+    let temp_dir = tempfile::tempdir().unwrap();
+    let counters_path = temp_dir.path().join("errors.json");
+    record_tool_error(
+        &counters_path,
+        "read_text_file",
+        "'/path/to/dir/a.txt' not found",
+    );
+    record_tool_error(
+        &counters_path,
+        "read_text_file",
+        "'/path/to/dir/b.txt' not found",
+    );
+
+    let info = debug_info(&result, None, true, Some(&counters_path));
+    assert_eq!(info["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(info["configuration"]["allow_write"], true);
+    assert_eq!(
+        info["configuration"]["allowed_directories"][0]["path"],
+        "<allowed_directory_1>"
+    );
+    assert_eq!(info["tool_errors"]["read_text_file"]["count"], 2);
+    assert!(
+        info["tool_errors"]["read_text_file"]
+            .get("last_error")
+            .is_none()
+    );
+
+    let info = debug_info(&result, Some("invalid"), false, Some(&counters_path));
+    assert_eq!(info["validation_error"], "invalid");
+    assert_eq!(
+        info["configuration"]["allowed_directories"][0]["path"],
+        "/path/to/dir"
+    );
+    assert_eq!(
+        info["tool_errors"]["read_text_file"]["last_error"],
+        "'/path/to/dir/b.txt' not found"
+    );
+
+    // the counters are only readable by the user
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = std::fs::metadata(&counters_path)
+            .unwrap()
+            .permissions()
+            .mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+
+    // errors are only counted with `--count-errors`
+    assert!(!result.count_errors);
+    let result = parse_args(&["mcp-server", "--count-errors", "/path/to/dir"]).unwrap();
+    assert!(result.count_errors);
+    let info = debug_info(&result, None, false, None);
+    assert_eq!(info["configuration"]["count_errors"], true);
+    assert_eq!(info["tool_errors"], serde_json::json!({}));
+}
+
+#[test]
+fn test_cache_directory_sizes() {
+    let result = parse_args(&["mcp-server", "/path/to/dir"]).unwrap();
+    assert!(!result.cache_directory_sizes);
+    let result = parse_args(&["mcp-server", "--cache-directory-sizes", "/path/to/dir"]).unwrap();
+    assert!(result.cache_directory_sizes);
+}
+
+#[test]
+fn test_lazy_validation() {
+    let result = parse_args(&["mcp-server", "/path/to/dir"]).unwrap();
+    assert!(!result.lazy_validation);
+
+    let args = ["mcp-server", "--lazy-validation", "/path/to/dir"];
+    let result = parse_args(&args).unwrap();
+    assert!(result.lazy_validation);
+}
+
+#[test]
+fn test_pinned_paths() {
+    let result = parse_args(&["mcp-server", "/path/to/dir"]).unwrap();
+    assert!(result.pinned_paths.is_empty());
+
+    let args = [
+        "mcp-server",
+        "--pin",
+        "Cargo.lock",
+        "--pin",
+        ".github/workflows,/path/to/dir/LICENSE",
+        "/path/to/dir",
+    ];
+    let result = parse_args(&args).unwrap();
+    assert_eq!(
+        result.pinned_paths,
+        vec!["Cargo.lock", ".github/workflows", "/path/to/dir/LICENSE"]
+    );
+    assert_eq!(result.allowed_directories, vec!["/path/to/dir"]);
+}
+
+#[test]
+fn test_validation_profile() {
+    let result = parse_args(&["mcp-server", "/path/to/dir"]).unwrap();
+    assert_eq!(result.validation_profile, ValidationProfile::Strict);
+
+    let args = [
+        "mcp-server",
+        "--validation-profile",
+        "relaxed",
+        "/path/to/dir",
+    ];
+    let result = parse_args(&args).unwrap();
+    assert_eq!(result.validation_profile, ValidationProfile::Relaxed);
+
+    let args = ["mcp-server", "--validation-profile", "none", "/path/to/dir"];
+    assert!(parse_args(&args).is_err());
+}
+
+#[test]
+fn test_generate_tree_subcommand() {
+    let args = [
+        "mcp-server",
+        "generate-tree",
+        "/tmp/tree",
+        "--files",
+        "50",
+        "--duplicate-percent",
+        "20",
+    ];
+    let result = parse_args(&args).unwrap();
+    assert_eq!(
+        result.command,
+        Some(Command::GenerateTree {
+            target_dir: PathBuf::from("/tmp/tree"),
+            files: 50,
+            depth: 4,
+            fan_out: 8,
+            file_size: 4096,
+            duplicate_percent: 20,
+            seed: 42,
+        })
+    );
+    assert!(
+        parse_args(&[
+            "mcp-server",
+            "generate-tree",
+            "/tmp/tree",
+            "--duplicate-percent",
+            "101"
+        ])
+        .is_err()
+    );
+
+    // the same seed generates the same tree, in an empty directory only
+    let temp_dir = tempfile::tempdir().unwrap();
+    let options = SyntheticTreeOptions {
+        files: 50,
+        file_size: 256,
+        duplicate_percent: 20,
+        ..Default::default()
+    };
+    let summary = generate_tree(&temp_dir.path().join("first"), &options).unwrap();
+    assert_eq!(summary.files, 50);
+    assert!(summary.duplicates > 0 && summary.files_with_needle > 0);
+    assert_eq!(
+        generate_tree(&temp_dir.path().join("second"), &options).unwrap(),
+        summary
+    );
+    let files = |root: &str| -> Vec<(String, String)> {
+        walkdir::WalkDir::new(temp_dir.path().join(root))
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(Result::ok)
+            .filter(|entry| entry.file_type().is_file())
+            .map(|entry| {
+                (
+                    entry.file_name().to_string_lossy().to_string(),
+                    std::fs::read_to_string(entry.path()).unwrap(),
+                )
+            })
+            .collect()
+    };
+    assert_eq!(files("first").len(), 50);
+    assert_eq!(files("first"), files("second"));
+    assert!(generate_tree(&temp_dir.path().join("first"), &options).is_err());
+}
+
+#[test]
+fn test_record_and_replay_arguments() {
+    let result = parse_args(&["mcp-server", "/path/to/dir"]).unwrap();
+    assert_eq!(result.record_dir, None);
+
+    let args = [
+        "mcp-server",
+        "--record",
+        "/tmp/recordings",
+        "/path/to/dir",
+        "replay",
+        "/tmp/recordings/session.jsonl",
+    ];
+    let result = parse_args(&args).unwrap();
+    assert_eq!(result.record_dir.as_deref(), Some("/tmp/recordings"));
+    assert_eq!(result.allowed_directories, vec!["/path/to/dir"]);
+    assert_eq!(
+        result.command,
+        Some(Command::Replay {
+            recording: PathBuf::from("/tmp/recordings/session.jsonl"),
+            apply: false
+        })
+    );
+
+    // replays don't modify the tree unless applied
+    let args = replay_arguments(
+        parse_args(&[
+            "mcp-server",
+            "--allow-write",
+            "--record",
+            "/tmp/r",
+            "/path/to/dir",
+        ])
+        .unwrap(),
+        false,
+    );
+    assert!(args.simulate && args.record_dir.is_none());
+    let args = replay_arguments(
+        parse_args(&[
+            "mcp-server",
+            "--overlay-dir",
+            "/tmp/overlay",
+            "/path/to/dir",
+        ])
+        .unwrap(),
+        false,
+    );
+    assert!(args.simulate && args.overlay_dir.is_none());
+    let args = replay_arguments(parse_args(&["mcp-server", "/path/to/dir"]).unwrap(), false);
+    assert!(!args.simulate && !args.allow_write);
+    let args = replay_arguments(
+        parse_args(&["mcp-server", "--allow-write", "/path/to/dir"]).unwrap(),
+        true,
+    );
+    assert!(!args.simulate && args.allow_write);
+    let result = parse_args(&[
+        "mcp-server",
+        "/path/to/dir",
+        "replay",
+        "--apply",
+        "/tmp/recordings/session.jsonl",
+    ])
+    .unwrap();
+    assert!(matches!(
+        result.command,
+        Some(Command::Replay { apply: true, .. })
+    ));
+
+    // file contents are only kept on request, for a recording
+    assert!(!result.record_content);
+    let result = parse_args(&[
+        "mcp-server",
+        "--record",
+        "/tmp/recordings",
+        "--record-content",
+        "/path/to/dir",
+    ])
+    .unwrap();
+    assert!(result.record_content);
+    assert!(parse_args(&["mcp-server", "--record-content", "/path/to/dir"]).is_err());
+}
+
+#[test]
+fn test_batch_limits() {
+    let result = parse_args(&["mcp-server", "/path/to/dir"]).unwrap();
+    assert_eq!(result.batch_concurrency, 2);
+    assert_eq!(result.batch_threads, 0);
+
+    let args = [
+        "mcp-server",
+        "--batch-concurrency",
+        "1",
+        "--batch-threads",
+        "4",
+        "/path/to/dir",
+    ];
+    let result = parse_args(&args).unwrap();
+    assert_eq!(result.batch_concurrency, 1);
+    assert_eq!(result.batch_threads, 4);
+}
+
+#[test]
+fn test_no_editorconfig() {
+    let result = parse_args(&["mcp-server", "/path/to/dir"]).unwrap();
+    assert!(!result.no_editorconfig);
+
+    let result = parse_args(&["mcp-server", "--no-editorconfig", "/path/to/dir"]).unwrap();
+    assert!(result.no_editorconfig);
+}
+
+#[test]
+fn test_write_line_endings() {
+    let result = parse_args(&["mcp-server", "/path/to/dir"]).unwrap();
+    assert_eq!(result.write_line_endings, LineEndingPolicy::Preserve);
+
+    let args = ["mcp-server", "--write-line-endings", "crlf", "/path/to/dir"];
+    let result = parse_args(&args).unwrap();
+    assert_eq!(result.write_line_endings, LineEndingPolicy::Crlf);
+
+    let args = [
+        "mcp-server",
+        "--write-line-endings",
+        "mixed",
+        "/path/to/dir",
+    ];
+    assert!(parse_args(&args).is_err());
+}
+
+#[test]
+fn test_simulate() {
+    let result = parse_args(&["mcp-server", "/path/to/dir"]).unwrap();
+    assert!(!result.simulate);
+
+    let result = parse_args(&["mcp-server", "--simulate", "/path/to/dir"]).unwrap();
+    assert!(result.simulate);
+}
+
+#[test]
+fn test_overlay_dir() {
+    let result = parse_args(&["mcp-server", "/path/to/dir"]).unwrap();
+    assert_eq!(result.overlay_dir, None);
+
+    let args = [
+        "mcp-server",
+        "--overlay-dir",
+        "/tmp/overlay",
+        "/path/to/dir",
+    ];
+    let result = parse_args(&args).unwrap();
+    assert_eq!(result.overlay_dir.as_deref(), Some("/tmp/overlay"));
+
+    let args = [
+        "mcp-server",
+        "--overlay-dir",
+        "/tmp/overlay",
+        "--simulate",
+        "/path/to/dir",
+    ];
+    assert!(parse_args(&args).is_err());
+}
+
+#[test]
+fn test_response_cache_ttl() {
+    let result = parse_args(&["mcp-server", "/path/to/dir"]).unwrap();
+    assert_eq!(result.response_cache_ttl, 0);
+
+    let args = ["mcp-server", "--response-cache-ttl", "5", "/path/to/dir"];
+    let result = parse_args(&args).unwrap();
+    assert_eq!(result.response_cache_ttl, 5);
+}
+
+#[test]
+fn test_ascii_output() {
+    let result = parse_args(&["mcp-server", "/path/to/dir"]).unwrap();
+    assert!(!result.ascii_output);
+
+    let result = parse_args(&["mcp-server", "--ascii-output", "/path/to/dir"]).unwrap();
+    assert!(result.ascii_output);
+}
@@ -10,9 +10,23 @@ use common::setup_service;
 use dirs::home_dir;
 use grep::matcher::Match;
 use rust_mcp_filesystem::error::ServiceError;
+use rust_mcp_filesystem::fs_service::BackupConfig;
+use rust_mcp_filesystem::fs_service::BatchLimits;
+use rust_mcp_filesystem::fs_service::DirectoryAvailabilityChange;
 use rust_mcp_filesystem::fs_service::FileInfo;
-use rust_mcp_filesystem::fs_service::FileSystemService;
+use rust_mcp_filesystem::fs_service::ResolvedPath;
+use rust_mcp_filesystem::fs_service::TreeEntry;
+use rust_mcp_filesystem::fs_service::TwoPhaseOutcome;
+use rust_mcp_filesystem::fs_service::ZipProgress;
+use rust_mcp_filesystem::fs_service::platform::{PortableMetadata, is_read_only_filesystem};
 use rust_mcp_filesystem::fs_service::utils::*;
+use rust_mcp_filesystem::fs_service::{AllowedDirectoriesDelta, FileSystemService};
+use rust_mcp_filesystem::fs_service::{FinderTag, FinderTagColor, FinderTagsUpdate};
+use rust_mcp_filesystem::fs_service::{Indentation, detect_file_profile};
+use rust_mcp_filesystem::fs_service::{PathPlatform, TimelineBucket, check_path_length};
+use rust_mcp_filesystem::fs_service::{RetryPolicy, is_transient_error};
+use rust_mcp_filesystem::fs_service::{describe_lock_violation, is_lock_violation, lock_holders};
+use rust_mcp_filesystem::i18n::{Locale, Message};
 use rust_mcp_filesystem::tools::EditOperation;
 use std::fs::{self, File};
 use std::io::Write;
@@ -69,7 +83,7 @@ async fn test_validate_path_denied() {
     let (temp_dir, service, allowed_dirs) = setup_service(vec!["dir1".to_string()]);
     let outside_path = temp_dir.join("dir2").join("test.txt");
     let result = service.validate_path(&outside_path, allowed_dirs);
-    assert!(matches!(result, Err(ServiceError::FromString(_))));
+    assert!(matches!(result, Err(ServiceError::AccessDenied(_))));
 }
 
 #[test]
@@ -126,7 +140,13 @@ async fn test_zip_directory() {
         .zip_directory(
             dir_path.to_str().unwrap().to_string(),
             "*.txt".to_string(),
+            vec![],
             zip_path.to_str().unwrap().to_string(),
+            false,
+            false,
+            false,
+            false,
+            None,
         )
         .await
         .unwrap();
@@ -144,7 +164,13 @@ async fn test_zip_directory_already_exists() {
         .zip_directory(
             dir_path.to_str().unwrap().to_string(),
             "*.txt".to_string(),
+            vec![],
             zip_path.to_str().unwrap().to_string(),
+            false,
+            false,
+            false,
+            false,
+            None,
         )
         .await;
     assert!(matches!(
@@ -168,6 +194,7 @@ async fn test_zip_files() {
                 file2.to_str().unwrap().to_string(),
             ],
             zip_path.to_str().unwrap().to_string(),
+            false,
         )
         .await
         .unwrap();
@@ -181,7 +208,7 @@ async fn test_zip_files_empty_input() {
     let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
     let zip_path = temp_dir.join("output.zip");
     let result = service
-        .zip_files(vec![], zip_path.to_str().unwrap().to_string())
+        .zip_files(vec![], zip_path.to_str().unwrap().to_string(), false)
         .await;
     assert!(matches!(
         result,
@@ -199,6 +226,7 @@ async fn test_unzip_file() {
         .zip_files(
             vec![file1.to_str().unwrap().to_string()],
             zip_path.to_str().unwrap().to_string(),
+            false,
         )
         .await
         .unwrap();
@@ -358,7 +386,7 @@ async fn test_move_file() {
     let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
     let src_path = create_temp_file(temp_dir.join("dir1").as_path(), "src.txt", "content");
     let dest_path = temp_dir.join("dir1").join("dest.txt");
-    let result = service.move_file(&src_path, &dest_path).await;
+    let result = service.move_file(&src_path, &dest_path, false).await;
     assert!(result.is_ok());
     assert!(!src_path.exists());
     assert!(dest_path.exists());
@@ -370,7 +398,7 @@ async fn test_list_directory() {
     let dir_path = temp_dir.join("dir1");
     create_temp_file(&dir_path, "file1.txt", "content1");
     create_temp_file(&dir_path, "file2.txt", "content2");
-    let entries = service.list_directory(&dir_path).await.unwrap();
+    let entries = service.list_directory(&dir_path, false).await.unwrap();
     let names: Vec<_> = entries
         .into_iter()
         .map(|e| e.file_name().to_str().unwrap().to_string())
@@ -385,7 +413,9 @@ async fn test_write_file() {
     let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
     let file_path = temp_dir.join("dir1").join("test.txt");
     let content = "new content".to_string();
-    let result = service.write_file(&file_path, &content).await;
+    let result = service
+        .write_file(&file_path, &content, false, None, false)
+        .await;
     assert!(result.is_ok());
     assert_eq!(tokio_fs::read_to_string(&file_path).await.unwrap(), content);
 }
@@ -693,7 +723,9 @@ fn test_display_format_for_empty_timestamps() {
         accessed: None,
         is_directory: false,
         is_file: true,
-        metadata: metadata.clone(),
+        access: None,
+        finder_metadata: None,
+        metadata: Some(metadata.clone()),
     };
 
     let display_output = file_info.to_string();
@@ -1171,7 +1203,9 @@ async fn test_content_search() {
     let query = r#"Watso\d*n"#;
 
     // search as regex
-    let result = service.content_search(query, &file, Some(true)).unwrap();
+    let result = service
+        .content_search(query, &file, Some(true), None, None)
+        .unwrap();
 
     assert!(result.is_some());
     let result = result.unwrap();
@@ -1190,7 +1224,9 @@ async fn test_content_search() {
     );
 
     // search as literal
-    let result = service.content_search(query, &file, Some(false)).unwrap();
+    let result = service
+        .content_search(query, &file, Some(false), None, None)
+        .unwrap();
     assert!(result.is_some());
     let result = result.unwrap();
     assert_eq!(result.matches.len(), 1);
@@ -1315,11 +1351,13 @@ async fn search_files_content() {
         .search_files_content(
             temp_dir.as_path().join("dir_search"),
             "*.txt",
-            query,
+            &[query],
             true,
             None,
             None,
             None,
+            None,
+            None,
         )
         .await
         .unwrap();
@@ -1699,7 +1737,7 @@ async fn test_calculate_directory_size_normal() {
     create_temp_file(&temp_dir.join("dir1"), "file2.txt", "content22");
 
     let size = service
-        .calculate_directory_size(&temp_dir.join("dir1"))
+        .calculate_directory_size(&temp_dir.join("dir1"), false)
         .await
         .unwrap();
     assert_eq!(size, 17); // "content1" (8 bytes) + "content22" (9 bytes) = 17 bytes
@@ -1711,7 +1749,7 @@ async fn test_calculate_directory_size_empty_dir() {
     create_sub_dir(&temp_dir, "dir1").await;
 
     let size = service
-        .calculate_directory_size(&temp_dir.join("dir1"))
+        .calculate_directory_size(&temp_dir.join("dir1"), false)
         .await
         .unwrap();
     assert_eq!(size, 0);
@@ -1724,7 +1762,7 @@ async fn test_calculate_directory_size_nested_files() {
     create_temp_file(&temp_dir.join("dir1/subdir"), "file2.txt", "content22");
 
     let size = service
-        .calculate_directory_size(&temp_dir.join("dir1"))
+        .calculate_directory_size(&temp_dir.join("dir1"), false)
         .await
         .unwrap();
     assert_eq!(size, 17); // "content1" (8 bytes) + "content22" (9 bytes) = 17 bytes
@@ -1735,7 +1773,7 @@ async fn test_calculate_directory_size_invalid_path() {
     let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
     let invalid_path = temp_dir.join("dir2");
 
-    let result = service.calculate_directory_size(&invalid_path).await;
+    let result = service.calculate_directory_size(&invalid_path, false).await;
     assert!(result.is_err(), "Expected error for invalid path");
 }
 
@@ -2133,5 +2171,2893 @@ async fn test_search_files_brace_expanded_github_issue_50() {
     assert_eq!(names.len(), 5);
 }
 
+#[tokio::test]
+async fn test_read_file_preview_truncated() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let file_path = create_temp_file(&temp_dir.join("dir1"), "notes.txt", "hello preview world");
+
+    let preview = service.read_file_preview(&file_path, 5).await.unwrap();
+    assert_eq!(preview.content, b"hello");
+    assert!(preview.truncated);
+
+    let preview = service.read_file_preview(&file_path, 1024).await.unwrap();
+    assert_eq!(preview.content, b"hello preview world");
+    assert!(!preview.truncated);
+}
+
+#[tokio::test]
+async fn test_read_file_preview_outside_allowed() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let file_path = create_temp_file(&temp_dir.join("dir2"), "notes.txt", "secret");
+    let result = service.read_file_preview(&file_path, 16).await;
+    assert!(matches!(result, Err(ServiceError::AccessDenied(_))));
+}
+
+#[test]
+fn test_mime_category_from_extension() {
+    assert_eq!(
+        mime_category_from_extension(Path::new("photo.JPG")),
+        Some(MimeCategory::Image)
+    );
+    assert_eq!(
+        mime_category_from_extension(Path::new("backup.tar")),
+        Some(MimeCategory::Archive)
+    );
+    assert_eq!(mime_category_from_extension(Path::new("unknown.xyz")), None);
+    assert_eq!(mime_category_from_extension(Path::new("README")), None);
+}
+
+#[test]
+fn test_matches_mime_category_sniffs_content() {
+    let temp_dir = get_temp_dir();
+    // PNG signature stored under a misleading extension
+    let png_path = temp_dir.join("image.dat");
+    fs::write(
+        &png_path,
+        [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 0],
+    )
+    .unwrap();
+    let text_path = create_temp_file(&temp_dir, "notes", "plain text content");
+    let binary_path = temp_dir.join("blob");
+    fs::write(&binary_path, [0u8, 159, 146, 150]).unwrap();
+
+    assert!(matches_mime_category(&png_path, MimeCategory::Image));
+    assert!(!matches_mime_category(&png_path, MimeCategory::Text));
+    assert!(matches_mime_category(&text_path, MimeCategory::Text));
+    assert!(matches_mime_category(&binary_path, MimeCategory::Binary));
+}
+
+#[tokio::test]
+async fn test_find_file_type_mismatches() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    // an html error page saved with an image extension
+    create_temp_file(
+        &dir_path,
+        "photo.jpg",
+        "<!DOCTYPE html><html><body>404</body></html>",
+    );
+    // a real png
+    fs::write(
+        dir_path.join("real.png"),
+        [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 0],
+    )
+    .unwrap();
+    create_temp_file(&dir_path, "notes.txt", "plain text");
+    create_temp_file(&dir_path, "logo.svg", "<svg></svg>");
+
+    let mismatches = service
+        .find_file_type_mismatches(&dir_path, None, None)
+        .await
+        .unwrap();
+
+    assert_eq!(mismatches.len(), 1);
+    assert!(mismatches[0].path.ends_with("photo.jpg"));
+    assert_eq!(mismatches[0].expected, MimeCategory::Image);
+    assert_eq!(mismatches[0].detected, MimeCategory::Text);
+}
+
+#[tokio::test]
+async fn test_find_file_type_mismatches_invalid_path() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let result = service
+        .find_file_type_mismatches(&temp_dir.join("dir2"), None, None)
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_content_search_custom_snippet_length() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir_search".to_string()]);
+    let file = create_temp_file(
+        &temp_dir.join("dir_search"),
+        "long_line.txt",
+        "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa needle bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb",
+    );
+
+    let result = service
+        .content_search("needle", &file, None, Some(12), Some(3))
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(result.matches[0].line_text, "...aa needle bb...");
+}
+
+#[tokio::test]
+async fn test_content_search_multiple_matches_per_line() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir_search".to_string()]);
+    let file = create_temp_file(
+        &temp_dir.join("dir_search"),
+        "repeated.txt",
+        "foo bar foo baz foo\nno match here\nfoo",
+    );
+
+    let result = service
+        .content_search("foo", &file, None, None, None)
+        .unwrap()
+        .unwrap();
+
+    assert_eq!(result.matches.len(), 4);
+    let first_line: Vec<_> = result
+        .matches
+        .iter()
+        .filter(|m| m.line_number == 1)
+        .map(|m| (m.start_pos, m.end_pos))
+        .collect();
+    assert_eq!(first_line, vec![(0, 3), (8, 11), (16, 19)]);
+    assert_eq!(result.matches[3].line_number, 3);
+}
+
+#[tokio::test]
+async fn test_write_file_create_parents() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let file_path = temp_dir.join("dir1").join("src/new/module/mod.rs");
+    let content = "pub mod nested;".to_string();
+
+    let result = service
+        .write_file(&file_path, &content, false, None, false)
+        .await;
+    assert!(matches!(result, Err(ServiceError::IoError(_))));
+
+    service
+        .write_file(&file_path, &content, true, None, false)
+        .await
+        .unwrap();
+    assert_eq!(tokio_fs::read_to_string(&file_path).await.unwrap(), content);
+}
+
+#[tokio::test]
+async fn test_move_file_create_parents() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let src_path = create_temp_file(temp_dir.join("dir1").as_path(), "src.txt", "content");
+    let dest_path = temp_dir.join("dir1").join("a/b/dest.txt");
+    service
+        .move_file(&src_path, &dest_path, true)
+        .await
+        .unwrap();
+    assert!(!src_path.exists());
+    assert!(dest_path.exists());
+}
+
+#[tokio::test]
+async fn test_zip_files_create_parents() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    let file1 = create_temp_file(&dir_path, "file1.txt", "content1");
+    let zip_path = dir_path.join("archives/2024/output.zip");
+    service
+        .zip_files(
+            vec![file1.to_str().unwrap().to_string()],
+            zip_path.to_str().unwrap().to_string(),
+            true,
+        )
+        .await
+        .unwrap();
+    assert!(zip_path.exists());
+}
+
+#[tokio::test]
+async fn test_write_file_if_exists_fail() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let file_path = create_temp_file(&temp_dir.join("dir1"), "existing.txt", "original");
+
+    let result = service
+        .write_file(&file_path, "new", false, Some(WriteIfExists::Fail), false)
+        .await;
+    assert!(matches!(
+        result,
+        Err(ServiceError::IoError(ref e)) if e.kind() == std::io::ErrorKind::AlreadyExists
+    ));
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), "original");
+
+    // a missing file is created as usual
+    let new_path = temp_dir.join("dir1").join("new.txt");
+    service
+        .write_file(&new_path, "new", false, Some(WriteIfExists::Fail), false)
+        .await
+        .unwrap();
+    assert_eq!(fs::read_to_string(&new_path).unwrap(), "new");
+}
+
+#[tokio::test]
+async fn test_write_file_if_exists_append() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let file_path = create_temp_file(&temp_dir.join("dir1"), "log.txt", "line1\n");
+    let outcome = service
+        .write_file(
+            &file_path,
+            "line2\n",
+            false,
+            Some(WriteIfExists::Append),
+            false,
+        )
+        .await
+        .unwrap();
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), "line1\nline2\n");
+    // the digest covers the whole file, the existing content included
+    assert_eq!(
+        outcome.digest,
+        service.file_digest(&file_path).await.unwrap()
+    );
+    assert_eq!(outcome.digest.bytes, 12);
+}
+
+#[tokio::test]
+async fn test_write_file_service_default_if_exists() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let service = service.with_write_if_exists(WriteIfExists::Fail);
+    let file_path = create_temp_file(&temp_dir.join("dir1"), "existing.txt", "original");
+    let result = service
+        .write_file(&file_path, "new", false, None, false)
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_write_file_returns_diff() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let file_path = create_temp_file(&temp_dir.join("dir1"), "config.txt", "a\nb\nc\n");
+
+    let diff = service
+        .write_file(&file_path, "a\nB\nc\n", false, None, true)
+        .await
+        .unwrap()
+        .diff
+        .unwrap();
+    assert!(diff.starts_with("```diff\n"));
+    assert!(diff.contains("-b\n+B"));
+
+    // no diff for a newly created file
+    let new_path = temp_dir.join("dir1").join("new.txt");
+    let outcome = service
+        .write_file(&new_path, "content", false, None, true)
+        .await
+        .unwrap();
+    assert!(outcome.diff.is_none());
+}
+
+fn backup_config(retention: usize) -> BackupConfig {
+    BackupConfig {
+        dir: PathBuf::from(".mcp-backups"),
+        retention,
+    }
+}
+
+#[tokio::test]
+async fn test_write_file_creates_backup() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let service = service.with_backups(backup_config(10));
+    let file_path = create_temp_file(&temp_dir.join("dir1").join("sub"), "notes.txt", "v1");
+
+    service
+        .write_file(&file_path, "v2", false, None, false)
+        .await
+        .unwrap();
+
+    let backups = service.list_backups(None).await.unwrap();
+    assert_eq!(backups.len(), 1);
+    assert_eq!(backups[0].original_path, file_path);
+    assert!(
+        backups[0]
+            .backup_path
+            .starts_with(temp_dir.join("dir1").join(".mcp-backups").join("sub"))
+    );
+    assert_eq!(fs::read_to_string(&backups[0].backup_path).unwrap(), "v1");
+    assert_eq!(backups[0].size, 2);
+}
+
+#[tokio::test]
+async fn test_backups_disabled_by_default() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let file_path = create_temp_file(&temp_dir.join("dir1"), "notes.txt", "v1");
+
+    service
+        .write_file(&file_path, "v2", false, None, false)
+        .await
+        .unwrap();
+
+    assert!(!temp_dir.join("dir1").join(".mcp-backups").exists());
+    assert!(matches!(
+        service.list_backups(None).await,
+        Err(ServiceError::FromString(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_backup_retention() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let service = service.with_backups(backup_config(2));
+    let file_path = create_temp_file(&temp_dir.join("dir1"), "notes.txt", "v0");
+    let other_path = create_temp_file(&temp_dir.join("dir1"), "other.txt", "other");
+
+    service
+        .write_file(&other_path, "other2", false, None, false)
+        .await
+        .unwrap();
+    for version in 1..=4 {
+        // backup names have millisecond resolution
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        service
+            .write_file(&file_path, &format!("v{version}"), false, None, false)
+            .await
+            .unwrap();
+    }
+
+    let backups = service.list_backups(Some(&file_path)).await.unwrap();
+    let contents: Vec<String> = backups
+        .iter()
+        .map(|b| fs::read_to_string(&b.backup_path).unwrap())
+        .collect();
+    // newest first, only the two most recent are kept
+    assert_eq!(contents, vec!["v3", "v2"]);
+
+    // retention is applied per file
+    assert_eq!(
+        service.list_backups(Some(&other_path)).await.unwrap().len(),
+        1
+    );
+}
+
+#[tokio::test]
+async fn test_edit_file_creates_backup_unless_dry_run() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let service = service.with_backups(backup_config(10));
+    let file_path = create_temp_file(&temp_dir.join("dir1"), "test.txt", "line1\nline2");
+    let edits = vec![EditOperation {
+        old_text: "line2".to_string(),
+        new_text: "line3".to_string(),
+    }];
+
+    service
+        .apply_file_edits(&file_path, edits.clone(), Some(true), None, None)
+        .await
+        .unwrap();
+    assert!(service.list_backups(None).await.unwrap().is_empty());
+
+    service
+        .apply_file_edits(&file_path, edits, Some(false), None, None)
+        .await
+        .unwrap();
+    let backups = service.list_backups(None).await.unwrap();
+    assert_eq!(backups.len(), 1);
+    assert_eq!(
+        fs::read_to_string(&backups[0].backup_path).unwrap(),
+        "line1\nline2"
+    );
+}
+
+#[tokio::test]
+async fn test_restore_backup() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let service = service.with_backups(backup_config(10));
+    let file_path = create_temp_file(&temp_dir.join("dir1"), "notes.txt", "v1");
+
+    service
+        .write_file(&file_path, "v2", false, None, false)
+        .await
+        .unwrap();
+    let backup = service.list_backups(None).await.unwrap().remove(0);
+
+    std::thread::sleep(std::time::Duration::from_millis(5));
+    let restored = service.restore_backup(&backup.backup_path).await.unwrap();
+    assert_eq!(restored, file_path);
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), "v1");
+
+    // the content replaced by the restore is backed up as well
+    let backups = service.list_backups(Some(&file_path)).await.unwrap();
+    assert_eq!(backups.len(), 2);
+    assert_eq!(fs::read_to_string(&backups[0].backup_path).unwrap(), "v2");
+
+    // only files inside the backup directory can be restored
+    let result = service.restore_backup(&file_path).await;
+    assert!(matches!(result, Err(ServiceError::FromString(_))));
+
+    // the original path derived from a backup is validated like any written path
+    let backup_dir = backup.backup_path.parent().unwrap();
+    let backup_name = backup.backup_path.file_name().unwrap().to_str().unwrap();
+    let crafted_backup = |relative_dir: &str, original_name: &str| {
+        let path =
+            backup_dir
+                .join(relative_dir)
+                .join(backup_name.replacen("notes.txt", original_name, 1));
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(&path, "crafted").unwrap();
+        path
+    };
+    let result = service.restore_backup(&crafted_backup("", "..")).await;
+    assert!(matches!(result, Err(ServiceError::FromString(_))));
+    #[cfg(unix)]
+    {
+        let outside = temp_dir.join("outside");
+        fs::create_dir_all(&outside).unwrap();
+        std::os::unix::fs::symlink(&outside, temp_dir.join("dir1/link")).unwrap();
+        let result = service
+            .restore_backup(&crafted_backup("link", "secret.txt"))
+            .await;
+        assert!(matches!(result, Err(ServiceError::AccessDenied(_))));
+        assert!(!outside.join("secret.txt").exists());
+    }
+    let service = service.with_pinned_paths(vec![file_path.clone()]);
+    let result = service.restore_backup(&backup.backup_path).await;
+    assert!(matches!(result, Err(ServiceError::PinnedPath(_))));
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), "v1");
+}
+
+#[tokio::test]
+async fn test_list_directory_exclude_hidden() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    create_temp_file(&dir_path, "visible.txt", "content");
+    create_temp_file(&dir_path, ".hidden", "content");
+
+    let names = |entries: Vec<TreeEntry>| {
+        let mut names: Vec<_> = entries
+            .into_iter()
+            .map(|e| e.file_name().to_str().unwrap().to_string())
+            .collect();
+        names.sort();
+        names
+    };
+
+    let entries = service.list_directory(&dir_path, false).await.unwrap();
+    assert_eq!(names(entries), vec![".hidden", "visible.txt"]);
+
+    #[cfg(not(windows))]
+    {
+        let entries = service.list_directory(&dir_path, true).await.unwrap();
+        assert_eq!(names(entries), vec!["visible.txt"]);
+    }
+}
+
+#[tokio::test]
+#[cfg(not(windows))]
+async fn test_get_file_stats_has_no_access_summary_outside_windows() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let file_path = create_temp_file(&temp_dir.join("dir1"), "file.txt", "content");
+
+    let file_info = service.get_file_stats(&file_path).await.unwrap();
+    assert!(file_info.access.is_none());
+    assert!(!file_info.to_string().contains("attributes:"));
+}
+
+#[test]
+fn test_finder_tags_round_trip() {
+    let mut tags = vec![
+        FinderTag {
+            name: "Red".to_string(),
+            color: FinderTagColor::Red,
+        },
+        FinderTag {
+            name: "A rather long tag name".to_string(),
+            color: FinderTagColor::None,
+        },
+        FinderTag {
+            name: "Ünïcödé".to_string(),
+            color: FinderTagColor::Blue,
+        },
+    ];
+    // more than 14 entries needs an extended array length
+    tags.extend((0..20).map(|i| FinderTag {
+        name: format!("tag{i}"),
+        color: FinderTagColor::Gray,
+    }));
+
+    let encoded = FinderTag::encode_list(&tags);
+    assert!(encoded.starts_with(b"bplist00"));
+    assert_eq!(FinderTag::decode_list(&encoded).unwrap(), tags);
+}
+
+#[test]
+fn test_finder_tags_decode_finder_plist() {
+    // ["Important\n6"] as written by Finder
+    let mut data = b"bplist00\xa1\x01\x5bImportant\n6\x08\x0a".to_vec();
+    data.extend_from_slice(&[0, 0, 0, 0, 0, 0, 1, 1]);
+    data.extend_from_slice(&2u64.to_be_bytes());
+    data.extend_from_slice(&0u64.to_be_bytes());
+    data.extend_from_slice(&22u64.to_be_bytes());
+
+    let tags = FinderTag::decode_list(&data).unwrap();
+    assert_eq!(
+        tags,
+        vec![FinderTag {
+            name: "Important".to_string(),
+            color: FinderTagColor::Red,
+        }]
+    );
+
+    assert!(FinderTag::decode_list(b"not a plist").is_none());
+}
+
+#[tokio::test]
+#[cfg(not(target_os = "macos"))]
+async fn test_finder_tags_unsupported_outside_macos() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let file_path = create_temp_file(&temp_dir.join("dir1"), "file.txt", "content");
+
+    let result = service
+        .update_finder_tags(&file_path, vec![], FinderTagsUpdate::Add)
+        .await;
+    assert!(matches!(result, Err(ServiceError::FromString(_))));
+    assert!(
+        service
+            .get_file_stats(&file_path)
+            .await
+            .unwrap()
+            .finder_metadata
+            .is_none()
+    );
+}
+
+#[test]
+fn test_portable_metadata() {
+    let temp_dir = get_temp_dir();
+    let file_path = temp_dir.join("portable.txt");
+    fs::write(&file_path, vec![b'a'; 1000]).unwrap();
+
+    let mut permissions = fs::metadata(&file_path).unwrap().permissions();
+    permissions.set_readonly(true);
+    fs::set_permissions(&file_path, permissions).unwrap();
+
+    let metadata = fs::metadata(&file_path).unwrap();
+    // no write bits on a read-only file
+    assert_eq!(metadata.permission_bits() & 0o222, 0);
+    assert_ne!(metadata.permission_bits() & 0o444, 0);
+    assert_eq!(metadata.owner_uid().is_some(), cfg!(unix));
+    assert_eq!(metadata.owner_gid().is_some(), cfg!(unix));
+    assert_eq!(metadata.windows_attributes().is_some(), cfg!(windows));
+
+    let dir_metadata = fs::metadata(&temp_dir).unwrap();
+    assert_ne!(dir_metadata.permission_bits() & 0o100, 0);
+}
+
+#[tokio::test]
+async fn test_zip_directory_reports_progress() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    create_temp_file(&dir_path, "file1.txt", "content1");
+    create_temp_file(&dir_path.join("sub"), "file2.txt", "content22");
+    create_temp_file(&dir_path.join("sub"), "file3.txt", "content333");
+    let zip_path = dir_path.join("output.zip");
+
+    let updates = std::sync::Mutex::new(vec![]);
+    let on_progress = |progress: ZipProgress| updates.lock().unwrap().push(progress);
+    let result = service
+        .zip_directory(
+            dir_path.to_str().unwrap().to_string(),
+            "**/*".to_string(),
+            vec![],
+            zip_path.to_str().unwrap().to_string(),
+            false,
+            false,
+            false,
+            false,
+            Some(&on_progress),
+        )
+        .await
+        .unwrap();
+    assert!(result.contains("3 files"));
+
+    let updates = updates.into_inner().unwrap();
+    let files: Vec<usize> = updates.iter().map(|p| p.files).collect();
+    assert_eq!(files, vec![1, 2, 3]);
+    assert_eq!(updates.last().unwrap().bytes, 27);
+
+    // the archive being written is not added to itself
+    let file = tokio_fs::File::open(&zip_path).await.unwrap();
+    let reader =
+        async_zip::tokio::read::seek::ZipFileReader::with_tokio(tokio::io::BufReader::new(file))
+            .await
+            .unwrap();
+    let mut names: Vec<String> = reader
+        .file()
+        .entries()
+        .iter()
+        .map(|e| e.filename().as_str().unwrap().to_string())
+        .collect();
+    names.sort();
+    assert_eq!(names, vec!["file1.txt", "sub/file2.txt", "sub/file3.txt"]);
+}
+
+#[tokio::test]
+async fn test_zip_directory_parallel_compression_round_trip() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1").join("source");
+    for i in 0..60 {
+        let content = format!("file {i} ").repeat(i * 50 + 1);
+        create_temp_file(
+            &dir_path.join(format!("sub{}", i % 4)),
+            &format!("file{i}.txt"),
+            &content,
+        );
+    }
+    let zip_path = temp_dir.join("dir1").join("output.zip");
+
+    let result = service
+        .zip_directory(
+            dir_path.to_str().unwrap().to_string(),
+            "**/*".to_string(),
+            vec![],
+            zip_path.to_str().unwrap().to_string(),
+            false,
+            false,
+            false,
+            false,
+            None,
+        )
+        .await
+        .unwrap();
+    assert!(result.contains("60 files"));
+
+    let extract_path = temp_dir.join("dir1").join("extracted");
+    service
+        .unzip_file(zip_path.to_str().unwrap(), extract_path.to_str().unwrap())
+        .await
+        .unwrap();
+    for i in 0..60 {
+        let relative = PathBuf::from(format!("sub{}", i % 4)).join(format!("file{i}.txt"));
+        assert_eq!(
+            fs::read_to_string(extract_path.join(&relative)).unwrap(),
+            fs::read_to_string(dir_path.join(&relative)).unwrap()
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_zip_directory_excludes_target_archive() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    create_temp_file(&dir_path, "file1.txt", "content1");
+    create_temp_file(&dir_path.join("sub"), "file2.txt", "content2");
+    // the target is referenced through a path that differs from the walked one
+    let zip_path = dir_path.join("sub").join("..").join("output.zip");
+
+    let result = service
+        .zip_directory(
+            dir_path.to_str().unwrap().to_string(),
+            "**/*".to_string(),
+            vec![],
+            zip_path.to_str().unwrap().to_string(),
+            false,
+            false,
+            false,
+            false,
+            None,
+        )
+        .await
+        .unwrap();
+    assert!(result.contains("2 files"));
+    assert!(result.contains("was excluded from the archive"));
+
+    // no note when the archive is created outside the input directory
+    let outside_zip_path = temp_dir.join("dir1").join("archives").join("output.zip");
+    let result = service
+        .zip_directory(
+            dir_path.join("sub").to_str().unwrap().to_string(),
+            "**/*".to_string(),
+            vec![],
+            outside_zip_path.to_str().unwrap().to_string(),
+            true,
+            false,
+            false,
+            false,
+            None,
+        )
+        .await
+        .unwrap();
+    assert!(result.contains("1 file,"));
+    assert!(!result.contains("excluded"));
+}
+
+#[tokio::test]
+async fn test_zip_directory_preserves_empty_directories() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1").join("project");
+    create_temp_file(&dir_path.join("src"), "main.rs", "fn main() {}");
+    fs::create_dir_all(dir_path.join("assets").join("images")).unwrap();
+    fs::create_dir_all(dir_path.join("logs")).unwrap();
+    let zip_path = temp_dir.join("dir1").join("project.zip");
+
+    let result = service
+        .zip_directory(
+            dir_path.to_str().unwrap().to_string(),
+            "**/*".to_string(),
+            vec![],
+            zip_path.to_str().unwrap().to_string(),
+            false,
+            true,
+            false,
+            false,
+            None,
+        )
+        .await
+        .unwrap();
+    assert!(result.contains("1 file,"));
+    assert!(result.contains("Included 2 empty directories."));
+
+    let extract_dir = temp_dir.join("dir1").join("extracted");
+    let result = service
+        .unzip_file(zip_path.to_str().unwrap(), extract_dir.to_str().unwrap())
+        .await
+        .unwrap();
+    assert!(result.contains("Successfully extracted 1 file and 2 directories"));
+    assert!(extract_dir.join("src").join("main.rs").is_file());
+    assert!(extract_dir.join("assets").join("images").is_dir());
+    assert!(extract_dir.join("logs").is_dir());
+
+    // empty directories are skipped unless requested
+    let zip_path = temp_dir.join("dir1").join("project_files.zip");
+    let result = service
+        .zip_directory(
+            dir_path.to_str().unwrap().to_string(),
+            "**/*".to_string(),
+            vec![],
+            zip_path.to_str().unwrap().to_string(),
+            false,
+            false,
+            false,
+            false,
+            None,
+        )
+        .await
+        .unwrap();
+    assert!(!result.contains("empty"));
+}
+
+#[tokio::test]
+async fn test_zip_directory_exclude_patterns() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1").join("project");
+    create_temp_file(&dir_path, "README.md", "readme");
+    create_temp_file(&dir_path, "build.log", "log");
+    create_temp_file(&dir_path.join("src"), "main.rs", "fn main() {}");
+    create_temp_file(&dir_path.join("target").join("debug"), "app", "binary");
+    create_temp_file(&dir_path.join(".git"), "HEAD", "ref: refs/heads/main");
+    create_temp_file(&dir_path.join("node_modules").join("pkg"), "index.js", "");
+    let zip_path = temp_dir.join("dir1").join("project.zip");
+
+    let result = service
+        .zip_directory(
+            dir_path.to_str().unwrap().to_string(),
+            "**/*".to_string(),
+            vec![
+                "target".to_string(),
+                ".git".to_string(),
+                "node_modules".to_string(),
+                "*.log".to_string(),
+            ],
+            zip_path.to_str().unwrap().to_string(),
+            false,
+            false,
+            false,
+            false,
+            None,
+        )
+        .await
+        .unwrap();
+    assert!(result.contains("2 files"));
+
+    let extract_dir = temp_dir.join("dir1").join("extracted");
+    service
+        .unzip_file(zip_path.to_str().unwrap(), extract_dir.to_str().unwrap())
+        .await
+        .unwrap();
+    assert!(extract_dir.join("README.md").is_file());
+    assert!(extract_dir.join("src").join("main.rs").is_file());
+    assert!(!extract_dir.join("build.log").exists());
+    assert!(!extract_dir.join("target").exists());
+    assert!(!extract_dir.join(".git").exists());
+    assert!(!extract_dir.join("node_modules").exists());
+}
+
+#[tokio::test]
+async fn test_zip_directory_deterministic_output() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1").join("project");
+    for name in ["b.txt", "a.txt", "c.txt"] {
+        create_temp_file(&dir_path, name, &format!("content of {name}"));
+    }
+    create_temp_file(&dir_path.join("src"), "lib.rs", "pub fn lib() {}");
+    fs::create_dir_all(dir_path.join("empty")).unwrap();
+
+    let zip_directory = |target: &str| {
+        service.zip_directory(
+            dir_path.to_str().unwrap().to_string(),
+            "**/*".to_string(),
+            vec![],
+            temp_dir
+                .join("dir1")
+                .join(target)
+                .to_str()
+                .unwrap()
+                .to_string(),
+            false,
+            true,
+            true,
+            // always compress, to compare archives created from the files
+            true,
+            None,
+        )
+    };
+
+    zip_directory("first.zip").await.unwrap();
+    // timestamps don't affect a deterministic archive
+    let modified = std::time::SystemTime::now() - std::time::Duration::from_secs(86_400);
+    fs::File::options()
+        .write(true)
+        .open(dir_path.join("a.txt"))
+        .unwrap()
+        .set_modified(modified)
+        .unwrap();
+    zip_directory("second.zip").await.unwrap();
+
+    let first = fs::read(temp_dir.join("dir1").join("first.zip")).unwrap();
+    let second = fs::read(temp_dir.join("dir1").join("second.zip")).unwrap();
+    assert_eq!(first, second);
+}
+
+#[tokio::test]
+async fn test_read_archive_entry() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    let file1 = create_temp_file(&dir_path, "notes.txt", "archived notes");
+    let file2 = create_temp_file(&dir_path, "data.bin", &"x".repeat(100));
+    let zip_path = dir_path.join("archive.zip");
+    service
+        .zip_files(
+            vec![
+                file1.to_str().unwrap().to_string(),
+                file2.to_str().unwrap().to_string(),
+            ],
+            zip_path.to_str().unwrap().to_string(),
+            false,
+        )
+        .await
+        .unwrap();
+
+    let entry = service
+        .read_archive_entry(&zip_path, "notes.txt", 1024)
+        .await
+        .unwrap();
+    assert_eq!(entry.name, "notes.txt");
+    assert_eq!(entry.size, 14);
+    assert_eq!(entry.content, b"archived notes");
+
+    let result = service.read_archive_entry(&zip_path, "data.bin", 50).await;
+    assert!(matches!(result, Err(ServiceError::FileTooLarge(50))));
+
+    let result = service
+        .read_archive_entry(&zip_path, "missing.txt", 1024)
+        .await;
+    assert!(matches!(
+        result,
+        Err(ServiceError::IoError(ref e)) if e.kind() == std::io::ErrorKind::NotFound
+    ));
+}
+
+#[tokio::test]
+async fn test_validate_path_localized_access_denied() {
+    let (temp_dir, service, allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let service = service.with_locale(Locale::De);
+    let outside_path = temp_dir.join("dir2").join("file.txt");
+
+    let result = service.validate_path(&outside_path, allowed_dirs);
+    match result {
+        Err(ServiceError::AccessDenied(message)) => {
+            assert!(message.starts_with("Zugriff verweigert - der Pfad liegt außerhalb"));
+        }
+        other => panic!("Expected an access denied error, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_archive_messages_localized() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let service = service.with_locale(Locale::Fr);
+    let dir_path = temp_dir.join("dir1");
+    let file1 = create_temp_file(&dir_path, "file1.txt", "content1");
+    let zip_path = dir_path.join("output.zip");
+
+    let result = service
+        .zip_files(
+            vec![file1.to_str().unwrap().to_string()],
+            zip_path.to_str().unwrap().to_string(),
+            false,
+        )
+        .await
+        .unwrap();
+    assert!(result.starts_with("Compression réussie de 1 fichier dans"));
+
+    let result = service
+        .unzip_file(
+            zip_path.to_str().unwrap(),
+            dir_path.join("extracted").to_str().unwrap(),
+        )
+        .await
+        .unwrap();
+    assert!(result.starts_with("Extraction réussie de 1 fichier ("));
+}
+
+#[tokio::test]
+async fn test_check_path_lengths() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    let short_path = dir_path.join("src").join("main.rs");
+    let long_component = "a".repeat(256);
+    let long_component_path = dir_path.join(&long_component).join("file.txt");
+
+    let reports = service
+        .check_path_lengths(
+            &[
+                short_path.to_str().unwrap().to_string(),
+                long_component_path.to_str().unwrap().to_string(),
+            ],
+            PathPlatform::Linux,
+        )
+        .await
+        .unwrap();
+    assert_eq!(reports.len(), 2);
+    assert!(reports[0].valid);
+    assert!(reports[0].issues.is_empty());
+    assert!(!reports[1].valid);
+    assert_eq!(reports[1].issues.len(), 1);
+    assert_eq!(reports[1].issues[0].component, Some(long_component));
+    assert_eq!(reports[1].issues[0].limit, 255);
+
+    // a path under MAX_PATH on Linux exceeds it on Windows
+    let deep_path = dir_path.join(["abcdefghij"; 30].join("/"));
+    let reports = service
+        .check_path_lengths(
+            &[deep_path.to_str().unwrap().to_string()],
+            PathPlatform::Windows,
+        )
+        .await
+        .unwrap();
+    assert!(!reports[0].valid);
+    assert_eq!(reports[0].issues[0].component, None);
+    assert_eq!(reports[0].issues[0].limit, 259);
+    assert!(check_path_length(&deep_path, PathPlatform::Linux).is_empty());
+
+    // paths outside the allowed directories are rejected
+    let result = service
+        .check_path_lengths(&["/outside/file.txt".to_string()], PathPlatform::Linux)
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_resolve_path() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    let root = dir_path.canonicalize().unwrap();
+    fs::create_dir_all(dir_path.join("src")).unwrap();
+    fs::write(dir_path.join("src").join("main.rs"), "fn main() {}").unwrap();
+
+    let resolved: ResolvedPath = service
+        .resolve_path(
+            &dir_path.join("src").join("..").join("src").join("main.rs"),
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(resolved.resolved, root.join("src").join("main.rs"));
+    assert!(resolved.exists);
+    assert!(!resolved.is_directory);
+    assert_eq!(resolved.allowed_root, Some(root.clone()));
+    assert_eq!(
+        resolved.relative_to_root,
+        Some(Path::new("src").join("main.rs"))
+    );
+    assert_eq!(resolved.relative_path, None);
+
+    // paths that don't exist are resolved lexically
+    let resolved = service
+        .resolve_path(
+            &dir_path.join("docs").join(".").join("guide.md"),
+            Some(&dir_path.join("src")),
+        )
+        .await
+        .unwrap();
+    assert_eq!(resolved.resolved, root.join("docs").join("guide.md"));
+    assert!(!resolved.exists);
+    assert_eq!(
+        resolved.relative_path,
+        Some(Path::new("..").join("docs").join("guide.md"))
+    );
+
+    let result = service
+        .resolve_path(&dir_path.join("file.txt"), Some(Path::new("/outside")))
+        .await;
+    assert!(result.is_err());
+}
+
+#[test]
+fn test_relative_path() {
+    assert_eq!(
+        relative_path(Path::new("/a/b"), Path::new("/a/b")),
+        Some(PathBuf::from("."))
+    );
+    assert_eq!(
+        relative_path(Path::new("/a/b/c"), Path::new("/a/d")),
+        Some(PathBuf::from("../../d"))
+    );
+    assert_eq!(
+        relative_path(Path::new("/a"), Path::new("/a/b/c")),
+        Some(PathBuf::from("b/c"))
+    );
+    assert_eq!(clean_path(Path::new("/a/./b/../c")), PathBuf::from("/a/c"));
+}
+
+#[tokio::test]
+async fn test_validate_path_relative_to_base_dir() {
+    let (temp_dir, service, allowed_dirs) =
+        setup_service(vec!["dir1".to_string(), "dir2".to_string()]);
+
+    // relative paths resolve against the first allowed directory by default
+    let result = service
+        .validate_path(Path::new("src/main.rs"), allowed_dirs.clone())
+        .unwrap();
+    assert_eq!(result, temp_dir.join("dir1").join("src").join("main.rs"));
+
+    let service = service.with_base_dir(PathBuf::from("sub"));
+    let result = service
+        .validate_path(Path::new("main.rs"), allowed_dirs.clone())
+        .unwrap();
+    assert_eq!(result, temp_dir.join("dir1").join("sub").join("main.rs"));
+
+    let service = service.with_base_dir(temp_dir.join("dir2"));
+    let result = service
+        .validate_path(Path::new("main.rs"), allowed_dirs.clone())
+        .unwrap();
+    assert_eq!(result, temp_dir.join("dir2").join("main.rs"));
+
+    // escaping the allowed directories is still rejected
+    let result = service.validate_path(Path::new("../../outside.txt"), allowed_dirs);
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_mcpignore_excludes_paths_from_traversal() {
+    let (temp_dir, service, allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    fs::write(
+        dir_path.join(".mcpignore"),
+        "# build output\ntarget/\n*.env\n!example.env\n/config/credentials.json\n",
+    )
+    .unwrap();
+    create_temp_file(&dir_path, "src/main.rs", "fn main() {}");
+    create_temp_file(&dir_path, "target/debug/app", "binary");
+    create_temp_file(&dir_path, "prod.env", "SECRET=1");
+    create_temp_file(&dir_path, "src/local.env", "SECRET=2");
+    create_temp_file(&dir_path, "example.env", "SECRET=");
+    create_temp_file(&dir_path, "config/credentials.json", "{}");
+    create_temp_file(&dir_path, "src/config/credentials.json", "{}");
+
+    let mut found: Vec<String> = service
+        .search_files(&dir_path, "**/*".to_string(), vec![], None, None)
+        .await
+        .unwrap()
+        .iter()
+        .filter(|entry| entry.is_file())
+        .map(|entry| {
+            entry
+                .path()
+                .strip_prefix(&dir_path)
+                .unwrap()
+                .to_string_lossy()
+                .replace('\\', "/")
+        })
+        .collect();
+    found.sort();
+    assert_eq!(
+        found,
+        vec![
+            ".mcpignore",
+            "example.env",
+            "src/config/credentials.json",
+            "src/main.rs"
+        ]
+    );
+
+    let size = service
+        .calculate_directory_size(&dir_path, false)
+        .await
+        .unwrap();
+    let expected_size = fs::metadata(dir_path.join(".mcpignore")).unwrap().len() + 7 + 2 + 12;
+    assert_eq!(size, expected_size);
+
+    let (tree, _) = service
+        .directory_tree(&dir_path, None, None, &mut 0, allowed_dirs)
+        .unwrap();
+    let names: Vec<&str> = tree
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["name"].as_str().unwrap())
+        .collect();
+    assert!(!names.contains(&"target"));
+    assert!(!names.contains(&"prod.env"));
+    assert!(names.contains(&"example.env"));
+
+    let zip_path = temp_dir.join("dir1").join("out").join("archive.zip");
+    service
+        .zip_directory(
+            dir_path.to_str().unwrap().to_string(),
+            "**/*".to_string(),
+            vec![],
+            zip_path.to_str().unwrap().to_string(),
+            true,
+            false,
+            true,
+            false,
+            None,
+        )
+        .await
+        .unwrap();
+    let extract_dir = temp_dir.join("dir1").join("extracted");
+    service
+        .unzip_file(zip_path.to_str().unwrap(), extract_dir.to_str().unwrap())
+        .await
+        .unwrap();
+    assert!(extract_dir.join("src").join("main.rs").exists());
+    assert!(extract_dir.join("example.env").exists());
+    assert!(!extract_dir.join("target").exists());
+    assert!(!extract_dir.join("prod.env").exists());
+    assert!(!extract_dir.join("src").join("local.env").exists());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_calculate_directory_size_cache() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    create_temp_file(&dir_path, "file1.txt", "content1");
+    let nested_file = create_temp_file(&dir_path.join("subdir"), "file2.txt", "content22");
+
+    // directories modified recently are not cached
+    let an_hour_ago = SystemTime::now() - std::time::Duration::from_secs(3600);
+    for dir in [&dir_path, &dir_path.join("subdir")] {
+        File::open(dir).unwrap().set_modified(an_hour_ago).unwrap();
+    }
+
+    // without the cache, the files rewritten in place are counted with their new size
+    let size = service
+        .calculate_directory_size(&dir_path, false)
+        .await
+        .unwrap();
+    assert_eq!(size, 17);
+    fs::write(&nested_file, "content22 rewritten").unwrap();
+    let size = service
+        .calculate_directory_size(&dir_path, false)
+        .await
+        .unwrap();
+    assert_eq!(size, 27);
+    fs::write(&nested_file, "content22").unwrap();
+    File::open(dir_path.join("subdir"))
+        .unwrap()
+        .set_modified(an_hour_ago)
+        .unwrap();
+
+    let service = service.with_directory_size_cache(true);
+    let size = service
+        .calculate_directory_size(&dir_path, false)
+        .await
+        .unwrap();
+    assert_eq!(size, 17);
+
+    // rewriting a file in place leaves the directory unchanged, the cached size is returned
+    fs::write(&nested_file, "content22 updated").unwrap();
+    let size = service
+        .calculate_directory_size(&dir_path, false)
+        .await
+        .unwrap();
+    assert_eq!(size, 17);
+
+    let size = service
+        .calculate_directory_size(&dir_path, true)
+        .await
+        .unwrap();
+    assert_eq!(size, 25);
+
+    // adding a file modifies the directory
+    create_temp_file(&dir_path, "file3.txt", "abc");
+    let size = service
+        .calculate_directory_size(&dir_path, false)
+        .await
+        .unwrap();
+    assert_eq!(size, 28);
+}
+
+#[tokio::test]
+async fn test_find_oldest_files() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    let now = SystemTime::now();
+    for (name, age_days) in [("new.txt", 1), ("old.txt", 300), ("sub/older.txt", 600)] {
+        let path = create_temp_file(&dir_path, name, "content");
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(now - std::time::Duration::from_secs(age_days * 86400))
+            .unwrap();
+    }
+
+    let files = service
+        .find_oldest_files(&dir_path, None, None, FileTimestamp::Modified, 2)
+        .await
+        .unwrap();
+    let names: Vec<_> = files
+        .iter()
+        .map(|file| file.path.file_name().unwrap().to_str().unwrap())
+        .collect();
+    assert_eq!(names, vec!["older.txt", "old.txt"]);
+    assert_eq!(files[0].size, 7);
+    assert!(files[0].modified.unwrap() < files[1].modified.unwrap());
+
+    let files = service
+        .find_oldest_files(
+            &dir_path,
+            None,
+            Some(vec!["sub".to_string()]),
+            FileTimestamp::Modified,
+            10,
+        )
+        .await
+        .unwrap();
+    assert_eq!(files.len(), 2);
+    assert!(files[0].path.ends_with("old.txt"));
+}
+
+#[tokio::test]
+async fn test_usage_by_extension() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    create_temp_file(&dir_path, "a.log", "0123456789");
+    create_temp_file(&dir_path, "sub/b.LOG", "0123456789");
+    create_temp_file(&dir_path, "image.png", "png");
+    create_temp_file(&dir_path, "README", "readme");
+
+    let usage = service
+        .usage_by_extension(&dir_path, None, None)
+        .await
+        .unwrap();
+    let summary: Vec<_> = usage
+        .iter()
+        .map(|usage| (usage.extension.as_str(), usage.files, usage.bytes))
+        .collect();
+    assert_eq!(summary, vec![("log", 2, 20), ("", 1, 6), ("png", 1, 3)]);
+    assert_eq!(usage[0].category, Some(MimeCategory::Text));
+    assert_eq!(usage[1].category, None);
+    assert_eq!(usage[2].category, Some(MimeCategory::Image));
+}
+
+#[tokio::test]
+async fn test_file_timeline() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    // noon local time, so the dates don't depend on the time zone
+    let local_noon = |date: &str| -> SystemTime {
+        chrono::NaiveDateTime::parse_from_str(&format!("{date} 12:00:00"), "%Y-%m-%d %H:%M:%S")
+            .unwrap()
+            .and_local_timezone(chrono::Local)
+            .unwrap()
+            .into()
+    };
+    for (name, content, date) in [
+        ("a.txt", "a", "2024-03-04"),
+        ("b.txt", "bb", "2024-03-10"),
+        ("c.txt", "ccc", "2024-03-11"),
+        ("d.txt", "dddd", "2024-05-20"),
+    ] {
+        let path = create_temp_file(&dir_path, name, content);
+        File::options()
+            .write(true)
+            .open(&path)
+            .unwrap()
+            .set_modified(local_noon(date))
+            .unwrap();
+    }
+
+    let summary = |buckets: Vec<TimelineBucket>| -> Vec<(String, usize, u64)> {
+        buckets
+            .into_iter()
+            .map(|bucket| (bucket.period, bucket.files, bucket.bytes))
+            .collect()
+    };
+
+    let months = service
+        .file_timeline(
+            &dir_path,
+            None,
+            None,
+            FileTimestamp::Modified,
+            TimelineInterval::Month,
+        )
+        .await
+        .unwrap();
+    assert_eq!(months[0].start, "2024-03-01");
+    assert_eq!(
+        summary(months),
+        vec![("2024-03".to_string(), 3, 6), ("2024-05".to_string(), 1, 4)]
+    );
+
+    // 2024-03-04 and 2024-03-10 are the Monday and Sunday of the same ISO week
+    let weeks = service
+        .file_timeline(
+            &dir_path,
+            None,
+            None,
+            FileTimestamp::Modified,
+            TimelineInterval::Week,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        summary(weeks),
+        vec![
+            ("2024-W10".to_string(), 2, 3),
+            ("2024-W11".to_string(), 1, 3),
+            ("2024-W21".to_string(), 1, 4)
+        ]
+    );
+
+    let days = service
+        .file_timeline(
+            &dir_path,
+            Some("*.txt".to_string()),
+            None,
+            FileTimestamp::Modified,
+            TimelineInterval::Day,
+        )
+        .await
+        .unwrap();
+    assert_eq!(days.len(), 4);
+    assert_eq!(days[0].period, "2024-03-04");
+}
+
+#[tokio::test]
+async fn test_retry_io_transient_errors() {
+    assert!(is_transient_error(&std::io::Error::from(
+        std::io::ErrorKind::ResourceBusy
+    )));
+    assert!(is_transient_error(&std::io::Error::from(
+        std::io::ErrorKind::WouldBlock
+    )));
+    assert!(!is_transient_error(&std::io::Error::from(
+        std::io::ErrorKind::NotFound
+    )));
+
+    let (_, service, _) = setup_service(vec!["dir1".to_string()]);
+    let service = service.with_retry_policy(RetryPolicy {
+        retries: 2,
+        initial_backoff: std::time::Duration::from_millis(1),
+    });
+
+    // succeeds once the transient errors are gone
+    let mut attempts = 0;
+    let result = service
+        .retry_io(|| {
+            attempts += 1;
+            let attempt = attempts;
+            async move {
+                if attempt < 3 {
+                    Err(std::io::Error::from(std::io::ErrorKind::ResourceBusy))
+                } else {
+                    Ok(attempt)
+                }
+            }
+        })
+        .await;
+    assert_eq!(result.unwrap(), 3);
+
+    // gives up after the configured number of retries
+    let mut attempts = 0;
+    let result: std::io::Result<()> = service
+        .retry_io(|| {
+            attempts += 1;
+            async { Err(std::io::Error::from(std::io::ErrorKind::ResourceBusy)) }
+        })
+        .await;
+    assert!(result.is_err());
+    assert_eq!(attempts, 3);
+
+    // other errors are not retried
+    let mut attempts = 0;
+    let result: std::io::Result<()> = service
+        .retry_io(|| {
+            attempts += 1;
+            async { Err(std::io::Error::from(std::io::ErrorKind::NotFound)) }
+        })
+        .await;
+    assert!(result.is_err());
+    assert_eq!(attempts, 1);
+}
+
+#[test]
+fn test_describe_lock_violation() {
+    let path = Path::new("locked.txt");
+
+    // other errors are returned unchanged
+    let err = std::io::Error::new(std::io::ErrorKind::NotFound, "not found");
+    assert!(!is_lock_violation(&err));
+    let err = describe_lock_violation(err, &[path]);
+    assert_eq!(err.kind(), std::io::ErrorKind::NotFound);
+    assert_eq!(err.to_string(), "not found");
+
+    #[cfg(windows)]
+    {
+        // ERROR_SHARING_VIOLATION
+        let err = std::io::Error::from_raw_os_error(32);
+        assert!(is_lock_violation(&err));
+        let err = describe_lock_violation(err, &[path]);
+        assert!(
+            err.to_string()
+                .contains("'locked.txt' is locked by another process")
+        );
+    }
+
+    #[cfg(not(windows))]
+    assert!(lock_holders(&[path]).is_empty());
+}
+
+#[tokio::test]
+async fn test_read_only_filesystem_detection() {
+    let (temp_dir, service, _) = setup_service(vec!["dir1".to_string()]);
+    let dir = temp_dir.join("dir1");
+
+    // paths that don't exist yet are checked against their closest existing ancestor
+    assert!(!is_read_only_filesystem(&dir));
+    assert!(!is_read_only_filesystem(&dir.join("new/nested/file.txt")));
+    assert!(
+        service
+            .ensure_writable_filesystem(&dir.join("new.txt"))
+            .is_ok()
+    );
+
+    // /proc is mounted read-only in most containers, only check it where that's the case
+    #[cfg(target_os = "linux")]
+    if is_read_only_filesystem(Path::new("/proc/sys")) {
+        let err = service
+            .ensure_writable_filesystem(Path::new("/proc/sys/new.txt"))
+            .unwrap_err();
+        assert!(err.to_string().contains("mounted read-only"));
+    }
+}
+
+#[tokio::test]
+async fn test_check_allowed_directories() {
+    let (temp_dir, service, _) = setup_service(vec!["dir1".to_string(), "dir2".to_string()]);
+    let dir1 = temp_dir.join("dir1");
+    let dir2 = temp_dir.join("dir2");
+    assert!(service.check_allowed_directories().await.is_empty());
+
+    // a missing directory is excluded from validation
+    fs::remove_dir_all(&dir2).unwrap();
+    assert_eq!(
+        service.check_allowed_directories().await,
+        vec![DirectoryAvailabilityChange::Lost(dir2.clone())]
+    );
+    assert_eq!(*service.allowed_directories().await, vec![dir1.clone()]);
+    assert_eq!(service.configured_directories().await.len(), 2);
+    let allowed_directories = service.allowed_directories().await;
+    assert!(
+        service
+            .validate_path(&dir2.join("file.txt"), allowed_directories)
+            .is_err()
+    );
+    // changes are only reported once
+    assert!(service.check_allowed_directories().await.is_empty());
+
+    // and included again when it returns
+    fs::create_dir_all(&dir2).unwrap();
+    assert_eq!(
+        service.check_allowed_directories().await,
+        vec![DirectoryAvailabilityChange::Restored(dir2.clone())]
+    );
+    assert_eq!(*service.allowed_directories().await, vec![dir1, dir2]);
+}
+
+#[test]
+fn test_parse_file_path_uris() {
+    // raw paths are returned unchanged
+    assert_eq!(
+        parse_file_path(" /home/user/my docs ").unwrap(),
+        PathBuf::from("/home/user/my docs")
+    );
+
+    #[cfg(unix)]
+    {
+        for uri in [
+            "file:///home/user/my%20docs",
+            "file://localhost/home/user/my%20docs",
+            "FILE://LOCALHOST/home/user/my%20docs",
+            "file:/home/user/my%20docs",
+            "file:///home/user/my%20docs?query#fragment",
+        ] {
+            assert_eq!(
+                parse_file_path(uri).unwrap(),
+                PathBuf::from("/home/user/my docs"),
+                "{uri}"
+            );
+        }
+        assert_eq!(
+            parse_file_path("file:///home/user/caf%C3%A9%25").unwrap(),
+            PathBuf::from("/home/user/café%")
+        );
+        assert!(parse_file_path("file://server/share/file.txt").is_err());
+    }
+
+    #[cfg(windows)]
+    {
+        for uri in [
+            "file:///C:/Users/my%20docs",
+            "file://localhost/C:/Users/my%20docs",
+            "file:///C|/Users/my%20docs",
+        ] {
+            assert_eq!(
+                parse_file_path(uri).unwrap(),
+                PathBuf::from(r"C:\Users\my docs"),
+                "{uri}"
+            );
+        }
+        assert_eq!(
+            parse_file_path("file://server/share/file.txt").unwrap(),
+            PathBuf::from(r"\\server\share\file.txt")
+        );
+    }
+
+    // invalid URIs, incomplete escapes are kept as they are
+    assert!(parse_file_path("file://[invalid/home").is_err());
+    #[cfg(unix)]
+    assert_eq!(
+        parse_file_path("file:///home/100%").unwrap(),
+        PathBuf::from("/home/100%")
+    );
+}
+
+#[tokio::test]
+async fn test_update_allowed_paths_delta() {
+    let (temp_dir, service, _) = setup_service(vec!["dir1".to_string(), "dir2".to_string()]);
+    let dir1 = temp_dir.join("dir1");
+    let dir2 = temp_dir.join("dir2");
+    let dir3 = temp_dir.join("dir3");
+
+    let delta = service
+        .update_allowed_paths(vec![dir1.clone(), dir3.clone()])
+        .await;
+    assert_eq!(
+        delta,
+        AllowedDirectoriesDelta {
+            added: vec![dir3.clone()],
+            removed: vec![dir2.clone()],
+        }
+    );
+    let summary = delta.summary(&[dir1.clone(), dir3.clone()]);
+    assert!(summary.starts_with("Allowed directories changed: 1 added, 1 removed."));
+    assert!(summary.contains(&format!("Added:\n  {}", dir3.display())));
+    assert!(summary.contains(&format!("\n  {}", dir2.display())));
+
+    let delta = service
+        .update_allowed_paths(vec![dir1.clone(), dir3.clone()])
+        .await;
+    assert!(delta.is_empty());
+    assert!(
+        delta
+            .summary(&[dir1, dir3])
+            .starts_with("Allowed directories unchanged.")
+    );
+}
+
+#[tokio::test]
+async fn test_lazy_validation_of_allowed_directories() {
+    let temp_dir = get_temp_dir();
+    let mounted = temp_dir.join("mounted");
+    let share = temp_dir.join("share");
+    fs::create_dir_all(&mounted).unwrap();
+    let allowed = vec![
+        mounted.to_str().unwrap().to_string(),
+        share.to_str().unwrap().to_string(),
+    ];
+
+    assert!(FileSystemService::try_new(&allowed).is_err());
+
+    let service = FileSystemService::new_lazy(&allowed);
+    assert_eq!(service.unavailable_directories(), vec![share.clone()]);
+    assert_eq!(*service.allowed_directories().await, vec![mounted.clone()]);
+    let allowed_directories = service.allowed_directories().await;
+    assert!(
+        service
+            .validate_path(&share.join("file.txt"), allowed_directories)
+            .is_err()
+    );
+
+    // the directory is allowed as soon as it exists
+    fs::create_dir_all(&share).unwrap();
+    assert!(service.unavailable_directories().is_empty());
+    let allowed_directories = service.allowed_directories().await;
+    assert!(
+        service
+            .validate_path(&share.join("file.txt"), allowed_directories)
+            .is_ok()
+    );
+    assert_eq!(
+        service.check_allowed_directories().await,
+        vec![DirectoryAvailabilityChange::Restored(share)]
+    );
+}
+
+#[tokio::test]
+async fn test_pinned_paths_cannot_be_modified() {
+    let (temp_dir, service, _) = setup_service(vec!["dir1".to_string()]);
+    let dir = temp_dir.join("dir1");
+    fs::write(dir.join("Cargo.lock"), "lock").unwrap();
+    fs::create_dir_all(dir.join("ci")).unwrap();
+    fs::write(dir.join("ci").join("build.yml"), "build").unwrap();
+    let service = service.with_pinned_paths(vec![PathBuf::from("Cargo.lock"), dir.join("ci")]);
+
+    let err = service
+        .write_file(Path::new("Cargo.lock"), "changed", false, None, false)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains("is pinned"));
+    assert_eq!(fs::read_to_string(dir.join("Cargo.lock")).unwrap(), "lock");
+
+    // the content of pinned directories is pinned as well
+    let result = service
+        .write_file(&dir.join("ci").join("new.yml"), "new", false, None, false)
+        .await;
+    assert!(result.is_err());
+
+    // moving a pinned file or a directory containing one is refused
+    assert!(
+        service
+            .move_file(&dir.join("Cargo.lock"), &dir.join("moved.lock"), false)
+            .await
+            .is_err()
+    );
+    assert!(
+        service
+            .move_file(&dir, &temp_dir.join("dir1").join("moved"), false)
+            .await
+            .is_err()
+    );
+
+    // other paths are unaffected
+    service
+        .write_file(Path::new("Cargo.toml"), "[package]", false, None, false)
+        .await
+        .unwrap();
+    assert!(dir.join("Cargo.toml").exists());
+}
+
+#[tokio::test]
+async fn test_preview_or_apply_confirmation_tokens() {
+    let (_, service, _) = setup_service(vec!["dir1".to_string()]);
+    let arguments = serde_json::json!({ "pattern": "*.tmp" });
+    let preview = vec!["a.tmp".to_string(), "b.tmp".to_string()];
+
+    let apply = |preview: Vec<String>| async move { Ok(preview.len()) };
+
+    // without a token, only the preview is returned
+    let outcome = service
+        .preview_or_apply("bulk_delete", &arguments, preview.clone(), None, apply)
+        .await
+        .unwrap();
+    let TwoPhaseOutcome::Preview {
+        preview: previewed,
+        confirmation_token,
+        ..
+    } = outcome
+    else {
+        panic!("expected a preview");
+    };
+    assert_eq!(previewed, preview);
+
+    // tokens are bound to the operation, the arguments and the preview
+    let other_arguments = serde_json::json!({ "pattern": "*" });
+    for (operation, arguments, preview) in [
+        ("bulk_rename", &arguments, preview.clone()),
+        ("bulk_delete", &other_arguments, preview.clone()),
+        ("bulk_delete", &arguments, vec!["a.tmp".to_string()]),
+    ] {
+        let result = service
+            .preview_or_apply(
+                operation,
+                arguments,
+                preview,
+                Some(&confirmation_token),
+                apply,
+            )
+            .await;
+        assert!(result.is_err());
+    }
+    let (signed, last) = confirmation_token.split_at(confirmation_token.len() - 1);
+    let forged = format!("{signed}{}", if last == "0" { "1" } else { "0" });
+    assert!(
+        service
+            .preview_or_apply(
+                "bulk_delete",
+                &arguments,
+                preview.clone(),
+                Some(&forged),
+                apply
+            )
+            .await
+            .is_err()
+    );
+
+    // a valid token applies the operation once
+    let outcome = service
+        .preview_or_apply(
+            "bulk_delete",
+            &arguments,
+            preview.clone(),
+            Some(&confirmation_token),
+            apply,
+        )
+        .await
+        .unwrap();
+    assert_eq!(outcome, TwoPhaseOutcome::Applied { result: 2 });
+    for token in [
+        confirmation_token.clone(),
+        confirmation_token.to_uppercase(),
+    ] {
+        assert!(
+            service
+                .preview_or_apply(
+                    "bulk_delete",
+                    &arguments,
+                    preview.clone(),
+                    Some(&token),
+                    apply
+                )
+                .await
+                .is_err()
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_search_paths_content() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir_search".to_string()]);
+    let dir = temp_dir.join("dir_search");
+    let file1 = create_temp_file(&dir, "file1.txt", "needle in file1");
+    create_temp_file(&dir, "file2.txt", "needle in file2");
+    let file3 = create_temp_file(
+        &dir,
+        "nested/file3.txt",
+        "needle in file3\nand another needle",
+    );
+
+    // only the listed files are searched
+    let paths = vec![
+        file1.display().to_string(),
+        file3.display().to_string(),
+        dir.join("nested").display().to_string(),
+    ];
+    let results = service
+        .search_paths_content(&paths, &["needle"], false, None, None, None, None)
+        .await
+        .unwrap();
+    assert_eq!(results.len(), 2);
+    assert_eq!(results[0].file_path, file1);
+    assert_eq!(results[1].file_path, file3);
+    assert_eq!(results[1].matches.len(), 2);
+
+    // size filters still apply
+    let results = service
+        .search_paths_content(&paths, &["needle"], false, Some(20), None, None, None)
+        .await
+        .unwrap();
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].file_path, file3);
+
+    // paths outside of the allowed directories are rejected
+    let outside = create_temp_file(temp_dir.as_path(), "outside.txt", "needle");
+    let result = service
+        .search_paths_content(
+            &[outside.display().to_string()],
+            &["needle"],
+            false,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_search_files_content_multiple_queries() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir_search".to_string()]);
+    let dir = temp_dir.join("dir_search");
+    create_temp_file(&dir, "file1.txt", "fn alpha() {}\nfn beta() { alpha() }");
+    create_temp_file(&dir, "file2.txt", "let GAMMA = 1;");
+    create_temp_file(&dir, "file3.txt", "nothing to see");
+
+    let results = service
+        .search_files_content(
+            &dir,
+            "*.txt",
+            &["alpha", "beta", "gamma"],
+            false,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    let mut hits: Vec<(String, u64, Option<String>)> = results
+        .iter()
+        .flat_map(|result| {
+            let file_name = result.file_path.file_name().unwrap().to_string_lossy();
+            result
+                .matches
+                .iter()
+                .map(move |m| (file_name.to_string(), m.line_number, m.query.clone()))
+        })
+        .collect();
+    hits.sort();
+    assert_eq!(
+        hits,
+        vec![
+            ("file1.txt".to_string(), 1, Some("alpha".to_string())),
+            ("file1.txt".to_string(), 2, Some("alpha".to_string())),
+            ("file1.txt".to_string(), 2, Some("beta".to_string())),
+            ("file2.txt".to_string(), 1, Some("gamma".to_string())),
+        ]
+    );
+
+    // regex queries report the query that matched, a single query reports none
+    let results = service
+        .search_files_content(
+            &dir,
+            "*.txt",
+            &[r"fn \w+", r"\d+"],
+            true,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    let queries: Vec<_> = results
+        .iter()
+        .flat_map(|result| result.matches.iter().map(|m| m.query.clone().unwrap()))
+        .collect();
+    assert_eq!(queries.iter().filter(|q| *q == r"fn \w+").count(), 2);
+    assert_eq!(queries.iter().filter(|q| *q == r"\d+").count(), 1);
+
+    let results = service
+        .search_files_content(
+            &dir,
+            "*.txt",
+            &["alpha"],
+            false,
+            None,
+            None,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    assert!(results[0].matches.iter().all(|m| m.query.is_none()));
+}
+
+#[tokio::test]
+async fn test_search_files_without_content() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir_search".to_string()]);
+    let dir = temp_dir.join("dir_search");
+    create_temp_file(&dir, "a.rs", "// SPDX-License-Identifier: MIT\nfn a() {}");
+    let b = create_temp_file(&dir, "b.rs", "fn b() {}");
+    let c = create_temp_file(
+        &dir,
+        "nested/c.rs",
+        "fn c() {}\n// spdx-license-identifier: MIT",
+    );
+    create_temp_file(&dir, "notes.txt", "no license here");
+
+    let files = service
+        .search_files_without_content(
+            &dir,
+            "*.rs",
+            &["SPDX-License-Identifier"],
+            false,
+            None,
+            None,
+            None,
+        )
+        .await
+        .unwrap();
+    assert_eq!(files, vec![b.clone()]);
+
+    // a file lacks a match only when it contains none of the queries
+    let paths = vec![b.display().to_string(), c.display().to_string()];
+    let files = service
+        .search_paths_without_content(&paths, &["fn b", "fn a"], false, None, None)
+        .await
+        .unwrap();
+    assert_eq!(files, vec![c]);
+}
+
+#[tokio::test]
+async fn test_find_relevant_files() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir_search".to_string()]);
+    let dir = temp_dir.join("dir_search");
+    let parser = create_temp_file(
+        &dir,
+        "src/parser.rs",
+        "fn parse_config() {}\n// parser for the config file",
+    );
+    let config = create_temp_file(&dir, "src/config/loader.rs", "fn load() {}\nfn save() {}");
+    let notes = create_temp_file(
+        &dir,
+        "notes.md",
+        "the parser reads\nthe config\n\n\n\n\n\n\n",
+    );
+    create_temp_file(&dir, "unrelated.rs", "fn main() {}");
+
+    let files = service
+        .find_relevant_files(&dir, "*", "parser config", None, 10)
+        .await
+        .unwrap();
+    let paths: Vec<_> = files.iter().map(|file| file.path.clone()).collect();
+    // name and dense content first, then the sparse content, then the directory name only
+    assert_eq!(paths, vec![parser.clone(), notes, config]);
+    assert_eq!(files[0].matched_terms, vec!["parser", "config"]);
+    assert_eq!(files[0].path_score, 0.5);
+    assert_eq!(files[2].path_score, 0.25);
+    assert_eq!(files[2].matches, 0);
+
+    // the glob pattern narrows down the candidates and limit the number of results
+    let files = service
+        .find_relevant_files(&dir, "*.rs", "parser config", None, 1)
+        .await
+        .unwrap();
+    assert_eq!(files.len(), 1);
+    assert_eq!(files[0].path, parser);
+}
+
+#[tokio::test]
+async fn test_service_error_kinds() {
+    use rust_mcp_filesystem::ErrorKind;
+
+    let (temp_dir, service, allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let service = service.with_pinned_paths(vec![temp_dir.join("dir1").join("pinned.txt")]);
+
+    let err = service
+        .validate_path(&temp_dir.join("dir2"), allowed_dirs)
+        .unwrap_err();
+    assert!(matches!(err, ServiceError::AccessDenied(_)));
+    assert_eq!(err.kind(), ErrorKind::AccessDenied);
+    // errors with a localized message have the code of the message
+    assert_eq!(
+        err.code(),
+        Message::AccessDenied {
+            is_symlink_target: false,
+            path: "",
+            allowed_directories: ""
+        }
+        .code()
+    );
+
+    let err = service
+        .ensure_modifiable(&temp_dir.join("dir1").join("pinned.txt"))
+        .await
+        .unwrap_err();
+    assert!(matches!(err, ServiceError::PinnedPath(_)));
+    assert_eq!(err.kind(), ErrorKind::NotWritable);
+    assert_eq!(
+        err.code(),
+        Message::PinnedPath {
+            path: "",
+            pinned_path: ""
+        }
+        .code()
+    );
+
+    let err = service
+        .read_text_file(&temp_dir.join("dir1").join("missing.txt"), false)
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::NotFound);
+    assert_eq!(err.code(), "not_found");
+
+    assert_eq!(ServiceError::NoWriteAccess.kind(), ErrorKind::NotWritable);
+    assert_eq!(
+        ServiceError::NoWriteAccess.code(),
+        Message::NoWriteAccess.code()
+    );
+    assert_eq!(
+        ServiceError::ReadOnlyFilesystem(String::new()).code(),
+        Message::ReadOnlyFilesystem { path: "" }.code()
+    );
+    assert_eq!(
+        ServiceError::FileTooLarge(1).kind(),
+        ErrorKind::UnsupportedFile
+    );
+    assert_eq!(
+        ServiceError::FromString("failure".to_string()).kind(),
+        ErrorKind::Other
+    );
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_validation_profile_symlink_escape() {
+    let (temp_dir, service, allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir1 = temp_dir.join("dir1");
+    let outside = create_temp_file(&temp_dir.join("outside"), "secret.txt", "secret");
+    let inside = create_temp_file(&dir1, "sub/notes.txt", "notes");
+    std::os::unix::fs::symlink(temp_dir.join("outside"), dir1.join("escape")).unwrap();
+    std::os::unix::fs::symlink(&outside, dir1.join("secret_link.txt")).unwrap();
+    std::os::unix::fs::symlink(temp_dir.join("outside/new.txt"), dir1.join("dangling.txt"))
+        .unwrap();
+    std::os::unix::fs::symlink(dir1.join("sub"), dir1.join("sub_link")).unwrap();
+
+    // links leading outside of the allowed directories are rejected, even dangling ones
+    assert_eq!(service.validation_profile(), ValidationProfile::Strict);
+    for path in [
+        dir1.join("escape/secret.txt"),
+        dir1.join("escape/new_file.txt"),
+        dir1.join("secret_link.txt"),
+        dir1.join("dangling.txt"),
+    ] {
+        let result = service.validate_path(&path, allowed_dirs.clone());
+        assert!(
+            matches!(result, Err(ServiceError::AccessDenied(_))),
+            "{path:?} should be rejected"
+        );
+    }
+
+    // links within the allowed directories and missing paths are accepted
+    for path in [
+        inside.clone(),
+        dir1.join("sub_link/notes.txt"),
+        dir1.join("missing/new_file.txt"),
+    ] {
+        assert_eq!(
+            service.validate_path(&path, allowed_dirs.clone()).unwrap(),
+            path
+        );
+    }
+
+    // the relaxed profile only checks the requested paths
+    let service = service.with_validation_profile(ValidationProfile::Relaxed);
+    let path = dir1.join("escape/secret.txt");
+    assert_eq!(
+        service.validate_path(&path, allowed_dirs.clone()).unwrap(),
+        path
+    );
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_validation_symlink_followed_by_parent_dir() {
+    let (temp_dir, service, allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir1 = temp_dir.join("dir1");
+    create_temp_file(&temp_dir.join("outside"), "secret.txt", "TOP SECRET");
+    fs::create_dir_all(temp_dir.join("outside/inner")).unwrap();
+    let notes = create_temp_file(&dir1, "sub/notes.txt", "notes");
+    fs::create_dir_all(dir1.join("sub/deeper")).unwrap();
+    std::os::unix::fs::symlink(temp_dir.join("outside/inner"), dir1.join("link")).unwrap();
+    std::os::unix::fs::symlink(dir1.join("sub/deeper"), dir1.join("deep_link")).unwrap();
+
+    // `..` applies to the link target, which is outside of the allowed directories
+    let path = dir1.join("link/../secret.txt");
+    assert!(matches!(
+        service.validate_path(&path, allowed_dirs.clone()),
+        Err(ServiceError::AccessDenied(_))
+    ));
+    assert!(service.read_text_file(&path, false).await.is_err());
+
+    // within the allowed directories, the resolved path is returned
+    assert_eq!(
+        service
+            .validate_path(&dir1.join("deep_link/../notes.txt"), allowed_dirs)
+            .unwrap(),
+        notes
+    );
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_validation_profile_symlinked_allowed_directory() {
+    let temp_dir = get_temp_dir();
+    let real_dir = temp_dir.join("real");
+    fs::create_dir_all(&real_dir).unwrap();
+    let file = create_temp_file(&real_dir, "file.txt", "content");
+    let link_dir = temp_dir.join("link");
+    std::os::unix::fs::symlink(&real_dir, &link_dir).unwrap();
+
+    let service = FileSystemService::try_new(&[link_dir.display().to_string()]).unwrap();
+    let allowed_dirs = service.allowed_directories().await;
+    let path = link_dir.join("file.txt");
+    assert_eq!(
+        service.validate_path(&path, allowed_dirs.clone()).unwrap(),
+        path
+    );
+    // the target of the allowed directory is allowed as well
+    assert_eq!(service.validate_path(&file, allowed_dirs).unwrap(), file);
+}
+
+#[tokio::test]
+async fn test_validate_paths() {
+    let (temp_dir, service, allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let inside = create_temp_file(&temp_dir.join("dir1"), "a.txt", "alpha");
+    let missing = temp_dir.join("dir1").join("missing.txt");
+    let outside = create_temp_file(&temp_dir.join("dir2"), "b.txt", "beta");
+
+    let paths = vec![inside.clone(), outside.clone(), missing.clone()];
+    let results = service.validate_paths(&paths).await;
+    assert_eq!(results.len(), 3);
+    assert_eq!(results[0].as_ref().unwrap(), &inside);
+    assert!(matches!(results[1], Err(ServiceError::AccessDenied(_))));
+    assert_eq!(results[2].as_ref().unwrap(), &missing);
+
+    // same outcome as validating the paths one by one
+    for (path, result) in paths.iter().zip(&results) {
+        assert_eq!(
+            service.validate_path(path, allowed_dirs.clone()).ok(),
+            result.as_ref().ok().cloned()
+        );
+    }
+
+    let contents = service.read_text_files(&paths, false).await;
+    assert_eq!(contents[0].as_ref().unwrap(), "alpha");
+    assert!(contents[1].is_err());
+    assert!(contents[2].is_err());
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_batch_operations_within_limits() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let service = service.with_batch_limits(BatchLimits {
+        concurrency: 0,
+        threads: 1,
+    });
+    // zero is raised to one, a batch operation must be able to run
+    assert_eq!(
+        service.batch_limits(),
+        BatchLimits {
+            concurrency: 1,
+            threads: 1
+        }
+    );
+
+    let dir = temp_dir.join("dir1");
+    let prefix = "x".repeat(8192);
+    let file1 = create_temp_file(&dir, "a/file1.txt", &format!("{prefix}same"));
+    let file2 = create_temp_file(&dir, "b/file2.txt", &format!("{prefix}same"));
+    // same size and first 4KB, different content
+    create_temp_file(&dir, "c/file3.txt", &format!("{prefix}diff"));
+    let notes = create_temp_file(&dir, "notes.txt", "notes");
+
+    let find_duplicates = || service.find_duplicate_files(&dir, None, None, None, None);
+    let (first, second, read) = tokio::join!(
+        find_duplicates(),
+        find_duplicates(),
+        service.read_text_file(&notes, false)
+    );
+    let expected = vec![vec![
+        file1.display().to_string(),
+        file2.display().to_string(),
+    ]];
+    assert_eq!(sort_duplicate_groups(first.unwrap()), expected);
+    assert_eq!(sort_duplicate_groups(second.unwrap()), expected);
+    assert_eq!(read.unwrap(), "notes");
+}
+
+#[tokio::test]
+async fn test_zip_directory_reuses_unchanged_archive() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1").join("project");
+    create_temp_file(&dir_path, "a.txt", "content of a");
+    create_temp_file(&dir_path.join("src"), "lib.rs", "pub fn lib() {}");
+    let archives = temp_dir.join("dir1").join("archives");
+    fs::create_dir_all(&archives).unwrap();
+
+    let zip_directory = |target: &str, pattern: &str, force: bool| {
+        service.zip_directory(
+            dir_path.display().to_string(),
+            pattern.to_string(),
+            vec![],
+            archives.join(target).display().to_string(),
+            false,
+            false,
+            false,
+            force,
+            None,
+        )
+    };
+
+    let result = zip_directory("first.zip", "**/*", false).await.unwrap();
+    assert!(result.starts_with("Successfully compressed"));
+    let result = zip_directory("second.zip", "**/*", false).await.unwrap();
+    assert!(result.starts_with("Reused the archive"), "{result}");
+    assert!(result.contains("2 files"));
+    assert_eq!(
+        fs::read(archives.join("first.zip")).unwrap(),
+        fs::read(archives.join("second.zip")).unwrap()
+    );
+
+    // forced, or with other options
+    let result = zip_directory("forced.zip", "**/*", true).await.unwrap();
+    assert!(result.starts_with("Successfully compressed"));
+    let result = zip_directory("lib.zip", "**/lib.rs", false).await.unwrap();
+    assert!(result.starts_with("Successfully compressed"));
+    assert!(result.contains("(1 file,"), "{result}");
+
+    // the tree changed
+    create_temp_file(&dir_path, "b.txt", "content of b");
+    let result = zip_directory("third.zip", "**/*", false).await.unwrap();
+    assert!(result.starts_with("Successfully compressed"));
+    assert!(result.contains("3 files"));
+    let result = zip_directory("fourth.zip", "**/*", false).await.unwrap();
+    assert!(result.starts_with("Reused the archive"));
+
+    // the previous archive changed
+    fs::write(archives.join("fourth.zip"), "not a zip").unwrap();
+    fs::write(archives.join("third.zip"), "not a zip").unwrap();
+    let result = zip_directory("fifth.zip", "**/*", false).await.unwrap();
+    assert!(result.starts_with("Successfully compressed"));
+    service
+        .unzip_file(
+            archives.join("fifth.zip").to_str().unwrap(),
+            temp_dir.join("dir1").join("extracted").to_str().unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        fs::read_to_string(temp_dir.join("dir1").join("extracted").join("b.txt")).unwrap(),
+        "content of b"
+    );
+}
+
+/// Writes an archive of stored (uncompressed) entries, then replaces `corrupt` in its content so
+/// that the entry containing it fails its CRC-32 check.
+async fn write_corrupt_zip(zip_path: &Path, entries: &[(&str, &str)], corrupt: &str) {
+    let zip_file = tokio::fs::File::create(zip_path).await.unwrap();
+    let mut zip_writer = ZipFileWriter::new(zip_file.compat());
+    for (name, content) in entries {
+        let builder = async_zip::ZipEntryBuilder::new(
+            (*name).to_string().into(),
+            async_zip::Compression::Stored,
+        );
+        zip_writer
+            .write_entry_whole(builder, content.as_bytes())
+            .await
+            .unwrap();
+    }
+    zip_writer.close().await.unwrap();
+
+    let mut bytes = fs::read(zip_path).unwrap();
+    let offset = bytes
+        .windows(corrupt.len())
+        .position(|window| window == corrupt.as_bytes())
+        .unwrap();
+    bytes[offset..offset + corrupt.len()].fill(b'X');
+    fs::write(zip_path, bytes).unwrap();
+}
+
+#[tokio::test]
+async fn test_unzip_file_continue_on_error() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    let zip_path = dir_path.join("partial.zip");
+    write_corrupt_zip(
+        &zip_path,
+        &[
+            ("good.txt", "readable content"),
+            ("nested/bad.txt", "corrupted content"),
+            ("../escape.txt", "outside of the target"),
+        ],
+        "corrupted content",
+    )
+    .await;
+
+    // aborts at the corrupt entry, without leaving a partially extracted directory behind
+    let extract_dir = dir_path.join("aborted");
+    let result = service
+        .unzip_file(zip_path.to_str().unwrap(), extract_dir.to_str().unwrap())
+        .await;
+    assert!(result.is_err());
+    assert!(!extract_dir.exists());
+
+    let extract_dir = dir_path.join("extracted");
+    let report = service
+        .unzip_file_with_report(
+            zip_path.to_str().unwrap(),
+            extract_dir.to_str().unwrap(),
+            true,
+            false,
+        )
+        .await
+        .unwrap();
+    assert_eq!(report.files, 1);
+    assert_eq!(
+        fs::read_to_string(extract_dir.join("good.txt")).unwrap(),
+        "readable content"
+    );
+    assert!(!extract_dir.join("nested/bad.txt").exists());
+    assert!(!dir_path.join("escape.txt").exists());
+    let skipped: Vec<_> = report.skipped.iter().map(|s| s.entry.as_str()).collect();
+    assert_eq!(skipped, ["nested/bad.txt", "../escape.txt"]);
+    let message = report.message(Locale::En);
+    assert!(message.contains("Successfully extracted 1 file"));
+    assert!(message.contains("Skipped 2 unreadable entries"));
+
+    // no readable entry at all, the extraction fails and the target directory is removed
+    let zip_path = dir_path.join("unreadable.zip");
+    write_corrupt_zip(
+        &zip_path,
+        &[("bad.txt", "corrupted content")],
+        "corrupted content",
+    )
+    .await;
+    let extract_dir = dir_path.join("unreadable");
+    let result = service
+        .unzip_file_with_report(
+            zip_path.to_str().unwrap(),
+            extract_dir.to_str().unwrap(),
+            true,
+            false,
+        )
+        .await;
+    assert!(result.is_err());
+    assert!(!extract_dir.exists());
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_unzip_file_merge_refuses_symlinks_and_pinned_files() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    let zip_path = dir_path.join("merge.zip");
+    let zip_file = tokio::fs::File::create(&zip_path).await.unwrap();
+    let mut zip_writer = ZipFileWriter::new(zip_file.compat());
+    for (name, content) in [
+        ("linkdir/pwned.txt", "pwned"),
+        ("pinned.txt", "replaced"),
+        ("notes.txt", "new notes"),
+    ] {
+        let builder = async_zip::ZipEntryBuilder::new(
+            name.to_string().into(),
+            async_zip::Compression::Deflate,
+        );
+        zip_writer
+            .write_entry_whole(builder, content.as_bytes())
+            .await
+            .unwrap();
+    }
+    zip_writer.close().await.unwrap();
+
+    let target_dir = dir_path.join("target");
+    let outside = temp_dir.join("outside");
+    fs::create_dir_all(&outside).unwrap();
+    fs::create_dir_all(&target_dir).unwrap();
+    std::os::unix::fs::symlink(&outside, target_dir.join("linkdir")).unwrap();
+    create_temp_file(&target_dir, "pinned.txt", "original");
+    create_temp_file(&target_dir, "notes.txt", "old notes");
+    let service = service.with_pinned_paths(vec![target_dir.join("pinned.txt")]);
+
+    // the preview only lists the files the extraction replaces
+    let overwritten = service
+        .unzip_overwrites(zip_path.to_str().unwrap(), target_dir.to_str().unwrap())
+        .await
+        .unwrap();
+    let paths: Vec<_> = overwritten.iter().map(|file| file.path.clone()).collect();
+    assert_eq!(paths, [PathBuf::from("notes.txt")]);
+
+    let result = service
+        .unzip_file_with_report(
+            zip_path.to_str().unwrap(),
+            target_dir.to_str().unwrap(),
+            false,
+            true,
+        )
+        .await;
+    assert!(result.is_err());
+    assert!(!outside.join("pwned.txt").exists());
+
+    let report = service
+        .unzip_file_with_report(
+            zip_path.to_str().unwrap(),
+            target_dir.to_str().unwrap(),
+            true,
+            true,
+        )
+        .await
+        .unwrap();
+    let skipped: Vec<_> = report.skipped.iter().map(|s| s.entry.as_str()).collect();
+    assert_eq!(skipped, ["linkdir/pwned.txt", "pinned.txt"]);
+    assert!(!outside.join("pwned.txt").exists());
+    assert_eq!(
+        fs::read_to_string(target_dir.join("pinned.txt")).unwrap(),
+        "original"
+    );
+    assert_eq!(
+        fs::read_to_string(target_dir.join("notes.txt")).unwrap(),
+        "new notes"
+    );
+}
+
+#[tokio::test]
+async fn test_unzip_file_layout_summary() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    let zip_path = dir_path.join("layout.zip");
+    let zip_file = tokio::fs::File::create(&zip_path).await.unwrap();
+    let mut zip_writer = ZipFileWriter::new(zip_file.compat());
+    for (name, content) in [
+        ("README.md", "readme"),
+        ("src/lib.rs", "pub mod a;"),
+        ("src/a/mod.rs", "fn a() {}"),
+        ("assets/", ""),
+    ] {
+        let builder = async_zip::ZipEntryBuilder::new(
+            name.to_string().into(),
+            async_zip::Compression::Deflate,
+        );
+        zip_writer
+            .write_entry_whole(builder, content.as_bytes())
+            .await
+            .unwrap();
+    }
+    zip_writer.close().await.unwrap();
+
+    let extract_dir = dir_path.join("extracted");
+    let report = service
+        .unzip_file_with_report(
+            zip_path.to_str().unwrap(),
+            extract_dir.to_str().unwrap(),
+            false,
+            false,
+        )
+        .await
+        .unwrap();
+    assert_eq!(report.files, 3);
+    assert_eq!(report.directories, 1);
+    assert_eq!(report.bytes, 25);
+    let top_level: Vec<_> = report
+        .top_level
+        .iter()
+        .map(|entry| {
+            (
+                entry.name.as_str(),
+                entry.is_directory,
+                entry.files,
+                entry.bytes,
+            )
+        })
+        .collect();
+    assert_eq!(
+        top_level,
+        [
+            ("README.md", false, 1, 6),
+            ("assets", true, 0, 0),
+            ("src", true, 2, 19),
+        ]
+    );
+    let message = report.message(Locale::En);
+    assert!(message.contains(
+        "Top-level entries:\n  README.md (6 bytes)\n  assets/ (0 files, 0 bytes)\n  src/ (2 files, 19 bytes)"
+    ));
+}
+
+#[tokio::test]
+async fn test_apply_file_edits_preserves_final_newline() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+
+    // matched line by line, ignoring the indentation
+    let file_path = create_temp_file(&dir_path, "lines.txt", "fn main() {\n    old();\n}\n");
+    let edits = vec![EditOperation {
+        old_text: "  old(); ".to_string(),
+        new_text: "new();".to_string(),
+    }];
+    service
+        .apply_file_edits(&file_path, edits, Some(false), None, None)
+        .await
+        .unwrap();
+    assert_eq!(
+        tokio_fs::read_to_string(&file_path).await.unwrap(),
+        "fn main() {\n    new();\n}\n"
+    );
+
+    // exact match removing the last line ending
+    let file_path = create_temp_file(&dir_path, "exact.txt", "line1\r\nline2\r\n");
+    let edits = vec![EditOperation {
+        old_text: "line2\n".to_string(),
+        new_text: "line3".to_string(),
+    }];
+    service
+        .apply_file_edits(&file_path, edits, Some(false), None, None)
+        .await
+        .unwrap();
+    assert_eq!(
+        tokio_fs::read_to_string(&file_path).await.unwrap(),
+        "line1\r\nline3\r\n"
+    );
+
+    // no final newline to preserve
+    let file_path = create_temp_file(&dir_path, "none.txt", "a\n  b");
+    let edits = vec![EditOperation {
+        old_text: "b ".to_string(),
+        new_text: "c".to_string(),
+    }];
+    service
+        .apply_file_edits(&file_path, edits, Some(false), None, None)
+        .await
+        .unwrap();
+    assert_eq!(
+        tokio_fs::read_to_string(&file_path).await.unwrap(),
+        "a\n  c"
+    );
+}
+
+#[tokio::test]
+async fn test_get_file_profile() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+
+    let file_path = create_temp_file(
+        &dir_path,
+        "spaces.rs",
+        "fn main() {\n    if true {\n        run();\n    }\n}\n",
+    );
+    let profile = service.get_file_profile(&file_path).await.unwrap();
+    assert_eq!(profile.encoding, "utf-8");
+    assert_eq!(profile.line_ending, "lf");
+    assert!(!profile.mixed_line_endings);
+    assert!(profile.final_newline);
+    assert_eq!(profile.indentation, Indentation::Spaces { width: 4 });
+    assert_eq!(profile.line_count, 5);
+
+    let profile = detect_file_profile(b"\xEF\xBB\xBFa:\r\n\tb\r\n\tc\n  d");
+    assert_eq!(profile.encoding, "utf-8-bom");
+    assert_eq!(profile.line_ending, "crlf");
+    assert!(profile.mixed_line_endings);
+    assert!(!profile.final_newline);
+    assert_eq!(profile.indentation, Indentation::Tabs);
+    assert_eq!(
+        (profile.tab_indented_lines, profile.space_indented_lines),
+        (2, 1)
+    );
+    assert!(profile.to_string().contains("indentation: tabs (mixed"));
+
+    let utf16: Vec<u8> = [0xFF, 0xFE]
+        .into_iter()
+        .chain("x\n  y\n".encode_utf16().flat_map(u16::to_le_bytes))
+        .collect();
+    let profile = detect_file_profile(&utf16);
+    assert_eq!(profile.encoding, "utf-16le");
+    assert_eq!(profile.indentation, Indentation::Spaces { width: 2 });
+
+    assert_eq!(detect_file_profile(b"").indentation, Indentation::None);
+}
+
+#[tokio::test]
+async fn test_write_and_edit_follow_editorconfig() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    // outside of the allowed directory, ignored
+    create_temp_file(&temp_dir, ".editorconfig", "[*]\nend_of_line = cr\n");
+    create_temp_file(
+        &dir_path,
+        ".editorconfig",
+        "root = true\n\n[*]\nend_of_line = lf\ninsert_final_newline = true\ntrim_trailing_whitespace = true\n\n[*.go]\nindent_style = tab\ntab_width = 4\n",
+    );
+
+    let file_path = dir_path.join("main.go");
+    service
+        .write_file(
+            &file_path,
+            "func main() {  \r\n    if x {\r\n        y()\r\n    }\r\n}",
+            false,
+            None,
+            false,
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        fs::read_to_string(&file_path).unwrap(),
+        "func main() {\n\tif x {\n\t\ty()\n\t}\n}\n"
+    );
+
+    // only the inserted text is reformatted
+    let file_path = create_temp_file(&dir_path, "notes.txt", "keep   \nold\n");
+    let edits = vec![EditOperation {
+        old_text: "old\n".to_string(),
+        new_text: "new   ".to_string(),
+    }];
+    service
+        .apply_file_edits(&file_path, edits, Some(false), None, None)
+        .await
+        .unwrap();
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), "keep   \nnew\n");
+
+    // disabled
+    let service = service.with_editorconfig(false);
+    let file_path = dir_path.join("raw.go");
+    service
+        .write_file(&file_path, "    x  ", false, None, false)
+        .await
+        .unwrap();
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), "    x  ");
+}
+
+#[tokio::test]
+async fn test_write_line_endings_policy() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    let service = service.with_write_line_endings(LineEndingPolicy::Crlf);
+
+    let file_path = dir_path.join("mixed.txt");
+    service
+        .write_file(&file_path, "a\nb\r\nc\n", false, None, false)
+        .await
+        .unwrap();
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), "a\r\nb\r\nc\r\n");
+
+    // an LF file is converted when edited, over its own line endings and the editorconfig
+    create_temp_file(&dir_path, ".editorconfig", "[*]\nend_of_line = lf\n");
+    let file_path = create_temp_file(&dir_path, "unix.txt", "one\ntwo\n");
+    let edits = vec![EditOperation {
+        old_text: "two".to_string(),
+        new_text: "2".to_string(),
+    }];
+    service
+        .apply_file_edits(&file_path, edits, Some(false), None, None)
+        .await
+        .unwrap();
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), "one\r\n2\r\n");
+
+    let service = service.with_write_line_endings(LineEndingPolicy::Preserve);
+    let file_path = dir_path.join("raw.txt");
+    service
+        .write_file(&file_path, "a\r\nb", false, None, false)
+        .await
+        .unwrap();
+    // the editorconfig still applies
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), "a\nb");
+
+    assert_eq!(LineEndingPolicy::Preserve.line_ending(), None);
+    assert_eq!(LineEndingPolicy::Lf.line_ending(), Some("\n"));
+    assert_eq!(
+        LineEndingPolicy::Os.line_ending(),
+        Some(if cfg!(windows) { "\r\n" } else { "\n" })
+    );
+}
+
+#[tokio::test]
+async fn test_cleanup_whitespace() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    let messy = create_temp_file(
+        &dir_path,
+        "src/messy.py",
+        "def f():  \r\n    if x:\r\n\t    pass\r\n    return 1\t\r\n\r\n\r\n",
+    );
+    let clean = create_temp_file(&dir_path, "src/clean.py", "x = 1\n");
+    let empty = create_temp_file(&dir_path, "src/empty.py", "\n\n");
+    let other = create_temp_file(&dir_path, "notes.txt", "trailing   ");
+    fs::write(dir_path.join("src/data.py"), b"\0\x01 \n\n").unwrap();
+
+    let cleanups = service
+        .cleanup_whitespace(&dir_path, "*.py".to_string(), vec![], true)
+        .await
+        .unwrap();
+    let mut paths: Vec<_> = cleanups.iter().map(|c| c.path.clone()).collect();
+    paths.sort();
+    assert_eq!(paths, [empty.clone(), messy.clone()]);
+    assert!(cleanups.iter().all(|c| c.error.is_none()));
+    // dry run
+    assert_eq!(fs::read_to_string(&empty).unwrap(), "\n\n");
+
+    service
+        .cleanup_whitespace(&dir_path, "*.py".to_string(), vec![], false)
+        .await
+        .unwrap();
+    assert_eq!(
+        fs::read_to_string(&messy).unwrap(),
+        "def f():\r\n    if x:\r\n        pass\r\n    return 1\r\n"
+    );
+    assert_eq!(fs::read_to_string(&empty).unwrap(), "");
+    assert_eq!(fs::read_to_string(&clean).unwrap(), "x = 1\n");
+    assert_eq!(fs::read_to_string(&other).unwrap(), "trailing   ");
+
+    let cleanups = service
+        .cleanup_whitespace(&dir_path, "*.py".to_string(), vec![], false)
+        .await
+        .unwrap();
+    assert!(cleanups.is_empty());
+}
+
+#[tokio::test]
+async fn test_file_lock_state() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+
+    let state = service
+        .file_lock_state(&dir_path.join("missing.txt"))
+        .await
+        .unwrap();
+    assert!(!state.exists);
+    assert!(state.warning().is_none());
+
+    // just written by another process
+    let file_path = create_temp_file(&dir_path, "notes.txt", "draft\n");
+    let state = service.file_lock_state(&file_path).await.unwrap();
+    assert!(state.exists);
+    assert!(state.recently_modified);
+    assert!(
+        state
+            .warning()
+            .unwrap()
+            .contains("modified by another process")
+    );
+
+    // the writes of the server are not reported
+    service
+        .write_file(&file_path, "final\n", false, None, false)
+        .await
+        .unwrap();
+    let state = service.file_lock_state(&file_path).await.unwrap();
+    assert!(!state.recently_modified);
+    assert!(state.warning().is_none());
+
+    #[cfg(any(target_os = "macos", target_os = "linux"))]
+    {
+        assert_eq!(state.locked, Some(false));
+        let file = std::fs::File::open(&file_path).unwrap();
+        file.lock().unwrap();
+        let state = service.file_lock_state(&file_path).await.unwrap();
+        assert_eq!(state.locked, Some(true));
+        assert!(
+            state
+                .warning()
+                .unwrap()
+                .contains("locked by another process")
+        );
+        file.unlock().unwrap();
+    }
+}
+
+#[tokio::test]
+async fn test_cancelled_operations_leave_no_partial_output() {
+    use rust_mcp_filesystem::ErrorKind;
+
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    let file1 = create_temp_file(&dir_path, "file1.txt", "content1");
+    create_temp_file(&dir_path, "file2.txt", "content2");
+    let zip_path = dir_path.join("archive.zip");
+    service
+        .zip_files(
+            vec![file1.to_str().unwrap().to_string()],
+            zip_path.to_str().unwrap().to_string(),
+            false,
+        )
+        .await
+        .unwrap();
+
+    service.cancel_operations();
+    assert!(service.is_cancelled());
+
+    let partial_zip = dir_path.join("partial.zip");
+    let err = service
+        .zip_directory(
+            dir_path.to_str().unwrap().to_string(),
+            "*.txt".to_string(),
+            vec![],
+            partial_zip.to_str().unwrap().to_string(),
+            false,
+            false,
+            false,
+            false,
+            None,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Cancelled);
+    assert!(!partial_zip.exists());
+
+    let err = service
+        .zip_files(
+            vec![file1.to_str().unwrap().to_string()],
+            partial_zip.to_str().unwrap().to_string(),
+            false,
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Cancelled);
+    assert!(!partial_zip.exists());
+
+    let extract_dir = dir_path.join("extracted");
+    let err = service
+        .unzip_file(zip_path.to_str().unwrap(), extract_dir.to_str().unwrap())
+        .await
+        .unwrap_err();
+    assert_eq!(err.kind(), ErrorKind::Cancelled);
+    assert!(!extract_dir.exists());
+}
+
+#[tokio::test]
+async fn test_overlay_directory_survives_restart() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    let overlay_dir = temp_dir.join("overlay");
+    create_temp_file(&dir_path, "old.txt", "old");
+    let service = service.with_overlay_directory(overlay_dir.clone()).unwrap();
+    service
+        .write_file(&dir_path.join("new.txt"), "pending", false, None, false)
+        .await
+        .unwrap();
+    service
+        .move_file(
+            &dir_path.join("old.txt"),
+            &dir_path.join("moved.txt"),
+            false,
+        )
+        .await
+        .unwrap();
+    drop(service);
+
+    // the changes are picked up after a restart
+    let service = FileSystemService::try_new(&[dir_path.display().to_string()])
+        .unwrap()
+        .with_overlay_directory(overlay_dir.clone())
+        .unwrap();
+    assert_eq!(
+        service
+            .read_text_file(&dir_path.join("new.txt"), false)
+            .await
+            .unwrap(),
+        "pending"
+    );
+    assert!(!dir_path.join("new.txt").exists());
+    assert_eq!(service.commit_overlay(false).await.unwrap().len(), 2);
+    assert_eq!(
+        fs::read_to_string(dir_path.join("new.txt")).unwrap(),
+        "pending"
+    );
+    assert!(dir_path.join("moved.txt").exists() && !dir_path.join("old.txt").exists());
+    assert_eq!(fs::read_dir(&overlay_dir).unwrap().count(), 0);
+
+    // an unreadable index is reported instead of dropping the changes
+    fs::write(overlay_dir.join("overlay-index.json"), "{").unwrap();
+    let result = FileSystemService::try_new(&[dir_path.display().to_string()])
+        .unwrap()
+        .with_overlay_directory(overlay_dir);
+    assert!(result.is_err());
+}
+
 #[tokio::test]
 async fn adhock() {}
@@ -0,0 +1,335 @@
+//! Randomized property tests exercising path validation, file edits, snippet extraction and archive
+//! round-trips with adversarial inputs (unusual Unicode, deep nesting, huge lines, symbolic links
+//! leading outside of the allowed directory).
+//!
+//! Enabled with the `fuzz` feature: `cargo test --features fuzz --test test_fuzz`.
+//! `FUZZ_ITERATIONS` sets the number of cases per property (default: 200) and `FUZZ_SEED` replays
+//! the cases of a failed run, its seed being printed in the failure message.
+#![cfg(feature = "fuzz")]
+
+#[path = "common/common.rs"]
+pub mod common;
+
+use common::{create_temp_file, get_temp_dir, setup_service};
+use grep::matcher::Match;
+use rust_mcp_filesystem::fs_service::FileSystemService;
+use rust_mcp_filesystem::tools::EditOperation;
+use std::fs;
+use std::path::{Component, Path, PathBuf};
+
+const DEFAULT_ITERATIONS: usize = 200;
+
+/// Characters known to trip up byte-oriented code: multi-byte, combining, zero-width,
+/// bidirectional control and whitespace characters, along with path separators.
+const TRICKY_CHARS: &[char] = &[
+    'a', 'Z', '0', ' ', '\t', '-', '_', '.', '/', '\\', '~', 'é', 'ß', 'Ж', '漢', '字', '🦀', '👩',
+    '\u{200D}', '\u{0301}', '\u{200B}', '\u{FEFF}', '\u{202E}', '\u{00A0}', '\u{3000}', 'ﬃ', 'İ',
+];
+
+/// Random generator seeded from `FUZZ_SEED` or randomly, reporting its seed on failure.
+struct Fuzzer {
+    rng: fastrand::Rng,
+    seed: u64,
+    iterations: usize,
+}
+
+impl Fuzzer {
+    fn new() -> Self {
+        let seed = std::env::var("FUZZ_SEED")
+            .ok()
+            .and_then(|seed| seed.parse().ok())
+            .unwrap_or_else(|| fastrand::u64(..));
+        let iterations = std::env::var("FUZZ_ITERATIONS")
+            .ok()
+            .and_then(|iterations| iterations.parse().ok())
+            .unwrap_or(DEFAULT_ITERATIONS);
+        Self {
+            rng: fastrand::Rng::with_seed(seed),
+            seed,
+            iterations,
+        }
+    }
+
+    fn context(&self, case: usize) -> String {
+        format!("case {case} failed, replay with FUZZ_SEED={}", self.seed)
+    }
+
+    fn tricky_char(&mut self) -> char {
+        if self.rng.u8(..4) == 0 {
+            self.rng.char(..)
+        } else {
+            TRICKY_CHARS[self.rng.usize(..TRICKY_CHARS.len())]
+        }
+    }
+
+    fn tricky_string(&mut self, max_chars: usize) -> String {
+        let len = self.rng.usize(..=max_chars);
+        (0..len).map(|_| self.tricky_char()).collect()
+    }
+
+    /// A file name without separators, not `.` or `..` and within the usual 255 bytes limit.
+    fn file_name(&mut self) -> String {
+        loop {
+            let name: String = self
+                .tricky_string(12)
+                .chars()
+                .filter(|c| !matches!(c, '/' | '\\' | '\0'))
+                .collect();
+            let name = name.trim().to_string();
+            if !name.is_empty() && name != "." && name != ".." && name.len() <= 255 {
+                return name;
+            }
+        }
+    }
+
+    fn line(&mut self) -> String {
+        match self.rng.u8(..10) {
+            // huge line
+            0 => self.tricky_string(20_000),
+            // indented line
+            1 => format!(
+                "{}{}",
+                " ".repeat(self.rng.usize(..12)),
+                self.tricky_string(40)
+            ),
+            _ => self.tricky_string(80),
+        }
+        .replace(['\n', '\r'], "")
+    }
+
+    fn char_boundary(&mut self, text: &str) -> usize {
+        let boundaries: Vec<usize> = text
+            .char_indices()
+            .map(|(index, _)| index)
+            .chain([text.len()])
+            .collect();
+        boundaries[self.rng.usize(..boundaries.len())]
+    }
+}
+
+/// The path the operating system accesses for `path`: its nearest existing ancestor is
+/// canonicalized, the missing components can't be links.
+fn resolve_on_disk(path: &Path) -> PathBuf {
+    let mut missing = vec![];
+    let mut existing = path;
+    loop {
+        if let Ok(canonical) = fs::canonicalize(existing) {
+            return missing
+                .iter()
+                .rev()
+                .fold(canonical, |resolved, name| resolved.join(name));
+        }
+        match (existing.parent(), existing.file_name()) {
+            (Some(parent), Some(name)) => {
+                missing.push(name);
+                existing = parent;
+            }
+            _ => return path.to_path_buf(),
+        }
+    }
+}
+
+#[test]
+fn fuzz_validate_path() {
+    let mut fuzzer = Fuzzer::new();
+    let (temp_dir, service, allowed_dirs) = setup_service(vec!["allowed".to_string()]);
+    let allowed = temp_dir.join("allowed");
+    create_temp_file(&allowed, "existing/file.txt", "content");
+    create_temp_file(&temp_dir, "outside/inner/secret.txt", "secret");
+    // links within the allowed directory, leading outside of it, and relative ones
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::symlink;
+        symlink(allowed.join("existing"), allowed.join("link_in")).unwrap();
+        symlink(temp_dir.join("outside/inner"), allowed.join("link_out")).unwrap();
+        symlink("..", allowed.join("existing/link_up")).unwrap();
+        symlink("../outside", allowed.join("link_relative_out")).unwrap();
+    }
+    let canonical_allowed = fs::canonicalize(&allowed).unwrap();
+
+    for case in 0..fuzzer.iterations {
+        let depth = fuzzer.rng.usize(..64);
+        let components: Vec<String> = (0..depth)
+            .map(|_| match fuzzer.rng.u8(..10) {
+                0 | 1 => "..".to_string(),
+                2 => ".".to_string(),
+                3 => "existing".to_string(),
+                4 => "link_in".to_string(),
+                5 => "link_out".to_string(),
+                6 => "link_up".to_string(),
+                7 => "link_relative_out".to_string(),
+                _ => fuzzer.tricky_string(16),
+            })
+            .collect();
+        let joined = components.join("/");
+        let requested = match fuzzer.rng.u8(..3) {
+            0 => allowed.join(&joined),
+            1 => PathBuf::from(format!("/{joined}")),
+            _ => PathBuf::from(&joined),
+        };
+
+        let context = fuzzer.context(case);
+        if let Ok(valid_path) = service.validate_path(&requested, allowed_dirs.clone()) {
+            assert!(
+                !valid_path
+                    .components()
+                    .any(|component| component == Component::ParentDir),
+                "{context}: {requested:?} validated as {valid_path:?}, with `..` components"
+            );
+            let resolved = resolve_on_disk(&valid_path);
+            assert!(
+                resolved.starts_with(&canonical_allowed),
+                "{context}: {requested:?} validated as {valid_path:?}, resolved to {resolved:?} outside of {allowed:?}"
+            );
+        }
+    }
+}
+
+#[test]
+fn fuzz_extract_snippet() {
+    let mut fuzzer = Fuzzer::new();
+    let service = FileSystemService::try_new(&[get_temp_dir().display().to_string()]).unwrap();
+
+    for case in 0..fuzzer.iterations {
+        let line = if fuzzer.rng.bool() {
+            format!("{}{}", " ".repeat(fuzzer.rng.usize(..8)), fuzzer.line())
+        } else {
+            fuzzer.line()
+        };
+        let start = fuzzer.char_boundary(&line);
+        let end = start + fuzzer.char_boundary(&line[start..]);
+        let max_chars = fuzzer.rng.usize(..300);
+        let context_chars = fuzzer.rng.usize(..100);
+
+        let context = fuzzer.context(case);
+        let snippet = std::panic::catch_unwind(std::panic::AssertUnwindSafe(|| {
+            service.extract_snippet(
+                &line,
+                Match::new(start, end),
+                Some(max_chars),
+                Some(context_chars),
+            )
+        }))
+        .unwrap_or_else(|_| panic!("{context}: line {line:?}, match {start}..{end}"));
+        // up to `max_chars` characters of the line, with ellipses on both sides
+        assert!(
+            snippet.chars().count() <= max_chars + 6,
+            "{context}: snippet {snippet:?} longer than {max_chars} characters"
+        );
+    }
+}
+
+#[tokio::test]
+async fn fuzz_apply_file_edits() {
+    let mut fuzzer = Fuzzer::new();
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["edits".to_string()]);
+    let file_path = temp_dir.join("edits").join("file.txt");
+
+    for case in 0..fuzzer.iterations {
+        let line_count = fuzzer.rng.usize(1..20);
+        let content: String = (0..line_count)
+            .map(|_| fuzzer.line())
+            .collect::<Vec<_>>()
+            .join("\n");
+        fs::write(&file_path, &content).unwrap();
+
+        let start = fuzzer.char_boundary(&content);
+        let end = start + fuzzer.char_boundary(&content[start..]);
+        let old_text = content[start..end].to_string();
+        let new_text = fuzzer.tricky_string(30).replace(['\r', '\u{FEFF}'], "");
+        let replace_all = fuzzer.rng.bool();
+
+        let context = fuzzer.context(case);
+        let result = service
+            .apply_file_edits(
+                &file_path,
+                vec![EditOperation {
+                    old_text: old_text.clone(),
+                    new_text: new_text.clone(),
+                }],
+                Some(false),
+                None,
+                Some(replace_all),
+            )
+            .await;
+
+        // exact and unique matches are replaced as is
+        if !old_text.is_empty() && content.matches(&old_text).count() == 1 {
+            assert!(
+                result.is_ok(),
+                "{context}: replacing {old_text:?} failed: {result:?}"
+            );
+            assert_eq!(
+                fs::read_to_string(&file_path).unwrap(),
+                content.replacen(&old_text, &new_text, 1),
+                "{context}: replacing {old_text:?} with {new_text:?}"
+            );
+        }
+    }
+}
+
+#[tokio::test]
+async fn fuzz_archive_round_trip() {
+    let mut fuzzer = Fuzzer::new();
+    // archives are slower to build, fewer cases are enough to cover unusual names and nesting
+    let iterations = fuzzer.iterations.div_ceil(10);
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["archives".to_string()]);
+    let root = temp_dir.join("archives");
+
+    for case in 0..iterations {
+        let source = root.join(format!("source_{case}"));
+        let target = root.join(format!("target_{case}"));
+        let zip_file = root.join(format!("archive_{case}.zip"));
+
+        let mut files: Vec<(PathBuf, String)> = vec![];
+        for _ in 0..fuzzer.rng.usize(1..12) {
+            let depth = fuzzer.rng.usize(..10);
+            let mut relative_path: PathBuf = (0..depth).map(|_| fuzzer.file_name()).collect();
+            relative_path.push(fuzzer.file_name());
+            let content = fuzzer.line();
+            if create_file(&source, &relative_path, &content) {
+                files.push((relative_path, content));
+            }
+        }
+
+        let context = fuzzer.context(case);
+        service
+            .zip_directory(
+                source.display().to_string(),
+                "**/*".to_string(),
+                vec![],
+                zip_file.display().to_string(),
+                false,
+                false,
+                fuzzer.rng.bool(),
+                false,
+                None,
+            )
+            .await
+            .unwrap_or_else(|err| panic!("{context}: zip_directory failed: {err}"));
+        service
+            .unzip_file(
+                &zip_file.display().to_string(),
+                &target.display().to_string(),
+            )
+            .await
+            .unwrap_or_else(|err| panic!("{context}: unzip_file failed: {err}"));
+
+        for (relative_path, content) in &files {
+            assert_eq!(
+                fs::read_to_string(target.join(relative_path)).ok().as_ref(),
+                Some(content),
+                "{context}: {relative_path:?} differs after the round-trip"
+            );
+        }
+    }
+}
+
+/// Creates the file unless a file and a directory of the generated tree collide.
+fn create_file(root: &Path, relative_path: &Path, content: &str) -> bool {
+    let path = root.join(relative_path);
+    if path.exists() || path.ancestors().skip(1).any(|ancestor| ancestor.is_file()) {
+        return false;
+    }
+    fs::create_dir_all(path.parent().unwrap()).is_ok() && fs::write(&path, content).is_ok()
+}
@@ -2,8 +2,20 @@
 pub mod common;
 
 use common::setup_service;
+use rust_mcp_filesystem::error::ToolError;
+use rust_mcp_filesystem::fs_service::utils::{MimeCategory, OutputFormat, WriteIfExists};
+use rust_mcp_filesystem::handler::FileSystemHandler;
+use rust_mcp_filesystem::idempotency::IdempotencyCache;
+use rust_mcp_filesystem::metrics::measure;
+use rust_mcp_filesystem::recording::{
+    ReplayStatus, ReplayedRequest, SessionRecorder, read_recording, replay,
+};
+use rust_mcp_filesystem::test_support::TestServer;
 use rust_mcp_filesystem::tools::*;
-use rust_mcp_sdk::schema::{ContentBlock, schema_utils::CallToolError};
+use rust_mcp_sdk::schema::{
+    CallToolRequestParams, CallToolResult, ContentBlock, EmbeddedResourceResource,
+    schema_utils::CallToolError,
+};
 use std::{collections::HashSet, fs};
 
 #[tokio::test]
@@ -12,6 +24,8 @@ async fn test_create_directory_new_directory() {
     let new_dir = temp_dir.join("dir1").join("new_dir");
     let params = CreateDirectory {
         path: new_dir.to_str().unwrap().to_string(),
+        output_format: None,
+        idempotency_key: None,
     };
 
     let result = CreateDirectory::run_tool(params, &service).await;
@@ -44,6 +58,8 @@ async fn test_create_directory_existing_directory() {
     fs::create_dir_all(&existing_dir).unwrap();
     let params = CreateDirectory {
         path: existing_dir.to_str().unwrap().to_string(),
+        output_format: None,
+        idempotency_key: None,
     };
 
     let result = CreateDirectory::run_tool(params, &service).await;
@@ -75,6 +91,8 @@ async fn test_create_directory_nested() {
     let nested_dir = temp_dir.join("dir1").join("nested/subdir");
     let params = CreateDirectory {
         path: nested_dir.to_str().unwrap().to_string(),
+        output_format: None,
+        idempotency_key: None,
     };
 
     let result = CreateDirectory::run_tool(params, &service).await;
@@ -104,6 +122,8 @@ async fn test_create_directory_outside_allowed() {
     let outside_dir = temp_dir.join("dir2").join("forbidden");
     let params = CreateDirectory {
         path: outside_dir.to_str().unwrap().to_string(),
+        output_format: None,
+        idempotency_key: None,
     };
 
     let result = CreateDirectory::run_tool(params, &service).await;
@@ -121,6 +141,8 @@ async fn test_create_directory_invalid_path() {
         path: invalid_path
             .to_str()
             .map_or("invalid\0dir".to_string(), |s| s.to_string()),
+        output_format: None,
+        idempotency_key: None,
     };
 
     let result = CreateDirectory::run_tool(params, &service).await;
@@ -165,5 +187,2028 @@ async fn ensure_tools_duplication() {
     assert_eq!(duplicate_descriptions.join(","), "");
 }
 
+#[tokio::test]
+async fn test_search_files_with_previews() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    fs::write(dir_path.join("a.txt"), "alpha content").unwrap();
+    fs::write(dir_path.join("b.txt"), "beta content").unwrap();
+
+    let params = SearchFiles {
+        path: dir_path.to_str().unwrap().to_string(),
+        pattern: "*.txt".to_string(),
+        exclude_patterns: None,
+        min_bytes: None,
+        max_bytes: None,
+        mime_category: None,
+        include_previews: Some(true),
+        preview_bytes: Some(5),
+        preview_budget: Some(8),
+        output_format: None,
+    };
+
+    let call_result = SearchFiles::run_tool(params, &service).await.unwrap();
+    // one text block with the listing, followed by previews until the budget is spent
+    assert_eq!(call_result.content.len(), 3);
+
+    let previews: Vec<_> = call_result
+        .content
+        .iter()
+        .filter_map(|c| match c {
+            ContentBlock::EmbeddedResource(resource) => match &resource.resource {
+                EmbeddedResourceResource::TextResourceContents(text) => Some(text.text.clone()),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    assert_eq!(previews.iter().map(|p| p.len()).sum::<usize>(), 8);
+}
+
+#[tokio::test]
+async fn test_search_files_preview_limits() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    for index in 0..25 {
+        fs::write(dir_path.join(format!("my notes {index}.txt")), "content").unwrap();
+    }
+    let params = |preview_bytes| SearchFiles {
+        path: dir_path.to_str().unwrap().to_string(),
+        pattern: "*.txt".to_string(),
+        exclude_patterns: None,
+        min_bytes: None,
+        max_bytes: None,
+        mime_category: None,
+        include_previews: Some(true),
+        preview_bytes: Some(preview_bytes),
+        preview_budget: None,
+        output_format: None,
+    };
+
+    // at most 20 previews, whatever the budget left
+    let call_result = SearchFiles::run_tool(params(1), &service).await.unwrap();
+    assert_eq!(call_result.content.len(), 21);
+    match &call_result.content[1] {
+        ContentBlock::EmbeddedResource(resource) => match &resource.resource {
+            EmbeddedResourceResource::TextResourceContents(text) => {
+                assert!(text.uri.starts_with("file:///"));
+                assert!(text.uri.contains("/my%20notes%20"));
+            }
+            _ => panic!("Expected a text resource"),
+        },
+        _ => panic!("Expected an embedded resource"),
+    }
+
+    // empty previews are not embedded
+    let call_result = SearchFiles::run_tool(params(0), &service).await.unwrap();
+    assert_eq!(call_result.content.len(), 1);
+}
+
+#[tokio::test]
+async fn test_search_files_without_previews() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    fs::write(dir_path.join("a.txt"), "alpha content").unwrap();
+
+    let params = SearchFiles {
+        path: dir_path.to_str().unwrap().to_string(),
+        pattern: "*.txt".to_string(),
+        exclude_patterns: None,
+        min_bytes: None,
+        max_bytes: None,
+        mime_category: None,
+        include_previews: None,
+        preview_bytes: None,
+        preview_budget: None,
+        output_format: None,
+    };
+
+    let call_result = SearchFiles::run_tool(params, &service).await.unwrap();
+    assert_eq!(call_result.content.len(), 1);
+}
+
+#[tokio::test]
+async fn test_directory_tree_mermaid_output() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    fs::create_dir_all(dir_path.join("src")).unwrap();
+    fs::write(dir_path.join("src").join("main.rs"), "fn main() {}").unwrap();
+
+    let params = DirectoryTree {
+        path: dir_path.to_str().unwrap().to_string(),
+        max_depth: None,
+        max_entries: None,
+        output_format: Some(TreeOutputFormat::Mermaid),
+    };
+
+    let call_result = DirectoryTree::run_tool(params, &service).await.unwrap();
+    match call_result.content.first().unwrap() {
+        ContentBlock::TextContent(text_content) => {
+            assert_eq!(
+                text_content.text,
+                "graph TD\n    n0[\"dir1/\"]\n    n0 --> n1[\"src/\"]\n    n1 --> n2(\"main.rs\")\n"
+            );
+        }
+        _ => panic!("Expected TextContent result"),
+    }
+}
+
+#[tokio::test]
+async fn test_directory_tree_dot_output_with_max_entries() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    fs::write(dir_path.join("a.txt"), "a").unwrap();
+    fs::write(dir_path.join("b.txt"), "b").unwrap();
+
+    let params = DirectoryTree {
+        path: dir_path.to_str().unwrap().to_string(),
+        max_depth: None,
+        max_entries: Some(1),
+        output_format: Some(TreeOutputFormat::Dot),
+    };
+
+    let call_result = DirectoryTree::run_tool(params, &service).await.unwrap();
+    match call_result.content.first().unwrap() {
+        ContentBlock::TextContent(text_content) => {
+            assert!(text_content.text.starts_with("digraph tree {"));
+            assert!(text_content.text.contains("n0 -> n1;"));
+            assert!(!text_content.text.contains("n2"));
+        }
+        _ => panic!("Expected TextContent result"),
+    }
+    assert!(call_result.meta.unwrap().contains_key("warning"));
+}
+
+#[tokio::test]
+async fn test_search_files_by_mime_category() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    fs::write(
+        dir_path.join("picture.bin"),
+        [0x89, 0x50, 0x4E, 0x47, 0x0D, 0x0A, 0x1A, 0x0A, 0, 0, 0, 0],
+    )
+    .unwrap();
+    fs::write(dir_path.join("notes.txt"), "not an image").unwrap();
+
+    let params = SearchFiles {
+        path: dir_path.to_str().unwrap().to_string(),
+        pattern: "*".to_string(),
+        exclude_patterns: None,
+        min_bytes: None,
+        max_bytes: None,
+        mime_category: Some(MimeCategory::Image),
+        include_previews: None,
+        preview_bytes: None,
+        preview_budget: None,
+        output_format: None,
+    };
+
+    let call_result = SearchFiles::run_tool(params, &service).await.unwrap();
+    match call_result.content.first().unwrap() {
+        ContentBlock::TextContent(text_content) => {
+            assert!(text_content.text.ends_with("picture.bin"));
+            assert!(!text_content.text.contains("notes.txt"));
+        }
+        _ => panic!("Expected TextContent result"),
+    }
+}
+
+#[tokio::test]
+async fn test_write_file_structured_checksum() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let file_path = temp_dir.join("dir1").join("hello.txt");
+
+    let params = WriteFile {
+        path: file_path.to_str().unwrap().to_string(),
+        content: "hello".to_string(),
+        create_parents: None,
+        if_exists: None,
+        return_diff: None,
+        output_format: None,
+        idempotency_key: None,
+    };
+
+    let call_result = WriteFile::run_tool(params, &service).await.unwrap();
+    let structured = call_result.structured_content.unwrap();
+    assert_eq!(
+        structured.get("sha256").unwrap(),
+        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+    );
+    assert_eq!(structured.get("bytes").unwrap(), 5);
+}
+
+#[tokio::test]
+async fn test_edit_file_structured_checksum() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let file_path = temp_dir.join("dir1").join("hello.txt");
+    fs::write(&file_path, "hello world").unwrap();
+
+    let params = EditFile {
+        path: file_path.to_str().unwrap().to_string(),
+        edits: vec![EditOperation {
+            old_text: " world".to_string(),
+            new_text: "".to_string(),
+        }],
+        dry_run: None,
+        replace_all: None,
+        expected_anchors: None,
+        output_format: None,
+        idempotency_key: None,
+    };
+
+    let call_result = EditFile::run_tool(params, &service).await.unwrap();
+    let structured = call_result.structured_content.unwrap();
+    assert_eq!(
+        structured.get("sha256").unwrap(),
+        "2cf24dba5fb0a30e26e83b2ac5b9e29e1b161e5c1fa7425e73043362938b9824"
+    );
+    assert_eq!(structured.get("bytes").unwrap(), 5);
+}
+
+#[tokio::test]
+async fn test_idempotency_key_prevents_double_append() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let file_path = temp_dir.join("dir1").join("log.txt");
+    let cache = IdempotencyCache::default();
+    let params = WriteFile {
+        path: file_path.to_str().unwrap().to_string(),
+        content: "entry\n".to_string(),
+        create_parents: None,
+        if_exists: Some(WriteIfExists::Append),
+        return_diff: None,
+        output_format: None,
+        idempotency_key: Some("append-1".to_string()),
+    };
+
+    let first = cache
+        .run(
+            "write_file:append-1".to_string(),
+            WriteFile::run_tool(params.clone(), &service),
+        )
+        .await
+        .unwrap();
+    let retried = cache
+        .run(
+            "write_file:append-1".to_string(),
+            WriteFile::run_tool(params.clone(), &service),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        serde_json::to_value(&first).unwrap(),
+        serde_json::to_value(&retried).unwrap()
+    );
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), "entry\n");
+
+    // a different key executes the operation again
+    cache
+        .run(
+            "write_file:append-2".to_string(),
+            WriteFile::run_tool(params, &service),
+        )
+        .await
+        .unwrap();
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), "entry\nentry\n");
+}
+
+#[tokio::test]
+async fn test_idempotency_cache_does_not_remember_failures() {
+    let cache = IdempotencyCache::new(1);
+
+    let result = cache
+        .run("key".to_string(), async {
+            Err(CallToolError::from_message("transient"))
+        })
+        .await;
+    assert!(result.is_err());
+
+    let result = cache
+        .run("key".to_string(), async {
+            Ok(rust_mcp_sdk::schema::CallToolResult::text_content(vec![]))
+        })
+        .await;
+    assert!(result.is_ok());
+}
+
+#[tokio::test]
+async fn test_read_archive_entry_returns_binary_as_resource() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    let png_path = dir_path.join("image.png");
+    fs::write(
+        &png_path,
+        [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, 0xFF],
+    )
+    .unwrap();
+    let zip_path = dir_path.join("images.zip");
+    service
+        .zip_files(
+            vec![png_path.to_str().unwrap().to_string()],
+            zip_path.to_str().unwrap().to_string(),
+            false,
+        )
+        .await
+        .unwrap();
+
+    let params = ReadArchiveEntry {
+        path: zip_path.to_str().unwrap().to_string(),
+        entry: "image.png".to_string(),
+        max_bytes: None,
+    };
+    let call_result = ReadArchiveEntry::run_tool(params, &service).await.unwrap();
+
+    match call_result.content.first().unwrap() {
+        ContentBlock::EmbeddedResource(resource) => match &resource.resource {
+            EmbeddedResourceResource::BlobResourceContents(blob) => {
+                assert_eq!(blob.mime_type.as_deref(), Some("image/png"));
+                assert_eq!(blob.blob, "iVBORw0KGgr/");
+                assert!(blob.uri.ends_with("images.zip!/image.png"));
+            }
+            other => panic!("Expected a blob resource, got {other:?}"),
+        },
+        other => panic!("Expected an embedded resource, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_json_output_format() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    fs::create_dir_all(dir_path.join("sub")).unwrap();
+    fs::write(dir_path.join("file.txt"), "hello").unwrap();
+
+    let params = ListDirectoryWithSizes {
+        path: dir_path.to_str().unwrap().to_string(),
+        exclude_hidden: None,
+        output_format: Some(OutputFormat::Json),
+    };
+    let call_result = ListDirectoryWithSizes::run_tool(params, &service)
+        .await
+        .unwrap();
+    let structured = call_result.structured_content.unwrap();
+    assert_eq!(
+        serde_json::Value::Object(structured.clone()),
+        serde_json::json!({
+            "path": dir_path.to_str().unwrap(),
+            "entries": [
+                { "name": "file.txt", "type": "file", "size": 5 },
+                { "name": "sub", "type": "directory" },
+            ],
+            "files": 1,
+            "directories": 1,
+            "total_size": 5,
+        })
+    );
+    // the text content holds the same document
+    match call_result.content.first().unwrap() {
+        ContentBlock::TextContent(text_content) => {
+            let parsed: serde_json::Value = serde_json::from_str(&text_content.text).unwrap();
+            assert_eq!(parsed, serde_json::Value::Object(structured));
+        }
+        other => panic!("Expected text content, got {other:?}"),
+    }
+
+    let params = MoveFile {
+        source: dir_path.join("file.txt").to_str().unwrap().to_string(),
+        destination: dir_path.join("moved.txt").to_str().unwrap().to_string(),
+        create_parents: None,
+        output_format: Some(OutputFormat::Json),
+        idempotency_key: None,
+    };
+    let call_result = MoveFile::run_tool(params, &service).await.unwrap();
+    let structured = call_result.structured_content.unwrap();
+    assert!(
+        structured["destination"]
+            .as_str()
+            .unwrap()
+            .ends_with("moved.txt")
+    );
+
+    // text remains the default
+    let params = GetFileInfo {
+        path: dir_path.join("moved.txt").to_str().unwrap().to_string(),
+        output_format: None,
+        advanced: None,
+    };
+    let call_result = GetFileInfo::run_tool(params, &service).await.unwrap();
+    assert!(call_result.structured_content.is_none());
+
+    let params = GetFileInfo {
+        path: dir_path.join("moved.txt").to_str().unwrap().to_string(),
+        output_format: Some(OutputFormat::Json),
+        advanced: None,
+    };
+    let call_result = GetFileInfo::run_tool(params, &service).await.unwrap();
+    let structured = call_result.structured_content.unwrap();
+    assert_eq!(structured["size"], 5);
+    assert_eq!(structured["is_file"], true);
+    assert!(structured.get("raw_metadata").is_none());
+}
+
+#[tokio::test]
+async fn test_find_duplicate_files_json_groups_sorted_by_wasted_bytes() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    fs::create_dir_all(&dir_path).unwrap();
+    fs::write(dir_path.join("small_a.txt"), "abc").unwrap();
+    fs::write(dir_path.join("small_b.txt"), "abc").unwrap();
+    fs::write(dir_path.join("large_a.txt"), "0123456789").unwrap();
+    fs::write(dir_path.join("large_b.txt"), "0123456789").unwrap();
+    fs::write(dir_path.join("large_c.txt"), "0123456789").unwrap();
+    fs::write(dir_path.join("unique.txt"), "unique").unwrap();
+
+    let params = FindDuplicateFiles {
+        root_path: dir_path.to_str().unwrap().to_string(),
+        pattern: None,
+        exclude_patterns: None,
+        min_bytes: None,
+        max_bytes: None,
+        output_format: Some(OutputFormat::Json),
+        prefer_keep_paths: None,
+    };
+    let call_result = FindDuplicateFiles::run_tool(params, &service)
+        .await
+        .unwrap();
+    let structured = call_result.structured_content.unwrap();
+    assert_eq!(structured["total_wasted_bytes"], 23);
+
+    let groups = structured["groups"].as_array().unwrap();
+    assert_eq!(groups.len(), 2);
+    assert_eq!(groups[0]["id"], 1);
+    assert_eq!(groups[0]["file_count"], 3);
+    assert_eq!(groups[0]["wasted_bytes"], 20);
+    assert_eq!(groups[1]["id"], 2);
+    assert_eq!(groups[1]["wasted_bytes"], 3);
+
+    let files = groups[0]["files"].as_array().unwrap();
+    let names: Vec<&str> = files
+        .iter()
+        .map(|file| file["path"].as_str().unwrap().rsplit('/').next().unwrap())
+        .collect();
+    assert_eq!(names, vec!["large_a.txt", "large_b.txt", "large_c.txt"]);
+    assert!(
+        files
+            .iter()
+            .all(|file| file["size"] == 10 && file["modified"].is_string())
+    );
+}
+
+#[tokio::test]
+async fn test_find_duplicate_files_prefer_keep_paths() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let dir_path = temp_dir.join("dir1");
+    fs::create_dir_all(dir_path.join("Downloads")).unwrap();
+    fs::create_dir_all(dir_path.join("Photos")).unwrap();
+    fs::write(dir_path.join("Downloads").join("a.jpg"), "image").unwrap();
+    fs::write(dir_path.join("Photos").join("a.jpg"), "image").unwrap();
+
+    let params = FindDuplicateFiles {
+        root_path: dir_path.to_str().unwrap().to_string(),
+        pattern: None,
+        exclude_patterns: None,
+        min_bytes: None,
+        max_bytes: None,
+        output_format: None,
+        prefer_keep_paths: Some(vec!["Photos/".to_string()]),
+    };
+    let call_result = FindDuplicateFiles::run_tool(params, &service)
+        .await
+        .unwrap();
+    match call_result.content.first().unwrap() {
+        ContentBlock::TextContent(text_content) => {
+            let text = &text_content.text;
+            assert!(text.contains(&format!(
+                "[keep] {}",
+                dir_path.join("Photos").join("a.jpg").display()
+            )));
+            assert!(text.contains(&format!(
+                "[remove] {}",
+                dir_path.join("Downloads").join("a.jpg").display()
+            )));
+        }
+        other => panic!("Expected text content, got {other:?}"),
+    }
+}
+
+#[tokio::test]
+async fn test_set_default_root() {
+    let (temp_dir, service, _allowed_dirs) =
+        setup_service(vec!["dir1".to_string(), "dir2".to_string()]);
+    fs::create_dir_all(temp_dir.join("dir2").join("src")).unwrap();
+    fs::write(
+        temp_dir.join("dir2").join("src").join("main.rs"),
+        "fn main() {}",
+    )
+    .unwrap();
+
+    let params = SetDefaultRoot {
+        path: temp_dir.join("dir2").to_str().unwrap().to_string(),
+        output_format: None,
+    };
+    SetDefaultRoot::run_tool(params, &service).await.unwrap();
+
+    // relative paths are resolved against the new default root
+    let params = SetDefaultRoot {
+        path: "src".to_string(),
+        output_format: Some(OutputFormat::Json),
+    };
+    let call_result = SetDefaultRoot::run_tool(params, &service).await.unwrap();
+    assert_eq!(
+        call_result.structured_content.unwrap()["default_root"],
+        temp_dir.join("dir2").join("src").to_str().unwrap()
+    );
+    let content = service
+        .read_text_file(std::path::Path::new("main.rs"), false)
+        .await
+        .unwrap();
+    assert_eq!(content, "fn main() {}");
+
+    // files and directories outside the allowed directories are rejected
+    let params = SetDefaultRoot {
+        path: "main.rs".to_string(),
+        output_format: None,
+    };
+    assert!(SetDefaultRoot::run_tool(params, &service).await.is_err());
+    let params = SetDefaultRoot {
+        path: temp_dir.to_str().unwrap().to_string(),
+        output_format: None,
+    };
+    assert!(SetDefaultRoot::run_tool(params, &service).await.is_err());
+}
+
+#[tokio::test]
+async fn test_get_file_info_advanced() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let file_path = temp_dir.join("dir1").join("file.txt");
+    fs::write(&file_path, "hello").unwrap();
+
+    // advanced implies the json output format
+    let params = GetFileInfo {
+        path: file_path.to_str().unwrap().to_string(),
+        output_format: None,
+        advanced: Some(true),
+    };
+    let call_result = GetFileInfo::run_tool(params, &service).await.unwrap();
+    let structured = call_result.structured_content.unwrap();
+    let raw_metadata = &structured["raw_metadata"];
+    assert_eq!(raw_metadata["len"], 5);
+    assert_eq!(raw_metadata["is_symlink"], false);
+    assert!(raw_metadata["modified"].is_f64());
+    #[cfg(unix)]
+    assert_eq!(raw_metadata["stat"]["st_size"], 5);
+    #[cfg(windows)]
+    assert_eq!(raw_metadata["windows"]["file_size"], 5);
+    #[cfg(any(target_os = "macos", target_os = "linux"))]
+    assert!(raw_metadata["xattr_names"].is_array());
+}
+
+#[tokio::test]
+async fn test_edit_file_expected_anchors() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let file_path = temp_dir.join("dir1").join("lines.txt");
+    fs::write(&file_path, "one\ntwo\nthree\n").unwrap();
+
+    let params = ReadFileLines {
+        path: file_path.to_str().unwrap().to_string(),
+        offset: 1,
+        limit: Some(1),
+        with_anchors: Some(true),
+    };
+    let call_result = ReadFileLines::run_tool(params, &service).await.unwrap();
+    let text = match call_result.content.first().unwrap() {
+        ContentBlock::TextContent(text_content) => text_content.text.clone(),
+        other => panic!("Expected text content, got {other:?}"),
+    };
+    let (anchor, line) = text.split_once(" | ").unwrap();
+    assert_eq!(line, "two");
+    assert!(anchor.starts_with("2:"));
+
+    let edit_params = |anchor: &str| EditFile {
+        path: file_path.to_str().unwrap().to_string(),
+        edits: vec![EditOperation {
+            old_text: "two".to_string(),
+            new_text: "2".to_string(),
+        }],
+        dry_run: None,
+        replace_all: None,
+        expected_anchors: Some(vec![anchor.to_string()]),
+        output_format: None,
+        idempotency_key: None,
+    };
+
+    // the line changed since it was read
+    fs::write(&file_path, "one\nTWO two\nthree\n").unwrap();
+    let result = EditFile::run_tool(edit_params(anchor), &service).await;
+    assert!(result.is_err());
+    assert_eq!(
+        fs::read_to_string(&file_path).unwrap(),
+        "one\nTWO two\nthree\n"
+    );
+
+    fs::write(&file_path, "one\ntwo\nthree\n").unwrap();
+    EditFile::run_tool(edit_params(anchor), &service)
+        .await
+        .unwrap();
+    assert_eq!(fs::read_to_string(&file_path).unwrap(), "one\n2\nthree\n");
+
+    assert!(
+        EditFile::run_tool(edit_params("invalid"), &service)
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_tool_metrics_attached_to_result() {
+    let (temp_dir, service, _allowed_dirs) = setup_service(vec!["dir1".to_string()]);
+    let file_path = temp_dir.join("dir1").join("file.txt");
+    fs::write(&file_path, "hello world").unwrap();
+
+    let params = ReadTextFile {
+        path: file_path.to_str().unwrap().to_string(),
+        with_line_numbers: None,
+    };
+    let (result, metrics) = measure(ReadTextFile::run_tool(params, &service)).await;
+    assert_eq!(metrics.bytes_processed, Some(11));
+
+    let mut result = result.unwrap();
+    metrics.attach_to(&mut result);
+    let meta = result.meta.unwrap();
+    assert!(meta["duration_ms"].is_u64());
+    assert_eq!(meta["bytes_processed"], 11);
+
+    // tools not processing file content only report their duration
+    let params = CreateDirectory {
+        path: temp_dir
+            .join("dir1")
+            .join("new_dir")
+            .to_str()
+            .unwrap()
+            .to_string(),
+        output_format: None,
+        idempotency_key: None,
+    };
+    let (_, metrics) = measure(CreateDirectory::run_tool(params, &service)).await;
+    assert_eq!(metrics.bytes_processed, None);
+}
+
+#[tokio::test]
+async fn test_record_and_replay_session() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let root = temp_dir.path().join("root");
+    fs::create_dir_all(&root).unwrap();
+    let file_path = root.join("notes.txt");
+    fs::write(&file_path, "first version").unwrap();
+    let root_arg = root.display().to_string();
+    let handler =
+        FileSystemHandler::new(common::parse_args(&["mcp-server", &root_arg]).unwrap()).unwrap();
+
+    let tool_call = |name: &str, arguments: serde_json::Value| -> CallToolRequestParams {
+        serde_json::from_value(serde_json::json!({ "name": name, "arguments": arguments })).unwrap()
+    };
+    let recorder = SessionRecorder::create(&temp_dir.path().join("recordings"), true).unwrap();
+    let requests = [
+        tool_call(
+            "read_text_file",
+            serde_json::json!({ "path": file_path.display().to_string() }),
+        ),
+        tool_call(
+            "write_file",
+            serde_json::json!({ "path": "x", "content": "" }),
+        ),
+    ];
+    for params in requests {
+        let result = handler.call_tool(params.clone(), None).await;
+        recorder.record_tool_call(&params, &result);
+    }
+    recorder.record("ping", serde_json::json!({}), Ok(serde_json::json!({})));
+
+    let recording = read_recording(recorder.path()).unwrap();
+    assert_eq!(recording.len(), 3);
+    assert_eq!(recording[0].sequence, 1);
+    assert_eq!(recording[0].method, "tools/call");
+    assert!(recording[0].result.is_some());
+    // read-only mode, the write fails
+    assert!(recording[1].error.is_some());
+
+    let statuses = |replayed: Vec<ReplayedRequest>| -> Vec<ReplayStatus> {
+        replayed.iter().map(|request| request.status).collect()
+    };
+    assert_eq!(
+        statuses(replay(&handler, recording.clone()).await),
+        vec![
+            ReplayStatus::Match,
+            ReplayStatus::Match,
+            ReplayStatus::Skipped
+        ]
+    );
+
+    fs::write(&file_path, "second version").unwrap();
+    let replayed = replay(&handler, recording).await;
+    assert_eq!(replayed[0].status, ReplayStatus::Differs);
+    assert!(
+        replayed[0]
+            .actual
+            .as_ref()
+            .unwrap()
+            .to_string()
+            .contains("second version")
+    );
+}
+
+#[tokio::test]
+async fn test_recording_redacts_content() {
+    let temp_dir = tempfile::tempdir().unwrap();
+    let root = temp_dir.path().join("root");
+    fs::create_dir_all(&root).unwrap();
+    let file_path = root.join("notes.txt");
+    fs::write(&file_path, "private notes").unwrap();
+    let root_arg = root.display().to_string();
+    let handler = FileSystemHandler::new(
+        common::parse_args(&["mcp-server", "--allow-write", &root_arg]).unwrap(),
+    )
+    .unwrap();
+
+    let tool_call = |name: &str, arguments: serde_json::Value| -> CallToolRequestParams {
+        serde_json::from_value(serde_json::json!({ "name": name, "arguments": arguments })).unwrap()
+    };
+    let recorder = SessionRecorder::create(&temp_dir.path().join("recordings"), false).unwrap();
+    let requests = [
+        tool_call(
+            "read_text_file",
+            serde_json::json!({ "path": file_path.display().to_string() }),
+        ),
+        tool_call(
+            "write_file",
+            serde_json::json!({
+                "path": root.join("secret.txt").display().to_string(),
+                "content": "secret token"
+            }),
+        ),
+        tool_call(
+            "edit_file",
+            serde_json::json!({
+                "path": file_path.display().to_string(),
+                "edits": [{ "oldText": "private", "newText": "shared" }]
+            }),
+        ),
+    ];
+    for params in requests {
+        let result = handler.call_tool(params.clone(), None).await;
+        assert!(result.is_ok());
+        recorder.record_tool_call(&params, &result);
+    }
+
+    let recorded = fs::read_to_string(recorder.path()).unwrap();
+    for content in ["private notes", "secret token", "private", "shared"] {
+        assert!(!recorded.contains(content), "{content} was recorded");
+    }
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let mode = fs::metadata(recorder.path()).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+    }
+    let recording = read_recording(recorder.path()).unwrap();
+    assert!(recording.iter().all(|request| request.redacted));
+    assert_eq!(
+        recording[1].params["arguments"]["content"],
+        "<12 bytes of content redacted>"
+    );
+
+    // the calls writing files are skipped, the results are compared by size
+    fs::write(&file_path, "private notes").unwrap();
+    let replayed = replay(&handler, recording).await;
+    assert_eq!(
+        replayed
+            .iter()
+            .map(|request| request.status)
+            .collect::<Vec<_>>(),
+        vec![
+            ReplayStatus::Match,
+            ReplayStatus::Skipped,
+            ReplayStatus::Skipped
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_in_process_test_server() {
+    let server = TestServer::new().unwrap();
+    server.create_file("docs/notes.txt", "hello");
+    let text = server
+        .call_tool_text(
+            "read_text_file",
+            serde_json::json!({ "path": server.path("docs/notes.txt") }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(text, "hello");
+
+    // read-only by default
+    let result = server
+        .call_tool(
+            "write_file",
+            serde_json::json!({ "path": server.path("new.txt"), "content": "new" }),
+        )
+        .await;
+    assert!(result.is_err());
+    assert!(
+        server
+            .call_tool("read_text_file", serde_json::json!(["not an object"]))
+            .await
+            .is_err()
+    );
+
+    let server = TestServer::with_args(&["--allow-write"]).unwrap();
+    server
+        .call_tool(
+            "write_file",
+            serde_json::json!({ "path": server.path("new.txt"), "content": "new" }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(fs::read_to_string(server.path("new.txt")).unwrap(), "new");
+    assert!(TestServer::with_args(&["--unknown-option"]).is_err());
+}
+
 #[tokio::test]
 async fn adhoc() {}
+
+#[tokio::test]
+async fn test_locate_and_load_context_prompt() {
+    use rust_mcp_filesystem::prompts::LOCATE_AND_LOAD_CONTEXT;
+    use rust_mcp_sdk::schema::GetPromptRequestParams;
+
+    let server = TestServer::new().unwrap();
+    server.create_file(
+        "src/billing/invoice.rs",
+        "pub fn total_invoice() -> u64 {\n    42\n}\n",
+    );
+    server.create_file("src/main.rs", "fn main() {}\n");
+    server.create_file("README.md", "A project.\n");
+
+    let arguments = |arguments: &[(&str, &str)]| {
+        Some(
+            arguments
+                .iter()
+                .map(|(name, value)| (name.to_string(), value.to_string()))
+                .collect(),
+        )
+    };
+    let result = server
+        .handler()
+        .get_prompt(GetPromptRequestParams {
+            name: LOCATE_AND_LOAD_CONTEXT.to_string(),
+            arguments: arguments(&[("topic", "invoice"), ("limit", "2")]),
+            meta: None,
+        })
+        .await
+        .unwrap();
+
+    let ContentBlock::TextContent(introduction) = &result.messages[0].content else {
+        panic!("expected a text introduction");
+    };
+    assert!(introduction.text.contains("1. "));
+    assert!(introduction.text.contains("invoice.rs"));
+    let embedded: Vec<_> = result.messages[1..]
+        .iter()
+        .filter_map(|message| match &message.content {
+            ContentBlock::EmbeddedResource(resource) => match &resource.resource {
+                EmbeddedResourceResource::TextResourceContents(text) => Some(text),
+                _ => None,
+            },
+            _ => None,
+        })
+        .collect();
+    assert_eq!(embedded.len(), 1);
+    assert!(embedded[0].uri.ends_with("invoice.rs"));
+    assert!(embedded[0].text.contains("total_invoice"));
+
+    let result = server
+        .handler()
+        .get_prompt(GetPromptRequestParams {
+            name: LOCATE_AND_LOAD_CONTEXT.to_string(),
+            arguments: arguments(&[("topic", "nowhere-to-be-found")]),
+            meta: None,
+        })
+        .await
+        .unwrap();
+    assert_eq!(result.messages.len(), 1);
+
+    for (name, arguments) in [
+        (LOCATE_AND_LOAD_CONTEXT, None),
+        (
+            LOCATE_AND_LOAD_CONTEXT,
+            arguments(&[("topic", "invoice"), ("limit", "many")]),
+        ),
+        ("unknown_prompt", arguments(&[("topic", "invoice")])),
+    ] {
+        let params = GetPromptRequestParams {
+            name: name.to_string(),
+            arguments,
+            meta: None,
+        };
+        assert!(server.handler().get_prompt(params).await.is_err());
+    }
+}
+
+#[tokio::test]
+async fn test_content_annotations() {
+    use rust_mcp_sdk::schema::Role;
+
+    let server = TestServer::new().unwrap();
+    server.create_file("small.txt", "hello\n");
+    server.create_file("large.txt", "a line of text\n".repeat(10_000));
+
+    let annotations = |result: &rust_mcp_sdk::schema::CallToolResult| match &result.content[0] {
+        ContentBlock::TextContent(text) => text.annotations.clone().unwrap(),
+        _ => panic!("expected a text content"),
+    };
+
+    // text summaries are meant for everyone, JSON output for the assistant
+    for (output_format, audience) in [
+        ("text", vec![Role::User, Role::Assistant]),
+        ("json", vec![Role::Assistant]),
+    ] {
+        let result = server
+            .call_tool(
+                "get_file_profile",
+                serde_json::json!({ "path": server.path("small.txt"), "output_format": output_format }),
+            )
+            .await
+            .unwrap();
+        let annotations = annotations(&result);
+        assert_eq!(annotations.audience, audience);
+        assert_eq!(annotations.priority, Some(1.0));
+    }
+
+    // file contents are meant for the assistant, large ones with a lower priority
+    let result = server
+        .call_tool(
+            "read_text_file",
+            serde_json::json!({ "path": server.path("large.txt") }),
+        )
+        .await
+        .unwrap();
+    let annotations = annotations(&result);
+    assert_eq!(annotations.audience, vec![Role::Assistant]);
+    assert!(annotations.priority.unwrap() < 0.5);
+}
+
+#[tokio::test]
+async fn test_simulate_mode() {
+    // write mode is implied
+    let server = TestServer::with_args(&["--simulate"]).unwrap();
+    server.create_file("src/lib.rs", "pub fn one() -> u32 {\n    1\n}\n");
+    server.create_dir("docs");
+    async fn read(server: &TestServer, path: &str) -> Result<String, CallToolError> {
+        let path = server.path(path);
+        server
+            .call_tool_text("read_text_file", serde_json::json!({ "path": path }))
+            .await
+    }
+
+    // a new file is readable but not written
+    server
+        .call_tool(
+            "write_file",
+            serde_json::json!({ "path": server.path("docs/notes.md"), "content": "draft\n" }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(read(&server, "docs/notes.md").await.unwrap(), "draft\n");
+    assert!(!server.path("docs/notes.md").exists());
+
+    // edits return their diff and apply to the simulated content
+    let diff = server
+        .call_tool_text(
+            "edit_file",
+            serde_json::json!({
+                "path": server.path("src/lib.rs"),
+                "edits": [{ "oldText": "    1", "newText": "    2" }]
+            }),
+        )
+        .await
+        .unwrap();
+    assert!(diff.contains("+    2"));
+    assert!(read(&server, "src/lib.rs").await.unwrap().contains("    2"));
+    assert_eq!(
+        fs::read_to_string(server.path("src/lib.rs")).unwrap(),
+        "pub fn one() -> u32 {\n    1\n}\n"
+    );
+
+    // moves and directories
+    server
+        .call_tool(
+            "create_directory",
+            serde_json::json!({ "path": server.path("archive/2024") }),
+        )
+        .await
+        .unwrap();
+    server
+        .call_tool(
+            "move_file",
+            serde_json::json!({
+                "source": server.path("docs"),
+                "destination": server.path("archive/2024/docs")
+            }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        read(&server, "archive/2024/docs/notes.md").await.unwrap(),
+        "draft\n"
+    );
+    assert!(read(&server, "docs/notes.md").await.is_err());
+    assert!(server.path("docs").exists());
+    assert!(!server.path("archive").exists());
+
+    // archives are created in memory and can be extracted
+    let message = server
+        .call_tool_text(
+            "zip_directory",
+            serde_json::json!({
+                "input_directory": server.path("src"),
+                "target_zip_file": server.path("src.zip")
+            }),
+        )
+        .await
+        .unwrap();
+    assert!(message.contains("Successfully compressed"));
+    assert!(!server.path("src.zip").exists());
+    server
+        .call_tool(
+            "unzip_file",
+            serde_json::json!({
+                "zip_file": server.path("src.zip"),
+                "target_path": server.path("extracted")
+            }),
+        )
+        .await
+        .unwrap();
+    // archived from the disk
+    assert!(
+        read(&server, "extracted/lib.rs")
+            .await
+            .unwrap()
+            .contains("    1")
+    );
+    assert!(!server.path("extracted").exists());
+
+    // validation still applies, also to the simulated changes
+    let result = server
+        .call_tool(
+            "write_file",
+            serde_json::json!({ "path": server.path("docs/other.md"), "content": "moved" }),
+        )
+        .await;
+    assert!(result.is_err());
+    let result = server
+        .call_tool(
+            "write_file",
+            serde_json::json!({
+                "path": server.path("src/lib.rs"),
+                "content": "again",
+                "if_exists": "fail"
+            }),
+        )
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_simulate_mode_listings() {
+    let server = TestServer::with_args(&["--simulate"]).unwrap();
+    server.create_file("docs/guide.md", "# Guide\n");
+    server.create_file("docs/old.md", "obsolete\n");
+    server.create_file("src/lib.rs", "pub fn one() {}\n");
+
+    server
+        .call_tool(
+            "write_file",
+            serde_json::json!({ "path": server.path("docs/notes.md"), "content": "simulated draft\n" }),
+        )
+        .await
+        .unwrap();
+    server
+        .call_tool(
+            "move_file",
+            serde_json::json!({ "source": server.path("src"), "destination": server.path("lib") }),
+        )
+        .await
+        .unwrap();
+    server
+        .call_tool(
+            "move_file",
+            serde_json::json!({ "source": server.path("docs/old.md"), "destination": server.path("retired.md") }),
+        )
+        .await
+        .unwrap();
+
+    // added, moved and deleted entries show up in the listings
+    let listing = server
+        .call_tool_text(
+            "list_directory",
+            serde_json::json!({ "path": server.path("docs") }),
+        )
+        .await
+        .unwrap();
+    assert!(listing.contains("[FILE] notes.md") && !listing.contains("old.md"));
+    let listing = server
+        .call_tool_text(
+            "list_directory_with_sizes",
+            serde_json::json!({ "path": server.root() }),
+        )
+        .await
+        .unwrap();
+    assert!(listing.contains("[DIR]  lib") && !listing.contains("src"));
+    let tree = server
+        .call_tool_text(
+            "directory_tree",
+            serde_json::json!({ "path": server.root() }),
+        )
+        .await
+        .unwrap();
+    assert!(tree.contains("notes.md") && tree.contains("lib.rs") && !tree.contains("old.md"));
+
+    // searches walk the overlay and read the simulated content
+    let matches = server
+        .call_tool_text(
+            "search_files",
+            serde_json::json!({ "path": server.root(), "pattern": "*.*" }),
+        )
+        .await
+        .unwrap();
+    assert!(matches.contains(&server.path("lib/lib.rs").display().to_string()));
+    assert!(matches.contains(&server.path("docs/notes.md").display().to_string()));
+    assert!(!matches.contains("old.md"));
+    assert!(!matches.contains(&server.path("src").display().to_string()));
+    let found = server
+        .call_tool_text(
+            "search_files_content",
+            serde_json::json!({ "path": server.root(), "pattern": "*.md", "query": "draft" }),
+        )
+        .await
+        .unwrap();
+    assert!(found.contains("notes.md"));
+
+    // metadata and sizes are those of the simulated files
+    let info = server
+        .call_tool_text(
+            "get_file_info",
+            serde_json::json!({ "path": server.path("docs/notes.md") }),
+        )
+        .await
+        .unwrap();
+    assert!(info.contains("size: 16") && info.contains("isFile: true"));
+    let size = server
+        .call_tool_text(
+            "calculate_directory_size",
+            serde_json::json!({ "root_path": server.path("docs"), "output_format": "bytes" }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(size, "24");
+}
+
+#[tokio::test]
+async fn test_commit_overlay() {
+    let overlay_dir = tempfile::TempDir::new().unwrap();
+    let overlay_arg = overlay_dir.path().display().to_string();
+    // write mode is implied
+    let server = TestServer::with_args(&["--overlay-dir", &overlay_arg]).unwrap();
+    server.create_file("src/lib.rs", "pub fn one() -> u32 {\n    1\n}\n");
+    server.create_file("data/a.txt", "a\n");
+
+    server
+        .call_tool(
+            "write_file",
+            serde_json::json!({ "path": server.path("notes.md"), "content": "draft\n" }),
+        )
+        .await
+        .unwrap();
+    server
+        .call_tool(
+            "edit_file",
+            serde_json::json!({
+                "path": server.path("src/lib.rs"),
+                "edits": [{ "oldText": "    1", "newText": "    2" }]
+            }),
+        )
+        .await
+        .unwrap();
+    server
+        .call_tool(
+            "move_file",
+            serde_json::json!({ "source": server.path("data"), "destination": server.path("archive") }),
+        )
+        .await
+        .unwrap();
+    server
+        .call_tool(
+            "create_directory",
+            serde_json::json!({ "path": server.path("out/logs") }),
+        )
+        .await
+        .unwrap();
+
+    // the changes are read from the overlay, the tree is unchanged
+    let content = server
+        .call_tool_text(
+            "read_text_file",
+            serde_json::json!({ "path": server.path("archive/a.txt") }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(content, "a\n");
+    assert!(!server.path("notes.md").exists());
+    assert!(server.path("data/a.txt").exists());
+    // the two stored files and the index
+    assert_eq!(fs::read_dir(overlay_dir.path()).unwrap().count(), 3);
+    let listing = server
+        .call_tool_text(
+            "list_directory",
+            serde_json::json!({ "path": server.root() }),
+        )
+        .await
+        .unwrap();
+    assert!(listing.contains("[FILE] notes.md") && listing.contains("[DIR] archive"));
+    assert!(!listing.contains("data"));
+
+    let pending = server
+        .call_tool_text("commit_overlay", serde_json::json!({ "dry_run": true }))
+        .await
+        .unwrap();
+    assert!(pending.starts_with("The overlay has 5 changes:"));
+    assert!(pending.contains(&format!(
+        "move {} -> {}",
+        server.path("data").display(),
+        server.path("archive").display()
+    )));
+
+    // a failing change leaves the tree as it was, the moved directory is put back
+    fs::write(server.path("out"), "not a directory").unwrap();
+    let result = server
+        .call_tool("commit_overlay", serde_json::json!({}))
+        .await;
+    assert!(result.is_err());
+    assert!(server.path("data/a.txt").exists());
+    assert!(!server.path("archive").exists());
+    assert_eq!(fs::read_dir(server.root()).unwrap().count(), 3);
+
+    fs::remove_file(server.path("out")).unwrap();
+    let committed = server
+        .call_tool_text("commit_overlay", serde_json::json!({}))
+        .await
+        .unwrap();
+    assert!(committed.starts_with("Committed 5 changes:"));
+    assert_eq!(
+        fs::read_to_string(server.path("notes.md")).unwrap(),
+        "draft\n"
+    );
+    assert!(
+        fs::read_to_string(server.path("src/lib.rs"))
+            .unwrap()
+            .contains("    2")
+    );
+    assert_eq!(
+        fs::read_to_string(server.path("archive/a.txt")).unwrap(),
+        "a\n"
+    );
+    assert!(!server.path("data").exists());
+    assert!(server.path("out/logs").is_dir());
+    assert_eq!(fs::read_dir(overlay_dir.path()).unwrap().count(), 0);
+
+    // discarded changes are dropped
+    server
+        .call_tool(
+            "write_file",
+            serde_json::json!({ "path": server.path("notes.md"), "content": "second draft\n", "if_exists": "overwrite" }),
+        )
+        .await
+        .unwrap();
+    let discarded = server
+        .call_tool_text("commit_overlay", serde_json::json!({ "discard": true }))
+        .await
+        .unwrap();
+    assert!(discarded.starts_with("Discarded 1 change:"));
+    assert_eq!(
+        fs::read_to_string(server.path("notes.md")).unwrap(),
+        "draft\n"
+    );
+    let content = server
+        .call_tool_text(
+            "read_text_file",
+            serde_json::json!({ "path": server.path("notes.md") }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(content, "draft\n");
+}
+
+#[tokio::test]
+async fn test_commit_overlay_keeps_concurrent_writes() {
+    let overlay_dir = tempfile::TempDir::new().unwrap();
+    let overlay_arg = overlay_dir.path().display().to_string();
+    let server = TestServer::with_args(&["--overlay-dir", &overlay_arg]).unwrap();
+    async fn write(server: &TestServer, name: &str, content: &str) {
+        server
+            .call_tool(
+                "write_file",
+                serde_json::json!({ "path": server.path(name), "content": content, "if_exists": "overwrite" }),
+            )
+            .await
+            .unwrap();
+    }
+    for i in 0..50 {
+        write(&server, &format!("file-{i}.txt"), "committed\n").await;
+    }
+
+    // files written while the commit is in progress, new ones and pending ones again
+    let writes = async {
+        for i in 0..10 {
+            write(&server, &format!("late-{i}.txt"), "late\n").await;
+            write(&server, &format!("file-{i}.txt"), "rewritten\n").await;
+            tokio::task::yield_now().await;
+        }
+    };
+    let (committed, _) = tokio::join!(
+        server.call_tool_text("commit_overlay", serde_json::json!({})),
+        writes
+    );
+    assert!(committed.unwrap().starts_with("Committed 50 changes:"));
+
+    // they are either committed or still in the overlay, none is lost
+    for (name, expected) in (0..10)
+        .map(|i| (format!("late-{i}.txt"), "late\n"))
+        .chain((0..10).map(|i| (format!("file-{i}.txt"), "rewritten\n")))
+    {
+        let content = server
+            .call_tool_text(
+                "read_text_file",
+                serde_json::json!({ "path": server.path(&name) }),
+            )
+            .await
+            .unwrap();
+        assert_eq!(content, expected, "{name}");
+    }
+    assert_eq!(
+        fs::read_to_string(server.path("file-49.txt")).unwrap(),
+        "committed\n"
+    );
+
+    // the kept changes are committed next
+    server
+        .call_tool_text("commit_overlay", serde_json::json!({}))
+        .await
+        .unwrap();
+    assert_eq!(
+        fs::read_to_string(server.path("late-9.txt")).unwrap(),
+        "late\n"
+    );
+    assert_eq!(
+        fs::read_to_string(server.path("file-0.txt")).unwrap(),
+        "rewritten\n"
+    );
+    assert_eq!(fs::read_dir(overlay_dir.path()).unwrap().count(), 0);
+}
+
+#[tokio::test]
+async fn test_diff_workspace() {
+    let overlay_dir = tempfile::TempDir::new().unwrap();
+    let overlay_arg = overlay_dir.path().display().to_string();
+    let server = TestServer::with_args(&["--overlay-dir", &overlay_arg]).unwrap();
+    server.create_file("src/lib.rs", "pub fn one() -> u32 {\n    1\n}\n");
+    server.create_file("data/a.txt", "a\n");
+
+    let diff = server
+        .call_tool_text("diff_workspace", serde_json::json!({}))
+        .await
+        .unwrap();
+    assert_eq!(diff, "The overlay has no pending file changes.");
+
+    server
+        .call_tool(
+            "write_file",
+            serde_json::json!({ "path": server.path("notes.md"), "content": "draft\n" }),
+        )
+        .await
+        .unwrap();
+    server
+        .call_tool(
+            "edit_file",
+            serde_json::json!({
+                "path": server.path("src/lib.rs"),
+                "edits": [{ "oldText": "    1", "newText": "    2" }]
+            }),
+        )
+        .await
+        .unwrap();
+    server
+        .call_tool(
+            "move_file",
+            serde_json::json!({ "source": server.path("data"), "destination": server.path("archive") }),
+        )
+        .await
+        .unwrap();
+
+    let diff = server
+        .call_tool_text("diff_workspace", serde_json::json!({}))
+        .await
+        .unwrap();
+    assert!(diff.starts_with("4 files changed (2 added, 1 modified, 1 deleted):"));
+    assert!(diff.contains(&format!("modified {}", server.path("src/lib.rs").display())));
+    assert!(diff.contains("-    1\n+    2"));
+    assert!(diff.contains(&format!("added {}", server.path("notes.md").display())));
+    assert!(diff.contains("+draft"));
+
+    let result = server
+        .call_tool(
+            "diff_workspace",
+            serde_json::json!({ "output_format": "json" }),
+        )
+        .await
+        .unwrap();
+    let file_changes: serde_json::Value =
+        serde_json::from_str(&result.content[0].as_text_content().unwrap().text).unwrap();
+    let statuses: Vec<(String, String)> = file_changes
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|file_change| {
+            (
+                file_change["path"].as_str().unwrap().to_string(),
+                file_change["status"].as_str().unwrap().to_string(),
+            )
+        })
+        .collect();
+    let path = |relative_path: &str| server.path(relative_path).display().to_string();
+    assert_eq!(
+        statuses,
+        vec![
+            (path("archive/a.txt"), "added".to_string()),
+            (path("data/a.txt"), "deleted".to_string()),
+            (path("notes.md"), "added".to_string()),
+            (path("src/lib.rs"), "modified".to_string()),
+        ]
+    );
+
+    // nothing is pending once committed
+    server
+        .call_tool("commit_overlay", serde_json::json!({}))
+        .await
+        .unwrap();
+    let diff = server
+        .call_tool_text("diff_workspace", serde_json::json!({}))
+        .await
+        .unwrap();
+    assert_eq!(diff, "The overlay has no pending file changes.");
+}
+
+#[tokio::test]
+async fn test_response_cache() {
+    let server = TestServer::with_args(&["--allow-write", "--response-cache-ttl", "60"]).unwrap();
+    server.create_file("a.txt", "a\n");
+    let search = serde_json::json!({ "path": server.root(), "pattern": "*.txt" });
+    let is_cached = |result: &CallToolResult| {
+        result
+            .meta
+            .as_ref()
+            .is_some_and(|meta| meta.get("cached") == Some(&serde_json::json!(true)))
+    };
+
+    let first = server
+        .call_tool("search_files", search.clone())
+        .await
+        .unwrap();
+    assert!(!is_cached(&first));
+    let second = server
+        .call_tool("search_files", search.clone())
+        .await
+        .unwrap();
+    assert!(is_cached(&second));
+    assert_eq!(
+        first.content[0].as_text_content().unwrap().text,
+        second.content[0].as_text_content().unwrap().text
+    );
+
+    // other arguments are not served from the cache
+    let other = serde_json::json!({ "path": server.root(), "pattern": "*.md" });
+    let result = server.call_tool("search_files", other).await.unwrap();
+    assert!(!is_cached(&result));
+
+    // a path modified since runs again
+    server.create_file("b.txt", "b\n");
+    let result = server
+        .call_tool("search_files", search.clone())
+        .await
+        .unwrap();
+    assert!(!is_cached(&result));
+    assert!(
+        result.content[0]
+            .as_text_content()
+            .unwrap()
+            .text
+            .contains("b.txt")
+    );
+
+    // a change made by a tool forgets the cached results
+    let result = server
+        .call_tool("search_files", search.clone())
+        .await
+        .unwrap();
+    assert!(is_cached(&result));
+    server
+        .call_tool(
+            "create_directory",
+            serde_json::json!({ "path": server.path("sub/dir") }),
+        )
+        .await
+        .unwrap();
+    let result = server.call_tool("search_files", search).await.unwrap();
+    assert!(!is_cached(&result));
+}
+
+#[tokio::test]
+async fn test_read_multiple_text_files_byte_budget() {
+    let server = TestServer::new().unwrap();
+    server.create_file("small.txt", "small\n");
+    let lines: String = (1..=100).map(|line| format!("line {line:03}\n")).collect();
+    server.create_file("large.txt", &lines);
+
+    // everything fits in the default budget
+    let text = server
+        .call_tool_text(
+            "read_multiple_text_files",
+            serde_json::json!({ "paths": [server.path("small.txt"), server.path("large.txt")] }),
+        )
+        .await
+        .unwrap();
+    assert!(text.contains(&format!(
+        "{} (6 bytes):\nsmall\n",
+        server.path("small.txt").display()
+    )));
+    assert!(text.contains(&format!(
+        "{} (900 bytes):\n{lines}",
+        server.path("large.txt").display()
+    )));
+
+    // the small file is returned fully, the large one gets the rest of the budget
+    let text = server
+        .call_tool_text(
+            "read_multiple_text_files",
+            serde_json::json!({
+                "paths": [server.path("large.txt"), server.path("small.txt")],
+                "byte_budget": 186
+            }),
+        )
+        .await
+        .unwrap();
+    assert!(text.contains(&format!(
+        "{} (6 bytes):\nsmall\n",
+        server.path("small.txt").display()
+    )));
+    assert!(text.contains(&format!(
+        "{} (900 bytes, 180 shown):\n",
+        server.path("large.txt").display()
+    )));
+    assert!(text.contains(
+        "line 010\n\n[... 720 bytes omitted (lines 11-90), use read_file_lines to read them ...]\nline 091\n"
+    ));
+    assert!(text.contains("line 001\n") && text.contains("line 100\n"));
+    assert!(!text.contains("line 050"));
+}
+
+#[tokio::test]
+async fn test_ascii_output() {
+    assert_eq!(escape_non_ascii("plain"), "plain");
+    assert_eq!(escape_non_ascii("é日😀"), "\\u00e9\\u65e5\\ud83d\\ude00");
+
+    let server = TestServer::with_args(&["--ascii-output"]).unwrap();
+    server.create_file("привет.txt", "Привет, 世界\n");
+
+    let text = server
+        .call_tool_text(
+            "read_text_file",
+            serde_json::json!({ "path": server.path("привет.txt") }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        text,
+        "\\u041f\\u0440\\u0438\\u0432\\u0435\\u0442, \\u4e16\\u754c\n"
+    );
+
+    // the JSON output stays valid JSON, decoding to the original names
+    let result = server
+        .call_tool(
+            "list_directory",
+            serde_json::json!({ "path": server.root(), "output_format": "json" }),
+        )
+        .await
+        .unwrap();
+    let text = &result.content[0].as_text_content().unwrap().text;
+    assert!(text.is_ascii());
+    let entries: serde_json::Value = serde_json::from_str(text).unwrap();
+    assert!(entries.to_string().contains("привет.txt"));
+    assert!(
+        serde_json::to_string(&result.structured_content)
+            .unwrap()
+            .is_ascii()
+    );
+
+    let err = server
+        .call_tool_text(
+            "read_text_file",
+            serde_json::json!({ "path": server.path("日本.txt") }),
+        )
+        .await
+        .unwrap_err();
+    assert!(err.to_string().is_ascii());
+}
+
+#[tokio::test]
+async fn test_tool_error_codes() {
+    let server = TestServer::new().unwrap();
+
+    let err = server
+        .call_tool(
+            "write_file",
+            serde_json::json!({ "path": server.path("new.txt"), "content": "text" }),
+        )
+        .await
+        .unwrap_err();
+    let error = ToolError::from_call_error(&err);
+    assert_eq!(error.code, "no_write_access");
+    let result = error.into_result();
+    assert_eq!(result.is_error, Some(true));
+    assert_eq!(
+        result.meta.unwrap().get("error_code"),
+        Some(&serde_json::json!("no_write_access"))
+    );
+
+    let err = server
+        .call_tool(
+            "read_text_file",
+            serde_json::json!({ "path": "/etc/passwd" }),
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(ToolError::from_call_error(&err).code, "access_denied");
+
+    let err = server
+        .call_tool(
+            "read_text_file",
+            serde_json::json!({ "path": server.path("missing.txt") }),
+        )
+        .await
+        .unwrap_err();
+    assert_eq!(ToolError::from_call_error(&err).code, "not_found");
+}
+
+#[tokio::test]
+async fn test_find_project_root() {
+    let server = TestServer::new().unwrap();
+    server.create_dir("repo/.git");
+    server.create_file("repo/Cargo.toml", "[workspace]\n");
+    server.create_file("repo/web/package.json", "{}\n");
+    server.create_file("repo/web/src/index.js", "\n");
+    server.create_file("notes/todo.md", "\n");
+
+    let text = server
+        .call_tool_text(
+            "find_project_root",
+            serde_json::json!({ "path": server.path("repo/web/src/index.js") }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        text,
+        format!(
+            "project root: {} (node)\nmarkers: package.json\nrepository root: {}\n",
+            server.path("repo/web").display(),
+            server.path("repo").display()
+        )
+    );
+
+    let result = server
+        .call_tool(
+            "find_project_root",
+            serde_json::json!({ "path": server.path("repo"), "output_format": "json" }),
+        )
+        .await
+        .unwrap();
+    let project_root = result.structured_content.unwrap();
+    assert_eq!(project_root["kinds"], serde_json::json!(["rust", "git"]));
+    assert_eq!(
+        project_root["markers"],
+        serde_json::json!(["Cargo.toml", ".git"])
+    );
+    assert_eq!(project_root["root"], project_root["repository_root"]);
+
+    // the search stops at the allowed directory
+    let text = server
+        .call_tool_text(
+            "find_project_root",
+            serde_json::json!({ "path": server.path("notes/todo.md") }),
+        )
+        .await
+        .unwrap();
+    assert!(text.starts_with(
+        "No project marker (Cargo.toml, package.json, pyproject.toml, .git) was found"
+    ));
+}
+
+#[tokio::test]
+async fn test_count_lines_of_code() {
+    let server = TestServer::new().unwrap();
+    server.create_file(
+        "src/main.rs",
+        "// entry point\nfn main() {\n\n    /* block\n       comment */\n    println!(\"hi\");\n}\n",
+    );
+    server.create_file("src/lib.rs", "pub mod a;\n");
+    server.create_file("scripts/run.py", "# run\nprint(1)\n\n");
+    server.create_file("data/values.dat", "1\n2\n");
+    server.create_file("target/build.rs", "fn main() {}\n");
+    server.create_file(".mcpignore", "target/\n");
+    std::fs::write(server.path("image.bin"), [0u8, 1, 2, b'\n']).unwrap();
+
+    let result = server
+        .call_tool(
+            "count_lines_of_code",
+            serde_json::json!({
+                "root_path": server.root(),
+                "classify_lines": true,
+                "output_format": "json"
+            }),
+        )
+        .await
+        .unwrap();
+    let output = result.structured_content.unwrap();
+    assert_eq!(
+        output["languages"][0],
+        serde_json::json!({
+            "language": "Rust", "files": 2, "lines": 8, "code": 4, "comments": 3, "blanks": 1
+        })
+    );
+    assert_eq!(
+        output["languages"][1],
+        serde_json::json!({
+            "language": "Python", "files": 1, "lines": 3, "code": 1, "comments": 1, "blanks": 1
+        })
+    );
+    // ignored and binary files are skipped
+    assert_eq!(output["total"]["files"], 5);
+    assert_eq!(output["total"]["lines"], 14);
+
+    let text = server
+        .call_tool_text(
+            "count_lines_of_code",
+            serde_json::json!({ "root_path": server.path("src") }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        text,
+        "  language          files      lines\n  Rust                  2          8\n  total                 2          8\n"
+    );
+}
+
+#[tokio::test]
+async fn test_find_dependency_manifests() {
+    let server = TestServer::new().unwrap();
+    server.create_file("Cargo.toml", "[workspace]\nmembers = [\"cli\"]\n");
+    server.create_file("cli/Cargo.toml", "[package]\nname = \"cli\"\n");
+    server.create_file("web/package.json", "{\n  \"name\": \"web\"\n}\n");
+    server.create_file("web/node_modules/left-pad/package.json", "{}\n");
+    server.create_file("tools/go.mod", "module example.com/tools\n\ngo 1.22\n");
+    server.create_file("ml/requirements.txt", "numpy==2.0\n");
+
+    let result = server
+        .call_tool(
+            "find_dependency_manifests",
+            serde_json::json!({ "root_path": server.root(), "output_format": "json" }),
+        )
+        .await
+        .unwrap();
+    let groups = result.structured_content.unwrap()["ecosystems"].clone();
+    let ecosystems: Vec<&str> = groups
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|group| group["ecosystem"].as_str().unwrap())
+        .collect();
+    assert_eq!(ecosystems, ["rust", "node", "python", "go"]);
+    assert_eq!(groups[0]["manifests"].as_array().unwrap().len(), 2);
+    // installed packages are skipped
+    assert_eq!(groups[1]["manifests"].as_array().unwrap().len(), 1);
+    assert_eq!(
+        groups[3]["manifests"][0]["content"],
+        "module example.com/tools\n\ngo 1.22\n"
+    );
+
+    let text = server
+        .call_tool_text(
+            "find_dependency_manifests",
+            serde_json::json!({ "root_path": server.path("tools"), "max_bytes": 30 }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        text,
+        format!(
+            "## go (1 manifest)\n\n{} (34 bytes, 26 shown):\nmodule example.com/tools\n\n",
+            server.path("tools/go.mod").display()
+        )
+    );
+}
+
+#[tokio::test]
+async fn test_compare_similarity() {
+    let server = TestServer::new().unwrap();
+    let config: String = (1..=10).map(|i| format!("key{i} = {i}\n")).collect();
+    server.create_file("a.conf", &config);
+    server.create_file("b.conf", config.replace("key5 = 5", "key5 = 50"));
+    server.create_file("c.conf", "unrelated = true\n");
+
+    let text = server
+        .call_tool_text(
+            "compare_similarity",
+            serde_json::json!({ "paths": [server.path("a.conf"), server.path("b.conf")] }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        text,
+        format!(
+            "{} and {} are 90% similar (9 common lines, 10 and 10 lines).\n",
+            server.path("a.conf").display(),
+            server.path("b.conf").display()
+        )
+    );
+
+    let paths = [
+        server.path("c.conf"),
+        server.path("a.conf"),
+        server.path("b.conf"),
+    ];
+    let result = server
+        .call_tool(
+            "compare_similarity",
+            serde_json::json!({ "paths": paths, "output_format": "json" }),
+        )
+        .await
+        .unwrap();
+    let report = result.structured_content.unwrap();
+    assert_eq!(report["pairs"].as_array().unwrap().len(), 3);
+    assert_eq!(report["pairs"][0]["similarity"], 90.0);
+    assert_eq!(
+        report["clusters"],
+        serde_json::json!([[paths[1], paths[2]]])
+    );
+
+    // a stricter threshold leaves the files apart
+    let result = server
+        .call_tool(
+            "compare_similarity",
+            serde_json::json!({ "paths": paths, "threshold": 95, "output_format": "json" }),
+        )
+        .await
+        .unwrap();
+    assert_eq!(
+        result.structured_content.unwrap()["clusters"],
+        serde_json::json!([])
+    );
+
+    let result = server
+        .call_tool(
+            "compare_similarity",
+            serde_json::json!({ "paths": [server.path("a.conf")] }),
+        )
+        .await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_unzip_file_merge() {
+    let server = TestServer::with_args(&["--allow-write"]).unwrap();
+    server.create_file("src/a.txt", "new a\n");
+    server.create_file("src/b.txt", "new b\n");
+    server.create_file("out/a.txt", "old a\n");
+    server.create_file("out/keep.txt", "kept\n");
+    server
+        .call_tool(
+            "zip_directory",
+            serde_json::json!({
+                "input_directory": server.path("src"),
+                "target_zip_file": server.path("src.zip")
+            }),
+        )
+        .await
+        .unwrap();
+    let arguments = serde_json::json!({
+        "zip_file": server.path("src.zip"),
+        "target_path": server.path("out"),
+        "merge": true
+    });
+
+    // the target directory must not exist without merge
+    let mut without_merge = arguments.clone();
+    without_merge["merge"] = false.into();
+    assert!(server.call_tool("unzip_file", without_merge).await.is_err());
+
+    // the files which would be overwritten are listed first
+    let text = server
+        .call_tool_text("unzip_file", arguments.clone())
+        .await
+        .unwrap();
+    assert!(text.contains(
+        "would overwrite 1 existing file:\n  a.txt (6 bytes -> 6 bytes)\nNothing was extracted."
+    ));
+    let mut json_arguments = arguments.clone();
+    json_arguments["output_format"] = "json".into();
+    let result = server
+        .call_tool("unzip_file", json_arguments)
+        .await
+        .unwrap();
+    let preview = result.structured_content.unwrap();
+    assert_eq!(preview["status"], "preview");
+    assert_eq!(preview["preview"][0]["path"], "a.txt");
+    assert_eq!(
+        std::fs::read_to_string(server.path("out/a.txt")).unwrap(),
+        "old a\n"
+    );
+    assert!(!server.path("out/b.txt").exists());
+
+    let mut confirmed = arguments.clone();
+    confirmed["confirmation_token"] = preview["confirmation_token"].clone();
+    let text = server
+        .call_tool_text("unzip_file", confirmed.clone())
+        .await
+        .unwrap();
+    assert!(text.starts_with("Successfully extracted 2 files"));
+    assert_eq!(
+        std::fs::read_to_string(server.path("out/a.txt")).unwrap(),
+        "new a\n"
+    );
+    assert_eq!(
+        std::fs::read_to_string(server.path("out/b.txt")).unwrap(),
+        "new b\n"
+    );
+    assert!(server.path("out/keep.txt").exists());
+    assert!(!server.path("out/a.txt.unzip-partial").exists());
+
+    // the token can only be used once
+    std::fs::write(server.path("out/a.txt"), "old a\n").unwrap();
+    assert!(server.call_tool("unzip_file", confirmed).await.is_err());
+}
+
+#[tokio::test]
+async fn test_startup_banner() {
+    let server =
+        TestServer::with_args(&["--allow-write", "--disable-tools", "zip_files,write_file"])
+            .unwrap();
+    let banner = server.handler().startup_banner().await;
+    assert_eq!(
+        banner.message,
+        "Secure MCP Filesystem Server running in \"read/write\" mode without \"MCP Roots\" support, 1 allowed directory, 2 tools disabled."
+    );
+
+    let data = serde_json::to_value(&banner).unwrap();
+    assert_eq!(data["mode"], "read/write");
+    assert_eq!(data["roots_source"], "command_line");
+    assert_eq!(
+        data["allowed_directories"],
+        serde_json::json!([server.root()])
+    );
+    assert_eq!(
+        data["disabled_tools"],
+        serde_json::json!(["write_file", "zip_files"])
+    );
+    assert_eq!(data["capabilities"]["write"], true);
+    assert_eq!(data["capabilities"]["simulation"], false);
+    assert!(data.get("recording").is_none());
+
+    let banner = TestServer::with_args(&["--simulate"])
+        .unwrap()
+        .handler()
+        .startup_banner()
+        .await;
+    assert_eq!(banner.mode, "simulated read/write");
+    assert!(banner.capabilities.simulation);
+
+    // with roots support, the command line directories are used until the client provides roots
+    let server = TestServer::with_args(&["--enable-roots"]).unwrap();
+    let banner = server.handler().startup_banner().await;
+    assert_eq!(banner.roots_source, "command_line");
+    let root = server.path("project");
+    fs::create_dir(&root).unwrap();
+    server
+        .handler()
+        .apply_client_roots(vec![root.clone()])
+        .await;
+    let banner = server.handler().startup_banner().await;
+    assert_eq!(banner.roots_source, "mcp_roots");
+    assert_eq!(banner.allowed_directories, vec![root]);
+}